@@ -0,0 +1,49 @@
+//! Compares computing CIDs through [`banyanfs::utils::calculate_cid`] against hashing the same
+//! data serially with blake3. Without the `parallel-hashing` feature both paths are serial, run
+//! with `cargo bench --bench cid_hashing --features parallel-hashing` to measure the speedup.
+
+use std::time::{Duration, Instant};
+
+use banyanfs::utils::{calculate_cid, PARALLEL_HASH_THRESHOLD};
+
+const INPUT_SIZES: [usize; 4] = [
+    PARALLEL_HASH_THRESHOLD / 2,
+    PARALLEL_HASH_THRESHOLD,
+    16 * 1024 * 1024,
+    256 * 1024 * 1024,
+];
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    for size in INPUT_SIZES {
+        let data: Vec<u8> = (0..size).map(|idx| (idx % 251) as u8).collect();
+
+        let mut serial_total = Duration::ZERO;
+        let mut serial_hash = [0u8; 32];
+        for _ in 0..ITERATIONS {
+            let start = Instant::now();
+            serial_hash = blake3::hash(&data).into();
+            serial_total += start.elapsed();
+        }
+
+        let mut cid_total = Duration::ZERO;
+        let mut cid = calculate_cid(&[]);
+        for _ in 0..ITERATIONS {
+            let start = Instant::now();
+            cid = calculate_cid(&data);
+            cid_total += start.elapsed();
+        }
+
+        assert_eq!(
+            cid.as_bytes(),
+            &serial_hash,
+            "CID of {size} bytes differs from the serial hash"
+        );
+
+        println!(
+            "{size:>10} bytes  serial: {:?}/iter  calculate_cid: {:?}/iter",
+            serial_total / ITERATIONS,
+            cid_total / ITERATIONS,
+        );
+    }
+}
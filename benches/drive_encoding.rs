@@ -0,0 +1,101 @@
+//! Compares encoding a freshly loaded drive, where every node needs to be encoded, against
+//! encoding the same drive again once each node's encoding has been cached. Run with
+//! `cargo bench --bench drive_encoding`.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::main]
+async fn main() {
+    use std::time::{Duration, Instant};
+
+    use banyanfs::prelude::*;
+
+    const DIRECTORY_COUNT: usize = 50;
+    const FILES_PER_DIRECTORY: usize = 40;
+    const ITERATIONS: u32 = 10;
+
+    let mut rng = banyanfs::utils::crypto_rng();
+    let signing_key = std::sync::Arc::new(SigningKey::generate(&mut rng));
+    let mut store = MemoryDataStore::default();
+
+    let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+    let mut root = drive.root().await.unwrap();
+
+    for dir_idx in 0..DIRECTORY_COUNT {
+        let dir_name = format!("directory-{dir_idx}");
+        root.mkdir(&mut rng, &[&dir_name], false).await.unwrap();
+
+        for file_idx in 0..FILES_PER_DIRECTORY {
+            let file_name = format!("file-{file_idx}.txt");
+            root.write(&mut rng, &mut store, &[&dir_name, &file_name], b"")
+                .await
+                .unwrap();
+        }
+    }
+
+    let mut encoded_drive = Vec::new();
+    drive
+        .encode(&mut rng, ContentOptions::metadata(), &mut encoded_drive)
+        .await
+        .unwrap();
+
+    println!(
+        "drive with {} files, {} bytes encoded",
+        DIRECTORY_COUNT * FILES_PER_DIRECTORY,
+        encoded_drive.len()
+    );
+
+    // Cold: every node of a freshly loaded drive needs to be encoded
+    let mut cold_total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let loaded = DriveLoader::new(&signing_key)
+            .from_reader(encoded_drive.as_slice())
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        loaded
+            .encode(&mut rng, ContentOptions::metadata(), &mut Vec::new())
+            .await
+            .unwrap();
+        cold_total += start.elapsed();
+    }
+
+    // Warm: nothing has changed since the last encode
+    let mut warm_total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        drive
+            .encode(&mut rng, ContentOptions::metadata(), &mut Vec::new())
+            .await
+            .unwrap();
+        warm_total += start.elapsed();
+    }
+
+    // Single change: one file has been modified since the last encode
+    let mut changed_total = Duration::ZERO;
+    for iteration in 0..ITERATIONS {
+        let contents = format!("revision {iteration}");
+        root.write(
+            &mut rng,
+            &mut store,
+            &["directory-0", "file-0.txt"],
+            contents.as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        let start = Instant::now();
+        drive
+            .encode(&mut rng, ContentOptions::metadata(), &mut Vec::new())
+            .await
+            .unwrap();
+        changed_total += start.elapsed();
+    }
+
+    println!("cold encode:        {:?}/iter", cold_total / ITERATIONS);
+    println!("warm encode:        {:?}/iter", warm_total / ITERATIONS);
+    println!("one changed file:   {:?}/iter", changed_total / ITERATIONS);
+}
@@ -0,0 +1,26 @@
+//! Feeds arbitrary bytes through [`DriveLoader::parse_untrusted`], which must return an error
+//! rather than panic or exhaust memory for any input. Run with:
+//!
+//! ```text
+//! cargo +nightly fuzz run parse_untrusted
+//! ```
+//!
+//! The loader uses a fixed key, so the fuzzer mostly exercises the unauthenticated parts of the
+//! format. Seeding the corpus with drives encoded for this key lets it reach further.
+
+#![no_main]
+
+use std::sync::OnceLock;
+
+use banyanfs::codec::crypto::SigningKey;
+use banyanfs::filesystem::DriveLoader;
+use libfuzzer_sys::fuzz_target;
+
+fn signing_key() -> &'static SigningKey {
+    static KEY: OnceLock<SigningKey> = OnceLock::new();
+    KEY.get_or_init(|| SigningKey::from_bytes(&[0x42; 48]).expect("valid fixed key"))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = DriveLoader::new(signing_key()).parse_untrusted(data);
+});
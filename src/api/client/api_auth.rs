@@ -1,25 +1,61 @@
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 
 use async_std::sync::RwLock;
 use reqwest::Url;
+use serde_json::{Map, Value};
 
 use crate::api::client::{
-    ApiClient, PlatformToken, PlatformTokenError, StorageHostAuth, StorageTokenError,
+    ApiClient, ApiError, KeyResolver, PlatformToken, PlatformTokenError, StorageHostAuth,
+    StorageTokenError,
 };
 use crate::codec::crypto::SigningKey;
 
 #[derive(Clone)]
 pub(crate) struct ApiAuth {
     account_id: String,
-    key: Arc<SigningKey>,
+
+    /// Seconds added to the times in generated tokens to compensate for a skewed local clock.
+    clock_offset: Arc<AtomicI64>,
+
+    extra_claims: Arc<Map<String, Value>>,
+    key_resolver: Arc<dyn KeyResolver>,
     platform_token: PlatformToken,
     storage_hosts: Arc<RwLock<StorageHostAuth>>,
 }
 
 impl ApiAuth {
+    pub(crate) fn clock_offset(&self) -> i64 {
+        self.clock_offset.load(Ordering::Relaxed)
+    }
+
+    pub(crate) async fn ensure_storage_access(
+        &self,
+        client: &ApiClient,
+        host_url: &Url,
+    ) -> Result<(), ApiError> {
+        let mut storage_hosts = self.storage_hosts.write().await;
+
+        storage_hosts
+            .ensure_access(
+                client,
+                host_url,
+                &self.account_id,
+                &self.key_resolver.storage_host_key(host_url),
+                &self.extra_claims,
+                self.clock_offset(),
+            )
+            .await
+    }
+
     pub(crate) async fn platform_token(&self) -> Result<String, PlatformTokenError> {
         self.platform_token
-            .get_token(&self.account_id, &self.key)
+            .get_token(
+                &self.account_id,
+                &self.key_resolver.platform_key(),
+                &self.extra_claims,
+                self.clock_offset(),
+            )
             .await
     }
 
@@ -28,22 +64,43 @@ impl ApiAuth {
         storage_hosts.record_grant(storage_host_url, auth_token.to_string());
     }
 
-    pub fn new(account_id: impl Into<String>, key: Arc<SigningKey>) -> Self {
+    pub fn new(
+        account_id: impl Into<String>,
+        key_resolver: Arc<dyn KeyResolver>,
+        extra_claims: Map<String, Value>,
+    ) -> Self {
         let account_id = account_id.into();
+        let extra_claims = Arc::new(extra_claims);
         let platform_token = PlatformToken::default();
         let storage_hosts = Arc::new(RwLock::new(StorageHostAuth::default()));
 
         Self {
             account_id,
-            key,
+            clock_offset: Arc::new(AtomicI64::new(0)),
+            extra_claims,
+            key_resolver,
 
             platform_token,
             storage_hosts,
         }
     }
 
+    /// Changes the offset applied to generated tokens, discarding any tokens that were generated
+    /// with the previous offset.
+    pub(crate) async fn set_clock_offset(&self, clock_offset: i64) {
+        self.clock_offset.store(clock_offset, Ordering::Relaxed);
+
+        self.platform_token.clear().await;
+        self.storage_hosts.write().await.clear_tokens();
+    }
+
+    #[cfg(all(target_arch = "wasm32", feature = "tomb-compat"))]
     pub(crate) fn signing_key(&self) -> Arc<SigningKey> {
-        self.key.clone()
+        self.key_resolver.platform_key()
+    }
+
+    pub(crate) fn storage_host_key(&self, host_url: &Url) -> Arc<SigningKey> {
+        self.key_resolver.storage_host_key(host_url)
     }
 
     pub(crate) async fn storage_host_token(
@@ -54,7 +111,14 @@ impl ApiAuth {
         let mut storage_hosts = self.storage_hosts.write().await;
 
         storage_hosts
-            .get_token(client, host_url, &self.account_id, &self.key)
+            .get_token(
+                client,
+                host_url,
+                &self.account_id,
+                &self.key_resolver.storage_host_key(host_url),
+                &self.extra_claims,
+                self.clock_offset(),
+            )
             .await
     }
 
@@ -65,3 +129,211 @@ impl ApiAuth {
             .clear_authentication(host_url);
     }
 }
+
+#[cfg(all(test, feature = "test-utils", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    use crate::api::client::utils::api_fingerprint_key;
+    use std::time::Duration;
+
+    use crate::api::client::{KeyResolver, MockTransport, RegistrationStep, RetryPolicy};
+
+    struct PerHostResolver {
+        platform_key: Arc<SigningKey>,
+        storage_host_url: Url,
+        storage_host_key: Arc<SigningKey>,
+    }
+
+    impl KeyResolver for PerHostResolver {
+        fn platform_key(&self) -> Arc<SigningKey> {
+            self.platform_key.clone()
+        }
+
+        fn storage_host_key(&self, storage_host_url: &Url) -> Arc<SigningKey> {
+            if storage_host_url == &self.storage_host_url {
+                self.storage_host_key.clone()
+            } else {
+                self.platform_key.clone()
+            }
+        }
+    }
+
+    fn token_key_id(token: &str) -> String {
+        let header_b64 = token.split('.').next().unwrap();
+        let header: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header_b64).unwrap()).unwrap();
+        header["kid"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_signed_with_resolved_keys() {
+        let mut rng = crate::utils::crypto_rng();
+        let platform_key = Arc::new(SigningKey::generate(&mut rng));
+        let storage_host_key = Arc::new(SigningKey::generate(&mut rng));
+        let storage_host_url = Url::parse("http://storage.invalid/").unwrap();
+
+        let resolver = PerHostResolver {
+            platform_key: platform_key.clone(),
+            storage_host_url: storage_host_url.clone(),
+            storage_host_key: storage_host_key.clone(),
+        };
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new_with_key_resolver(
+            "http://platform.invalid/",
+            "test-account",
+            resolver,
+            Map::new(),
+        )
+        .unwrap()
+        .with_transport(transport.clone());
+
+        let platform_token = client.auth.platform_token().await.unwrap();
+        assert_eq!(
+            token_key_id(&platform_token),
+            api_fingerprint_key(&platform_key.verifying_key())
+        );
+
+        // The storage host confirms it already knows the key
+        transport.push_json(
+            200,
+            serde_json::json!({
+                "consumed_storage": 0,
+                "platform_id": "test",
+                "remaining_storage": 1024,
+            }),
+        );
+
+        let storage_token = client
+            .auth
+            .storage_host_token(&client, &storage_host_url)
+            .await
+            .unwrap();
+        assert_eq!(
+            token_key_id(&storage_token),
+            api_fingerprint_key(&storage_host_key.verifying_key())
+        );
+        assert_eq!(transport.take_requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_storage_access_registers_grant_once() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let storage_host_url = Url::parse("http://storage.invalid/").unwrap();
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        // The storage host doesn't know the client yet, so a grant is retrieved and registered
+        transport.push_response(401, "");
+        transport.push_json(200, serde_json::json!({ "api_version": "1.0.0" }));
+        transport.push_json(200, serde_json::json!({ "authorization_token": "grant" }));
+        transport.push_response(204, "");
+
+        client
+            .ensure_storage_access(&storage_host_url)
+            .await
+            .unwrap();
+
+        let hosts: Vec<_> = transport
+            .take_requests()
+            .iter()
+            .map(|request| request.url().host_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            hosts,
+            vec![
+                "storage.invalid",
+                "platform.invalid",
+                "platform.invalid",
+                "storage.invalid"
+            ]
+        );
+
+        client
+            .ensure_storage_access(&storage_host_url)
+            .await
+            .unwrap();
+        assert!(transport.take_requests().is_empty());
+
+        // Failures to retrieve a grant are reported
+        let other_host_url = Url::parse("http://other-storage.invalid/").unwrap();
+        transport.push_response(401, "");
+        transport.push_json(403, serde_json::json!({ "msg": "no storage available" }));
+
+        let result = client.ensure_storage_access(&other_host_url).await;
+        match result {
+            Err(ApiError::StorageTokenError(StorageTokenError::RegistrationFailed {
+                step,
+                source,
+            })) => {
+                assert_eq!(step, RegistrationStep::GrantFetch);
+                assert!(matches!(
+                    *source,
+                    ApiError::Message {
+                        status_code: 403,
+                        ..
+                    }
+                ));
+            }
+            other => panic!("expected the grant fetch to fail, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registration_retries_temporary_failures() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let storage_host_url = Url::parse("http://storage.invalid/").unwrap();
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone())
+            .with_registration_retry_policy(RetryPolicy::new(2, Duration::ZERO, Duration::ZERO));
+
+        // The storage host briefly fails before recognizing the client
+        transport.push_response(503, "");
+        transport.push_json(
+            200,
+            serde_json::json!({
+                "consumed_storage": 0,
+                "platform_id": "test",
+                "remaining_storage": 1024,
+            }),
+        );
+
+        client
+            .ensure_storage_access(&storage_host_url)
+            .await
+            .unwrap();
+        assert_eq!(transport.take_requests().len(), 2);
+
+        // Registration keeps failing until the attempts run out
+        let other_host_url = Url::parse("http://other-storage.invalid/").unwrap();
+        transport.push_response(401, "");
+        transport.push_json(200, serde_json::json!({ "api_version": "1.0.0" }));
+        transport.push_json(200, serde_json::json!({ "authorization_token": "grant" }));
+        transport.push_response(502, "");
+        transport.push_response(502, "");
+
+        let result = client.ensure_storage_access(&other_host_url).await;
+        assert!(matches!(
+            result,
+            Err(ApiError::StorageTokenError(
+                StorageTokenError::RegistrationFailed {
+                    step: RegistrationStep::Register,
+                    ..
+                }
+            ))
+        ));
+        assert_eq!(transport.remaining_responses(), 0);
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use reqwest::Url;
+
+use crate::codec::crypto::SigningKey;
+
+/// Chooses which [`SigningKey`] the [`crate::api::ApiClient`] authenticates with. Users that
+/// have different keys authorized for different drives can use a single client, authenticating
+/// with the platform using one key while minting the tokens for the storage host holding a
+/// particular drive's data with another.
+///
+/// Tokens are cached per audience, and for storage hosts per host, so the resolver should
+/// consistently return the same key for the same input. Every key that is returned needs to have
+/// been registered with the platform under the client's account.
+pub trait KeyResolver: Send + Sync {
+    /// The key used to authenticate requests to the platform.
+    fn platform_key(&self) -> Arc<SigningKey>;
+
+    /// The key used to register with and authenticate requests to the storage host at the
+    /// provided base URL. Defaults to the platform key.
+    fn storage_host_key(&self, _storage_host_url: &Url) -> Arc<SigningKey> {
+        self.platform_key()
+    }
+}
+
+/// The [`KeyResolver`] used when the client is created with a single key, that key is used to
+/// authenticate with the platform and every storage host.
+#[derive(Clone)]
+pub struct SingleKeyResolver(Arc<SigningKey>);
+
+impl SingleKeyResolver {
+    pub fn new(key: Arc<SigningKey>) -> Self {
+        Self(key)
+    }
+}
+
+impl KeyResolver for SingleKeyResolver {
+    fn platform_key(&self) -> Arc<SigningKey> {
+        self.0.clone()
+    }
+}
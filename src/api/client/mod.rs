@@ -2,34 +2,50 @@ mod api_auth;
 mod direct_response;
 mod error;
 mod expiring_token;
+mod key_resolver;
 mod platform_token;
+mod retry_policy;
 mod storage_host_auth;
 mod traits;
+mod transport;
 pub(crate) mod utils;
 
 pub use error::ApiClientError;
-pub use utils::VecStream;
+pub use key_resolver::{KeyResolver, SingleKeyResolver};
+pub use retry_policy::RetryPolicy;
+pub use storage_host_auth::{RegistrationStep, StorageTokenError};
+pub use transport::HttpTransport;
+#[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+pub use transport::MockTransport;
+pub use utils::{ChunkedVecStream, VecStream};
+
+pub(crate) use utils::{BandwidthLimit, UploadLimit};
 
 pub(crate) use api_auth::ApiAuth;
 pub(crate) use direct_response::DirectResponse;
 pub(crate) use expiring_token::ExpiringToken;
 pub(crate) use platform_token::{PlatformToken, PlatformTokenError};
-pub(crate) use storage_host_auth::{StorageHostAuth, StorageTokenError};
-pub(crate) use traits::{
-    ApiRequest, FromReqwestResponse, PlatformApiRequest, StorageHostApiRequest,
-};
+pub(crate) use storage_host_auth::StorageHostAuth;
+pub use traits::{ApiRequest, FromReqwestResponse, PlatformApiRequest, StorageHostApiRequest};
 
 pub(crate) const PLATFORM_AUDIENCE: &str = "banyan-platform";
 
 pub(crate) const STORAGE_HOST_AUDIENCE: &str = "banyan-storage";
 
+use std::ops::RangeInclusive;
 use std::sync::{Arc, OnceLock};
 
 use reqwest::{Client, StatusCode, Url};
 use serde::Deserialize;
 use tracing::debug;
 
+use crate::api::platform::ApiVersion;
 use crate::codec::crypto::{SigningKey, VerifyingKey};
+use crate::filesystem::{DriveAccessError, DriveEncodeError, DriveLoaderError};
+
+/// The major versions of the platform API this release of the library is able to communicate
+/// with. Newer minor versions within a supported major version are expected to remain compatible.
+pub const SUPPORTED_PLATFORM_API_MAJOR_VERSIONS: RangeInclusive<u32> = 1..=1;
 
 /// An HTTP client for interacting with the Banyan API (both platform and storage hosts). Specific
 /// requests can be found the in appropriate module for their request type either
@@ -41,9 +57,18 @@ use crate::codec::crypto::{SigningKey, VerifyingKey};
 #[derive(Clone)]
 pub struct ApiClient {
     auth: ApiAuth,
+    bandwidth_limit: BandwidthLimit,
     base_url: Url,
     client: Client,
+    transport: Arc<dyn HttpTransport>,
     platform_pubkey: OnceLock<VerifyingKey>,
+
+    /// The API version the platform reported the first time it was contacted, `None` if the
+    /// platform didn't report one.
+    platform_version: OnceLock<Option<ApiVersion>>,
+
+    registration_retry_policy: RetryPolicy,
+    upload_limit: UploadLimit,
 }
 
 impl ApiClient {
@@ -55,19 +80,90 @@ impl ApiClient {
         base_url: &str,
         account_id: &str,
         key: Arc<SigningKey>,
+    ) -> Result<Self, ApiClientError> {
+        Self::new_with_claims(base_url, account_id, key, serde_json::Map::new())
+    }
+
+    /// Create a new instance that includes additional claims in every authentication token it
+    /// generates. This is intended for customized platform deployments that require extra claims
+    /// (such as a tenant identifier). The standard claims (`aud`, `exp`, `iat`, `nbf`, `nonce`,
+    /// and `sub`) can not be overridden and any attempt to do so will be ignored.
+    pub fn new_with_claims(
+        base_url: &str,
+        account_id: &str,
+        key: Arc<SigningKey>,
+        extra_claims: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self, ApiClientError> {
+        Self::new_with_key_resolver(
+            base_url,
+            account_id,
+            SingleKeyResolver::new(key),
+            extra_claims,
+        )
+    }
+
+    /// Create a new instance that selects the key used for each audience with the provided
+    /// [`KeyResolver`] rather than using a single key for everything. This allows one client to
+    /// authenticate with the platform using one key while authenticating with storage hosts using
+    /// the keys authorized on the drives they hold. Extra claims behave the same as in
+    /// [`ApiClient::new_with_claims`].
+    pub fn new_with_key_resolver(
+        base_url: &str,
+        account_id: &str,
+        key_resolver: impl KeyResolver + 'static,
+        extra_claims: serde_json::Map<String, serde_json::Value>,
     ) -> Result<Self, ApiClientError> {
         let base_url = Url::parse(base_url)?;
-        let auth = ApiAuth::new(account_id, key);
+        let auth = ApiAuth::new(account_id, Arc::new(key_resolver), extra_claims);
         let client = default_reqwest_client()?;
 
         Ok(Self {
             auth,
+            bandwidth_limit: BandwidthLimit::default(),
             base_url,
+            transport: Arc::new(client.clone()),
             client,
             platform_pubkey: OnceLock::default(),
+            platform_version: OnceLock::default(),
+            registration_retry_policy: RetryPolicy::default(),
+            upload_limit: UploadLimit::default(),
         })
     }
 
+    /// The maximum rate, in bytes per second, blocks are transferred to and from storage hosts.
+    /// `None` when transfers are not limited.
+    pub fn bandwidth_limit(&self) -> Option<u64> {
+        self.bandwidth_limit.get()
+    }
+
+    pub(crate) fn bandwidth_limiter(&self) -> &BandwidthLimit {
+        &self.bandwidth_limit
+    }
+
+    /// Stops every block and metadata upload currently being read by this client (or any of its
+    /// clones), they fail with [`ApiError::UploadCancelled`] before anything more is read from
+    /// their bodies. Uploads started after this call are not affected.
+    pub fn cancel_uploads(&self) {
+        self.upload_limit.cancel();
+    }
+
+    /// Changes how the requests made while authenticating with a storage host (checking whether
+    /// the host knows the client, retrieving a storage grant from the platform, and registering
+    /// that grant with the host) are retried when they fail for temporary reasons. Data transfers
+    /// are never retried by this policy. Defaults to [`RetryPolicy::default`].
+    pub fn with_registration_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.registration_retry_policy = policy;
+        self
+    }
+
+    /// Replaces how the client sends its requests, every request (including those made by clones
+    /// of the returned client) will be handed to the provided [`HttpTransport`] once it has been
+    /// assembled. Allows the API layer to be exercised without a network connection.
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
     /// Returns the configured base URL for the API client. If you wish to change this you should
     /// create a new [`ApiClient`] instance the desired base URL.
     pub fn base_url(&self) -> Url {
@@ -88,6 +184,45 @@ impl ApiClient {
         &self,
         base_url: &Url,
         bearer_token: &str,
+        request: R,
+    ) -> Result<Option<R::Response>, ApiError> {
+        self.send_request(base_url, Some(bearer_token), request)
+            .await
+    }
+
+    /// Sends a request authenticated with the provided bearer token instead of one generated by
+    /// the client, for use with tokens obtained through other means such as delegated or
+    /// capability tokens. The token is sent as is, it isn't checked or refreshed and rejections
+    /// don't affect the authentication the client manages itself. The platform's API version
+    /// also isn't checked before the request is sent.
+    ///
+    /// Platform requests should be sent to [`ApiClient::base_url`], storage host requests to the
+    /// URL of the relevant storage host.
+    pub async fn request_with_token<R: ApiRequest>(
+        &self,
+        base_url: &Url,
+        bearer_token: &str,
+        request: R,
+    ) -> Result<Option<R::Response>, ApiError> {
+        self.send_request(base_url, Some(bearer_token), request)
+            .await
+    }
+
+    /// Behaves the same as [`ApiClient::request`] but doesn't include any authentication with the
+    /// request. This is only appropriate for the handful of endpoints that are intentionally
+    /// public, such as health checks.
+    pub(crate) async fn unauthenticated_request<R: ApiRequest>(
+        &self,
+        base_url: &Url,
+        request: R,
+    ) -> Result<Option<R::Response>, ApiError> {
+        self.send_request(base_url, None, request).await
+    }
+
+    async fn send_request<R: ApiRequest>(
+        &self,
+        base_url: &Url,
+        bearer_token: Option<&str>,
         mut request: R,
     ) -> Result<Option<R::Response>, ApiError> {
         debug!(method = %R::METHOD, %base_url, url = %request.path(), "request");
@@ -95,12 +230,20 @@ impl ApiClient {
         let full_url = base_url.join(&request.path())?;
         let mut request_builder = self.client.request(R::METHOD, full_url);
 
-        request_builder = request_builder.bearer_auth(bearer_token);
+        if let Some(bearer_token) = bearer_token {
+            request_builder = request_builder.bearer_auth(bearer_token);
+        }
         request_builder = request.add_payload(request_builder).await?;
 
-        let response = request_builder.send().await?;
+        let response = self.transport.execute(request_builder.build()?).await?;
         let status = response.status();
 
+        let server_date = response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .map(String::from);
+
         debug!(response_status = ?status, "platform_request_response");
 
         if status.is_success() {
@@ -109,6 +252,16 @@ impl ApiClient {
             let resp_bytes = response.bytes().await?;
 
             if status == StatusCode::UNAUTHORIZED {
+                // Tokens from a client with a skewed clock are always rejected, this is reported
+                // separately as it can't be resolved through authorization
+                let clock_offset = self.auth.clock_offset();
+                if let Some(skew_seconds) =
+                    server_date.and_then(|date| utils::clock_skew(&date, clock_offset))
+                {
+                    tracing::warn!(skew_seconds, "request was rejected due to clock skew");
+                    return Err(ApiError::ClockSkew { skew_seconds });
+                }
+
                 return Err(ApiError::NotAuthorized);
             }
 
@@ -129,13 +282,87 @@ impl ApiClient {
         }
     }
 
+    /// Shifts the times used in the authentication tokens this client generates by an additional
+    /// number of seconds, compensating for a local clock that disagrees with the servers. Skew
+    /// reported by [`ApiError::ClockSkew`] is measured against the compensated time, so its
+    /// `skew_seconds` can always be provided here directly. This applies to every clone of the
+    /// client and any tokens that were already generated are discarded.
+    pub async fn compensate_clock_skew(&self, skew_seconds: i64) {
+        let clock_offset = self.auth.clock_offset().saturating_add(skew_seconds);
+        self.auth.set_clock_offset(clock_offset).await;
+    }
+
+    /// Makes sure the storage host is ready to accept requests from this client. The storage
+    /// host is asked whether it recognizes the client and when it doesn't, a storage grant is
+    /// retrieved from the platform and registered with the storage host. Once a storage host has
+    /// been confirmed the result is remembered and later calls return immediately.
+    ///
+    /// Requests to storage hosts go through the same process on their own as needed, but failures
+    /// there only surface as the request itself being rejected. Calling this first allows those
+    /// failures to be handled separately, such as before starting a large upload.
+    pub async fn ensure_storage_access(&self, storage_host_url: &Url) -> Result<(), ApiError> {
+        self.auth
+            .ensure_storage_access(self, storage_host_url)
+            .await
+    }
+
+    /// The largest body, in bytes, that will be read for a single block or metadata upload. `None`
+    /// when uploads are not limited.
+    pub fn max_upload_size(&self) -> Option<u64> {
+        self.upload_limit.get()
+    }
+
+    pub(crate) fn upload_limiter(&self) -> &UploadLimit {
+        &self.upload_limit
+    }
+
+    /// Limits the rate, in bytes per second, blocks are uploaded to and downloaded from storage
+    /// hosts. The limit is shared by every clone of this client and takes effect immediately,
+    /// including for transfers that are already in progress, so it can be adjusted while a sync is
+    /// running. Passing `None` (or zero) removes the limit.
+    ///
+    /// Block uploads are assembled in memory before being sent, the limit is applied while each
+    /// block is read so the rate is enforced on average across a sync rather than within a single
+    /// block.
+    pub fn set_bandwidth_limit(&self, bytes_per_second: Option<u64>) {
+        self.bandwidth_limit.set(bytes_per_second);
+    }
+
+    /// Limits the size, in bytes, of the body of any single block or metadata upload. Upload
+    /// bodies are currently assembled in memory before being sent, this bounds how much memory an
+    /// individual upload can consume. Uploads exceeding the limit fail with
+    /// [`ApiError::UploadTooLarge`] as soon as it is crossed. The limit is shared by every clone of
+    /// this client, passing `None` (or zero) removes it.
+    pub fn set_max_upload_size(&self, max_bytes: Option<u64>) {
+        self.upload_limit.set(max_bytes);
+    }
+
     /// Perform a request to the platform API. This is more restrictive than the
     /// [`ApiClient::request`] method, limiting the request to only those that are explicitly
     /// implementing the marker trait [`PlatformApiRequest`] but will handle the authentication for
     /// you.
+    ///
+    /// The first platform request made by the client will also confirm the platform is serving a
+    /// version of the API this library supports, see [`ApiClient::platform_api_version`].
     pub(crate) async fn platform_request<R: PlatformApiRequest>(
         &self,
         request: R,
+    ) -> Result<Option<R::Response>, ApiError> {
+        if let Some(version) = self.platform_api_version().await? {
+            if !SUPPORTED_PLATFORM_API_MAJOR_VERSIONS.contains(&version.major()) {
+                return Err(ApiError::VersionMismatch(version));
+            }
+        }
+
+        self.platform_request_unchecked(request).await
+    }
+
+    /// Behaves the same as [`ApiClient::platform_request`] without confirming the platform's API
+    /// version is supported first. This is only intended for the requests that are needed to
+    /// perform that check.
+    pub(crate) async fn platform_request_unchecked<R: PlatformApiRequest>(
+        &self,
+        request: R,
     ) -> Result<Option<R::Response>, ApiError> {
         // Send authentication if its available even if the request is not marked as requiring it
         let token = self.auth.platform_token().await?;
@@ -174,6 +401,46 @@ impl ApiClient {
         }
     }
 
+    /// Reports the version of the API the platform is serving. The platform is only queried until
+    /// it has given a definite answer, which is then cached for the lifetime of the client.
+    ///
+    /// Older platform releases don't have a version endpoint. When the platform reports the
+    /// endpoint doesn't exist a warning is logged once and `None` is returned, no compatibility
+    /// checks can be performed against these platforms. Any other failure is returned without
+    /// being cached so the version is queried again on the next request.
+    pub async fn platform_api_version(&self) -> Result<Option<ApiVersion>, ApiError> {
+        if let Some(version) = self.platform_version.get() {
+            return Ok(*version);
+        }
+
+        let version = match crate::api::platform::status::get_api_version(self).await {
+            Ok(version) => {
+                if !SUPPORTED_PLATFORM_API_MAJOR_VERSIONS.contains(&version.major()) {
+                    tracing::warn!(
+                        %version,
+                        supported = ?SUPPORTED_PLATFORM_API_MAJOR_VERSIONS,
+                        "platform API version is not supported by this client, requests will be rejected"
+                    );
+                }
+
+                Some(version)
+            }
+            Err(ApiError::Message {
+                status_code: 404, ..
+            }) => {
+                tracing::warn!(
+                    "platform doesn't report its API version, API compatibility can't be confirmed"
+                );
+                None
+            }
+            Err(err) => return Err(err),
+        };
+
+        let _ = self.platform_version.set(version);
+
+        Ok(version)
+    }
+
     pub async fn platform_public_key(&self) -> Result<VerifyingKey, ApiError> {
         if let Some(pubkey) = self.platform_pubkey.get() {
             return Ok(pubkey.clone());
@@ -193,14 +460,25 @@ impl ApiClient {
             .await;
     }
 
-    /// Provides direct access to the internal authentication's signing key that the API client was
-    /// initialized with. This isn't really ideal and should be avoided. We'll be refactoring this
-    /// out in the future. This isn't a problem but it is a smell that I don't like around
-    /// sensitive material.
+    pub(crate) fn registration_retry_policy(&self) -> &RetryPolicy {
+        &self.registration_retry_policy
+    }
+
+    /// Provides direct access to the internal authentication's signing key that the API client
+    /// authenticates with the platform using. This isn't really ideal and should be avoided. We'll
+    /// be refactoring this out in the future. This isn't a problem but it is a smell that I don't
+    /// like around sensitive material.
+    #[cfg(all(target_arch = "wasm32", feature = "tomb-compat"))]
     pub(crate) fn signing_key(&self) -> Arc<SigningKey> {
         self.auth.signing_key()
     }
 
+    /// The key the client authenticates with the storage host using, as selected by the client's
+    /// [`KeyResolver`].
+    pub(crate) fn storage_host_signing_key(&self, storage_host_url: &Url) -> Arc<SigningKey> {
+        self.auth.storage_host_key(storage_host_url)
+    }
+
     pub(crate) async fn storage_host_request<R: StorageHostApiRequest>(
         &self,
         storage_host_url: &Url,
@@ -270,6 +548,30 @@ pub enum ApiError {
     #[error("network client experienced issue: {0}")]
     ClientError(#[from] reqwest::Error),
 
+    /// The server rejected the request's authentication and the time it reported differs from
+    /// the local clock by more than the tokens tolerate. The `skew_seconds` is how far the server
+    /// is ahead of the local clock (negative when behind). Correcting the system clock resolves
+    /// this, alternatively tokens can be adjusted with [`ApiClient::compensate_clock_skew`].
+    #[error("the local clock differs from the server by {skew_seconds} seconds, authentication tokens will be rejected until it is corrected")]
+    ClockSkew { skew_seconds: i64 },
+
+    /// The drive was loaded but the key being used isn't currently authorized to read it, such as
+    /// when its access has been revoked. Refer to the specific [`DriveAccessError`] for the cause.
+    #[error("key is not authorized to access the drive: {0}")]
+    DriveAccessDenied(#[from] DriveAccessError),
+
+    /// The drive couldn't be encoded to be pushed to the platform, such as when the key being
+    /// used doesn't hold the keys needed to encode it. Refer to the specific
+    /// [`DriveEncodeError`] for the cause.
+    #[error("failed to encode drive: {0}")]
+    DriveEncodeFailed(#[from] DriveEncodeError),
+
+    /// Drive metadata retrieved from the platform could not be loaded. This most commonly occurs
+    /// when the key being used hasn't been granted access to the drive, refer to the specific
+    /// [`DriveLoaderError`] for the cause.
+    #[error("failed to load drive: {0}")]
+    DriveLoadFailed(#[from] DriveLoaderError),
+
     /// The server (could come from either the platform or a storage host) reported that the user
     /// doesn't have enough capacity to complete request. For requests to the platform this is
     /// frequently a result of the account limit and the intended amount of data to store at a
@@ -314,6 +616,14 @@ pub enum ApiError {
     #[error("the client was not authorized to make the request")]
     NotAuthorized,
 
+    /// The drive exists on the platform but no metadata version has been pushed for it yet. This
+    /// is expected for freshly created drives, clients should treat the drive as empty rather than
+    /// as a failure. An empty [`crate::filesystem::Drive`] that will be accepted as the drive's
+    /// first version can be created with [`crate::filesystem::Drive::initialize_private_with_id`]
+    /// using the ID from [`crate::api::platform::drives::filesystem_id`].
+    #[error("drive {0} does not have any metadata versions yet")]
+    NoMetadataVersion(String),
+
     /// While generating a JWT to authenticate a request for the platform, an operation failed.
     /// This is a highly unlikely error as its primarily wrapping operations that shouldn't fail as
     /// long as the arguments are correct. You'll need to refer to the specific error case reported
@@ -321,6 +631,12 @@ pub enum ApiError {
     #[error("failed to generate token for platform platform: {0}")]
     PlatformTokenError(#[from] PlatformTokenError),
 
+    /// A transfer was interrupted and needed to be resumed part way through, but the server
+    /// responded with the full content instead of the requested range. The transfer can only be
+    /// restarted from the beginning.
+    #[error("the server does not support resuming transfers with ranged requests")]
+    RangeNotSupported,
+
     /// This is a very specific error case. This library streams its large data uploads by
     /// consuming an asynchronous stream of bytes. When that stream has be consumed by an attempted
     /// upload, and the same request object is attempted to be re-used you'll get this error. The
@@ -356,6 +672,24 @@ pub enum ApiError {
     #[error("unexpected API response: {0}")]
     UnexpectedResponse(&'static str),
 
+    /// The upload was stopped with [`ApiClient::cancel_uploads`] before its body had been
+    /// completely read. Nothing was sent for the cancelled request.
+    #[error("the upload was cancelled")]
+    UploadCancelled,
+
+    /// The body of an upload was larger than the limit set with
+    /// [`ApiClient::set_max_upload_size`]. Reading stopped once the limit was crossed and nothing
+    /// was sent for the request.
+    #[error("upload body exceeded the maximum size of {limit} bytes")]
+    UploadTooLarge { limit: u64 },
+
+    /// The platform reported a version of its API that this release of the library doesn't
+    /// support (see [`SUPPORTED_PLATFORM_API_MAJOR_VERSIONS`]). Rather than failing in an opaque
+    /// way while (de)serializing requests, every platform request is rejected with this error.
+    /// Updating the library is the most likely fix.
+    #[error("platform API version {0} is not supported by this client")]
+    VersionMismatch(ApiVersion),
+
     /// A WASM specific error that likely occurred due to a browser API inconsistency. These are
     /// only present in a few specific operations so the internal cause should reveal more about
     /// the error itself.
@@ -364,6 +698,18 @@ pub enum ApiError {
     WasmInternal(String),
 }
 
+impl ApiError {
+    /// Whether the error is likely to be temporary, such that repeating the same request later
+    /// could succeed. This covers network failures, rate limiting, and server side errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::ClientError(err) => !err.is_builder() && !err.is_decode(),
+            Self::Message { status_code, .. } => *status_code == 429 || *status_code >= 500,
+            _ => false,
+        }
+    }
+}
+
 /// This is the inner error type that the API will always return. We don't return this directly as
 /// we want to include the status code as well. Will always become a [`ApiError::Message`].
 #[derive(Deserialize)]
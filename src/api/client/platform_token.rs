@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use async_std::sync::RwLock;
+use serde_json::{Map, Value};
 
 use crate::api::client::utils::create_jwt;
 use crate::api::client::{ExpiringToken, PLATFORM_AUDIENCE};
@@ -10,10 +11,17 @@ use crate::codec::crypto::SigningKey;
 pub(crate) struct PlatformToken(Arc<RwLock<Option<ExpiringToken>>>);
 
 impl PlatformToken {
+    /// Discards the cached token, the next request will generate a new one.
+    pub(crate) async fn clear(&self) {
+        *self.0.write().await = None;
+    }
+
     pub(crate) async fn get_token(
         &self,
         id: &str,
         key: &Arc<SigningKey>,
+        extra_claims: &Map<String, Value>,
+        clock_offset: i64,
     ) -> Result<String, PlatformTokenError> {
         // If we already have token and it's not expired, return it
         if let Some(expiring_token) = &*self.0.read().await {
@@ -23,7 +31,14 @@ impl PlatformToken {
         }
 
         let mut rng = crate::utils::crypto_rng();
-        let (token, expiration) = create_jwt(&mut rng, id, PLATFORM_AUDIENCE, key);
+        let (token, expiration) = create_jwt(
+            &mut rng,
+            id,
+            PLATFORM_AUDIENCE,
+            key,
+            extra_claims,
+            clock_offset,
+        );
 
         tracing::debug!("generated new platform token");
 
@@ -0,0 +1,118 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::api::client::ApiError;
+
+/// Controls how requests that failed for reasons that are likely to be temporary, such as network
+/// errors or the server being briefly unavailable, are retried. Each retry waits a random amount
+/// of time up to a delay that doubles with every attempt (capped at `max_delay`), spreading out
+/// retries from many clients that failed at the same time.
+///
+/// This is currently only applied to the requests involved in authenticating with a storage host,
+/// see [`crate::api::ApiClient::with_registration_retry_policy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Computes how long to wait before the provided retry (zero being the first retry).
+    fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+
+        let ceiling_ms = ceiling.as_millis() as u64;
+        if ceiling_ms == 0 {
+            return Duration::ZERO;
+        }
+
+        let mut rng = crate::utils::crypto_rng();
+        Duration::from_millis(rng.gen_range(0..=ceiling_ms))
+    }
+
+    /// The total number of times a request will be attempted, including the first attempt.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Creates a policy attempting requests up to `max_attempts` times in total. A policy making
+    /// fewer than one attempt would never make the request, so it is always attempted at least
+    /// once.
+    pub fn new(max_attempts: u32, initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+            max_delay,
+        }
+    }
+
+    /// A policy that never retries, every failure is reported immediately.
+    pub fn no_retries() -> Self {
+        Self::new(1, Duration::ZERO, Duration::ZERO)
+    }
+
+    /// Runs the operation until it succeeds, fails with an error that isn't temporary (see
+    /// [`ApiError::is_transient`]), or the attempts run out. The error of the last attempt is
+    /// returned.
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        step: impl std::fmt::Display,
+        mut operation: F,
+    ) -> Result<T, ApiError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ApiError>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match operation().await {
+                Err(err) if err.is_transient() && attempt < self.max_attempts => {
+                    let delay = self.delay(attempt - 1);
+                    tracing::warn!(%step, attempt, ?delay, "retrying after temporary failure: {err}");
+
+                    if !delay.is_zero() {
+                        async_std::task::sleep(delay).await;
+                    }
+
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(250), Duration::from_secs(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delays_are_bounded() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::from_millis(300));
+
+        for _ in 0..50 {
+            assert!(policy.delay(0) <= Duration::from_millis(100));
+            assert!(policy.delay(1) <= Duration::from_millis(200));
+            assert!(policy.delay(4) <= Duration::from_millis(300));
+        }
+
+        assert_eq!(RetryPolicy::no_retries().delay(3), Duration::ZERO);
+        assert_eq!(
+            RetryPolicy::new(0, Duration::ZERO, Duration::ZERO).max_attempts(),
+            1
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use reqwest::Url;
+use serde_json::{Map, Value};
 
 use crate::api::client::utils::create_jwt;
 use crate::api::client::{ApiClient, ApiError, ExpiringToken, STORAGE_HOST_AUDIENCE};
@@ -24,6 +25,74 @@ impl StorageHostAuth {
         self.authenticated_storage_hosts.remove(storage_host_url);
     }
 
+    /// Discards every cached token, new ones will be generated as they're needed. The storage
+    /// hosts the client has authenticated with are still remembered.
+    pub(crate) fn clear_tokens(&mut self) {
+        self.active_tokens.clear();
+    }
+
+    /// Confirms the storage host recognizes the client, registering a storage grant retrieved
+    /// from the platform when it doesn't. This follows the same process [`Self::get_token`] goes
+    /// through when it first encounters a storage host, but every failure along the way is
+    /// reported instead of being left for the following request to run into. Storage hosts that
+    /// were already confirmed aren't contacted again.
+    pub(crate) async fn ensure_access(
+        &mut self,
+        client: &ApiClient,
+        storage_host_url: &Url,
+        account_id: &str,
+        key: &Arc<SigningKey>,
+        extra_claims: &Map<String, Value>,
+        clock_offset: i64,
+    ) -> Result<(), ApiError> {
+        // A recorded grant may be all that is needed, if it fails to register we still check
+        // whether the host knows us below
+        if let Some(grant) = self.pending_grants.remove(storage_host_url) {
+            match register_with_retries(client, storage_host_url, &grant).await {
+                Ok(_) => {
+                    self.authenticated_storage_hosts
+                        .insert(storage_host_url.clone());
+                }
+                Err(err) => {
+                    tracing::warn!(%storage_host_url, "failed to register pending grant with storage host: {err}");
+                }
+            }
+        }
+
+        if self.authenticated_storage_hosts.contains(storage_host_url) {
+            return Ok(());
+        }
+
+        let token = match self.current_token(storage_host_url) {
+            Some(token) => token,
+            None => self.generate_token(
+                storage_host_url,
+                account_id,
+                key,
+                extra_claims,
+                clock_offset,
+            )?,
+        };
+
+        match who_am_i_with_retries(client, storage_host_url, &token).await {
+            Ok(_) => {}
+            Err(StorageTokenError::RegistrationFailed { source, .. })
+                if matches!(*source, ApiError::NotAuthorized) =>
+            {
+                tracing::debug!(%storage_host_url, "registering storage grant with storage host");
+
+                let grant = fetch_grant_with_retries(client, storage_host_url).await?;
+                register_with_retries(client, storage_host_url, &grant).await?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        self.authenticated_storage_hosts
+            .insert(storage_host_url.clone());
+
+        Ok(())
+    }
+
     /// Attempts to retrieve a current Bearer token if one is available. If there is a token and it's
     /// expired or the storage host is unknown, this will return None.
     fn current_token(&self, storage_host_url: &Url) -> Option<String> {
@@ -37,9 +106,18 @@ impl StorageHostAuth {
         storage_host_url: &Url,
         account_id: &str,
         key: &Arc<SigningKey>,
+        extra_claims: &Map<String, Value>,
+        clock_offset: i64,
     ) -> Result<String, StorageTokenError> {
         let mut rng = crate::utils::crypto_rng();
-        let (token, expiration) = create_jwt(&mut rng, account_id, STORAGE_HOST_AUDIENCE, key);
+        let (token, expiration) = create_jwt(
+            &mut rng,
+            account_id,
+            STORAGE_HOST_AUDIENCE,
+            key,
+            extra_claims,
+            clock_offset,
+        );
 
         self.active_tokens.insert(
             storage_host_url.clone(),
@@ -57,13 +135,15 @@ impl StorageHostAuth {
         storage_host_url: &Url,
         account_id: &str,
         key: &Arc<SigningKey>,
+        extra_claims: &Map<String, Value>,
+        clock_offset: i64,
     ) -> Result<String, StorageTokenError> {
         // Check if we have any pending grants for the storage host
         // - If so attempt to register it with the storage host and clear it locally
         // - If it succeeds mark the host as authenticated
         // - Continue on failure
         if let Some(grant) = self.pending_grants.remove(storage_host_url) {
-            if let Err(err) = register_grant(client, storage_host_url, &grant).await {
+            if let Err(err) = register_with_retries(client, storage_host_url, &grant).await {
                 tracing::warn!(
                     "failed to register pending grant with storage host: {}",
                     err
@@ -82,7 +162,13 @@ impl StorageHostAuth {
             // Since we know we're authenticated with the host we just create a new token and use
             // that. If we loose our authentication or get a not authorized we're removed from this
             // list and will do the extended authentication.
-            return self.generate_token(storage_host_url, account_id, key);
+            return self.generate_token(
+                storage_host_url,
+                account_id,
+                key,
+                extra_claims,
+                clock_offset,
+            );
         }
 
         // We're not explicitly aware that we're authenticated, but our key might have interacted
@@ -91,34 +177,38 @@ impl StorageHostAuth {
         // become valid afterwards so we mind as well cache it.
         let new_token = match self.current_token(storage_host_url) {
             Some(t) => t,
-            None => self.generate_token(storage_host_url, account_id, key)?,
+            None => self.generate_token(
+                storage_host_url,
+                account_id,
+                key,
+                extra_claims,
+                clock_offset,
+            )?,
         };
 
         // Perform a who_am_i request against it
         // - On success add it to the authenticated storage hosts set, generate, cache, and return a token
         // - On not authorized, request an updated grant from the platform and register it with
         //   the storage host
-        match who_am_i(client, storage_host_url, &new_token).await {
+        match who_am_i_with_retries(client, storage_host_url, &new_token).await {
             Ok(_) => {
                 self.authenticated_storage_hosts
                     .insert(storage_host_url.clone());
                 return Ok(new_token);
             }
-            Err(ApiError::NotAuthorized) => {
+            Err(StorageTokenError::RegistrationFailed { source, .. })
+                if matches!(*source, ApiError::NotAuthorized) =>
+            {
                 // We're not currently authorized we need to get a grant from the platform and
                 // attempt to register it.
-                match get_storage_grant(client, storage_host_url.clone()).await {
+                match fetch_grant_with_retries(client, storage_host_url).await {
                     Ok(grant) => {
-                        self.register_grant(
-                            client,
-                            storage_host_url.clone(),
-                            grant.authorization_token(),
-                        )
-                        .await;
+                        self.register_grant(client, storage_host_url.clone(), &grant)
+                            .await;
                     }
                     Err(err) => {
-                        tracing::error!("failed to retrieve storage grant from platform: {}", err);
-                        return Err(StorageTokenError::PlatformGrant);
+                        tracing::error!("{err}");
+                        return Err(err);
                     }
                 }
             }
@@ -127,7 +217,7 @@ impl StorageHostAuth {
                     "unexpected error attempted to check authentication status of storage host: {}",
                     err
                 );
-                return Err(StorageTokenError::StorageHostApi);
+                return Err(err);
             }
         }
 
@@ -146,7 +236,7 @@ impl StorageHostAuth {
     /// log the result but in all cases we attempt to continue with the authentication process as
     /// the update may not be needed to proceed so failures are not tracked.
     async fn register_grant(&mut self, client: &ApiClient, storage_host_url: Url, grant: &str) {
-        match register_grant(client, &storage_host_url, grant).await {
+        match register_with_retries(client, &storage_host_url, grant).await {
             Ok(_) => {
                 self.authenticated_storage_hosts.insert(storage_host_url);
             }
@@ -157,11 +247,101 @@ impl StorageHostAuth {
     }
 }
 
+/// Retrieves a storage grant for the storage host from the platform, retrying temporary failures
+/// according to the client's registration [`crate::api::RetryPolicy`].
+async fn fetch_grant_with_retries(
+    client: &ApiClient,
+    storage_host_url: &Url,
+) -> Result<String, StorageTokenError> {
+    let step = RegistrationStep::GrantFetch;
+
+    let grant = client
+        .registration_retry_policy()
+        .run(step, || get_storage_grant(client, storage_host_url.clone()))
+        .await
+        .map_err(|err| StorageTokenError::failed(step, err))?;
+
+    Ok(grant.authorization_token().to_string())
+}
+
+/// Registers a storage grant with the storage host, retrying temporary failures according to the
+/// client's registration [`crate::api::RetryPolicy`].
+async fn register_with_retries(
+    client: &ApiClient,
+    storage_host_url: &Url,
+    grant: &str,
+) -> Result<(), StorageTokenError> {
+    let step = RegistrationStep::Register;
+
+    client
+        .registration_retry_policy()
+        .run(step, || register_grant(client, storage_host_url, grant))
+        .await
+        .map_err(|err| StorageTokenError::failed(step, err))
+}
+
+/// Checks whether the storage host recognizes the token, retrying temporary failures according
+/// to the client's registration [`crate::api::RetryPolicy`]. A host that doesn't recognize the
+/// token reports [`ApiError::NotAuthorized`] as the source of the error, which is never retried.
+async fn who_am_i_with_retries(
+    client: &ApiClient,
+    storage_host_url: &Url,
+    token: &str,
+) -> Result<(), StorageTokenError> {
+    let step = RegistrationStep::WhoAmI;
+
+    client
+        .registration_retry_policy()
+        .run(step, || who_am_i(client, storage_host_url, token))
+        .await
+        .map(|_| ())
+        .map_err(|err| StorageTokenError::failed(step, err))
+}
+
+/// The requests made while authenticating with a storage host, used to report which of them
+/// failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationStep {
+    /// Checking whether the storage host already recognizes the client's key.
+    WhoAmI,
+
+    /// Retrieving a storage grant for the storage host from the platform.
+    GrantFetch,
+
+    /// Registering a storage grant with the storage host.
+    Register,
+}
+
+impl std::fmt::Display for RegistrationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let step = match self {
+            Self::WhoAmI => "who_am_i",
+            Self::GrantFetch => "storage grant fetch",
+            Self::Register => "storage grant registration",
+        };
+
+        f.write_str(step)
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum StorageTokenError {
-    #[error("failed to retrieve storage grant from platform")]
-    PlatformGrant,
+    /// One of the requests needed to authenticate with the storage host failed, after any retries
+    /// allowed by the client's registration [`crate::api::RetryPolicy`]. The `source` is the
+    /// error from the final attempt.
+    #[error("storage host authentication failed during {step}: {source}")]
+    RegistrationFailed {
+        step: RegistrationStep,
+        #[source]
+        source: Box<ApiError>,
+    },
+}
 
-    #[error("failed to check authentication against storage host")]
-    StorageHostApi,
+impl StorageTokenError {
+    fn failed(step: RegistrationStep, err: ApiError) -> Self {
+        Self::RegistrationFailed {
+            step,
+            source: Box::new(err),
+        }
+    }
 }
@@ -4,8 +4,11 @@ use serde::de::DeserializeOwned;
 
 use crate::api::client::ApiError;
 
+/// A single request to one of the Banyan APIs. Implementors describe where the request goes and
+/// what it carries, the [`crate::api::ApiClient`] takes care of sending it and parsing the
+/// response.
 #[async_trait(?Send)]
-pub(crate) trait ApiRequest {
+pub trait ApiRequest {
     type Response: FromReqwestResponse;
 
     const METHOD: Method = Method::GET;
@@ -20,8 +23,10 @@ pub(crate) trait ApiRequest {
     fn path(&self) -> String;
 }
 
+/// Produces a value from a successful response. This is implemented for every type that can be
+/// deserialized from JSON, responding with no content produces `None`.
 #[async_trait(?Send)]
-pub(crate) trait FromReqwestResponse: Sized {
+pub trait FromReqwestResponse: Sized {
     async fn from_response(response: Response) -> Result<Option<Self>, ApiError>;
 }
 
@@ -50,6 +55,8 @@ where
     }
 }
 
-pub(crate) trait PlatformApiRequest: ApiRequest {}
+/// Marks requests intended for the platform API.
+pub trait PlatformApiRequest: ApiRequest {}
 
-pub(crate) trait StorageHostApiRequest: ApiRequest {}
+/// Marks requests intended for the API of a storage host.
+pub trait StorageHostApiRequest: ApiRequest {}
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{Request, Response, StatusCode};
+
+use crate::api::client::{ApiError, HttpTransport};
+
+/// An [`HttpTransport`] that never touches the network. Responses are queued up ahead of time and
+/// returned in order, one for each request made. Every request received is recorded so tests can
+/// confirm how it was constructed, including the URL, headers, and authentication.
+///
+/// Requests made once the queued responses have run out fail with
+/// [`ApiError::UnexpectedResponse`].
+#[derive(Default)]
+pub struct MockTransport {
+    responses: Mutex<VecDeque<(StatusCode, Bytes)>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MockTransport {
+    /// Queues a JSON response with the provided status code.
+    pub fn push_json(&self, status: u16, body: serde_json::Value) {
+        self.push_response(status, body.to_string());
+    }
+
+    /// Queues a response with the provided status code and raw body.
+    pub fn push_response(&self, status: u16, body: impl Into<Bytes>) {
+        let status = StatusCode::from_u16(status).expect("valid status code");
+        self.responses
+            .lock()
+            .expect("mock lock poisoned")
+            .push_back((status, body.into()));
+    }
+
+    /// The number of queued responses that haven't been returned yet.
+    pub fn remaining_responses(&self) -> usize {
+        self.responses.lock().expect("mock lock poisoned").len()
+    }
+
+    /// Removes and returns every request received so far in the order they were made.
+    pub fn take_requests(&self) -> Vec<Request> {
+        std::mem::take(&mut *self.requests.lock().expect("mock lock poisoned"))
+    }
+}
+
+#[async_trait(?Send)]
+impl HttpTransport for MockTransport {
+    async fn execute(&self, request: Request) -> Result<Response, ApiError> {
+        self.requests
+            .lock()
+            .expect("mock lock poisoned")
+            .push(request);
+
+        let (status, body) = self
+            .responses
+            .lock()
+            .expect("mock lock poisoned")
+            .pop_front()
+            .ok_or(ApiError::UnexpectedResponse(
+                "mock transport has no responses queued",
+            ))?;
+
+        let response = http::Response::builder()
+            .status(status)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .expect("valid mock response");
+
+        Ok(Response::from(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use reqwest::Url;
+
+    use crate::api::client::{ApiClient, ApiRequest};
+    use crate::codec::crypto::SigningKey;
+
+    struct ExampleRequest;
+
+    #[async_trait(?Send)]
+    impl ApiRequest for ExampleRequest {
+        type Response = serde_json::Value;
+
+        fn path(&self) -> String {
+            "/api/v1/example".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_use_the_mock_transport() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        transport.push_json(200, serde_json::json!({"value": 42}));
+        transport.push_response(401, "");
+        transport.push_json(409, serde_json::json!({"msg": "already exists"}));
+
+        let base_url = Url::parse("http://storage.invalid/").unwrap();
+        let response = client.request(&base_url, "a-token", ExampleRequest).await;
+        assert_eq!(response.unwrap(), Some(serde_json::json!({"value": 42})));
+
+        let response = client.request(&base_url, "a-token", ExampleRequest).await;
+        assert!(matches!(response, Err(ApiError::NotAuthorized)));
+
+        let response = client.request(&base_url, "a-token", ExampleRequest).await;
+        assert!(matches!(
+            response,
+            Err(ApiError::Message { status_code: 409, message }) if message == "already exists"
+        ));
+
+        let response = client.request(&base_url, "a-token", ExampleRequest).await;
+        assert!(matches!(response, Err(ApiError::UnexpectedResponse(_))));
+
+        let requests = transport.take_requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            requests[0].url().as_str(),
+            "http://storage.invalid/api/v1/example"
+        );
+        assert_eq!(
+            requests[0].headers()[reqwest::header::AUTHORIZATION],
+            "Bearer a-token"
+        );
+        assert_eq!(transport.remaining_responses(), 0);
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+mod mock_transport;
+
+#[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+pub use mock_transport::MockTransport;
+
+use async_trait::async_trait;
+use reqwest::{Client, Request, Response};
+
+use crate::api::client::ApiError;
+
+/// The piece of the [`crate::api::ApiClient`] responsible for actually sending requests over the
+/// network. Requests are fully assembled, including their authentication, before being handed to
+/// the transport and the response it returns goes through the same status and error handling as
+/// any other. By default requests are sent using [`reqwest`], alternative transports can be
+/// provided with [`crate::api::ApiClient::with_transport`].
+///
+/// Replacing the transport is primarily useful for exercising code built on the API client
+/// without any network access, see the `MockTransport` available with the `test-utils` feature.
+#[async_trait(?Send)]
+pub trait HttpTransport: Send + Sync {
+    async fn execute(&self, request: Request) -> Result<Response, ApiError>;
+}
+
+#[async_trait(?Send)]
+impl<T: HttpTransport + ?Sized> HttpTransport for std::sync::Arc<T> {
+    async fn execute(&self, request: Request) -> Result<Response, ApiError> {
+        (**self).execute(request).await
+    }
+}
+
+#[async_trait(?Send)]
+impl HttpTransport for Client {
+    async fn execute(&self, request: Request) -> Result<Response, ApiError> {
+        Ok(Client::execute(self, request).await?)
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+
+/// A token bucket shared by every clone of an [`crate::api::ApiClient`] limiting how quickly
+/// block data is transferred to and from storage hosts. The limit can be changed at any time,
+/// including while transfers are in progress, and applies to the next chunk of data each of them
+/// transfers.
+///
+/// The bucket holds up to one second worth of data, allowing short bursts after a period of
+/// inactivity. Chunks larger than the bucket are allowed through and the transfer is delayed until
+/// the bucket has refilled to cover them. When no limit is set throttled streams only check the
+/// limit and pass their data through untouched.
+#[derive(Clone, Default)]
+pub(crate) struct BandwidthLimit {
+    /// Zero when transfers are not limited.
+    bytes_per_second: Arc<AtomicU64>,
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+impl BandwidthLimit {
+    pub(crate) fn get(&self) -> Option<u64> {
+        match self.bytes_per_second.load(Ordering::Relaxed) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// Sets the maximum transfer rate in bytes per second, `None` or a limit of zero removes the
+    /// limit.
+    pub(crate) fn set(&self, bytes_per_second: Option<u64>) {
+        self.bytes_per_second
+            .store(bytes_per_second.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Wraps a body stream so each chunk waits for the bucket to cover its size before being
+    /// passed along.
+    pub(crate) fn throttle<S, E>(&self, stream: S) -> impl Stream<Item = Result<Bytes, E>>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+    {
+        let limit = self.clone();
+
+        stream.then(move |item| {
+            let limit = limit.clone();
+
+            async move {
+                if let Ok(chunk) = &item {
+                    limit.consume(chunk.len() as u64).await;
+                }
+
+                item
+            }
+        })
+    }
+
+    async fn consume(&self, bytes: u64) {
+        let bytes_per_second = self.bytes_per_second.load(Ordering::Relaxed);
+        if bytes_per_second == 0 || bytes == 0 {
+            return;
+        }
+
+        let delay_ms = {
+            let mut bucket = self.bucket.lock().expect("bandwidth bucket lock poisoned");
+            bucket.take(crate::utils::current_time_ms(), bytes_per_second, bytes)
+        };
+
+        if delay_ms > 0 {
+            async_std::task::sleep(Duration::from_millis(delay_ms)).await;
+        }
+    }
+}
+
+#[derive(Default)]
+struct TokenBucket {
+    /// The bytes currently available to be transferred. This goes negative when a chunk is larger
+    /// than what was available, with the debt being paid off as the bucket refills.
+    available: i64,
+    last_refill_ms: Option<i64>,
+}
+
+impl TokenBucket {
+    /// Removes the bytes from the bucket returning how long, in milliseconds, the caller needs to
+    /// wait before the transfer is within the limit.
+    fn take(&mut self, now_ms: i64, bytes_per_second: u64, bytes: u64) -> u64 {
+        let capacity = i64::try_from(bytes_per_second).unwrap_or(i64::MAX);
+
+        self.available = match self.last_refill_ms {
+            Some(last_refill_ms) => {
+                let elapsed_ms = now_ms.saturating_sub(last_refill_ms).max(0);
+                let refilled = elapsed_ms.saturating_mul(capacity) / 1_000;
+                self.available.saturating_add(refilled).min(capacity)
+            }
+            None => capacity,
+        };
+        self.last_refill_ms = Some(now_ms);
+
+        let bytes = i64::try_from(bytes).unwrap_or(i64::MAX);
+        self.available = self.available.saturating_sub(bytes);
+
+        if self.available >= 0 {
+            return 0;
+        }
+
+        let deficit = self.available.unsigned_abs();
+        deficit.saturating_mul(1_000).div_ceil(bytes_per_second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_delays_once_drained() {
+        let mut bucket = TokenBucket::default();
+
+        // Starts with a full second of data available
+        assert_eq!(bucket.take(0, 1_000, 500), 0);
+        assert_eq!(bucket.take(0, 1_000, 1_000), 500);
+
+        // Refills with time, capped to a single second of data
+        assert_eq!(bucket.take(10_000, 1_000, 1_000), 0);
+        assert_eq!(bucket.take(10_000, 1_000, 1), 1);
+
+        // A lower limit applies to the next chunk taken
+        assert_eq!(bucket.take(12_000, 100, 200), 1_000);
+    }
+}
@@ -0,0 +1,127 @@
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Bytes, BytesMut};
+use futures::channel::mpsc::Sender;
+use futures::io::AsyncWrite;
+use futures::sink::Sink;
+
+/// An [`AsyncWrite`] that collects what is written to it into chunks of a fixed size and hands
+/// each one to a bounded channel as soon as it fills up. Pairing this with the receiving half of
+/// the channel allows the output of an encoder to be consumed as a stream without ever holding
+/// more than the channel's capacity (plus one partial chunk) in memory. Writes stall until the
+/// receiver has made room for the next chunk.
+///
+/// The final partial chunk is only sent when the writer is flushed or closed. Closing the writer
+/// also ends the receiver's stream once it has received every chunk.
+pub(crate) struct ChunkSender {
+    sender: Sender<Bytes>,
+    buffer: BytesMut,
+    chunk_size: usize,
+    sent_bytes: u64,
+}
+
+impl ChunkSender {
+    /// A `chunk_size` of zero is treated as one.
+    pub(crate) fn new(sender: Sender<Bytes>, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+
+        Self {
+            sender,
+            buffer: BytesMut::with_capacity(chunk_size),
+            chunk_size,
+            sent_bytes: 0,
+        }
+    }
+
+    /// The number of bytes that have been handed off to the channel so far.
+    pub(crate) fn sent_bytes(&self) -> u64 {
+        self.sent_bytes
+    }
+
+    fn poll_send_buffer(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if self.buffer.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+
+        ready!(Pin::new(&mut self.sender).poll_ready(cx)).map_err(receiver_gone)?;
+
+        let chunk = self.buffer.split().freeze();
+        self.sent_bytes += chunk.len() as u64;
+        Pin::new(&mut self.sender)
+            .start_send(chunk)
+            .map_err(receiver_gone)?;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for ChunkSender {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let inner = self.get_mut();
+
+        if inner.buffer.len() >= inner.chunk_size {
+            ready!(inner.poll_send_buffer(cx))?;
+        }
+
+        let accepted = buf.len().min(inner.chunk_size - inner.buffer.len());
+        inner.buffer.extend_from_slice(&buf[..accepted]);
+
+        Poll::Ready(Ok(accepted))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let inner = self.get_mut();
+
+        ready!(inner.poll_send_buffer(cx))?;
+        Pin::new(&mut inner.sender)
+            .poll_flush(cx)
+            .map_err(receiver_gone)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let inner = self.get_mut();
+
+        ready!(inner.poll_send_buffer(cx))?;
+        Pin::new(&mut inner.sender)
+            .poll_close(cx)
+            .map_err(receiver_gone)
+    }
+}
+
+fn receiver_gone(_: futures::channel::mpsc::SendError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "chunk receiver was dropped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::channel::mpsc;
+    use futures::io::AsyncWriteExt;
+    use futures::StreamExt;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_writes_are_sent_in_bounded_chunks() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let (sender, receiver) = mpsc::channel(1);
+        let mut writer = ChunkSender::new(sender, 256);
+
+        let writing = async {
+            writer.write_all(&data).await.unwrap();
+            writer.close().await.unwrap();
+            writer.sent_bytes()
+        };
+        let (sent_bytes, chunks) = futures::join!(writing, receiver.collect::<Vec<_>>());
+
+        assert_eq!(sent_bytes, 1000);
+        let chunk_sizes: Vec<_> = chunks.iter().map(|chunk| chunk.len()).collect();
+        assert_eq!(chunk_sizes, vec![256, 256, 256, 232]);
+        assert_eq!(chunks.concat(), data);
+    }
+}
@@ -1,6 +1,12 @@
+mod bandwidth_limit;
+mod chunk_sender;
+mod upload_limit;
 mod vec_stream;
 
-pub use vec_stream::VecStream;
+pub(crate) use bandwidth_limit::BandwidthLimit;
+pub(crate) use chunk_sender::ChunkSender;
+pub(crate) use upload_limit::UploadLimit;
+pub use vec_stream::{ChunkedVecStream, VecStream};
 
 use std::time::Duration;
 
@@ -10,14 +16,20 @@ use base64::Engine;
 use blake3::Hasher;
 use bytes::{Bytes, BytesMut};
 use elliptic_curve::rand_core::CryptoRngCore;
-use time::OffsetDateTime;
+use serde_json::{Map, Value};
+use time::{Date, Month, OffsetDateTime, Time};
 
+use crate::api::client::ApiError;
 use crate::codec::crypto::{SigningKey, VerifyingKey};
 
 const CLOCK_LEEWAY: Duration = Duration::from_secs(30);
 
 const FINGERPRINT_SIZE: usize = 20;
 
+/// Claims that are always generated by [`create_jwt`] and can not be replaced by any additional
+/// claims provided by the caller.
+const RESERVED_CLAIMS: &[&str] = &["aud", "exp", "iat", "nbf", "nonce", "sub"];
+
 const TOKEN_LIFETIME: Duration = Duration::from_secs(300);
 
 /// The API uses a truncated hex encoded blake3 hash for key identification in its JWTs. This
@@ -42,6 +54,22 @@ pub(crate) fn api_fingerprint_key(key: &VerifyingKey) -> String {
     })
 }
 
+/// Compares the time reported by a server in its `Date` header against the local clock, adjusted
+/// by the `clock_offset` applied to generated tokens. Returns the number of seconds the server is
+/// ahead of the client (negative when it is behind) when the difference is large enough that the
+/// server will reject the client's tokens, otherwise `None`.
+pub(crate) fn clock_skew(server_date: &str, clock_offset: i64) -> Option<i64> {
+    let server_time = parse_http_date(server_date)?;
+    let local_time = OffsetDateTime::now_utc() + time::Duration::seconds(clock_offset);
+
+    let skew_seconds = (server_time - local_time).whole_seconds();
+    if skew_seconds.unsigned_abs() <= CLOCK_LEEWAY.as_secs() {
+        return None;
+    }
+
+    Some(skew_seconds)
+}
+
 /// Consumes an async stream into a single Bytes object. This will consume potentially boundless
 /// memory which is especially problematic since we will be handling very large files. It is
 /// intended primarily for WASM targeted builds where async is significantly more limited.
@@ -60,23 +88,69 @@ where
     Ok(bytes_mut.freeze())
 }
 
+/// A variant of [`consume_stream_into_bytes`] that gives up rather than growing without bound.
+/// Once more than `max_bytes` have been read from the stream an [`ApiError::UploadTooLarge`] is
+/// returned and the data read so far is released. The `is_cancelled` check is consulted before
+/// each chunk is read, returning [`ApiError::UploadCancelled`] as soon as it reports true.
+pub(crate) async fn consume_stream_into_bytes_bounded<S, E>(
+    mut stream: S,
+    max_bytes: Option<u64>,
+    is_cancelled: impl Fn() -> bool,
+) -> Result<Bytes, ApiError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<ApiError>,
+{
+    let mut bytes_mut = BytesMut::new();
+
+    loop {
+        if is_cancelled() {
+            return Err(ApiError::UploadCancelled);
+        }
+
+        let Some(item) = stream.next().await else {
+            break;
+        };
+
+        let bytes = item.map_err(Into::into)?;
+        if let Some(limit) = max_bytes {
+            if (bytes_mut.len() + bytes.len()) as u64 > limit {
+                return Err(ApiError::UploadTooLarge { limit });
+            }
+        }
+
+        bytes_mut.extend_from_slice(&bytes);
+    }
+
+    Ok(bytes_mut.freeze())
+}
+
 /// Creates a JWT token to authenticated against the APIs. There are crates that perform this but
 /// they are more general and have a much larger attack surface (as well as dependencies with known
 /// vulnerabilities). This is a minimal implementation that generates exactly what we need.
+///
+/// The times within the token are shifted by `clock_offset` seconds to compensate for a local
+/// clock that disagrees with the servers, the returned expiration is always in local time.
+///
+/// Any `extra_claims` are merged into the generated claim set. Customized platform deployments
+/// may require these (such as a tenant identifier). The standard claims always take precedence,
+/// any extra claims attempting to replace one of them are dropped.
 pub(crate) fn create_jwt(
     rng: &mut impl CryptoRngCore,
     subject: &str,
     audience: &str,
     key: &SigningKey,
+    extra_claims: &Map<String, Value>,
+    clock_offset: i64,
 ) -> (String, OffsetDateTime) {
     let verifying_key = key.verifying_key();
     let fingerprint = crate::api::client::utils::api_fingerprint_key(&verifying_key);
 
-    let current_time = OffsetDateTime::now_utc();
+    let expiration = OffsetDateTime::now_utc() + TOKEN_LIFETIME;
+    let current_time = OffsetDateTime::now_utc() + time::Duration::seconds(clock_offset);
 
     let not_before = current_time - CLOCK_LEEWAY;
-    let expiration = OffsetDateTime::now_utc() + TOKEN_LIFETIME;
-    let not_after = expiration + CLOCK_LEEWAY;
+    let not_after = current_time + TOKEN_LIFETIME + CLOCK_LEEWAY;
 
     let mut nonce_bytes = [0u8; 24];
     rng.fill_bytes(&mut nonce_bytes);
@@ -85,15 +159,24 @@ pub(crate) fn create_jwt(
     let jwt_hdr = serde_json::json!({"alg": "ES384", "kid": fingerprint, "typ": "JWT"}).to_string();
     let jwt_hdr_b64 = URL_SAFE_NO_PAD.encode(jwt_hdr.as_bytes());
 
-    let jwt_claim = serde_json::json!({
-        "iat": current_time.unix_timestamp(),
-        "exp": not_after.unix_timestamp(),
-        "nbf": not_before.unix_timestamp(),
-        "sub": subject,
-        "aud": audience,
-        "nonce": nonce,
-    })
-    .to_string();
+    let mut claims = Map::new();
+    for (name, value) in extra_claims.iter() {
+        if RESERVED_CLAIMS.contains(&name.as_str()) {
+            tracing::warn!(claim = %name, "ignoring attempt to override reserved JWT claim");
+            continue;
+        }
+
+        claims.insert(name.clone(), value.clone());
+    }
+
+    claims.insert("iat".into(), current_time.unix_timestamp().into());
+    claims.insert("exp".into(), not_after.unix_timestamp().into());
+    claims.insert("nbf".into(), not_before.unix_timestamp().into());
+    claims.insert("sub".into(), subject.into());
+    claims.insert("aud".into(), audience.into());
+    claims.insert("nonce".into(), nonce.into());
+
+    let jwt_claim = Value::Object(claims).to_string();
     let jwt_claim_b64 = URL_SAFE_NO_PAD.encode(jwt_claim.as_bytes());
 
     let signed_data = format!("{}.{}", jwt_hdr_b64, jwt_claim_b64);
@@ -104,3 +187,122 @@ pub(crate) fn create_jwt(
 
     (token, expiration)
 }
+
+/// Parses the preferred format of the HTTP `Date` header (IMF-fixdate), such as
+/// `Sun, 06 Nov 1994 08:49:37 GMT`. The obsolete formats servers are no longer allowed to produce
+/// aren't supported.
+fn parse_http_date(value: &str) -> Option<OffsetDateTime> {
+    let mut parts = value.split_whitespace();
+
+    let _day_name = parts.next()?;
+    let day: u8 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => Month::January,
+        "Feb" => Month::February,
+        "Mar" => Month::March,
+        "Apr" => Month::April,
+        "May" => Month::May,
+        "Jun" => Month::June,
+        "Jul" => Month::July,
+        "Aug" => Month::August,
+        "Sep" => Month::September,
+        "Oct" => Month::October,
+        "Nov" => Month::November,
+        "Dec" => Month::December,
+        _ => return None,
+    };
+    let year: i32 = parts.next()?.parse().ok()?;
+
+    let mut time_parts = parts.next()?.split(':');
+    let hour: u8 = time_parts.next()?.parse().ok()?;
+    let minute: u8 = time_parts.next()?.parse().ok()?;
+    let second: u8 = time_parts.next()?.parse().ok()?;
+
+    if parts.next()? != "GMT" || parts.next().is_some() || time_parts.next().is_some() {
+        return None;
+    }
+
+    let date = Date::from_calendar_date(year, month, day).ok()?;
+    let time = Time::from_hms(hour, minute, second).ok()?;
+
+    Some(date.with_time(time).assume_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::codec::crypto::SigningKey;
+
+    fn decode_claims(token: &str) -> Map<String, Value> {
+        let claim_b64 = token.split('.').nth(1).expect("claim segment");
+        let claim_bytes = URL_SAFE_NO_PAD.decode(claim_b64).expect("valid base64");
+        serde_json::from_slice(&claim_bytes).expect("valid claim json")
+    }
+
+    #[test]
+    fn test_jwt_extra_claims_merged() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = SigningKey::generate(&mut rng);
+
+        let mut extra_claims = Map::new();
+        extra_claims.insert("tenant_id".into(), "tenant-1234".into());
+        extra_claims.insert("sub".into(), "someone-else".into());
+        extra_claims.insert("aud".into(), "elsewhere".into());
+
+        let (token, _) = create_jwt(&mut rng, "account", "audience", &key, &extra_claims, 0);
+        let claims = decode_claims(&token);
+
+        assert_eq!(claims["tenant_id"], "tenant-1234");
+        assert_eq!(claims["sub"], "account");
+        assert_eq!(claims["aud"], "audience");
+        assert!(claims.contains_key("exp"));
+        assert!(claims.contains_key("nbf"));
+    }
+
+    #[test]
+    fn test_jwt_clock_offset_shifts_times() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = SigningKey::generate(&mut rng);
+
+        let (token, _) = create_jwt(&mut rng, "account", "audience", &key, &Map::new(), 0);
+        let issued_at = decode_claims(&token)["iat"].as_i64().unwrap();
+
+        let (token, _) = create_jwt(&mut rng, "account", "audience", &key, &Map::new(), 3_600);
+        let shifted_issued_at = decode_claims(&token)["iat"].as_i64().unwrap();
+
+        assert!((shifted_issued_at - issued_at - 3_600).abs() <= 1);
+    }
+
+    #[test]
+    fn test_clock_skew_beyond_leeway() {
+        let parsed = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(parsed.unix_timestamp(), 784_111_777);
+
+        assert!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT").is_none());
+        assert!(parse_http_date("Sun, 06 Nov 1994 08:49:37 PST").is_none());
+        assert!(parse_http_date("Sun, 31 Feb 1994 08:49:37 GMT").is_none());
+
+        let now = OffsetDateTime::now_utc();
+        let format = |time: OffsetDateTime| {
+            format!(
+                "Mon, {:02} {} {} {:02}:{:02}:{:02} GMT",
+                time.day(),
+                &format!("{:?}", time.month())[..3],
+                time.year(),
+                time.hour(),
+                time.minute(),
+                time.second()
+            )
+        };
+
+        assert_eq!(clock_skew(&format(now), 0), None);
+
+        let ahead = now + time::Duration::seconds(600);
+        let skew = clock_skew(&format(ahead), 0).unwrap();
+        assert!((599..=601).contains(&skew));
+
+        // Compensating for the skew brings the clocks back in line
+        assert_eq!(clock_skew(&format(ahead), 600), None);
+    }
+}
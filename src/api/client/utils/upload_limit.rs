@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::Stream;
+
+use crate::api::client::ApiError;
+
+/// Limits shared by every clone of an [`crate::api::ApiClient`] on the bodies of uploads that
+/// have to be assembled in memory before they're sent. Uploads larger than the maximum size are
+/// rejected as soon as the limit is crossed rather than once the whole body has been read, and
+/// uploads in progress can be cancelled.
+#[derive(Clone, Default)]
+pub(crate) struct UploadLimit {
+    /// Incremented each time uploads are cancelled, an upload is cancelled when this no longer
+    /// matches the value from when it started.
+    cancellations: Arc<AtomicU64>,
+
+    /// Zero when uploads are not limited.
+    max_bytes: Arc<AtomicU64>,
+}
+
+impl UploadLimit {
+    /// Stops every upload currently in progress the next time it reads from its body. Uploads
+    /// started afterwards are unaffected.
+    pub(crate) fn cancel(&self) {
+        self.cancellations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reads the entire body of an upload into memory, enforcing the limits as it goes.
+    pub(crate) async fn consume<S, E>(&self, stream: S) -> Result<Bytes, ApiError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: Into<ApiError>,
+    {
+        let generation = self.cancellations.load(Ordering::Relaxed);
+        let is_cancelled = || self.cancellations.load(Ordering::Relaxed) != generation;
+
+        super::consume_stream_into_bytes_bounded(stream, self.get(), is_cancelled).await
+    }
+
+    pub(crate) fn get(&self) -> Option<u64> {
+        match self.max_bytes.load(Ordering::Relaxed) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// Sets the largest body, in bytes, that will be read for a single upload. `None` or a limit
+    /// of zero removes the limit.
+    pub(crate) fn set(&self, max_bytes: Option<u64>) {
+        self.max_bytes
+            .store(max_bytes.unwrap_or(0), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::{stream, StreamExt};
+
+    fn chunks(count: usize) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Unpin {
+        stream::iter((0..count).map(|_| Ok(Bytes::from_static(&[0u8; 16]))))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_limits_are_enforced_while_reading() {
+        let limit = UploadLimit::default();
+        assert_eq!(limit.consume(chunks(4)).await.unwrap().len(), 64);
+
+        limit.set(Some(64));
+        assert_eq!(limit.consume(chunks(4)).await.unwrap().len(), 64);
+        assert!(matches!(
+            limit.consume(chunks(5)).await,
+            Err(ApiError::UploadTooLarge { limit: 64 })
+        ));
+
+        // Cancelling stops an upload part way through its body
+        let cancelling = limit.clone();
+        let body = chunks(3).enumerate().map(move |(idx, chunk)| {
+            if idx == 1 {
+                cancelling.cancel();
+            }
+            chunk
+        });
+        assert!(matches!(
+            limit.consume(body).await,
+            Err(ApiError::UploadCancelled)
+        ));
+
+        // Later uploads aren't affected by an earlier cancellation
+        limit.set(None);
+        assert_eq!(limit.consume(chunks(8)).await.unwrap().len(), 128);
+    }
+}
@@ -4,6 +4,9 @@ use std::task::{Context, Poll};
 use async_std::stream::Stream;
 use bytes::Bytes;
 
+/// Yields the entire wrapped buffer as a single item. The buffer is copied into the produced
+/// [`Bytes`] when it's polled, so the peak memory use is twice the size of the payload. Prefer
+/// [`ChunkedVecStream`] for large payloads.
 pub struct VecStream {
     data: Vec<u8>,
     pos: usize,
@@ -36,3 +39,86 @@ impl Stream for VecStream {
         Poll::Ready(Some(Ok(bytes)))
     }
 }
+
+/// A bounded alternative to [`VecStream`] that yields the wrapped buffer as a series of
+/// [`Bytes`] chunks no larger than the configured chunk size. The buffer is taken over without
+/// being copied and each chunk is a reference counted view into it, so the total memory used
+/// never exceeds the size of the original payload no matter how many chunks are outstanding.
+///
+/// This is the reverse of [`super::consume_stream_into_bytes`], which has to grow a single
+/// allocation to hold every item a stream produces and has no upper bound. Handing out fixed
+/// size chunks lets the consumer of the stream (such as a streaming request body) apply its own
+/// back-pressure by only polling for the next chunk once the previous one has been sent.
+pub struct ChunkedVecStream {
+    data: Bytes,
+    chunk_size: usize,
+    pos: usize,
+}
+
+impl ChunkedVecStream {
+    /// Creates a new stream over the provided data. A `chunk_size` of zero is treated as one.
+    pub fn new(data: impl Into<Bytes>, chunk_size: usize) -> Self {
+        Self {
+            data: data.into(),
+            chunk_size: chunk_size.max(1),
+            pos: 0,
+        }
+    }
+
+    pub fn pinned(self) -> Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>>>> {
+        Box::pin(self)
+    }
+}
+
+impl Stream for ChunkedVecStream {
+    type Item = Result<Bytes, std::io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let inner = self.get_mut();
+
+        if inner.pos >= inner.data.len() {
+            return Poll::Ready(None);
+        }
+
+        let end_pos = (inner.pos + inner.chunk_size).min(inner.data.len());
+        let bytes = inner.data.slice(inner.pos..end_pos);
+        inner.pos = end_pos;
+
+        Poll::Ready(Some(Ok(bytes)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .data
+            .len()
+            .saturating_sub(self.pos)
+            .div_ceil(self.chunk_size);
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use async_std::prelude::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_chunked_stream_yields_bounded_chunks() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let mut stream = ChunkedVecStream::new(data.clone(), 256);
+        assert_eq!(stream.size_hint(), (4, Some(4)));
+
+        let mut chunk_sizes = Vec::new();
+        let mut reassembled = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            chunk_sizes.push(chunk.len());
+            reassembled.extend_from_slice(&chunk);
+        }
+
+        assert_eq!(chunk_sizes, vec![256, 256, 256, 232]);
+        assert_eq!(reassembled, data);
+    }
+}
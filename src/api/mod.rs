@@ -9,4 +9,10 @@ pub mod storage_host;
 
 pub(crate) mod client;
 
-pub use client::{ApiClient, ApiClientError, ApiError, VecStream};
+#[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+pub use client::MockTransport;
+pub use client::{
+    ApiClient, ApiClientError, ApiError, ApiRequest, ChunkedVecStream, FromReqwestResponse,
+    HttpTransport, KeyResolver, PlatformApiRequest, RegistrationStep, RetryPolicy,
+    SingleKeyResolver, StorageHostApiRequest, StorageTokenError, VecStream,
+};
@@ -10,9 +10,14 @@ use get_all_request::GetAllRequest;
 use get_request::GetRequest;
 use update_request::UpdateRequest;
 
+use futures::StreamExt;
+
 use crate::api::client::{ApiClient, ApiError};
-use crate::api::platform::{ApiDrive, ApiDriveId, DriveKind, StorageClass};
-use crate::codec::crypto::VerifyingKey;
+use crate::api::platform::{self, ApiDrive, ApiDriveId, DriveKind, StorageClass};
+use crate::codec::crypto::{SigningKey, VerifyingKey};
+use crate::codec::meta::{Cid, FilesystemId};
+use crate::codec::parser::SegmentStreamer;
+use crate::filesystem::{ChunkDecoder, Drive, DriveLoader, DriveLoaderError, MetadataEncoding};
 use crate::prelude::platform::ApiDriveUpdateAttributes;
 
 pub async fn create(
@@ -36,6 +41,24 @@ pub async fn create(
     Ok(created_drive.id)
 }
 
+/// Retrieves the root CID of the platform's current metadata version of the drive, or `None` when
+/// nothing has been pushed for the drive yet. Checking this against [`Drive::root_cid`] of the
+/// version a local copy was loaded from before pushing lets a client refuse to save over changes
+/// it hasn't seen. [`platform::metadata::compare_with_current`] performs a more complete check.
+pub async fn current_root_cid(client: &ApiClient, drive_id: &str) -> Result<Option<Cid>, ApiError> {
+    let current_metadata = match platform::metadata::get_current(client, drive_id).await {
+        Ok(metadata) => metadata,
+        Err(ApiError::NoMetadataVersion(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let root_cid = Cid::try_from(current_metadata.root_cid().as_str()).map_err(|err| {
+        ApiError::InvalidData(format!("platform reported an invalid root CID: {err}"))
+    })?;
+
+    Ok(Some(root_cid))
+}
+
 pub async fn delete(client: &ApiClient, drive_id: &str) -> Result<(), ApiError> {
     let request = DeleteRequest::new(drive_id.into());
     client.platform_request_empty_response(request).await
@@ -51,6 +74,176 @@ pub async fn get_all(client: &ApiClient) -> Result<Vec<ApiDrive>, ApiError> {
     client.platform_request_full(GetAllRequest).await
 }
 
+/// Converts the ID the platform assigned to a drive into the [`FilesystemId`] the drive's
+/// metadata is expected to be encoded with.
+pub fn filesystem_id(drive_id: &str) -> Result<FilesystemId, ApiError> {
+    let api_assigned_id = drive_id.replace('-', "");
+    if api_assigned_id.len() != FilesystemId::size() * 2 {
+        return Err(ApiError::InvalidData(format!(
+            "platform drive ID has an unexpected length: {drive_id}"
+        )));
+    }
+
+    let mut id_bytes = [0u8; 16];
+    for (i, byte_chunk) in api_assigned_id.as_bytes().chunks(2).enumerate() {
+        let byte = std::str::from_utf8(byte_chunk)
+            .ok()
+            .and_then(|byte_str| u8::from_str_radix(byte_str, 16).ok())
+            .ok_or_else(|| {
+                ApiError::InvalidData(format!(
+                    "platform drive ID contains non-hex characters: {drive_id}"
+                ))
+            })?;
+
+        id_bytes[i] = byte;
+    }
+
+    Ok(FilesystemId::from(id_bytes))
+}
+
+/// Opens the current version of a drive stored on the platform. This retrieves the drive's current
+/// metadata version, downloads it, and loads it using the provided key returning a [`Drive`]
+/// ready for use. The key must have been granted access to the drive, keys that have since been
+/// revoked are rejected with [`ApiError::DriveAccessDenied`].
+///
+/// Drives that haven't had any metadata pushed yet will fail with
+/// [`ApiError::NoMetadataVersion`], callers can start from an empty drive in that case.
+pub async fn load(
+    client: &ApiClient,
+    drive_id: &str,
+    signing_key: &SigningKey,
+) -> Result<Drive, ApiError> {
+    let current_metadata = platform::metadata::get_current(client, drive_id).await?;
+    load_version(client, drive_id, &current_metadata.id(), signing_key).await
+}
+
+/// The number of times [`load_version`] will resume an interrupted metadata transfer before
+/// giving up.
+pub const DEFAULT_PULL_RETRIES: usize = 3;
+
+/// Behaves the same as [`load`] but opens a specific metadata version of the drive rather than
+/// the current one.
+///
+/// The metadata is parsed as it arrives. If the transfer is interrupted part way through, the
+/// remainder is requested from the platform starting from the last byte received and parsing
+/// continues where it left off, up to [`DEFAULT_PULL_RETRIES`] times.
+///
+/// With the `compression` feature enabled the platform may send the metadata gzip or zstd
+/// compressed. It is decompressed as it arrives and an interrupted transfer resumes from the last
+/// compressed byte received.
+pub async fn load_version(
+    client: &ApiClient,
+    drive_id: &str,
+    metadata_id: &str,
+    signing_key: &SigningKey,
+) -> Result<Drive, ApiError> {
+    load_version_with_retries(
+        client,
+        drive_id,
+        metadata_id,
+        signing_key,
+        DEFAULT_PULL_RETRIES,
+    )
+    .await
+}
+
+/// Behaves the same as [`load_version`] with a custom limit on the number of times an interrupted
+/// transfer will be resumed. Once the limit has been reached the error that interrupted the last
+/// attempt is returned. If the platform doesn't support resuming the transfer from an offset
+/// [`ApiError::RangeNotSupported`] is returned without further attempts.
+pub async fn load_version_with_retries(
+    client: &ApiClient,
+    drive_id: &str,
+    metadata_id: &str,
+    signing_key: &SigningKey,
+    max_retries: usize,
+) -> Result<Drive, ApiError> {
+    let mut streamer = SegmentStreamer::new(DriveLoader::new(signing_key));
+
+    let mut received: u64 = 0;
+    let mut retries = 0;
+    let mut stream = None;
+    let mut decoder: Option<(MetadataEncoding, ChunkDecoder)> = None;
+    let mut loaded = None;
+
+    loop {
+        let active_stream = match stream.as_mut() {
+            Some(active_stream) => active_stream,
+            None => {
+                let pull_result = platform::metadata::pull_encoded_stream_from(
+                    client,
+                    drive_id,
+                    metadata_id,
+                    received,
+                )
+                .await;
+
+                match pull_result {
+                    Ok((encoding, new_stream)) => {
+                        match &decoder {
+                            Some((active_encoding, _)) if *active_encoding != encoding => {
+                                return Err(ApiError::UnexpectedResponse(
+                                    "metadata encoding changed when resuming the transfer",
+                                ));
+                            }
+                            Some(_) => {}
+                            None => decoder = Some((encoding, encoding.chunk_decoder()?)),
+                        }
+
+                        stream.insert(Box::pin(new_stream))
+                    }
+                    Err(ApiError::ClientError(err)) if retries < max_retries => {
+                        retries += 1;
+                        tracing::warn!(received, retries, "failed to resume metadata pull: {err}");
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        };
+
+        let chunk = match active_stream.next().await {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(err)) if retries < max_retries => {
+                retries += 1;
+                tracing::warn!(received, retries, "metadata pull interrupted: {err}");
+                stream = None;
+                continue;
+            }
+            Some(Err(err)) => return Err(err.into()),
+            None => {
+                // Finishing the decoder checks the compressed content wasn't cut short, which
+                // can only be known once the transfer is complete
+                let (_, chunk_decoder) = decoder.take().expect("set when the stream was opened");
+                let remaining = chunk_decoder.finish().await?;
+
+                if loaded.is_none() {
+                    streamer.add_chunk(&remaining);
+                    if let Some(result) = streamer.next().await {
+                        loaded = Some(result?.1);
+                    }
+                }
+
+                let drive = loaded.ok_or(DriveLoaderError::UnexpectedStreamEnd)?;
+                drive.assert_key_authorized(signing_key).await?;
+                return Ok(drive);
+            }
+        };
+
+        received += chunk.len() as u64;
+
+        let (_, chunk_decoder) = decoder.as_mut().expect("set when the stream was opened");
+        let chunk = chunk_decoder.decode(chunk).await?;
+
+        if loaded.is_none() {
+            streamer.add_chunk(&chunk);
+            if let Some(result) = streamer.next().await {
+                loaded = Some(result?.1);
+            }
+        }
+    }
+}
+
 pub async fn update(
     client: &ApiClient,
     drive_id: &str,
@@ -59,3 +252,189 @@ pub async fn update(
     let request = UpdateRequest::new(drive_id.into(), attrs);
     client.platform_request_empty_response(request).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filesystem_id_from_drive_id() {
+        let parsed_id = filesystem_id("01890c3d-2b4a-7e5f-8a1b-0c2d3e4f5a6b").unwrap();
+        let expected = FilesystemId::from([
+            0x01, 0x89, 0x0c, 0x3d, 0x2b, 0x4a, 0x7e, 0x5f, 0x8a, 0x1b, 0x0c, 0x2d, 0x3e, 0x4f,
+            0x5a, 0x6b,
+        ]);
+        assert_eq!(parsed_id, expected);
+
+        assert!(filesystem_id("01890c3d-2b4a").is_err());
+        assert!(filesystem_id("zz890c3d-2b4a-7e5f-8a1b-0c2d3e4f5a6b").is_err());
+    }
+
+    #[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_current_root_cid() {
+        use std::sync::Arc;
+
+        use crate::api::client::MockTransport;
+
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        let drive_id = "01890c3d-2b4a-7e5f-8a1b-0c2d3e4f5a6b";
+        let root_cid = Cid::from([0x42; 32]);
+        let metadata = |root_cid: String| {
+            serde_json::json!({
+                "id": "01890c3d-2b4a-7e5f-8a1b-0c2d3e4f5a6c",
+                "root_cid": root_cid,
+                "metadata_cid": "unused",
+                "data_size": 0,
+                "state": "current",
+                "created_at": 0,
+                "updated_at": 0,
+                "snapshot_id": null,
+            })
+        };
+
+        transport.push_json(200, serde_json::json!({ "api_version": "1.0.0" }));
+        transport.push_json(200, metadata(root_cid.as_base64url_multicodec()));
+        let current = current_root_cid(&client, drive_id).await.unwrap();
+        assert_eq!(current, Some(root_cid));
+
+        // The drive exists but nothing has been pushed for it
+        transport.push_json(404, serde_json::json!({ "msg": "not found" }));
+        transport.push_json(
+            200,
+            serde_json::json!({
+                "id": drive_id,
+                "name": "test",
+                "type": "interactive",
+                "storage_class": "hot",
+            }),
+        );
+        assert_eq!(current_root_cid(&client, drive_id).await.unwrap(), None);
+
+        transport.push_json(200, metadata("u".into()));
+        assert!(matches!(
+            current_root_cid(&client, drive_id).await,
+            Err(ApiError::InvalidData(_))
+        ));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod resume {
+        use super::*;
+
+        use std::sync::Arc;
+
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::{TcpListener, TcpStream};
+
+        use crate::codec::header::ContentOptions;
+
+        const DRIVE_ID: &str = "01890c3d-2b4a-7e5f-8a1b-0c2d3e4f5a6b";
+
+        const METADATA_ID: &str = "01890c3d-2b4a-7e5f-8a1b-0c2d3e4f5a6c";
+
+        async fn read_request(socket: &mut TcpStream) -> String {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            String::from_utf8_lossy(&request).to_lowercase()
+        }
+
+        /// Serves the encoded drive for every pull request. The first pull is cut off half way
+        /// through the drive, later pulls honor the requested range when `honor_ranges` is set.
+        async fn serve_drive(encoded: Vec<u8>, honor_ranges: bool) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+
+            tokio::spawn(async move {
+                let mut interrupted = false;
+
+                loop {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let request = read_request(&mut socket).await;
+
+                    if !request.contains("/pull") {
+                        let response = "HTTP/1.1 404 Not Found\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}";
+                        socket.write_all(response.as_bytes()).await.unwrap();
+                        continue;
+                    }
+
+                    let offset = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("range: bytes="))
+                        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+                        .filter(|_| honor_ranges)
+                        .unwrap_or(0);
+
+                    let status = if offset == 0 {
+                        "200 OK"
+                    } else {
+                        "206 Partial Content"
+                    };
+                    let body = &encoded[offset..];
+                    let header = format!(
+                        "HTTP/1.1 {status}\r\ncontent-type: application/octet-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    socket.write_all(header.as_bytes()).await.unwrap();
+
+                    if !interrupted {
+                        interrupted = true;
+                        socket.write_all(&body[..body.len() / 2]).await.unwrap();
+                        continue;
+                    }
+
+                    socket.write_all(body).await.unwrap();
+                }
+            });
+
+            format!("http://{address}/")
+        }
+
+        #[tokio::test]
+        async fn test_load_version_resumes_interrupted_pull() {
+            let mut rng = crate::utils::crypto_rng();
+            let key = Arc::new(SigningKey::generate(&mut rng));
+
+            let drive = Drive::initialize_private(&mut rng, key.clone()).unwrap();
+            let mut encoded = Vec::new();
+            drive
+                .encode(&mut rng, ContentOptions::metadata(), &mut encoded)
+                .await
+                .unwrap();
+
+            let url = serve_drive(encoded.clone(), true).await;
+            let client = ApiClient::new(&url, "test-account", key.clone()).unwrap();
+            let loaded = load_version(&client, DRIVE_ID, METADATA_ID, &key)
+                .await
+                .unwrap();
+            assert_eq!(loaded.id(), drive.id());
+
+            // A single interruption can't be recovered from when resuming isn't permitted
+            let url = serve_drive(encoded.clone(), true).await;
+            let client = ApiClient::new(&url, "test-account", key.clone()).unwrap();
+            let result = load_version_with_retries(&client, DRIVE_ID, METADATA_ID, &key, 0).await;
+            assert!(matches!(result, Err(ApiError::ClientError(_))));
+
+            let url = serve_drive(encoded, false).await;
+            let client = ApiClient::new(&url, "test-account", key.clone()).unwrap();
+            let result = load_version(&client, DRIVE_ID, METADATA_ID, &key).await;
+            assert!(matches!(result, Err(ApiError::RangeNotSupported)));
+        }
+    }
+}
@@ -10,13 +10,184 @@ use get_request::GetRequest;
 use pull_request::PullRequest;
 use push_request::{PushRequest, PushResponse};
 
+use std::pin::Pin;
+
 use bytes::Bytes;
-use futures::Stream;
+use elliptic_curve::rand_core::CryptoRngCore;
+use futures::channel::mpsc;
+use futures::io::AsyncWriteExt;
+use futures::{Stream, StreamExt};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 
+use crate::api::client::utils::ChunkSender;
 use crate::api::client::{ApiClient, ApiError};
-use crate::api::platform::{ApiMetadata, ApiMetadataId};
+use crate::api::platform::{self, ApiMetadata, ApiMetadataId};
 use crate::codec::crypto::Fingerprint;
+use crate::codec::header::ContentOptions;
 use crate::codec::Cid;
+use crate::filesystem::{Drive, MetadataEncoding};
+
+/// The size of the chunks the encoded drive is handed to the upload request in by
+/// [`EncodedDriveStream::into_stream`].
+const ENCODED_DRIVE_CHUNK_SIZE: usize = 256 * 1024;
+
+/// The output of [`encode_drive_stream`]. Holds the values the platform needs to know about the
+/// encoded drive before the upload begins along with a stream that produces the encoding itself.
+pub struct EncodedDriveStream {
+    encoded_size: u64,
+    root_cid: Cid,
+    stream: Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>>>>,
+}
+
+impl EncodedDriveStream {
+    pub fn encoded_size(&self) -> u64 {
+        self.encoded_size
+    }
+
+    pub fn into_stream(self) -> Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>>>> {
+        self.stream
+    }
+
+    pub fn root_cid(&self) -> &Cid {
+        &self.root_cid
+    }
+}
+
+/// How a local drive relates to the current version of that drive on the platform, as reported by
+/// [`compare_with_current`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionComparison {
+    /// The local drive matches the platform's current version.
+    UpToDate,
+
+    /// The local drive has changes that haven't been pushed yet and nothing new has been pushed
+    /// by anyone else. It is safe to push.
+    LocalAhead,
+
+    /// A new version was pushed since the local drive was pulled and there are no local changes.
+    /// The current version should be pulled before making any changes.
+    RemoteAhead,
+
+    /// Both the local drive and the platform have changed since the local drive was pulled.
+    /// Pushing would clobber the other changes, they'll need to be reconciled first.
+    Diverged,
+}
+
+/// Checks whether the platform's current version of the drive has moved since the local drive was
+/// pulled, to avoid clobbering changes pushed by someone else. The `base_version` is the metadata
+/// the local drive was loaded from (or most recently pushed as), `None` if the local drive has
+/// never been synced with the platform.
+pub async fn compare_with_current(
+    client: &ApiClient,
+    drive_id: &str,
+    drive: &Drive,
+    base_version: Option<&ApiMetadata>,
+) -> Result<VersionComparison, ApiError> {
+    let local_root = drive
+        .root_cid()
+        .await
+        .map_err(|e| ApiError::InvalidData(format!("unable to get drive root cid: {e}")))?;
+
+    let current_version = match get_current(client, drive_id).await {
+        Ok(metadata) => Some(metadata),
+        Err(ApiError::NoMetadataVersion(_)) => None,
+        Err(err) => return Err(err),
+    };
+
+    Ok(compare_versions(
+        &local_root,
+        base_version,
+        current_version.as_ref(),
+    ))
+}
+
+fn compare_versions(
+    local_root: &Cid,
+    base_version: Option<&ApiMetadata>,
+    current_version: Option<&ApiMetadata>,
+) -> VersionComparison {
+    let local_root = local_root.as_base64url_multicodec();
+
+    // Identical content is up to date no matter how we got there
+    if current_version.map(|m| m.root_cid()).as_ref() == Some(&local_root) {
+        return VersionComparison::UpToDate;
+    }
+
+    let remote_changed = base_version.map(|m| m.id()) != current_version.map(|m| m.id());
+    let local_changed = base_version.map(|m| m.root_cid()) != Some(local_root);
+
+    match (local_changed, remote_changed) {
+        (false, false) => VersionComparison::UpToDate,
+        (true, false) => VersionComparison::LocalAhead,
+        (false, true) => VersionComparison::RemoteAhead,
+        (true, true) => VersionComparison::Diverged,
+    }
+}
+
+/// Prepares the provided drive to be handed straight to [`push_stream`] without round tripping
+/// the encoding through a temporary file. The drive is encoded twice: once up front, discarding
+/// the output, to learn the size of the encoding and its root CID, then again chunk by chunk as
+/// the returned stream is polled. At most a couple of chunks of the encoding are held in memory
+/// at any time.
+///
+/// The drive shouldn't be modified until the stream has been consumed. If the second encoding no
+/// longer matches the size that was reported the stream ends with an error rather than uploading
+/// content that disagrees with the push request.
+pub async fn encode_drive_stream(
+    rng: &mut impl CryptoRngCore,
+    drive: &Drive,
+    content_options: ContentOptions,
+) -> Result<EncodedDriveStream, ApiError> {
+    let encoded_size = drive
+        .encode(rng, content_options, &mut futures::io::sink())
+        .await? as u64;
+
+    let root_cid = drive
+        .root_cid()
+        .await
+        .map_err(|e| ApiError::InvalidData(format!("unable to get drive root cid: {e}")))?;
+
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+    let mut stream_rng = ChaCha20Rng::from_seed(seed);
+
+    let drive = drive.clone();
+    let (sender, receiver) = mpsc::channel(1);
+    let encoding = async move {
+        let mut writer = ChunkSender::new(sender, ENCODED_DRIVE_CHUNK_SIZE);
+        drive
+            .encode(&mut stream_rng, content_options, &mut writer)
+            .await
+            .map_err(std::io::Error::other)?;
+        writer.close().await?;
+
+        if writer.sent_bytes() != encoded_size {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "drive changed while it was being streamed",
+            ));
+        }
+
+        Ok(())
+    };
+
+    // The encoding only produces an item of its own when it fails, everything it writes arrives
+    // through the channel.
+    let encoding_errors = futures::stream::once(encoding).filter_map(|result| async move {
+        match result {
+            Ok(()) => None,
+            Err(err) => Some(Err(err)),
+        }
+    });
+    let stream = futures::stream::select(receiver.map(Ok), encoding_errors);
+
+    Ok(EncodedDriveStream {
+        encoded_size,
+        root_cid,
+        stream: Box::pin(stream),
+    })
+}
 
 pub async fn get_all(client: &ApiClient, drive_id: &str) -> Result<Vec<ApiMetadata>, ApiError> {
     client
@@ -24,10 +195,26 @@ pub async fn get_all(client: &ApiClient, drive_id: &str) -> Result<Vec<ApiMetada
         .await
 }
 
+/// Retrieves the current metadata version of the drive. When the drive exists but nothing has
+/// been pushed for it yet this will return [`ApiError::NoMetadataVersion`].
 pub async fn get_current(client: &ApiClient, drive_id: &str) -> Result<ApiMetadata, ApiError> {
-    client
+    let result = client
         .platform_request_full(GetCurrentRequest::new(drive_id.into()))
-        .await
+        .await;
+
+    match result {
+        // The platform reports a missing drive and a drive without any metadata the same way, we
+        // need to confirm the drive itself exists before we can tell the two apart.
+        Err(
+            err @ ApiError::Message {
+                status_code: 404, ..
+            },
+        ) => match platform::drives::get(client, drive_id).await {
+            Ok(_) => Err(ApiError::NoMetadataVersion(drive_id.to_string())),
+            Err(_) => Err(err),
+        },
+        result => result,
+    }
 }
 
 pub async fn get(
@@ -45,11 +232,69 @@ pub async fn pull_stream(
     drive_id: &str,
     metadata_id: &str,
 ) -> Result<impl Stream<Item = Result<Bytes, reqwest::Error>>, ApiError> {
-    let response = client
-        .platform_request_full(PullRequest::new(drive_id.into(), metadata_id.into()))
-        .await?;
+    pull_stream_from(client, drive_id, metadata_id, 0).await
+}
+
+/// Behaves the same as [`pull_stream`] but only requests the metadata starting from the provided
+/// byte offset, used to resume a transfer that was interrupted part way through. When the offset
+/// is non-zero and the platform responds with the full content instead of the requested range
+/// [`ApiError::RangeNotSupported`] is returned.
+pub async fn pull_stream_from(
+    client: &ApiClient,
+    drive_id: &str,
+    metadata_id: &str,
+    offset: u64,
+) -> Result<impl Stream<Item = Result<Bytes, reqwest::Error>>, ApiError> {
+    let request = PullRequest::new(drive_id.into(), metadata_id.into()).starting_at(offset);
+    let response = pull_response(client, request, offset).await?;
+
+    Ok(response.bytes_stream())
+}
+
+/// Behaves the same as [`pull_stream_from`] but allows the platform to compress the metadata,
+/// returning the encoding it was sent with alongside the still compressed stream. The offset
+/// refers to a position in the compressed content, a transfer resumed part way through has to
+/// continue feeding the same decoder.
+pub(crate) async fn pull_encoded_stream_from(
+    client: &ApiClient,
+    drive_id: &str,
+    metadata_id: &str,
+    offset: u64,
+) -> Result<
+    (
+        MetadataEncoding,
+        impl Stream<Item = Result<Bytes, reqwest::Error>>,
+    ),
+    ApiError,
+> {
+    let request = PullRequest::new(drive_id.into(), metadata_id.into())
+        .starting_at(offset)
+        .accept_compressed();
+    let response = pull_response(client, request, offset).await?;
+
+    let content_encoding = response
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .map(|value| value.to_str())
+        .transpose()
+        .map_err(|_| ApiError::UnexpectedResponse("content encoding was not valid text"))?;
+    let encoding = MetadataEncoding::from_content_encoding(content_encoding)?;
+
+    Ok((encoding, response.bytes_stream()))
+}
+
+async fn pull_response(
+    client: &ApiClient,
+    request: PullRequest,
+    offset: u64,
+) -> Result<reqwest::Response, ApiError> {
+    let response = client.platform_request_full(request).await?.consume();
 
-    Ok(response.consume().bytes_stream())
+    if offset != 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Err(ApiError::RangeNotSupported);
+    }
+
+    Ok(response)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -77,8 +322,103 @@ where
         stream_body,
         valid_keys,
         deleted_block_cids,
+        client.upload_limiter(),
     )
     .await?;
 
     client.platform_request_full(push_request).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(id: &str, root_cid: &Cid) -> ApiMetadata {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "root_cid": root_cid.as_base64url_multicodec(),
+            "metadata_cid": root_cid.as_base64url_multicodec(),
+            "data_size": 0,
+            "state": "current",
+            "created_at": 0,
+            "updated_at": 0,
+            "snapshot_id": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_version_comparison() {
+        let base_root = Cid::from([1u8; 32]);
+        let local_root = Cid::from([2u8; 32]);
+        let remote_root = Cid::from([3u8; 32]);
+
+        let base = metadata("first", &base_root);
+        let remote = metadata("second", &remote_root);
+
+        assert_eq!(
+            compare_versions(&base_root, Some(&base), Some(&base)),
+            VersionComparison::UpToDate
+        );
+        assert_eq!(
+            compare_versions(&local_root, Some(&base), Some(&base)),
+            VersionComparison::LocalAhead
+        );
+        assert_eq!(
+            compare_versions(&base_root, Some(&base), Some(&remote)),
+            VersionComparison::RemoteAhead
+        );
+        assert_eq!(
+            compare_versions(&local_root, Some(&base), Some(&remote)),
+            VersionComparison::Diverged
+        );
+        assert_eq!(
+            compare_versions(&remote_root, Some(&base), Some(&remote)),
+            VersionComparison::UpToDate
+        );
+        assert_eq!(
+            compare_versions(&local_root, None, None),
+            VersionComparison::LocalAhead
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_encode_drive_stream_matches_reported_size() {
+        use std::sync::Arc;
+
+        use crate::codec::crypto::SigningKey;
+        use crate::filesystem::DriveLoader;
+
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["photos"], true).await.unwrap();
+
+        let encoded_drive = encode_drive_stream(&mut rng, &drive, ContentOptions::metadata())
+            .await
+            .unwrap();
+        assert_eq!(encoded_drive.root_cid(), &drive.root_cid().await.unwrap());
+
+        let encoded_size = encoded_drive.encoded_size();
+        let mut stream = encoded_drive.into_stream();
+        let mut encoded = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() <= ENCODED_DRIVE_CHUNK_SIZE);
+            encoded.extend_from_slice(&chunk);
+        }
+        assert_eq!(encoded.len() as u64, encoded_size);
+
+        let loaded = DriveLoader::new(&signing_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(
+            loaded.root_cid().await.unwrap(),
+            drive.root_cid().await.unwrap()
+        );
+    }
+}
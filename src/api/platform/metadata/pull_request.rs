@@ -1,13 +1,21 @@
 use async_trait::async_trait;
+use reqwest::header::RANGE;
+use reqwest::RequestBuilder;
 use serde::Serialize;
 
-use crate::api::client::{ApiRequest, DirectResponse, PlatformApiRequest};
+use crate::api::client::{ApiError, ApiRequest, DirectResponse, PlatformApiRequest};
 use crate::api::platform::{ApiDriveId, ApiMetadataId};
 
 #[derive(Serialize)]
 pub(crate) struct PullRequest {
     drive_id: ApiDriveId,
     metadata_id: ApiMetadataId,
+
+    #[serde(skip)]
+    offset: u64,
+
+    #[serde(skip)]
+    compressed: bool,
 }
 
 impl PullRequest {
@@ -15,14 +23,50 @@ impl PullRequest {
         Self {
             drive_id,
             metadata_id,
+            offset: 0,
+            compressed: false,
         }
     }
+
+    /// Allows the platform to compress the metadata it responds with. This only has an effect
+    /// when the `compression` feature is enabled, otherwise the metadata is always requested
+    /// uncompressed.
+    pub(crate) fn accept_compressed(mut self) -> Self {
+        self.compressed = true;
+        self
+    }
+
+    /// Requests the metadata starting from the provided byte offset rather than the beginning.
+    pub(crate) fn starting_at(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
 }
 
 #[async_trait(?Send)]
 impl ApiRequest for PullRequest {
     type Response = DirectResponse;
 
+    async fn add_payload(
+        &mut self,
+        request_builder: RequestBuilder,
+    ) -> Result<RequestBuilder, ApiError> {
+        #[cfg(feature = "compression")]
+        let request_builder = match self.compressed {
+            true => request_builder.header(
+                reqwest::header::ACCEPT_ENCODING,
+                crate::filesystem::ACCEPTED_ENCODINGS,
+            ),
+            false => request_builder,
+        };
+
+        if self.offset == 0 {
+            return Ok(request_builder);
+        }
+
+        Ok(request_builder.header(RANGE, format!("bytes={}-", self.offset)))
+    }
+
     fn path(&self) -> String {
         format!(
             "/api/v1/buckets/{}/metadata/{}/pull",
@@ -5,7 +5,7 @@ use reqwest::multipart::{Form, Part};
 use reqwest::{Body, Method, RequestBuilder, Url};
 use serde::{Deserialize, Serialize};
 
-use crate::api::client::{ApiError, ApiRequest, PlatformApiRequest};
+use crate::api::client::{ApiError, ApiRequest, PlatformApiRequest, UploadLimit};
 use crate::api::platform::{ApiDriveId, ApiMetadataId};
 use crate::codec::crypto::Fingerprint;
 use crate::codec::Cid;
@@ -30,6 +30,7 @@ pub(crate) struct PushRequest {
 }
 
 impl PushRequest {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new<S>(
         drive_id: ApiDriveId,
 
@@ -42,7 +43,8 @@ impl PushRequest {
 
         valid_keys: Vec<Fingerprint>,
         deleted_block_cids: Vec<Cid>,
-    ) -> Result<Self, std::io::Error>
+        upload_limit: &UploadLimit,
+    ) -> Result<Self, ApiError>
     where
         S: Stream<Item = Result<Bytes, std::io::Error>> + Unpin,
     {
@@ -58,7 +60,7 @@ impl PushRequest {
         // note(sstelfox): For the client case and general writing cases we need to wrap this in a
         // stream when not targeting WASM, a rough cut was left above. For expendiency I didn't
         // want to diagnose and test both cases so implmented the universal and simpler one.
-        let body_bytes = crate::api::client::utils::consume_stream_into_bytes(stream_body).await?;
+        let body_bytes = upload_limit.consume(stream_body).await?;
         let stream_body = Body::from(body_bytes);
 
         Ok(Self {
@@ -9,5 +9,6 @@ pub mod drives;
 pub mod metadata;
 pub mod snapshots;
 pub mod status;
+pub mod storage_hosts;
 
 pub use models::*;
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 use crate::api::platform::{DriveKind, StorageClass};
@@ -15,6 +17,10 @@ pub struct ApiDrive {
 
     pub(crate) storage_class: StorageClass,
 
+    // Older platform versions don't report labels, drives without any are treated the same
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) labels: HashMap<String, String>,
+
     // The following two are always present except for during bucket creation
     #[serde(rename = "owner_id", skip_serializing_if = "Option::is_none")]
     _owner_id: Option<String>,
@@ -27,6 +33,30 @@ pub struct ApiDrive {
     _unused_key: Option<InitialBucketKey>,
 }
 
+impl ApiDrive {
+    /// Whether the drive is used interactively or holds backups.
+    pub fn kind(&self) -> DriveKind {
+        self.kind
+    }
+
+    /// Looks up a single label attached to the drive on the platform.
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+
+    /// The labels attached to the drive on the platform. These are stored by the platform in the
+    /// clear alongside the drive's name, unlike the metadata within the encrypted drive itself,
+    /// and can be read without downloading the drive. They shouldn't hold anything sensitive.
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+
+    /// The storage tier the platform keeps the drive's data in.
+    pub fn storage_class(&self) -> StorageClass {
+        self.storage_class
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 struct InitialBucketKey {
@@ -35,8 +65,69 @@ struct InitialBucketKey {
     fingerprint: String,
 }
 
+/// The changes to make to a drive with [`crate::api::platform::drives::update`]. Only the
+/// attributes that have been set are sent, everything else is left as is on the platform.
 #[derive(Debug, Default, Serialize)]
 pub struct ApiDriveUpdateAttributes {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) labels: Option<HashMap<String, String>>,
+}
+
+impl ApiDriveUpdateAttributes {
+    /// Replaces all of the drive's labels with the provided ones, an empty set removes them all.
+    /// See [`ApiDrive::labels`].
+    pub fn with_labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels = Some(labels);
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_are_optional() {
+        let drive: ApiDrive = serde_json::from_value(serde_json::json!({
+            "id": "drive-id",
+            "name": "test",
+            "type": "interactive",
+            "storage_class": "hot",
+        }))
+        .unwrap();
+        assert!(drive.labels().is_empty());
+
+        let drive: ApiDrive = serde_json::from_value(serde_json::json!({
+            "id": "drive-id",
+            "name": "test",
+            "type": "interactive",
+            "storage_class": "hot",
+            "labels": { "app": "photos" },
+        }))
+        .unwrap();
+        assert_eq!(drive.label("app"), Some("photos"));
+        assert_eq!(drive.label("missing"), None);
+
+        // Name only updates are sent the same way they were before labels were supported
+        let name_only = ApiDriveUpdateAttributes::default().with_name("renamed");
+        assert_eq!(
+            serde_json::to_value(&name_only).unwrap(),
+            serde_json::json!({ "name": "renamed" })
+        );
+
+        let labels = HashMap::from([("app".to_string(), "photos".to_string())]);
+        let labelled = ApiDriveUpdateAttributes::default().with_labels(labels);
+        assert_eq!(
+            serde_json::to_value(&labelled).unwrap(),
+            serde_json::json!({ "labels": { "app": "photos" } })
+        );
+    }
 }
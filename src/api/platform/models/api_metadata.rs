@@ -24,6 +24,12 @@ impl ApiMetadata {
         self.id.clone()
     }
 
+    /// The root CID of the drive's filesystem as it was when this version was pushed, encoded the
+    /// same way as [`crate::codec::Cid::as_base64url_multicodec`].
+    pub fn root_cid(&self) -> RootCid {
+        self.root_cid.clone()
+    }
+
     pub fn snapshot_id(&self) -> Option<SnapshotId> {
         self.snapshot_id.clone()
     }
@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The version of the platform API as reported by the platform itself. Only the major and minor
+/// components are tracked, any patch level reported is ignored. Changes to the major version
+/// indicate breaking changes to the API that this library may not be able to handle.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct ApiVersion {
+    major: u32,
+    minor: u32,
+}
+
+impl ApiVersion {
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl Display for ApiVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix('v').unwrap_or(trimmed);
+
+        let mut components = trimmed.splitn(3, '.');
+        let parse_component = |component: Option<&str>| {
+            component
+                .map(|c| c.parse::<u32>())
+                .transpose()
+                .map_err(|_| format!("invalid api version: {}", s))
+        };
+
+        let major = parse_component(components.next())?
+            .ok_or_else(|| format!("invalid api version: {}", s))?;
+        let minor = parse_component(components.next())?.unwrap_or_default();
+
+        Ok(Self { major, minor })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_version_parsing() {
+        assert_eq!("1".parse::<ApiVersion>().unwrap(), ApiVersion::new(1, 0));
+        assert_eq!("v2.3".parse::<ApiVersion>().unwrap(), ApiVersion::new(2, 3));
+        assert_eq!(
+            "1.4.12".parse::<ApiVersion>().unwrap(),
+            ApiVersion::new(1, 4)
+        );
+
+        assert!("".parse::<ApiVersion>().is_err());
+        assert!("one.two".parse::<ApiVersion>().is_err());
+        assert!("1.x".parse::<ApiVersion>().is_err());
+    }
+}
@@ -3,6 +3,7 @@ mod api_drive_key;
 mod api_key;
 mod api_metadata;
 mod api_snapshot;
+mod api_version;
 mod drive_kind;
 mod storage_class;
 
@@ -11,5 +12,6 @@ pub use api_drive_key::{ApiDriveKey, ApiDriveKeyId};
 pub use api_key::{ApiKey, ApiKeyId};
 pub use api_metadata::{ApiMetadata, ApiMetadataId, ApiMetadataState};
 pub use api_snapshot::{ApiSnapshot, ApiSnapshotId, ApiSnapshotState};
+pub use api_version::ApiVersion;
 pub use drive_kind::DriveKind;
 pub use storage_class::StorageClass;
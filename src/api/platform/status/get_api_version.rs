@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::api::client::{ApiRequest, PlatformApiRequest};
+
+pub(crate) struct GetApiVersion;
+
+#[async_trait(?Send)]
+impl ApiRequest for GetApiVersion {
+    type Response = GetApiVersionResponse;
+
+    fn path(&self) -> String {
+        "/_status/version".to_string()
+    }
+}
+
+impl PlatformApiRequest for GetApiVersion {}
+
+#[derive(Deserialize)]
+pub(crate) struct GetApiVersionResponse {
+    api_version: String,
+}
+
+impl GetApiVersionResponse {
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+}
@@ -1,15 +1,93 @@
+mod get_api_version;
 mod get_public_key;
 
+use get_api_version::GetApiVersion;
 use get_public_key::GetPublicKey;
 
 use crate::api::client::{ApiClient, ApiError};
+use crate::api::platform::ApiVersion;
 use crate::codec::crypto::VerifyingKey;
 
+/// Queries the platform for the version of the API it is serving. This bypasses the version
+/// compatibility check the client performs before other platform requests, in most cases
+/// [`ApiClient::platform_api_version`] should be used instead as it caches the result.
+pub async fn get_api_version(client: &ApiClient) -> Result<ApiVersion, ApiError> {
+    let response = match client.platform_request_unchecked(GetApiVersion).await? {
+        Some(resp) => resp,
+        None => return Err(ApiError::UnexpectedResponse("response should not be empty")),
+    };
+
+    response
+        .api_version()
+        .parse()
+        .map_err(ApiError::MismatchedData)
+}
+
+/// Retrieves the key the platform signs its responses and grants with. Like
+/// [`get_api_version`] this is a status endpoint and doesn't wait on the version compatibility
+/// check.
 pub async fn get_public_key(client: &ApiClient) -> Result<VerifyingKey, ApiError> {
-    let response = client.platform_request_full(GetPublicKey).await?;
+    let response = match client.platform_request_unchecked(GetPublicKey).await? {
+        Some(resp) => resp,
+        None => return Err(ApiError::UnexpectedResponse("response should not be empty")),
+    };
 
     let public_key = VerifyingKey::from_spki(response.public_key())
         .map_err(|err| ApiError::MismatchedData(err.to_string()))?;
 
     Ok(public_key)
 }
+
+#[cfg(all(test, feature = "test-utils", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::api::client::MockTransport;
+    use crate::codec::crypto::SigningKey;
+
+    fn test_client() -> (ApiClient, Arc<MockTransport>) {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        (client, transport)
+    }
+
+    #[tokio::test]
+    async fn test_api_version_failures_are_not_cached() {
+        let (client, transport) = test_client();
+
+        transport.push_response(503, "");
+        assert!(matches!(
+            client.platform_api_version().await,
+            Err(ApiError::Message {
+                status_code: 503,
+                ..
+            })
+        ));
+
+        transport.push_json(200, serde_json::json!({ "api_version": "1.2.0" }));
+        let version = client.platform_api_version().await.unwrap().unwrap();
+        assert_eq!(version.major(), 1);
+
+        // Once known the version isn't requested again
+        assert_eq!(client.platform_api_version().await.unwrap(), Some(version));
+        assert_eq!(transport.take_requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_api_version_endpoint_is_cached() {
+        let (client, transport) = test_client();
+
+        transport.push_json(404, serde_json::json!({ "msg": "not found" }));
+        assert_eq!(client.platform_api_version().await.unwrap(), None);
+        assert_eq!(client.platform_api_version().await.unwrap(), None);
+        assert_eq!(transport.take_requests().len(), 1);
+    }
+}
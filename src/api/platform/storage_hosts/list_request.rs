@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::api::client::{ApiError, ApiRequest, PlatformApiRequest};
+
+pub(crate) struct ListRequest;
+
+#[async_trait(?Send)]
+impl ApiRequest for ListRequest {
+    type Response = Vec<InnerStorageHost>;
+
+    fn path(&self) -> String {
+        "/api/v1/storage_hosts".to_string()
+    }
+}
+
+impl PlatformApiRequest for ListRequest {}
+
+#[derive(Deserialize)]
+pub(crate) struct InnerStorageHost {
+    url: String,
+    region: String,
+
+    #[serde(default)]
+    active_grant: bool,
+}
+
+/// A storage host known to the platform, as returned by [`super::list`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageHostInfo {
+    url: Url,
+    region: String,
+    active_grant: bool,
+}
+
+impl StorageHostInfo {
+    /// Whether the current account already holds a grant for this storage host. Data can be
+    /// sent to a host with an active grant without requesting a new one from the platform.
+    pub fn has_active_grant(&self) -> bool {
+        self.active_grant
+    }
+
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// The base URL of the storage host, suitable for use with the storage host API.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl TryFrom<InnerStorageHost> for StorageHostInfo {
+    type Error = ApiError;
+
+    fn try_from(value: InnerStorageHost) -> Result<Self, Self::Error> {
+        let url = Url::parse(&value.url).map_err(|err| {
+            let err_msg = format!("failed to parse storage host URL: {err}");
+            ApiError::InvalidData(err_msg)
+        })?;
+
+        Ok(Self {
+            url,
+            region: value.region,
+            active_grant: value.active_grant,
+        })
+    }
+}
@@ -0,0 +1,93 @@
+mod list_request;
+
+use list_request::ListRequest;
+
+pub use list_request::StorageHostInfo;
+
+use crate::api::client::{ApiClient, ApiError};
+
+/// Lists the storage hosts known to the platform along with whether the current account already
+/// has an active grant with each of them. This allows a client to present the available hosts or
+/// pick one to sync with up front, rather than discovering them as a side effect of requesting
+/// storage grants.
+pub async fn list(client: &ApiClient) -> Result<Vec<StorageHostInfo>, ApiError> {
+    client
+        .platform_request_full(ListRequest)
+        .await?
+        .into_iter()
+        .map(StorageHostInfo::try_from)
+        .collect()
+}
+
+#[cfg(all(test, feature = "test-utils", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::api::client::MockTransport;
+    use crate::codec::crypto::SigningKey;
+
+    #[tokio::test]
+    async fn test_list_storage_hosts() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        transport.push_json(200, serde_json::json!({ "api_version": "1.0.0" }));
+        transport.push_json(
+            200,
+            serde_json::json!([
+                { "url": "https://us.storage.invalid/", "region": "us-east", "active_grant": true },
+                { "url": "https://eu.storage.invalid/", "region": "eu-west" },
+            ]),
+        );
+
+        let hosts = list(&client).await.unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0].url().as_str(), "https://us.storage.invalid/");
+        assert_eq!(hosts[0].region(), "us-east");
+        assert!(hosts[0].has_active_grant());
+        assert!(!hosts[1].has_active_grant());
+
+        let requests = transport.take_requests();
+        assert_eq!(requests[1].url().path(), "/api/v1/storage_hosts");
+
+        transport.push_json(
+            200,
+            serde_json::json!([{ "url": "not a url", "region": "x" }]),
+        );
+        assert!(matches!(list(&client).await, Err(ApiError::InvalidData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_request_with_token_uses_provided_token() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        transport.push_json(200, serde_json::json!([]));
+
+        let hosts = client
+            .request_with_token(&client.base_url(), "delegated-token", ListRequest)
+            .await
+            .unwrap();
+        assert_eq!(hosts.map(|hosts| hosts.len()), Some(0));
+
+        // Only the request itself was sent, without the platform version check
+        let requests = transport.take_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].headers()[reqwest::header::AUTHORIZATION],
+            "Bearer delegated-token"
+        );
+    }
+}
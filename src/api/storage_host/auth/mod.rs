@@ -30,7 +30,9 @@ pub async fn register_grant(
     storage_host_url: &Url,
     grant_token: &str,
 ) -> Result<(), ApiError> {
-    let verifying_key = client.signing_key().verifying_key();
+    let verifying_key = client
+        .storage_host_signing_key(storage_host_url)
+        .verifying_key();
     let request = RegisterGrantRequest::new(verifying_key);
 
     let resp = client
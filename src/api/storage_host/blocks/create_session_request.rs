@@ -8,6 +8,13 @@ use crate::api::client::{ApiError, ApiRequest, StorageHostApiRequest};
 pub(crate) struct CreateSessionRequest {
     metadata_id: String,
     session_data_size: u64,
+
+    // Optional sizing hints, these are left out of the request entirely when not provided so
+    // storage hosts that predate them continue to accept the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_block_size: Option<u64>,
 }
 
 impl CreateSessionRequest {
@@ -15,8 +22,21 @@ impl CreateSessionRequest {
         Self {
             metadata_id: metadata_id.to_string(),
             session_data_size,
+
+            block_count: None,
+            max_block_size: None,
         }
     }
+
+    pub(crate) fn with_block_hints(
+        mut self,
+        block_count: u64,
+        max_block_size: Option<u64>,
+    ) -> Self {
+        self.block_count = Some(block_count);
+        self.max_block_size = max_block_size;
+        self
+    }
 }
 
 #[async_trait(?Send)]
@@ -49,3 +69,23 @@ impl CreateSessionResponse {
         &self.upload_id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_omitted_when_absent() {
+        let request = CreateSessionRequest::new("metadata", 1024);
+        let encoded = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            encoded,
+            serde_json::json!({"metadata_id": "metadata", "session_data_size": 1024})
+        );
+
+        let request = CreateSessionRequest::new("metadata", 1024).with_block_hints(4, Some(256));
+        let encoded = serde_json::to_value(&request).unwrap();
+        assert_eq!(encoded["block_count"], 4);
+        assert_eq!(encoded["max_block_size"], 256);
+    }
+}
@@ -27,6 +27,27 @@ pub async fn create_session(
         .await
 }
 
+/// Behaves the same as [`create_session`] while also telling the storage host how the session's
+/// data will be split up. Hosts use the number of blocks and the size of the largest block (when
+/// known) to pre-allocate for the upload, which matters most when a session is made up of many
+/// small blocks. Hosts that don't understand the hints ignore them and size the session using
+/// `session_data_size` alone.
+pub async fn create_session_with_hints(
+    client: &ApiClient,
+    storage_host_url: &Url,
+    metadata_id: &str,
+    session_data_size: u64,
+    block_count: u64,
+    max_block_size: Option<u64>,
+) -> Result<CreateSessionResponse, ApiError> {
+    let store_request = CreateSessionRequest::new(metadata_id, session_data_size)
+        .with_block_hints(block_count, max_block_size);
+
+    client
+        .storage_host_request_full(storage_host_url, store_request)
+        .await
+}
+
 pub async fn retrieve(
     client: &ApiClient,
     storage_host_url: &Url,
@@ -36,7 +57,8 @@ pub async fn retrieve(
         .storage_host_request_full(storage_host_url, RetrieveRequest::new(block_cid))
         .await?;
 
-    Ok(response.consume().bytes_stream())
+    let stream = response.consume().bytes_stream();
+    Ok(Box::pin(client.bandwidth_limiter().throttle(stream)))
 }
 
 pub async fn store_ongoing<S>(
@@ -53,7 +75,9 @@ where
         upload_id: upload_id.into(),
     };
 
-    let store_request = StoreRequest::new(cid.clone(), lifecycle, stream_body).await?;
+    let stream_body = Box::pin(client.bandwidth_limiter().throttle(stream_body));
+    let store_request =
+        StoreRequest::new(cid.clone(), lifecycle, stream_body, client.upload_limiter()).await?;
 
     client
         .storage_host_request_empty_response(storage_host_url, store_request)
@@ -74,7 +98,9 @@ where
         upload_id: upload_id.into(),
     };
 
-    let store_request = StoreRequest::new(cid.clone(), lifecycle, stream_body).await?;
+    let stream_body = Box::pin(client.bandwidth_limiter().throttle(stream_body));
+    let store_request =
+        StoreRequest::new(cid.clone(), lifecycle, stream_body, client.upload_limiter()).await?;
 
     client
         .storage_host_request_empty_response(storage_host_url, store_request)
@@ -5,7 +5,7 @@ use reqwest::multipart::{Form, Part};
 use reqwest::{Body, Method, RequestBuilder};
 use serde::Serialize;
 
-use crate::api::client::{ApiError, ApiRequest, StorageHostApiRequest};
+use crate::api::client::{ApiError, ApiRequest, StorageHostApiRequest, UploadLimit};
 use crate::codec::Cid;
 
 pub(crate) enum StoreLifecycle {
@@ -24,7 +24,8 @@ impl StoreRequest {
         block_cid: Cid,
         lifecycle: StoreLifecycle,
         stream_body: S,
-    ) -> Result<Self, std::io::Error>
+        upload_limit: &UploadLimit,
+    ) -> Result<Self, ApiError>
     where
         S: Stream<Item = Result<Bytes, std::io::Error>> + Unpin,
     {
@@ -42,7 +43,7 @@ impl StoreRequest {
         // note(sstelfox): For the client case and general writing cases we need to wrap this in a
         // stream when not targeting WASM, a rough cut was left above. For expendiency I didn't
         // want to diagnose and test both cases so implmented the universal and simpler one.
-        let body_bytes = crate::api::client::utils::consume_stream_into_bytes(stream_body).await?;
+        let body_bytes = upload_limit.consume(stream_body).await?;
         let stream_body = Body::from(body_bytes);
 
         Ok(Self {
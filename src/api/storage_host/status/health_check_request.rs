@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::api::client::{ApiRequest, StorageHostApiRequest};
+
+pub(crate) struct HealthCheckRequest;
+
+#[async_trait(?Send)]
+impl ApiRequest for HealthCheckRequest {
+    type Response = HealthCheckResponse;
+
+    fn path(&self) -> String {
+        "/_status/health".to_string()
+    }
+}
+
+impl StorageHostApiRequest for HealthCheckRequest {}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct HealthCheckResponse {
+    #[serde(default)]
+    free_capacity: Option<u64>,
+}
+
+impl HealthCheckResponse {
+    pub fn free_capacity(&self) -> Option<u64> {
+        self.free_capacity
+    }
+}
@@ -0,0 +1,153 @@
+//! Public status endpoints of the storage hosts. These don't require the client to be registered
+//! with the storage host and are safe to call before deciding which host should receive data.
+
+mod health_check_request;
+
+use health_check_request::HealthCheckRequest;
+
+use reqwest::Url;
+
+use crate::api::client::{ApiClient, ApiError};
+
+/// The result of checking in on a storage host with [`ping`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostStatus {
+    reachable: bool,
+    healthy: bool,
+    free_capacity: Option<u64>,
+}
+
+impl HostStatus {
+    /// The number of bytes the storage host reported it has available to accept new data. Not
+    /// every storage host reports its capacity, and this is always `None` when the host isn't
+    /// healthy.
+    pub fn free_capacity(&self) -> Option<u64> {
+        self.free_capacity
+    }
+
+    /// Whether the storage host responded and reported itself as ready to accept requests. Data
+    /// should only be sent to healthy hosts.
+    pub fn healthy(&self) -> bool {
+        self.healthy
+    }
+
+    /// Whether a connection to the storage host could be established at all. A host may be
+    /// reachable while still being unhealthy, such as when it is in the process of shutting down.
+    pub fn reachable(&self) -> bool {
+        self.reachable
+    }
+
+    fn unhealthy() -> Self {
+        Self {
+            reachable: true,
+            healthy: false,
+            free_capacity: None,
+        }
+    }
+
+    fn unreachable() -> Self {
+        Self {
+            reachable: false,
+            healthy: false,
+            free_capacity: None,
+        }
+    }
+}
+
+/// Checks whether the storage host is reachable and healthy using its unauthenticated health
+/// endpoint. Unlike [`crate::api::storage_host::auth::who_am_i`] this doesn't require a grant
+/// token or registration with the host, making it suitable for deciding where to route blocks
+/// before any other interaction with the host.
+///
+/// A host that can't be connected to, or doesn't respond in time, is reported as unreachable
+/// instead of as an error. Errors are only returned for problems on the client side such as an
+/// invalid URL.
+pub async fn ping(client: &ApiClient, storage_host_url: &Url) -> Result<HostStatus, ApiError> {
+    let response = match client
+        .unauthenticated_request(storage_host_url, HealthCheckRequest)
+        .await
+    {
+        Ok(response) => response,
+        Err(ApiError::ClientError(err)) if err.is_connect() || err.is_timeout() => {
+            tracing::warn!(%storage_host_url, "storage host is unreachable: {err}");
+            return Ok(HostStatus::unreachable());
+        }
+        Err(ApiError::Message {
+            status_code,
+            message,
+        }) => {
+            tracing::warn!(%storage_host_url, status_code, "storage host is unhealthy: {message}");
+            return Ok(HostStatus::unhealthy());
+        }
+        Err(ApiError::NotAuthorized) => return Ok(HostStatus::unhealthy()),
+        Err(err) => return Err(err),
+    };
+
+    Ok(HostStatus {
+        reachable: true,
+        healthy: true,
+        free_capacity: response.and_then(|resp| resp.free_capacity()),
+    })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::codec::crypto::SigningKey;
+
+    fn test_client() -> ApiClient {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        ApiClient::new("http://127.0.0.1/", "test-account", key).unwrap()
+    }
+
+    /// Serves a single canned HTTP response on a local port, returning the URL of the server.
+    async fn serve_once(status_line: &'static str, body: &'static str) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await.unwrap();
+
+            let response = format!(
+                "HTTP/1.1 {status_line}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        Url::parse(&format!("http://{address}/")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ping_reports_host_status() {
+        let client = test_client();
+
+        let url = serve_once("200 OK", r#"{"free_capacity":1024}"#).await;
+        let status = ping(&client, &url).await.unwrap();
+        assert!(status.reachable() && status.healthy());
+        assert_eq!(status.free_capacity(), Some(1024));
+
+        let url = serve_once("503 Service Unavailable", r#"{"msg":"draining"}"#).await;
+        let status = ping(&client, &url).await.unwrap();
+        assert!(status.reachable() && !status.healthy());
+
+        // Nothing is listening once the listener has been dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let url = Url::parse(&format!("http://{address}/")).unwrap();
+        let status = ping(&client, &url).await.unwrap();
+        assert!(!status.reachable());
+    }
+}
@@ -141,9 +141,9 @@ impl From<[u8; ACCESS_KEY_LENGTH]> for AccessKey {
 pub enum AccessKeyError<I> {
     /// When decoding the data that is supposed to represent an [`AccessKey`] is not represented as
     /// expected. This error frequently looks like a stream of bytes and its not always clear from
-    /// that why the decoding failed. There are future improvements in the work to improve these
-    /// kinds of errors by switching off of the `nom` library but for now additional context is
-    /// recommended to help identify what the specific failure was.
+    /// that why the decoding failed. There are future improvements in the work to attach more
+    /// context to these kinds of errors but for now additional context is recommended to help
+    /// identify what the specific failure was.
     #[error("decoding data failed: {0}")]
     FormatFailure(#[from] winnow::error::ErrMode<winnow::error::ContextError<I>>),
 
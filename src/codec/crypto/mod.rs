@@ -20,7 +20,7 @@ pub use fingerprint::Fingerprint;
 pub(crate) use hash::Hash;
 pub(crate) use key_id::KeyId;
 pub(crate) use nonce::Nonce;
-pub use signature::Signature;
+pub use signature::{Signature, SignatureError};
 pub use signing_key::SigningKey;
 pub(crate) use sym_locked_access_key::SymLockedAccessKey;
 pub use verifying_key::VerifyingKey;
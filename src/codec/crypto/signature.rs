@@ -2,10 +2,12 @@ use futures::{AsyncWrite, AsyncWriteExt};
 use p384::NistP384;
 use winnow::{token::take, Parser};
 
+use crate::codec::crypto::VerifyingKey;
 use crate::codec::{ParserResult, Stream};
 
 const SIGNATURE_SIZE: usize = 96;
 
+#[derive(Clone, Debug)]
 pub struct Signature {
     inner: ecdsa::Signature<NistP384>,
 }
@@ -41,9 +43,28 @@ impl Signature {
         Ok((remaining, signature))
     }
 
+    pub const fn size() -> usize {
+        SIGNATURE_SIZE
+    }
+
     pub fn to_vec(&self) -> Vec<u8> {
         self.inner.to_vec()
     }
+
+    /// Checks that this signature was produced by the [`crate::codec::crypto::SigningKey`]
+    /// matching the provided [`VerifyingKey`] over exactly the provided data. This mirrors the
+    /// digest used by [`crate::codec::crypto::SigningKey::sign`].
+    pub fn verify(&self, key: &VerifyingKey, data: &[u8]) -> Result<(), SignatureError> {
+        use ecdsa::signature::DigestVerifier;
+        use sha2::{Digest, Sha384};
+
+        let mut digest = Sha384::new();
+        digest.update(data);
+
+        key.verify_digest(digest, &self.inner)?;
+
+        Ok(())
+    }
 }
 
 impl From<ecdsa::Signature<NistP384>> for Signature {
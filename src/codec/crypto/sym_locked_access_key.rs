@@ -45,6 +45,10 @@ impl SymLockedAccessKey {
         Ok((remaining, parsed))
     }
 
+    pub const fn size() -> usize {
+        Nonce::size() + AccessKey::size() + AuthenticationTag::size()
+    }
+
     pub fn unlock(
         &self,
         decryption_key: &AccessKey,
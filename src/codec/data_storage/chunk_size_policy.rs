@@ -0,0 +1,221 @@
+use super::data_options::DataOptions;
+
+/// Chunk sizes are always a power of two no smaller than this, see [`DataOptions`].
+const MIN_CHUNK_SIZE_EXPONENT: u32 = 12;
+
+/// The largest chunk size exponent [`DataOptions`] can represent.
+const MAX_CHUNK_SIZE_EXPONENT: u8 = 15;
+
+/// The largest number of chunks a block can hold is `2^(1 + MAX_CHUNK_COUNT_EXPONENT)`.
+const MAX_CHUNK_COUNT_EXPONENT: u8 = 3;
+
+/// The number of chunks the policy aims to split a file into. Files are split into chunks of the
+/// smallest size that keeps them at or below this count, within the bounds of the policy.
+const TARGET_CHUNKS_PER_FILE: u64 = 64;
+
+/// Decides the size of the chunks file content is split into when it is written to a drive.
+///
+/// Smaller chunks let unchanged parts of similar files share blocks, larger ones keep the number
+/// of blocks a large file needs down. Each file gets the smallest chunk size that fits it into at
+/// most 64 chunks, bounded between the minimum and maximum sizes of the policy. Blocks are sized
+/// to the number of chunks the file needs (between 2 and 16) as unused chunks in a block are still
+/// stored as padding. With the defaults a file of a few kilobytes is stored in a single 8KiB block
+/// while files of hundreds of megabytes use the maximum 4MiB chunks.
+///
+/// When the total size isn't known up front, such as while streaming, the chunk size instead
+/// grows with the amount of data that has been seen so far, see
+/// [`ChunkSizePolicy::streaming_data_options`].
+///
+/// The options used are recorded in each block so content is always read back correctly no matter
+/// which policy wrote it, and a single file may contain blocks of different sizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkSizePolicy {
+    min_exponent: u8,
+    max_exponent: u8,
+}
+
+impl ChunkSizePolicy {
+    fn chunk_data_size(exponent: u8) -> u64 {
+        chunk_options(0, exponent).chunk_data_size() as u64
+    }
+
+    fn chunk_size_exponent(&self, size: u64) -> u8 {
+        let mut exponent = self.min_exponent;
+
+        while exponent < self.max_exponent
+            && Self::chunk_data_size(exponent) * TARGET_CHUNKS_PER_FILE < size
+        {
+            exponent += 1;
+        }
+
+        exponent
+    }
+
+    /// The options for the blocks holding a file of `total_size` bytes.
+    pub fn data_options(&self, total_size: u64) -> DataOptions {
+        let size_exponent = self.chunk_size_exponent(total_size);
+        let chunks_needed = total_size
+            .div_ceil(Self::chunk_data_size(size_exponent))
+            .max(1);
+
+        let mut count_exponent = 0;
+        while count_exponent < MAX_CHUNK_COUNT_EXPONENT && 2u64 << count_exponent < chunks_needed {
+            count_exponent += 1;
+        }
+
+        chunk_options(count_exponent, size_exponent)
+    }
+
+    /// Always uses chunks of the provided size, which is rounded up to the nearest supported size.
+    /// Sizes outside of the supported range are rejected, see [`ChunkSizePolicy::new`].
+    pub fn fixed(chunk_size: u32) -> Result<Self, ChunkSizePolicyError> {
+        Self::new(chunk_size, chunk_size)
+    }
+
+    /// The size of the chunks used for the largest files.
+    pub fn max_chunk_size(&self) -> u32 {
+        chunk_options(0, self.max_exponent).chunk_size()
+    }
+
+    /// The size of the chunks used for the smallest files.
+    pub fn min_chunk_size(&self) -> u32 {
+        chunk_options(0, self.min_exponent).chunk_size()
+    }
+
+    /// Creates a policy choosing chunk sizes between `min_chunk_size` and `max_chunk_size`. Chunk
+    /// sizes are powers of two between 4KiB and 128MiB, both bounds are rounded up to the nearest
+    /// supported size. Bounds outside of that range, or a maximum smaller than the minimum, are
+    /// rejected rather than adjusted.
+    pub fn new(min_chunk_size: u32, max_chunk_size: u32) -> Result<Self, ChunkSizePolicyError> {
+        if max_chunk_size < min_chunk_size {
+            return Err(ChunkSizePolicyError::InvertedBounds(
+                min_chunk_size,
+                max_chunk_size,
+            ));
+        }
+
+        Ok(Self {
+            min_exponent: supported_size_exponent(min_chunk_size)?,
+            max_exponent: supported_size_exponent(max_chunk_size)?,
+        })
+    }
+
+    /// The options for the next block of a stream whose total size isn't known, after `written`
+    /// bytes of it have already been stored. The data seen so far is treated as the size of the
+    /// file, so the chunk size grows as the stream progresses. Blocks always hold the largest
+    /// number of chunks as the stream may continue for some time.
+    pub fn streaming_data_options(&self, written: u64) -> DataOptions {
+        chunk_options(MAX_CHUNK_COUNT_EXPONENT, self.chunk_size_exponent(written))
+    }
+}
+
+impl Default for ChunkSizePolicy {
+    fn default() -> Self {
+        Self::new(4 * 1024, 4 * 1024 * 1024).expect("default chunk sizes to be supported")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChunkSizePolicyError {
+    #[error("minimum chunk size {0} is larger than the maximum chunk size {1}")]
+    InvertedBounds(u32, u32),
+
+    #[error("chunk size {0} is outside of the supported range of 4KiB to 128MiB")]
+    UnsupportedChunkSize(u32),
+}
+
+fn chunk_options(count_exponent: u8, size_exponent: u8) -> DataOptions {
+    DataOptions::new(true, count_exponent, 0, size_exponent)
+        .expect("exponents to be kept within the supported range")
+}
+
+/// The smallest supported chunk size exponent producing chunks of at least `chunk_size` bytes.
+fn supported_size_exponent(chunk_size: u32) -> Result<u8, ChunkSizePolicyError> {
+    let exponent = chunk_size
+        .checked_next_power_of_two()
+        .filter(|_| chunk_size >= 1 << MIN_CHUNK_SIZE_EXPONENT)
+        .and_then(|size| u8::try_from(size.trailing_zeros() - MIN_CHUNK_SIZE_EXPONENT).ok())
+        .filter(|exponent| *exponent <= MAX_CHUNK_SIZE_EXPONENT);
+
+    exponent.ok_or(ChunkSizePolicyError::UnsupportedChunkSize(chunk_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIB: u64 = 1024;
+
+    const MIB: u64 = 1024 * KIB;
+
+    #[test]
+    fn test_chunk_size_follows_file_size() {
+        let policy = ChunkSizePolicy::default();
+        assert_eq!(policy.min_chunk_size(), 4 * 1024);
+        assert_eq!(policy.max_chunk_size(), 4 * 1024 * 1024);
+
+        // Small files get a single minimally sized block
+        let tiny = policy.data_options(10);
+        assert_eq!(tiny.chunk_size(), 4 * 1024);
+        assert_eq!(tiny.chunk_count(), 2);
+
+        let medium = policy.data_options(MIB);
+        assert_eq!(medium.chunk_size(), 32 * 1024);
+        assert_eq!(medium.chunk_count(), 16);
+        assert!(medium.chunk_data_size() as u64 * TARGET_CHUNKS_PER_FILE >= MIB);
+
+        // Large files are capped at the maximum chunk size
+        let huge = policy.data_options(10 * 1024 * MIB);
+        assert_eq!(huge.chunk_size(), 4 * 1024 * 1024);
+
+        let fixed = ChunkSizePolicy::fixed(5000).unwrap();
+        assert_eq!(fixed.data_options(10 * MIB).chunk_size(), 8 * 1024);
+        assert_eq!(fixed.data_options(1).chunk_size(), 8 * 1024);
+    }
+
+    #[test]
+    fn test_bounds_are_validated() {
+        let policy = ChunkSizePolicy::new(5000, 100 * 1024).unwrap();
+        assert_eq!(policy.min_chunk_size(), 8 * 1024);
+        assert_eq!(policy.max_chunk_size(), 128 * 1024);
+
+        let widest = ChunkSizePolicy::new(4 * 1024, 128 * 1024 * 1024).unwrap();
+        assert_eq!(widest.min_chunk_size(), 4 * 1024);
+        assert_eq!(widest.max_chunk_size(), 128 * 1024 * 1024);
+
+        for chunk_size in [0, 4 * 1024 - 1, 128 * 1024 * 1024 + 1, u32::MAX] {
+            assert!(matches!(
+                ChunkSizePolicy::fixed(chunk_size),
+                Err(ChunkSizePolicyError::UnsupportedChunkSize(size)) if size == chunk_size
+            ));
+        }
+
+        assert!(matches!(
+            ChunkSizePolicy::new(1, 64 * 1024),
+            Err(ChunkSizePolicyError::UnsupportedChunkSize(1))
+        ));
+        assert!(matches!(
+            ChunkSizePolicy::new(4 * 1024, u32::MAX),
+            Err(ChunkSizePolicyError::UnsupportedChunkSize(u32::MAX))
+        ));
+        assert!(matches!(
+            ChunkSizePolicy::new(64 * 1024, 4 * 1024),
+            Err(ChunkSizePolicyError::InvertedBounds(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_streaming_chunk_size_grows() {
+        let policy = ChunkSizePolicy::default();
+
+        let mut previous = 0;
+        for written in [0, 256 * KIB, 4 * MIB, 64 * MIB, 1024 * MIB] {
+            let options = policy.streaming_data_options(written);
+            assert!(options.chunk_size() >= previous);
+            assert_eq!(options.chunk_count(), 16);
+            previous = options.chunk_size();
+        }
+
+        assert_eq!(previous, policy.max_chunk_size());
+    }
+}
@@ -212,6 +212,14 @@ impl DataBlock {
         })
     }
 
+    pub fn with_data_options(data_options: DataOptions) -> Result<Self, DataBlockError> {
+        Ok(Self {
+            data_options,
+            cid: Arc::new(RwLock::new(None)),
+            contents: Vec::new(),
+        })
+    }
+
     pub fn standard() -> Result<Self, DataBlockError> {
         let data_options = DataOptions::standard_encrypted_no_ecc();
 
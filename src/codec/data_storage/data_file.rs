@@ -0,0 +1,214 @@
+use elliptic_curve::rand_core::CryptoRngCore;
+use futures::AsyncWrite;
+use winnow::error::ErrMode;
+
+use super::data_block::DataBlock;
+use super::data_options::DataOptions;
+use crate::codec::header::BANYAN_DATA_MAGIC;
+use crate::codec::{Cid, Stream};
+
+/// Size of the portion of an encoded [`DataBlock`] that precedes the data its CID is calculated
+/// over: the magic, the format version, the CID itself, and the block's options.
+const BLOCK_HEADER_SIZE: usize = BANYAN_DATA_MAGIC.len() + 1 + Cid::size() + DataOptions::size();
+
+/// Iterates over the encoded [`DataBlock`]s held in a data file (conventionally using the `.bfd`
+/// extension). A data file is one or more blocks written back to back, each starting with the
+/// `BYFD` magic, exactly as produced by [`DataFileWriter`] or a single [`DataBlock::encode`].
+///
+/// Each block's recorded CID is checked against its contents before it is returned. Iteration
+/// stops after the first error as the position of any following block can't be trusted.
+pub struct DataFileReader<'a> {
+    remaining: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> DataFileReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            remaining: data,
+            failed: false,
+        }
+    }
+
+    fn read_block(&mut self) -> Result<(Cid, DataBlock), DataFileError> {
+        if !self.remaining.starts_with(BANYAN_DATA_MAGIC) {
+            return Err(DataFileError::InvalidMagic);
+        }
+
+        // The version is checked by the full parse, only the options are needed up front
+        let header = self
+            .remaining
+            .get(BANYAN_DATA_MAGIC.len() + 1..)
+            .ok_or(DataFileError::Truncated)?;
+        let (input, _cid) = Cid::parse(Stream::new(header)).map_err(parse_error)?;
+        let (_, data_options) = DataOptions::parse(input).map_err(parse_error)?;
+
+        // Checked ahead of the full parse which doesn't support these yet
+        if !data_options.encrypted || data_options.ecc_present() {
+            return Err(DataFileError::Unsupported(data_options));
+        }
+
+        let (remaining, block) =
+            DataBlock::parse_with_magic(Stream::new(self.remaining)).map_err(parse_error)?;
+
+        let block_len = self.remaining.len() - remaining.len();
+        let payload = &self.remaining[BLOCK_HEADER_SIZE..block_len];
+
+        let cid = block.cid().expect("parsed blocks always have a CID");
+        if crate::utils::calculate_cid(payload) != cid {
+            return Err(DataFileError::CidMismatch(cid));
+        }
+
+        self.remaining = &self.remaining[block_len..];
+
+        Ok((cid, block))
+    }
+}
+
+impl Iterator for DataFileReader<'_> {
+    type Item = Result<(Cid, DataBlock), DataFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.remaining.is_empty() {
+            return None;
+        }
+
+        let result = self.read_block();
+        self.failed = result.is_err();
+
+        Some(result)
+    }
+}
+
+/// Writes [`DataBlock`]s one after another to produce a data file that can be read back with a
+/// [`DataFileReader`].
+pub struct DataFileWriter<W: AsyncWrite + Unpin + Send> {
+    writer: W,
+    block_cids: Vec<Cid>,
+    written_bytes: usize,
+}
+
+impl<W: AsyncWrite + Unpin + Send> DataFileWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            block_cids: Vec::new(),
+            written_bytes: 0,
+        }
+    }
+
+    /// The CIDs of the blocks written so far, in the order they appear in the file.
+    pub fn block_cids(&self) -> &[Cid] {
+        &self.block_cids
+    }
+
+    /// Returns the underlying writer along with the CIDs of every block that was written.
+    pub fn finish(self) -> (W, Vec<Cid>) {
+        (self.writer, self.block_cids)
+    }
+
+    /// Encodes the block onto the end of the file, returning its CID.
+    pub async fn write_block(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        block: &DataBlock,
+    ) -> std::io::Result<Cid> {
+        let (written_bytes, _chunk_cids) = block.encode(rng, &mut self.writer).await?;
+        self.written_bytes += written_bytes;
+
+        let cid = block
+            .cid()
+            .expect("blocks always have a CID once they've been encoded");
+        self.block_cids.push(cid.clone());
+
+        Ok(cid)
+    }
+
+    pub fn written_bytes(&self) -> usize {
+        self.written_bytes
+    }
+}
+
+fn parse_error<E: std::fmt::Debug>(err: ErrMode<E>) -> DataFileError {
+    match err {
+        ErrMode::Incomplete(_) => DataFileError::Truncated,
+        err => DataFileError::Malformed(format!("{err:?}")),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DataFileError {
+    #[error("block content did not match its recorded CID {0:?}")]
+    CidMismatch(Cid),
+
+    #[error("block did not start with the data file magic")]
+    InvalidMagic,
+
+    #[error("block could not be parsed: {0}")]
+    Malformed(String),
+
+    #[error("data file ended part way through a block")]
+    Truncated,
+
+    #[error("blocks with options {0:?} are not yet supported")]
+    Unsupported(DataOptions),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::codec::crypto::AccessKey;
+    use crate::codec::data_storage::data_chunk::DataChunk;
+
+    async fn encrypted_block(rng: &mut impl CryptoRngCore, contents: &[u8]) -> DataBlock {
+        let key = AccessKey::generate(rng);
+        let mut block = DataBlock::small().unwrap();
+        let options = block.data_options();
+
+        let chunk = DataChunk::from_slice(contents, &options)
+            .unwrap()
+            .encrypt(rng, &options, &key)
+            .await
+            .unwrap();
+        block.push_chunk(chunk).unwrap();
+
+        block
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_data_file_round_trip() {
+        let mut rng = crate::utils::crypto_rng();
+
+        let mut writer = DataFileWriter::new(Vec::new());
+        for contents in [&b"first block"[..], &b"second block"[..]] {
+            let block = encrypted_block(&mut rng, contents).await;
+            writer.write_block(&mut rng, &block).await.unwrap();
+        }
+
+        let (file, written_cids) = writer.finish();
+        assert_eq!(written_cids.len(), 2);
+
+        let read_cids: Vec<Cid> = DataFileReader::new(&file)
+            .map(|block| block.unwrap().0)
+            .collect();
+        assert_eq!(read_cids, written_cids);
+
+        // A truncated file reports the first block and stops at the second
+        let truncated = &file[..file.len() - 10];
+        let results: Vec<_> = DataFileReader::new(truncated).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(DataFileError::Truncated)));
+
+        // Tampering with the contents of a block is caught by its CID
+        let mut tampered = file.clone();
+        tampered[BLOCK_HEADER_SIZE + 10] ^= 0xff;
+        let first = DataFileReader::new(&tampered).next().unwrap();
+        assert!(matches!(first, Err(DataFileError::CidMismatch(_))));
+
+        let first = DataFileReader::new(b"not a data file").next().unwrap();
+        assert!(matches!(first, Err(DataFileError::InvalidMagic)));
+    }
+}
@@ -1,6 +1,10 @@
+pub mod chunk_size_policy;
 pub mod data_block;
 pub mod data_chunk;
+pub mod data_file;
 pub mod data_options;
 pub mod encrypted_data_chunk;
 
+pub use chunk_size_policy::{ChunkSizePolicy, ChunkSizePolicyError};
 pub use data_block::{DataBlock, DataBlockError};
+pub use data_file::{DataFileError, DataFileReader, DataFileWriter};
@@ -4,4 +4,4 @@ mod permissions;
 
 pub use block_kind::BlockKind;
 pub use node_kind::NodeKind;
-pub use permissions::Permissions;
+pub use permissions::{Permissions, PermissionsError};
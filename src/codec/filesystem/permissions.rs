@@ -11,6 +11,20 @@ const PERMISSIONS_IMMUTABLE: u8 = 0b0000_0010;
 
 const PERMISSIONS_OWNER_WRITE_ONLY: u8 = 0b0000_0001;
 
+/// The permission flags carried by files and directories, encoded as a single byte. Only the
+/// lowest three bits are defined by the format:
+///
+/// | Bit | Flag                              | Meaning                                          |
+/// |-----|-----------------------------------|--------------------------------------------------|
+/// | 0   | [`Permissions::owner_write_only`] | Only the node's owner should make changes to it  |
+/// | 1   | [`Permissions::immutable`]        | The node shouldn't be changed by anyone          |
+/// | 2   | [`Permissions::executable`]       | The file should be treated as executable         |
+/// | 3-7 | reserved                          | Must be zero                                     |
+///
+/// The reserved bits may be assigned meaning by future versions of the format and can't be used
+/// by clients for their own flags. Parsing a node with any of them set fails when the `strict`
+/// feature is enabled and otherwise they are silently dropped. Clients that need flags of their
+/// own should store them as a custom metadata attribute instead.
 // todo(sstelfox): We only need one type of permission, they can be shared to simplify the
 // protocol.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -25,10 +39,9 @@ impl Permissions {
         self.owner_write_only
     }
 
-    pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
-        &self,
-        writer: &mut W,
-    ) -> std::io::Result<usize> {
+    /// The encoded form of the permissions, see the [`Permissions`] documentation for the meaning
+    /// of each bit.
+    pub fn bits(&self) -> u8 {
         let mut options: u8 = 0x00;
 
         if self.owner_write_only {
@@ -43,11 +56,36 @@ impl Permissions {
             options |= PERMISSIONS_IMMUTABLE;
         }
 
-        writer.write_all(&[options]).await?;
+        options
+    }
 
+    pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        writer.write_all(&[self.bits()]).await?;
         Ok(1)
     }
 
+    /// Builds permissions from their encoded form. Any of the reserved bits being set is an
+    /// error, see the [`Permissions`] documentation for the bits that are defined.
+    pub fn from_bits(bits: u8) -> Result<Self, PermissionsError> {
+        let reserved = bits & PERMISSIONS_RESERVED_MASK;
+        if reserved != 0 {
+            return Err(PermissionsError::ReservedBits(reserved));
+        }
+
+        Ok(Self::from_defined_bits(bits))
+    }
+
+    fn from_defined_bits(bits: u8) -> Self {
+        Self {
+            owner_write_only: bits & PERMISSIONS_OWNER_WRITE_ONLY != 0,
+            executable: bits & PERMISSIONS_EXECUTABLE != 0,
+            immutable: bits & PERMISSIONS_IMMUTABLE != 0,
+        }
+    }
+
     pub fn executable(&self) -> bool {
         self.executable
     }
@@ -56,6 +94,21 @@ impl Permissions {
         self.immutable
     }
 
+    pub fn with_executable(mut self, executable: bool) -> Self {
+        self.executable = executable;
+        self
+    }
+
+    pub fn with_immutable(mut self, immutable: bool) -> Self {
+        self.immutable = immutable;
+        self
+    }
+
+    pub fn with_owner_write_only(mut self, owner_write_only: bool) -> Self {
+        self.owner_write_only = owner_write_only;
+        self
+    }
+
     pub fn parse(input: Stream) -> ParserResult<Self> {
         let (input, byte) = le_u8.parse_peek(input)?;
 
@@ -67,20 +120,36 @@ impl Permissions {
             return Err(winnow::error::ErrMode::Cut(err));
         }
 
-        let owner_write_only = byte & PERMISSIONS_OWNER_WRITE_ONLY != 0;
-        let executable = byte & PERMISSIONS_EXECUTABLE != 0;
-        let immutable = byte & PERMISSIONS_IMMUTABLE != 0;
-
-        let permissions = Self {
-            owner_write_only,
-            executable,
-            immutable,
-        };
-
-        Ok((input, permissions))
+        Ok((input, Self::from_defined_bits(byte)))
     }
 
     pub const fn size() -> usize {
         1
     }
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum PermissionsError {
+    #[error("reserved permission bits can't be set: {0:#010b}")]
+    ReservedBits(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_round_trip_and_reserved_bits_rejected() {
+        let permissions = Permissions::default()
+            .with_executable(true)
+            .with_owner_write_only(true);
+
+        assert_eq!(permissions.bits(), 0b0000_0101);
+        assert_eq!(Permissions::from_bits(0b0000_0101).unwrap(), permissions);
+
+        assert!(matches!(
+            Permissions::from_bits(0b0001_0100),
+            Err(PermissionsError::ReservedBits(0b0001_0000))
+        ));
+    }
+}
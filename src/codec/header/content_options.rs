@@ -3,7 +3,15 @@ use winnow::{binary::le_u8, Parser};
 
 use crate::codec::{ParserResult, Stream};
 
-const CONTENT_OPTIONS_RESERVED_MASK: u8 = 0b1111_1000;
+const CONTENT_OPTIONS_RESERVED_MASK: u8 = 0b1000_0000;
+
+const CONTENT_OPTIONS_MANIFEST_BIT: u8 = 0b0100_0000;
+
+const CONTENT_OPTIONS_DELTA_BIT: u8 = 0b0010_0000;
+
+const CONTENT_OPTIONS_JOURNAL_BIT: u8 = 0b0001_0000;
+
+const CONTENT_OPTIONS_SHARDED_BIT: u8 = 0b0000_1000;
 
 const CONTENT_OPTIONS_FILESYSTEM_BIT: u8 = 0b0000_0100;
 
@@ -11,11 +19,15 @@ const CONTENT_OPTIONS_MAINTENANCE_BIT: u8 = 0b0000_0010;
 
 const CONTENT_OPTIONS_DATA_BIT: u8 = 0b0000_0001;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct ContentOptions {
     filesystem: bool,
     maintenance: bool,
     data: bool,
+    sharded: bool,
+    journal: bool,
+    delta: bool,
+    manifest: bool,
 }
 
 impl ContentOptions {
@@ -24,6 +36,10 @@ impl ContentOptions {
             filesystem: false,
             maintenance: false,
             data: true,
+            sharded: false,
+            journal: false,
+            delta: false,
+            manifest: false,
         }
     }
 
@@ -32,6 +48,10 @@ impl ContentOptions {
             filesystem: true,
             maintenance: true,
             data: true,
+            sharded: false,
+            journal: false,
+            delta: false,
+            manifest: false,
         }
     }
 
@@ -40,6 +60,53 @@ impl ContentOptions {
             filesystem: true,
             maintenance: true,
             data: true,
+            sharded: false,
+            journal: false,
+            delta: false,
+            manifest: false,
+        }
+    }
+
+    /// Identical to [`ContentOptions::sharded_metadata`] but shards that are unchanged from a
+    /// previous version of the drive may be referenced rather than included, see
+    /// [`crate::filesystem::Drive::encode_delta`]. Loading a drive encoded this way requires the
+    /// previous version, see [`crate::filesystem::DriveLoader::with_previous_version`].
+    pub fn delta_metadata() -> Self {
+        Self {
+            delta: true,
+            ..Self::sharded_metadata()
+        }
+    }
+
+    /// Identical to [`ContentOptions::metadata`] but the filesystem metadata will be split into
+    /// multiple independently encrypted shards. Drives encoded this way only need to re-encrypt
+    /// the portions of the filesystem that have changed since they were last loaded or encoded.
+    pub fn sharded_metadata() -> Self {
+        Self {
+            sharded: true,
+            ..Self::metadata()
+        }
+    }
+
+    /// Includes the drive's journal of [`crate::codec::meta::JournalCheckpoint`]s after the
+    /// filesystem, see [`crate::filesystem::Drive::checkpoints`]. Encoding a drive with the
+    /// journal records a checkpoint for the current version of the filesystem when it has changed
+    /// since the last one. Readers that predate the journal and are built with the `strict`
+    /// feature reject encodings that include it.
+    pub fn with_journal(self) -> Self {
+        Self {
+            journal: true,
+            ..self
+        }
+    }
+
+    /// Appends a signed [`crate::codec::meta::IntegrityManifest`] to the encoded drive allowing
+    /// recipients to detect truncated or modified copies of it. The manifest records the root of
+    /// the filesystem so it is only included alongside the filesystem.
+    pub fn with_integrity_manifest(self) -> Self {
+        Self {
+            manifest: true,
+            ..self
         }
     }
 
@@ -61,6 +128,22 @@ impl ContentOptions {
             options |= CONTENT_OPTIONS_DATA_BIT;
         }
 
+        if self.sharded {
+            options |= CONTENT_OPTIONS_SHARDED_BIT;
+        }
+
+        if self.journal {
+            options |= CONTENT_OPTIONS_JOURNAL_BIT;
+        }
+
+        if self.delta {
+            options |= CONTENT_OPTIONS_DELTA_BIT;
+        }
+
+        if self.manifest {
+            options |= CONTENT_OPTIONS_MANIFEST_BIT;
+        }
+
         writer.write_all(&[options]).await?;
 
         Ok(1)
@@ -80,20 +163,50 @@ impl ContentOptions {
         let filesystem = byte & CONTENT_OPTIONS_FILESYSTEM_BIT != 0;
         let maintenance = byte & CONTENT_OPTIONS_MAINTENANCE_BIT != 0;
         let data = byte & CONTENT_OPTIONS_DATA_BIT != 0;
+        let sharded = byte & CONTENT_OPTIONS_SHARDED_BIT != 0;
+        let journal = byte & CONTENT_OPTIONS_JOURNAL_BIT != 0;
+        let delta = byte & CONTENT_OPTIONS_DELTA_BIT != 0;
+        let manifest = byte & CONTENT_OPTIONS_MANIFEST_BIT != 0;
 
         let content_options = ContentOptions {
             filesystem,
             maintenance,
             data,
+            sharded,
+            journal,
+            delta,
+            manifest,
         };
 
         Ok((input, content_options))
     }
 
+    /// Whether an [`crate::codec::meta::IntegrityManifest`] follows the filesystem (and journal
+    /// when present) at the end of the drive.
+    pub fn include_integrity_manifest(&self) -> bool {
+        self.filesystem && self.manifest
+    }
+
     pub fn include_filesystem(&self) -> bool {
         self.filesystem
     }
 
+    /// Whether shards of the filesystem may be references to a previous version of the drive
+    /// rather than being included. Only applies to sharded filesystems.
+    pub fn delta_filesystem(&self) -> bool {
+        self.sharded && self.delta
+    }
+
+    /// Whether the drive's journal of [`crate::codec::meta::JournalCheckpoint`]s follows the
+    /// filesystem. The journal is only included alongside the filesystem.
+    pub fn include_journal(&self) -> bool {
+        self.filesystem && self.journal
+    }
+
+    pub fn sharded_filesystem(&self) -> bool {
+        self.sharded
+    }
+
     pub const fn size() -> usize {
         1
     }
@@ -36,6 +36,10 @@ impl IdentityHeader {
 
         Ok((input, Self))
     }
+
+    pub const fn size() -> usize {
+        BANYAN_FS_MAGIC.len() + 1
+    }
 }
 
 fn banyanfs_magic_tag(input: Stream) -> ParserResult<&[u8]> {
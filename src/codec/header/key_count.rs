@@ -20,6 +20,10 @@ impl KeyCount {
         let (input, count) = take(1u8).parse_peek(input)?;
         Ok((input, Self(count[0])))
     }
+
+    pub(crate) const fn size() -> usize {
+        1
+    }
 }
 
 impl Deref for KeyCount {
@@ -72,6 +72,10 @@ impl PublicSettings {
     pub fn private(&self) -> bool {
         self.private
     }
+
+    pub const fn size() -> usize {
+        1
+    }
 }
 
 #[cfg(test)]
@@ -5,7 +5,7 @@ use winnow::{binary::le_u8, token::take, Parser};
 use crate::codec::{
     crypto::{AccessKey, AsymLockedAccessKey, AsymLockedAccessKeyError, SigningKey, VerifyingKey},
     header::AccessMask,
-    meta::{UserAgent, VectorClockActorSnapshot},
+    meta::{UserAgent, VectorClockActorSnapshot, WrappedKeyEntry},
     ParserResult, Stream,
 };
 
@@ -228,6 +228,54 @@ impl ActorSettings {
             + 3 * (1 + AsymLockedAccessKey::size())
     }
 
+    /// Replaces the actor's permission keys with the locked copies present in the entry, leaving
+    /// any keys the entry doesn't hold untouched. Used when the actor's own escrowed keys are
+    /// unusable, the caller is responsible for confirming the entry's keys unlock for the actor.
+    pub(crate) fn replace_wrapped_keys(&mut self, entry: &WrappedKeyEntry) {
+        if let Some(key) = entry.filesystem_key() {
+            self.filesystem_key = Some(key.clone());
+            self.access_mask.set_filesystem_key_present(true);
+        }
+
+        if let Some(key) = entry.data_key() {
+            self.data_key = Some(key.clone());
+            self.access_mask.set_data_key_present(true);
+        }
+
+        if let Some(key) = entry.maintenance_key() {
+            self.maintenance_key = Some(key.clone());
+            self.access_mask.set_maintenance_key_present(true);
+        }
+    }
+
+    /// Fills in any of the actor's permission keys that are missing with the locked copies
+    /// present in the entry, returning how many were restored. Keys the actor already holds are
+    /// never replaced. The caller is responsible for confirming the entry belongs to this actor
+    /// and may hold the restored keys.
+    pub(crate) fn restore_wrapped_keys(&mut self, entry: &WrappedKeyEntry) -> usize {
+        let mut restored = 0;
+
+        if let (None, Some(key)) = (&self.filesystem_key, entry.filesystem_key()) {
+            self.filesystem_key = Some(key.clone());
+            self.access_mask.set_filesystem_key_present(true);
+            restored += 1;
+        }
+
+        if let (None, Some(key)) = (&self.data_key, entry.data_key()) {
+            self.data_key = Some(key.clone());
+            self.access_mask.set_data_key_present(true);
+            restored += 1;
+        }
+
+        if let (None, Some(key)) = (&self.maintenance_key, entry.maintenance_key()) {
+            self.maintenance_key = Some(key.clone());
+            self.access_mask.set_maintenance_key_present(true);
+            restored += 1;
+        }
+
+        restored
+    }
+
     pub fn update_user_agent(&mut self) {
         self.user_agent = UserAgent::current();
     }
@@ -236,6 +284,28 @@ impl ActorSettings {
         self.user_agent.clone()
     }
 
+    /// The locked copies of the permission keys escrowed for this actor. Only the keys whose
+    /// unlocked form is provided are included, as the entry's check value is derived from them.
+    pub(crate) fn wrapped_keys(
+        &self,
+        filesystem_key: Option<&AccessKey>,
+        data_key: Option<&AccessKey>,
+        maintenance_key: Option<&AccessKey>,
+    ) -> WrappedKeyEntry {
+        // A key is only included when both its locked copy and unlocked form are available
+        let filesystem_key = self.filesystem_key.as_ref().and(filesystem_key);
+        let data_key = self.data_key.as_ref().and(data_key);
+        let maintenance_key = self.maintenance_key.as_ref().and(maintenance_key);
+
+        WrappedKeyEntry::new(
+            self.verifying_key.clone(),
+            filesystem_key.and(self.filesystem_key.clone()),
+            data_key.and(self.data_key.clone()),
+            maintenance_key.and(self.maintenance_key.clone()),
+            WrappedKeyEntry::key_check_for(filesystem_key, data_key, maintenance_key),
+        )
+    }
+
     pub fn vector_clock(&self) -> VectorClockActorSnapshot {
         self.vector_clock
     }
@@ -257,7 +327,7 @@ pub enum ActorSettingsError {
     UnlockFailed(AsymLockedAccessKeyError),
 }
 
-fn decode_optional_key(input: Stream) -> ParserResult<Option<AsymLockedAccessKey>> {
+pub(super) fn decode_optional_key(input: Stream) -> ParserResult<Option<AsymLockedAccessKey>> {
     let (input, presence_flag) = le_u8.parse_peek(input)?;
 
     if cfg!(feature = "strict") && presence_flag != 0 && presence_flag != KEY_PRESENT_BIT {
@@ -276,7 +346,7 @@ fn decode_optional_key(input: Stream) -> ParserResult<Option<AsymLockedAccessKey
     }
 }
 
-async fn encode_optional_key<W: AsyncWrite + Unpin + Send>(
+pub(super) async fn encode_optional_key<W: AsyncWrite + Unpin + Send>(
     writer: &mut W,
     key: &Option<AsymLockedAccessKey>,
 ) -> std::io::Result<usize> {
@@ -99,10 +99,7 @@ impl TryFrom<&str> for Cid {
     type Error = CidError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut value = value.to_string();
-        if value.remove(0) != 'u' {
-            return Err(CidError::InvalidEncoding);
-        }
+        let value = value.strip_prefix('u').ok_or(CidError::InvalidEncoding)?;
 
         use base64::engine::general_purpose::URL_SAFE_NO_PAD;
         use base64::Engine;
@@ -111,7 +108,7 @@ impl TryFrom<&str> for Cid {
             .decode(value.as_bytes())
             .map_err(|_| CidError::InvalidEncoding)?;
 
-        if data[0..4] != [0x01, 0x55, 0x1e, 0x20] {
+        if data.get(0..4) != Some(&[0x01, 0x55, 0x1e, 0x20][..]) {
             return Err(CidError::InvalidEncoding);
         }
 
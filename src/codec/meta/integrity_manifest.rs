@@ -0,0 +1,268 @@
+use elliptic_curve::rand_core::CryptoRngCore;
+use futures::{AsyncWrite, AsyncWriteExt};
+use winnow::binary::le_u64;
+use winnow::token::{literal, take};
+use winnow::Parser;
+
+use crate::codec::crypto::{Signature, SigningKey, VerifyingKey};
+use crate::codec::meta::Cid;
+use crate::codec::{ParserResult, Stream};
+
+const INTEGRITY_MANIFEST_MAGIC: &[u8] = b"BFSMANIFEST1";
+
+const CONTENT_HASH_LENGTH: usize = 32;
+
+/// A signed record appended to the end of an encoded drive when it was encoded with
+/// [`crate::codec::header::ContentOptions::with_integrity_manifest`]. It records the length and a
+/// blake3 hash of the encoded drive preceding it along with the CID of the drive's root directory,
+/// signed by the key that encoded the drive. Changing any byte of the drive invalidates it.
+///
+/// The length, hash, and signature can be checked by anyone holding the encoded drive, without any
+/// of the drive's keys, using [`IntegrityManifest::from_trailing`]. This catches truncated or
+/// modified copies of the drive before any attempt is made to load it. Loading the drive checks
+/// the manifest again along with the parts that require access to the drive: the root CID has to
+/// match the loaded filesystem and the signer has to be one of the drive's actors.
+///
+/// The manifest only vouches for the encoded drive as a whole, anyone able to produce a signature
+/// can append a valid looking manifest to a drive of their own. Who signed the manifest should be
+/// confirmed before it is trusted, which loading the drive takes care of.
+#[derive(Clone, Debug)]
+pub struct IntegrityManifest {
+    total_length: u64,
+    content_hash: [u8; CONTENT_HASH_LENGTH],
+    root_cid: Cid,
+    verifying_key: VerifyingKey,
+    signature: Signature,
+}
+
+impl IntegrityManifest {
+    pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        writer.write_all(INTEGRITY_MANIFEST_MAGIC).await?;
+        let mut written_bytes = INTEGRITY_MANIFEST_MAGIC.len();
+
+        let length_bytes = self.total_length.to_le_bytes();
+        writer.write_all(&length_bytes).await?;
+        written_bytes += length_bytes.len();
+
+        writer.write_all(&self.content_hash).await?;
+        written_bytes += self.content_hash.len();
+
+        written_bytes += self.root_cid.encode(writer).await?;
+        written_bytes += self.verifying_key.encode(writer).await?;
+        written_bytes += self.signature.encode(writer).await?;
+
+        Ok(written_bytes)
+    }
+
+    /// Locates the manifest at the end of a complete encoded drive and confirms it was signed by
+    /// the key it names and covers exactly the data preceding it, both its length and content. No
+    /// keys to the drive are needed, though the signer and root CID can only be checked against
+    /// the drive itself by loading it.
+    pub fn from_trailing(data: &[u8]) -> Result<Self, IntegrityManifestError> {
+        let manifest_start = data
+            .len()
+            .checked_sub(Self::size())
+            .ok_or(IntegrityManifestError::Missing)?;
+
+        let (_, manifest) = Self::parse(Stream::new(&data[manifest_start..]))
+            .map_err(|_| IntegrityManifestError::Missing)?;
+
+        let content_hash = blake3::hash(&data[..manifest_start]);
+        manifest.verify(manifest_start as u64, content_hash.as_bytes())?;
+
+        Ok(manifest)
+    }
+
+    pub fn parse(input: Stream) -> ParserResult<Self> {
+        let (input, _magic) = literal(INTEGRITY_MANIFEST_MAGIC).parse_peek(input)?;
+        let (input, total_length) = le_u64.parse_peek(input)?;
+        let (input, content_hash) = take(CONTENT_HASH_LENGTH).parse_peek(input)?;
+        let content_hash = content_hash.try_into().expect("taken the exact length");
+        let (input, root_cid) = Cid::parse(input)?;
+        let (input, verifying_key) = VerifyingKey::parse(input)?;
+        let (input, signature) = Signature::parse(input)?;
+
+        let manifest = Self {
+            total_length,
+            content_hash,
+            root_cid,
+            verifying_key,
+            signature,
+        };
+
+        Ok((input, manifest))
+    }
+
+    /// The CID of the root directory of the drive when it was encoded.
+    pub fn root_cid(&self) -> &Cid {
+        &self.root_cid
+    }
+
+    pub(crate) fn sign(
+        rng: &mut impl CryptoRngCore,
+        signing_key: &SigningKey,
+        total_length: u64,
+        content_hash: [u8; CONTENT_HASH_LENGTH],
+        root_cid: Cid,
+    ) -> Self {
+        let signature = signing_key.sign(rng, &signed_data(total_length, &content_hash, &root_cid));
+
+        Self {
+            total_length,
+            content_hash,
+            root_cid,
+            verifying_key: signing_key.verifying_key(),
+            signature,
+        }
+    }
+
+    pub const fn size() -> usize {
+        INTEGRITY_MANIFEST_MAGIC.len()
+            + 8
+            + CONTENT_HASH_LENGTH
+            + Cid::size()
+            + VerifyingKey::size()
+            + Signature::size()
+    }
+
+    /// The length of the encoded drive preceding the manifest.
+    pub fn total_length(&self) -> u64 {
+        self.total_length
+    }
+
+    /// The key of the actor that encoded the drive and signed the manifest.
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.verifying_key
+    }
+
+    /// Confirms the manifest's signature and that it covers `actual_length` bytes whose blake3
+    /// hash is `actual_hash`.
+    pub(crate) fn verify(
+        &self,
+        actual_length: u64,
+        actual_hash: &[u8; CONTENT_HASH_LENGTH],
+    ) -> Result<(), IntegrityManifestError> {
+        self.signature
+            .verify(
+                &self.verifying_key,
+                &signed_data(self.total_length, &self.content_hash, &self.root_cid),
+            )
+            .map_err(|_| IntegrityManifestError::InvalidSignature)?;
+
+        if self.total_length != actual_length {
+            return Err(IntegrityManifestError::LengthMismatch {
+                expected: self.total_length,
+                actual: actual_length,
+            });
+        }
+
+        if &self.content_hash != actual_hash {
+            return Err(IntegrityManifestError::ContentMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+fn signed_data(
+    total_length: u64,
+    content_hash: &[u8; CONTENT_HASH_LENGTH],
+    root_cid: &Cid,
+) -> Vec<u8> {
+    let mut data =
+        Vec::with_capacity(INTEGRITY_MANIFEST_MAGIC.len() + 8 + CONTENT_HASH_LENGTH + Cid::size());
+
+    data.extend_from_slice(INTEGRITY_MANIFEST_MAGIC);
+    data.extend_from_slice(&total_length.to_le_bytes());
+    data.extend_from_slice(content_hash);
+    data.extend_from_slice(root_cid.as_bytes());
+
+    data
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IntegrityManifestError {
+    #[error("manifest hash doesn't match the data preceding it")]
+    ContentMismatch,
+
+    #[error("manifest was signed by a key that isn't an actor of the drive")]
+    UnknownSigner,
+
+    #[error("manifest signature is invalid")]
+    InvalidSignature,
+
+    #[error("manifest covers {expected} bytes but {actual} bytes precede it")]
+    LengthMismatch { expected: u64, actual: u64 },
+
+    #[error("no integrity manifest is present at the end of the data")]
+    Missing,
+
+    #[error("manifest records a different root CID than the loaded drive")]
+    RootMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_trailing_manifest_detects_tampering() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = SigningKey::generate(&mut rng);
+
+        let mut data = b"encoded drive".to_vec();
+        let manifest = IntegrityManifest::sign(
+            &mut rng,
+            &signing_key,
+            data.len() as u64,
+            *blake3::hash(&data).as_bytes(),
+            Cid::from([7; 32]),
+        );
+        manifest.encode(&mut data).await.unwrap();
+        assert_eq!(data.len(), 13 + IntegrityManifest::size());
+
+        let parsed = IntegrityManifest::from_trailing(&data).unwrap();
+        assert_eq!(parsed.root_cid(), &Cid::from([7; 32]));
+        assert_eq!(parsed.verifying_key(), &signing_key.verifying_key());
+
+        let mut truncated = data[1..].to_vec();
+        assert!(matches!(
+            IntegrityManifest::from_trailing(&truncated),
+            Err(IntegrityManifestError::LengthMismatch {
+                expected: 13,
+                actual: 12
+            })
+        ));
+
+        // Changing any byte of the covered data, even keeping its length, is caught by the hash
+        for offset in 0..13 {
+            let mut modified = data.clone();
+            modified[offset] ^= 0x01;
+            assert!(matches!(
+                IntegrityManifest::from_trailing(&modified),
+                Err(IntegrityManifestError::ContentMismatch)
+            ));
+        }
+
+        // Altering the recorded root invalidates the signature
+        let cid_offset = 13 + INTEGRITY_MANIFEST_MAGIC.len() + 8 + CONTENT_HASH_LENGTH;
+        truncated = data.clone();
+        truncated[cid_offset] ^= 0xff;
+        assert!(matches!(
+            IntegrityManifest::from_trailing(&truncated),
+            Err(IntegrityManifestError::InvalidSignature)
+        ));
+
+        assert!(matches!(
+            IntegrityManifest::from_trailing(b"encoded drive"),
+            Err(IntegrityManifestError::Missing)
+        ));
+    }
+}
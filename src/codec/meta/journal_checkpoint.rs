@@ -1,18 +1,38 @@
-// Still needed for the journal entries
-#![allow(dead_code)]
-
-use futures::io::AsyncWrite;
+use futures::io::{AsyncWrite, AsyncWriteExt};
+use winnow::binary::le_i64;
+use winnow::Parser;
 
 use crate::codec::meta::{Cid, VectorClockFilesystemSnapshot};
 use crate::codec::{ParserResult, Stream};
 
+/// Records the state of the filesystem at the time a drive was encoded. A drive carries an
+/// ordered journal of these, one for each encoded version of the filesystem, which can be used to
+/// present the history of a drive and locate earlier versions of it.
 #[derive(Clone, Debug, PartialEq)]
 pub struct JournalCheckpoint {
     merkle_root_cid: Cid,
     vector: VectorClockFilesystemSnapshot,
+    created_at: i64,
 }
 
 impl JournalCheckpoint {
+    pub(crate) fn new(
+        merkle_root_cid: Cid,
+        vector: VectorClockFilesystemSnapshot,
+        created_at: i64,
+    ) -> Self {
+        Self {
+            merkle_root_cid,
+            vector,
+            created_at,
+        }
+    }
+
+    /// The time the checkpoint was recorded in milliseconds since the Unix epoch.
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
     pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
         &self,
         writer: &mut W,
@@ -22,23 +42,39 @@ impl JournalCheckpoint {
         written_bytes += self.merkle_root_cid.encode(writer).await?;
         written_bytes += self.vector.encode(writer).await?;
 
+        let created_at_bytes = self.created_at.to_le_bytes();
+        writer.write_all(&created_at_bytes).await?;
+        written_bytes += created_at_bytes.len();
+
         Ok(written_bytes)
     }
 
+    /// The CID of the root node of the filesystem when the checkpoint was recorded.
+    pub fn merkle_root_cid(&self) -> &Cid {
+        &self.merkle_root_cid
+    }
+
     pub fn parse(input: Stream) -> ParserResult<Self> {
         let (input, merkle_root_cid) = Cid::parse(input)?;
         let (input, vector) = VectorClockFilesystemSnapshot::parse(input)?;
+        let (input, created_at) = le_i64.parse_peek(input)?;
 
         let journal_checkpoint = JournalCheckpoint {
             merkle_root_cid,
             vector,
+            created_at,
         };
 
         Ok((input, journal_checkpoint))
     }
 
     pub const fn size() -> usize {
-        Cid::size() + VectorClockFilesystemSnapshot::size()
+        Cid::size() + VectorClockFilesystemSnapshot::size() + 8
+    }
+
+    /// The filesystem's vector clock when the checkpoint was recorded.
+    pub fn vector(&self) -> VectorClockFilesystemSnapshot {
+        self.vector
     }
 }
 
@@ -59,6 +95,7 @@ mod tests {
         let checkpoint = JournalCheckpoint {
             merkle_root_cid: Cid::from([0; 32]),
             vector: (&VectorClockFilesystem::initialize()).as_snapshot(),
+            created_at: 1_700_000_000_000,
         };
 
         let mut buffer = Vec::with_capacity(JournalCheckpoint::size());
@@ -66,6 +103,7 @@ mod tests {
             .encode(&mut buffer)
             .await
             .expect("encoding success");
+        assert_eq!(buffer.len(), JournalCheckpoint::size());
 
         let partial = Partial::new(buffer.as_slice());
         let (remaining, parsed) = JournalCheckpoint::parse(partial).expect("round trip");
@@ -52,38 +52,58 @@ impl MetaKey {
         key_count: u8,
         signing_key: &SigningKey,
     ) -> ParserResult<'a, Option<Self>> {
-        let mut asym_parser = repeat(
-            key_count as usize,
-            (unpeek(KeyId::parse), unpeek(AsymLockedAccessKey::parse)),
-        );
+        let (input, locked_keys) = Self::parse_escrow_entries(input, key_count)?;
+        Ok((input, Self::unlock_escrow(&locked_keys, signing_key)))
+    }
+
+    /// Parses the escrow section without attempting to unlock any of its entries, producing each
+    /// locked copy of the key along with the ID of the key it is addressed to.
+    pub(crate) fn parse_escrow_entries(
+        input: Stream,
+        key_count: u8,
+    ) -> ParserResult<Vec<(KeyId, AsymLockedAccessKey)>> {
+        let mut asym_parser = repeat(key_count as usize, unpeek(Self::parse_escrow_entry));
 
-        let (input, locked_keys): (_, Vec<_>) = match asym_parser.parse_peek(input) {
-            Ok(res) => res,
+        match asym_parser.parse_peek(input) {
+            Ok(res) => Ok(res),
             Err(winnow::error::ErrMode::Incomplete(Needed::Size(_))) => {
                 let record_size = KeyId::size() + AsymLockedAccessKey::size();
                 let total_size = key_count as usize * record_size;
 
-                return Err(winnow::error::ErrMode::Incomplete(Needed::new(
+                Err(winnow::error::ErrMode::Incomplete(Needed::new(
                     total_size - input.len(),
-                )));
+                )))
             }
-            Err(err) => return Err(err),
-        };
+            Err(err) => Err(err),
+        }
+    }
 
+    /// Parses a single entry of the escrow section, see [`MetaKey::parse_escrow_entries`].
+    pub(crate) fn parse_escrow_entry(input: Stream) -> ParserResult<(KeyId, AsymLockedAccessKey)> {
+        let (input, key_id) = KeyId::parse(input)?;
+        let (input, locked_key) = AsymLockedAccessKey::parse(input)?;
+
+        Ok((input, (key_id, locked_key)))
+    }
+
+    /// Attempts to unlock each of the escrowed entries addressed to the provided key, returning
+    /// the first that succeeds.
+    pub(crate) fn unlock_escrow(
+        locked_keys: &[(KeyId, AsymLockedAccessKey)],
+        signing_key: &SigningKey,
+    ) -> Option<Self> {
         let signing_key_id = signing_key.key_id();
-        let mut meta_key = None;
 
         for (key_id, potential_key) in locked_keys.iter().filter(|(kid, _)| *kid == signing_key_id)
         {
             tracing::trace!(candidate_key_id = ?key_id, "found_candidate");
 
             if let Ok(key) = potential_key.unlock(signing_key) {
-                meta_key = Some(Self::from(key));
-                break;
+                return Some(Self::from(key));
             }
         }
 
-        Ok((input, meta_key))
+        None
     }
 }
 
@@ -2,16 +2,19 @@ mod actor_id;
 mod actor_settings;
 mod cid;
 mod filesystem_id;
+mod integrity_manifest;
 mod journal_checkpoint;
 mod meta_key;
 mod permanent_id;
 mod user_agent;
 mod vector_clock;
+mod wrapped_key_entry;
 
 pub use actor_id::ActorId;
 pub use actor_settings::{ActorSettings, ActorSettingsError};
 pub use cid::Cid;
 pub use filesystem_id::FilesystemId;
+pub use integrity_manifest::{IntegrityManifest, IntegrityManifestError};
 pub use journal_checkpoint::JournalCheckpoint;
 pub use meta_key::MetaKey;
 pub use permanent_id::PermanentId;
@@ -23,3 +26,4 @@ pub use vector_clock::{
     FilesystemSnapshot as VectorClockFilesystemSnapshot, Node as VectorClockNode,
     NodeActorSnapshot as VectorClockNodeActorSnapshot, NodeSnapshot as VectorClockNodeSnapshot,
 };
+pub use wrapped_key_entry::WrappedKeyEntry;
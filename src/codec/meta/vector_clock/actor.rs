@@ -52,6 +52,11 @@ impl ActorSnapshot {
         Self { id, clock }
     }
 
+    /// The actor the clock belongs to.
+    pub fn actor_id(&self) -> ActorId {
+        self.id
+    }
+
     pub const fn size() -> usize {
         ActorId::size() + ClockInnerSnapshot::size()
     }
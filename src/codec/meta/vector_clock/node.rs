@@ -17,6 +17,20 @@ impl Node {
         Self::new(ClockInner::initialize())
     }
 
+    pub fn as_snapshot(&self) -> NodeSnapshot {
+        self.into()
+    }
+
+    /// See [`NodeSnapshot::precedes_lamport`]
+    pub fn precedes_lamport(&self, other: &Self) -> bool {
+        self.as_snapshot().precedes_lamport(&other.as_snapshot())
+    }
+
+    /// See [`NodeSnapshot::ties_lamport`]
+    pub fn ties_lamport(&self, other: &Self) -> bool {
+        self.as_snapshot().ties_lamport(&other.as_snapshot())
+    }
+
     pub fn parse(input: Stream) -> ParserResult<Self> {
         let (input, clock_snapshot) = NodeSnapshot::parse(input)?;
         Ok((input, Self::new(clock_snapshot.clock.into())))
@@ -39,6 +53,25 @@ impl NodeSnapshot {
         Self { clock }
     }
 
+    /// Whether this version of the node has a lower Lamport timestamp than the other one.
+    /// Wrapping of the underlying clock is taken into account.
+    ///
+    /// A node's clock is a single counter rather than a full vector clock, so this can't detect
+    /// concurrent changes. A version that causally precedes another always has a lower timestamp,
+    /// but the reverse doesn't hold: independent changes made from the same version of the node
+    /// are ordered by how many changes each side made.
+    pub fn precedes_lamport(&self, other: &Self) -> bool {
+        self < other
+    }
+
+    /// Whether both versions of the node have the same Lamport timestamp. This is the case for
+    /// identical versions as well as for independent changes that each advanced the clock by the
+    /// same amount, comparing the CIDs of the nodes is needed to tell the two apart. See
+    /// [`NodeSnapshot::precedes_lamport`] for why concurrent changes can't be detected in general.
+    pub fn ties_lamport(&self, other: &Self) -> bool {
+        !self.precedes_lamport(other) && !other.precedes_lamport(self)
+    }
+
     pub fn size() -> usize {
         ClockInnerSnapshot::size()
     }
@@ -73,3 +106,33 @@ impl From<&Node> for NodeSnapshot {
         Self::new((&value.clock).into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lamport_ordering() {
+        let earlier = NodeSnapshot::new(ClockInnerSnapshot::from(5));
+        let later = NodeSnapshot::new(ClockInnerSnapshot::from(6));
+
+        assert!(earlier.precedes_lamport(&later));
+        assert!(!later.precedes_lamport(&earlier));
+        assert!(!earlier.ties_lamport(&later));
+
+        let sibling = NodeSnapshot::new(ClockInnerSnapshot::from(5));
+        assert!(!earlier.precedes_lamport(&sibling));
+        assert!(earlier.ties_lamport(&sibling));
+
+        // Independent changes from the same version are still ordered when one side made more
+        // changes than the other
+        let one_change = NodeSnapshot::new(ClockInnerSnapshot::from(6));
+        let two_changes = NodeSnapshot::new(ClockInnerSnapshot::from(7));
+        assert!(one_change.precedes_lamport(&two_changes));
+        assert!(!one_change.ties_lamport(&two_changes));
+
+        let wrapped = NodeSnapshot::new(ClockInnerSnapshot::from(0));
+        let before_wrap = NodeSnapshot::new(ClockInnerSnapshot::from(u64::MAX));
+        assert!(before_wrap.precedes_lamport(&wrapped));
+    }
+}
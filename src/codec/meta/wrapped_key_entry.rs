@@ -0,0 +1,163 @@
+use futures::{AsyncWrite, AsyncWriteExt};
+use winnow::error::{ErrMode, ErrorKind, ParserError};
+use winnow::token::take;
+use winnow::Parser;
+
+use crate::codec::crypto::{AccessKey, AsymLockedAccessKey, KeyId, VerifyingKey};
+use crate::codec::meta::actor_settings::{decode_optional_key, encode_optional_key};
+use crate::codec::{ParserResult, Stream};
+
+const KEY_CHECK_CONTEXT: &str = "banyanfs wrapped key entry check v1";
+
+const KEY_CHECK_LENGTH: usize = 32;
+
+/// The copies of a drive's permission keys escrowed for a single actor, as produced by
+/// [`crate::filesystem::Drive::export_key_escrow`]. Every key is locked to the actor's public key
+/// so an entry is only useful to the holder of the matching [`crate::codec::crypto::SigningKey`],
+/// though it does reveal which public keys have access to the drive. Entries can be encoded to be
+/// kept separately from the drive and restored with
+/// [`crate::filesystem::Drive::import_key_escrow`].
+///
+/// Each entry also carries a check value derived from the unlocked keys it holds. Only holders of
+/// the drive's keys are able to produce or confirm it, which allows an entry to be checked
+/// against a drive without unlocking the entry itself.
+#[derive(Clone)]
+pub struct WrappedKeyEntry {
+    key_id: KeyId,
+    verifying_key: VerifyingKey,
+
+    filesystem_key: Option<AsymLockedAccessKey>,
+    data_key: Option<AsymLockedAccessKey>,
+    maintenance_key: Option<AsymLockedAccessKey>,
+
+    key_check: [u8; KEY_CHECK_LENGTH],
+}
+
+impl WrappedKeyEntry {
+    pub(crate) fn data_key(&self) -> Option<&AsymLockedAccessKey> {
+        self.data_key.as_ref()
+    }
+
+    pub async fn encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let mut written_bytes = 0;
+
+        written_bytes += self.key_id.encode(writer).await?;
+        written_bytes += self.verifying_key.encode(writer).await?;
+
+        written_bytes += encode_optional_key(writer, &self.filesystem_key).await?;
+        written_bytes += encode_optional_key(writer, &self.data_key).await?;
+        written_bytes += encode_optional_key(writer, &self.maintenance_key).await?;
+
+        writer.write_all(&self.key_check).await?;
+        written_bytes += self.key_check.len();
+
+        Ok(written_bytes)
+    }
+
+    pub(crate) fn filesystem_key(&self) -> Option<&AsymLockedAccessKey> {
+        self.filesystem_key.as_ref()
+    }
+
+    pub(crate) fn key_check(&self) -> &[u8; KEY_CHECK_LENGTH] {
+        &self.key_check
+    }
+
+    /// Derives the check value for an entry holding locked copies of the provided keys, see
+    /// [`WrappedKeyEntry`].
+    pub(crate) fn key_check_for(
+        filesystem_key: Option<&AccessKey>,
+        data_key: Option<&AccessKey>,
+        maintenance_key: Option<&AccessKey>,
+    ) -> [u8; KEY_CHECK_LENGTH] {
+        let mut hasher = blake3::Hasher::new_derive_key(KEY_CHECK_CONTEXT);
+
+        for key in [filesystem_key, data_key, maintenance_key] {
+            match key {
+                Some(key) => {
+                    hasher.update(&[0x01]);
+                    hasher.update(key.chacha_key().as_slice());
+                }
+                None => {
+                    hasher.update(&[0x00]);
+                }
+            }
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// The short identifier of the actor's key, the same one used to find the actor's keys in
+    /// the escrow section of an encoded drive.
+    pub fn key_id(&self) -> u16 {
+        *self.key_id
+    }
+
+    pub(crate) fn maintenance_key(&self) -> Option<&AsymLockedAccessKey> {
+        self.maintenance_key.as_ref()
+    }
+
+    pub(crate) fn new(
+        verifying_key: VerifyingKey,
+        filesystem_key: Option<AsymLockedAccessKey>,
+        data_key: Option<AsymLockedAccessKey>,
+        maintenance_key: Option<AsymLockedAccessKey>,
+        key_check: [u8; KEY_CHECK_LENGTH],
+    ) -> Self {
+        Self {
+            key_id: verifying_key.key_id(),
+            verifying_key,
+
+            filesystem_key,
+            data_key,
+            maintenance_key,
+
+            key_check,
+        }
+    }
+
+    pub fn parse(input: Stream) -> ParserResult<Self> {
+        let (input, key_id) = KeyId::parse(input)?;
+        let (input, verifying_key) = VerifyingKey::parse(input)?;
+
+        if verifying_key.key_id() != key_id {
+            return Err(ErrMode::Cut(ParserError::from_error_kind(
+                &input,
+                ErrorKind::Verify,
+            )));
+        }
+
+        let (input, filesystem_key) = decode_optional_key(input)?;
+        let (input, data_key) = decode_optional_key(input)?;
+        let (input, maintenance_key) = decode_optional_key(input)?;
+
+        let (input, key_check) = take(KEY_CHECK_LENGTH).parse_peek(input)?;
+        let key_check = key_check.try_into().expect("taken the exact length");
+
+        let entry = Self {
+            key_id,
+            verifying_key,
+
+            filesystem_key,
+            data_key,
+            maintenance_key,
+
+            key_check,
+        };
+
+        Ok((input, entry))
+    }
+
+    pub const fn size() -> usize {
+        KeyId::size()
+            + VerifyingKey::size()
+            + 3 * (1 + AsymLockedAccessKey::size())
+            + KEY_CHECK_LENGTH
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey {
+        &self.verifying_key
+    }
+}
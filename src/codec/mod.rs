@@ -5,6 +5,10 @@
 //! the underlying structure of the data. The components have been roughly organized into modules
 //! according to their logical groupings but things are still a little hectic and some modules can
 //! be found outside their ideal location but we're cleaning things up as we go.
+//!
+//! All of the parsing is built on top of [`winnow`] using the [`Stream`] and [`ParserResult`]
+//! types from the [`parser`] module. This is the only parser for the format, there is no separate
+//! legacy decoding path.
 
 pub mod crypto;
 pub mod data_storage;
@@ -27,17 +27,10 @@ pub type ParserResult<'a, T> = winnow::IResult<Stream<'a>, T>;
 
 pub type StateResult<T, E> = Result<ProgressType<T>, E>;
 
-//#[async_trait]
-//pub trait AsyncParser<'a>: Parser + Sized {
-//    async fn next(input: &'a [u8], ctx: &'a Self::Context) -> ParserResult<'a, Option<Self>> {
-//        match Self::parse(input, ctx) {
-//            Ok((remaining, parsed)) => Ok((remaining, Some(parsed))),
-//            Err(winnow::error::ErrMode::Incomplete(_)) => Ok((input, None)),
-//            Err(err) => Err(err),
-//        }
-//    }
-//}
-
+/// Parsing with an external context. Nothing in the crate implements this, every codec type
+/// parses itself through an inherent `parse(input: Stream) -> ParserResult<Self>` function built
+/// on winnow, which is the only parse path.
+#[deprecated(note = "use the inherent `parse` function of each codec type or `winnow::Parser`")]
 pub trait Parser: Sized {
     type Context: Send + Sync;
 
@@ -4,3 +4,60 @@ pub fn bytes_to_hex_string(bytes: &[u8]) -> String {
         .iter()
         .fold(String::new(), |acc, &b| format!("{acc}{:02x}", b))
 }
+
+/// Passes everything written through to the wrapped writer while keeping count of the bytes that
+/// were accepted by it, along with a blake3 hash of them.
+pub(crate) struct CountingWriter<'a, W> {
+    inner: &'a mut W,
+    written: u64,
+    hasher: blake3::Hasher,
+}
+
+impl<'a, W: futures::AsyncWrite + Unpin> CountingWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self {
+            inner,
+            written: 0,
+            hasher: blake3::Hasher::new(),
+        }
+    }
+
+    pub(crate) fn hash(&self) -> [u8; 32] {
+        self.hasher.finalize().into()
+    }
+
+    pub(crate) fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+impl<W: futures::AsyncWrite + Unpin> futures::AsyncWrite for CountingWriter<'_, W> {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let poll = std::pin::Pin::new(&mut *self.inner).poll_write(cx, buf);
+
+        if let std::task::Poll::Ready(Ok(written)) = &poll {
+            self.written += *written as u64;
+            self.hasher.update(&buf[..*written]);
+        }
+
+        poll
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut *self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut *self.inner).poll_close(cx)
+    }
+}
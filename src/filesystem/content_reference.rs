@@ -27,6 +27,16 @@ impl ContentReference {
         }
     }
 
+    /// References the entirety of an unencrypted block that wasn't produced by BanyanFS, such as
+    /// one imported from another system. The raw content of the block is the data.
+    pub(crate) fn raw_block(data_block_cid: Cid) -> Self {
+        let data_options =
+            DataOptions::new(false, 0, 0, 0).expect("We know this wont fail with these parameters");
+        let chunks = vec![ContentLocation::data(data_block_cid.clone(), 0)];
+
+        Self::new(data_block_cid, data_options, chunks)
+    }
+
     pub(crate) fn chunks(&self) -> &[ContentLocation] {
         &self.chunks
     }
@@ -35,6 +45,10 @@ impl ContentReference {
         self.data_block_cid.clone()
     }
 
+    pub(crate) fn data_options(&self) -> &DataOptions {
+        &self.data_options
+    }
+
     pub async fn encode<W: AsyncWrite + Unpin + Send>(
         &self,
         writer: &mut W,
@@ -82,7 +96,6 @@ impl ContentReference {
         repeat(ref_count as usize, unpeek(Self::parse)).parse_peek(input)
     }
 
-    #[allow(dead_code)]
     pub fn size(&self) -> usize {
         let base_size = Cid::size() + DataOptions::size() + 2;
         let chunk_size = self.chunks.iter().map(ContentLocation::size).sum::<usize>();
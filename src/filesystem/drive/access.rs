@@ -4,11 +4,12 @@ use elliptic_curve::rand_core::CryptoRngCore;
 use futures::io::AsyncWrite;
 
 use crate::codec::{
-    crypto::{AccessKey, KeyId, SigningKey, VerifyingKey},
+    crypto::{AccessKey, AsymLockedAccessKey, KeyId, SigningKey, VerifyingKey},
     header::{AccessMask, AccessMaskBuilder, AccessMaskError},
-    meta::VectorClockActorSnapshot,
+    meta::{VectorClockActorSnapshot, WrappedKeyEntry},
     ActorId, ActorSettings, ActorSettingsError, ParserResult, Stream,
 };
+use crate::filesystem::drive::{AccessSummary, ActorStatus, CapabilityToken};
 
 /// [`DriveAccess`] maintains a mapping of [`ActorId`] instances to their available permissions
 /// within the drive itself. When loaded this holds on to copies of any of the general keys the
@@ -45,6 +46,34 @@ impl DriveAccess {
             .map(|settings| settings.access())
     }
 
+    /// Classifies the actor as [`ActorStatus::Active`] along with its current permissions,
+    /// [`ActorStatus::Historical`] if its access has been revoked, or [`ActorStatus::Unknown`] if
+    /// it has never been registered with the drive.
+    pub fn actor_status(&self, actor_id: &ActorId) -> ActorStatus {
+        match self.actor_access(actor_id) {
+            Some(access) if access.is_historical() => ActorStatus::Historical,
+            Some(access) => ActorStatus::Active(access),
+            None => ActorStatus::Unknown,
+        }
+    }
+
+    /// Collects all of the access checks for a single actor in one place. Historical actors and
+    /// actors that aren't present in the drive receive an empty summary.
+    pub fn access_summary(&self, actor_id: &ActorId) -> AccessSummary {
+        let access = match self.active_actor_access(actor_id) {
+            Some(a) => a,
+            None => return AccessSummary::default(),
+        };
+
+        AccessSummary::new(
+            self.has_read_access(actor_id),
+            self.has_write_access(actor_id),
+            self.has_maintenance_access(actor_id),
+            access.has_filesystem_key(),
+            access.has_data_key(),
+        )
+    }
+
     pub fn actor_vector_clock(&self, actor_id: &ActorId) -> Option<VectorClockActorSnapshot> {
         self.actor_settings
             .get(actor_id)
@@ -66,6 +95,65 @@ impl DriveAccess {
         self.has_data_access(actor_id)
     }
 
+    /// Determines which of the unlocked permission keys an actor is allowed to hand out for the
+    /// requested access mask. Actors can only grant keys they hold themselves and only owners are
+    /// able to grant ownership or protection. The keys are returned in the order filesystem,
+    /// data, and maintenance.
+    pub(crate) fn capability_keys(
+        &self,
+        issuer_id: &ActorId,
+        access_mask: AccessMask,
+    ) -> Result<[Option<&AccessKey>; 3], DriveAccessError> {
+        let issuer_access = self
+            .active_actor_access(issuer_id)
+            .ok_or(DriveAccessError::AccessDenied("issuer has no access"))?;
+
+        if access_mask.is_historical() {
+            return Err(DriveAccessError::InvalidCapability(
+                "historical access can't be granted",
+            ));
+        }
+
+        if (access_mask.is_owner() || access_mask.is_protected()) && !issuer_access.is_owner() {
+            return Err(DriveAccessError::AccessDenied(
+                "only owners can grant owner or protected access",
+            ));
+        }
+
+        let permitted_key = |requested: bool, held: bool, key| {
+            if !requested {
+                return Ok(None);
+            }
+
+            if !held {
+                return Err(DriveAccessError::PermissionEscalation);
+            }
+
+            match key {
+                Some(key) => Ok(Some(key)),
+                None => Err(DriveAccessError::PermissionEscalation),
+            }
+        };
+
+        Ok([
+            permitted_key(
+                access_mask.has_filesystem_key(),
+                issuer_access.has_filesystem_key(),
+                self.filesystem_key.as_ref(),
+            )?,
+            permitted_key(
+                access_mask.has_data_key(),
+                issuer_access.has_data_key(),
+                self.data_key.as_ref(),
+            )?,
+            permitted_key(
+                access_mask.has_maintenance_key(),
+                issuer_access.has_maintenance_key(),
+                self.maintenance_key.as_ref(),
+            )?,
+        ])
+    }
+
     pub(crate) fn data_key(&self) -> Option<&AccessKey> {
         self.data_key.as_ref()
     }
@@ -98,6 +186,24 @@ impl DriveAccess {
         Ok(written_bytes)
     }
 
+    /// Collects the locked copies of the permission keys escrowed for every active actor, sorted
+    /// by each actor's [`ActorId`]. Historical actors are left out as their keys should never be
+    /// restored. Keys the current actor doesn't hold are left out as well, the entries couldn't
+    /// be checked against the drive when they're restored without them.
+    pub fn export_key_escrow(&self) -> Vec<WrappedKeyEntry> {
+        self.sorted_actor_settings()
+            .into_iter()
+            .filter(|settings| !settings.access().is_historical())
+            .map(|settings| {
+                settings.wrapped_keys(
+                    self.filesystem_key.as_ref(),
+                    self.data_key.as_ref(),
+                    self.maintenance_key.as_ref(),
+                )
+            })
+            .collect()
+    }
+
     pub(crate) fn filesystem_key(&self) -> Option<&AccessKey> {
         self.filesystem_key.as_ref()
     }
@@ -170,6 +276,85 @@ impl DriveAccess {
         access.has_filesystem_key() && access.has_data_key() && access.has_maintenance_key()
     }
 
+    /// Restores permission keys missing from the escrow of registered actors using entries
+    /// previously produced by [`DriveAccess::export_key_escrow`], returning the number of keys
+    /// that were restored. Keys an actor already holds are left untouched.
+    ///
+    /// Restoring keys requires the maintenance key, and only keys the current actor holds itself
+    /// can be restored for others. Every entry's check value is confirmed against the keys in use
+    /// by this drive, and entries for the current actor are additionally confirmed to unlock to
+    /// them. Every entry is checked before any key is restored so either all of them are applied
+    /// or none are.
+    pub fn import_key_escrow(
+        &mut self,
+        current_key: &SigningKey,
+        entries: &[WrappedKeyEntry],
+    ) -> Result<usize, DriveAccessError> {
+        let current_actor_id = current_key.actor_id();
+        if !self.has_maintenance_access(&current_actor_id) {
+            return Err(DriveAccessError::AccessDenied(
+                "must be able to record changes to restore escrowed keys",
+            ));
+        }
+
+        for entry in entries {
+            let actor_id = entry.verifying_key().actor_id();
+
+            let settings = self
+                .actor_settings
+                .get(&actor_id)
+                .ok_or(DriveAccessError::UnknownActorId(actor_id))?;
+
+            if settings.access().is_historical() {
+                return Err(DriveAccessError::HistoricalActor(actor_id));
+            }
+
+            let escrowed_keys = [
+                (entry.filesystem_key(), self.filesystem_key.as_ref()),
+                (entry.data_key(), self.data_key.as_ref()),
+                (entry.maintenance_key(), self.maintenance_key.as_ref()),
+            ];
+
+            let mut checked_keys = [None; 3];
+            for (idx, (escrowed_key, held_key)) in escrowed_keys.into_iter().enumerate() {
+                let (escrowed_key, held_key) = match (escrowed_key, held_key) {
+                    (Some(escrowed_key), Some(held_key)) => (escrowed_key, held_key),
+                    (Some(_), None) => return Err(DriveAccessError::PermissionEscalation),
+                    (None, _) => continue,
+                };
+
+                if actor_id == current_actor_id
+                    && escrowed_key.unlock(current_key).ok().as_ref() != Some(held_key)
+                {
+                    return Err(DriveAccessError::InvalidKeyEscrow(
+                        "escrowed keys don't belong to this drive",
+                    ));
+                }
+
+                checked_keys[idx] = Some(held_key);
+            }
+
+            let [filesystem_key, data_key, maintenance_key] = checked_keys;
+            let key_check =
+                WrappedKeyEntry::key_check_for(filesystem_key, data_key, maintenance_key);
+            if &key_check != entry.key_check() {
+                return Err(DriveAccessError::InvalidKeyEscrow(
+                    "escrowed keys don't belong to this drive",
+                ));
+            }
+        }
+
+        let mut restored = 0;
+        for entry in entries {
+            let actor_id = entry.verifying_key().actor_id();
+            if let Some(settings) = self.actor_settings.get_mut(&actor_id) {
+                restored += settings.restore_wrapped_keys(entry);
+            }
+        }
+
+        Ok(restored)
+    }
+
     /// Create a new DriveAccess instance with the provided actor as an owner with full
     /// permissions.
     pub(crate) fn initialize(
@@ -196,7 +381,7 @@ impl DriveAccess {
     }
 
     pub fn is_historical(&self, actor_id: &ActorId) -> bool {
-        match self.active_actor_access(actor_id) {
+        match self.actor_access(actor_id) {
             Some(a) => a.is_historical(),
             None => false,
         }
@@ -241,6 +426,26 @@ impl DriveAccess {
         key_count: u8,
         signing_key: &SigningKey,
     ) -> ParserResult<'a, Self> {
+        let (remaining, mut drive_access) = Self::parse_locked(input, key_count)?;
+
+        if let Err(err) = drive_access.unlock_keys(signing_key) {
+            tracing::error!("failed to unlock permission keys: {}", err);
+
+            return Err(winnow::error::ErrMode::Cut(
+                winnow::error::ParserError::from_error_kind(
+                    &input,
+                    winnow::error::ErrorKind::Verify,
+                ),
+            ));
+        }
+
+        Ok((remaining, drive_access))
+    }
+
+    /// Parses the access settings of every actor without unlocking any of the permission keys,
+    /// they need to be unlocked afterwards with [`DriveAccess::unlock_keys`] or
+    /// [`DriveAccess::unlock_keys_with_escrow`].
+    pub(crate) fn parse_locked(input: Stream, key_count: u8) -> ParserResult<Self> {
         if key_count == 0 {
             return Err(winnow::error::ErrMode::Cut(
                 winnow::error::ParserError::from_error_kind(
@@ -269,7 +474,7 @@ impl DriveAccess {
             actor_settings.insert(actor_id, settings);
         }
 
-        let mut drive_access = Self {
+        let drive_access = Self {
             actor_settings,
 
             filesystem_key: None,
@@ -277,17 +482,6 @@ impl DriveAccess {
             maintenance_key: None,
         };
 
-        if let Err(err) = drive_access.unlock_keys(signing_key) {
-            tracing::error!("failed to unlock permission keys: {}", err);
-
-            return Err(winnow::error::ErrMode::Cut(
-                winnow::error::ParserError::from_error_kind(
-                    &input,
-                    winnow::error::ErrorKind::Verify,
-                ),
-            ));
-        }
-
         Ok((buf_slice, drive_access))
     }
 
@@ -352,6 +546,41 @@ impl DriveAccess {
         Ok(())
     }
 
+    /// Registers the recipient of a [`CapabilityToken`] as a new actor. The token's signature must
+    /// already have been checked, this confirms the issuer is still allowed to grant the
+    /// requested access and that the escrowed keys unlock with the recipient's key to the same
+    /// permission keys used by this drive.
+    pub fn redeem_capability(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        token: &CapabilityToken,
+        recipient_key: &SigningKey,
+        vector_clock_actor_snapshot: VectorClockActorSnapshot,
+    ) -> Result<(), DriveAccessError> {
+        let access_mask = token.access_mask();
+
+        let expected_keys = self.capability_keys(&token.issuer().actor_id(), access_mask)?;
+        let escrowed_keys = token.unlock_keys(recipient_key)?;
+
+        let keys_match = expected_keys
+            .iter()
+            .zip(escrowed_keys.iter())
+            .all(|(expected, escrowed)| *expected == escrowed.as_ref());
+
+        if !keys_match {
+            return Err(DriveAccessError::InvalidCapability(
+                "escrowed keys don't belong to this drive",
+            ));
+        }
+
+        self.register_actor(
+            rng,
+            token.recipient().clone(),
+            access_mask,
+            vector_clock_actor_snapshot,
+        )
+    }
+
     /// This removes an actor's access by marking it as historical. The key is intentionally kept
     /// around to allow validation of signatures generated by this Actor as long as there are
     /// references to those signatures are present.
@@ -414,6 +643,135 @@ impl DriveAccess {
         Ok(())
     }
 
+    /// Marks every active actor that is neither an owner nor protected as historical, returning
+    /// the IDs of the revoked actors sorted by their [`ActorId`]. Only owners able to record the
+    /// change may perform this. All permission checks happen before any actor is changed so
+    /// either every eligible actor is revoked or none are.
+    pub fn revoke_all_except_owners(
+        &mut self,
+        current_key: &SigningKey,
+    ) -> Result<Vec<ActorId>, DriveAccessError> {
+        let current_actor_id = current_key.verifying_key().actor_id();
+        let current_permissions =
+            self.active_actor_access(&current_actor_id)
+                .ok_or(DriveAccessError::AccessDenied(
+                    "current actor has no access",
+                ))?;
+
+        if !current_permissions.is_owner() {
+            return Err(DriveAccessError::AccessDenied(
+                "only owners can revoke all other keys",
+            ));
+        }
+
+        if !current_permissions.has_maintenance_key() {
+            return Err(DriveAccessError::AccessDenied(
+                "must be able to record changes to remove an actor",
+            ));
+        }
+
+        let mut revoked = Vec::new();
+        for (actor_id, settings) in self.actor_settings.iter_mut() {
+            let access = settings.access();
+            if access.is_historical() || access.is_owner() || access.is_protected() {
+                continue;
+            }
+
+            settings.access_mut().set_historical(true);
+            revoked.push(*actor_id);
+        }
+
+        revoked.sort();
+
+        Ok(revoked)
+    }
+
+    /// Replaces the data key with `new_key`, escrowing it for every active actor that held the
+    /// previous data key. Historical actors have their escrowed copy of the previous key removed.
+    /// The current actor needs to hold the data key and be able to record changes. Returns the
+    /// previous data key, which file keys are still sealed with until they're resealed by
+    /// [`crate::filesystem::Drive::rekey_data_references`].
+    pub(crate) fn rotate_data_key(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        current_key: &SigningKey,
+        new_key: &AccessKey,
+    ) -> Result<AccessKey, DriveAccessError> {
+        let current_actor_id = current_key.actor_id();
+        if !self.has_maintenance_access(&current_actor_id) {
+            return Err(DriveAccessError::AccessDenied(
+                "must be able to record changes to rotate the data key",
+            ));
+        }
+
+        if !self.has_data_access(&current_actor_id) {
+            return Err(DriveAccessError::AccessDenied(
+                "must hold the data key to rotate it",
+            ));
+        }
+
+        let previous_key = self.data_key.clone().ok_or(DriveAccessError::AccessDenied(
+            "data key hasn't been unlocked",
+        ))?;
+
+        for settings in self.actor_settings.values_mut() {
+            let access = settings.access();
+
+            if access.is_historical() {
+                settings.clear_data_key();
+            } else if access.has_data_key() {
+                settings
+                    .grant_data_key(rng, new_key)
+                    .map_err(DriveAccessError::GrantFailed)?;
+            }
+        }
+
+        self.data_key = Some(new_key.clone());
+
+        Ok(previous_key)
+    }
+
+    /// Replaces the filesystem key with `new_key`, escrowing it for every active actor that held
+    /// the previous filesystem key. Historical actors have their escrowed copy of the previous key
+    /// removed, so metadata encrypted after the rotation is unreadable to them even if they kept
+    /// the previous key. The current actor needs to hold the filesystem key and be able to record
+    /// changes.
+    pub(crate) fn rotate_filesystem_key(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        current_key: &SigningKey,
+        new_key: &AccessKey,
+    ) -> Result<(), DriveAccessError> {
+        let current_actor_id = current_key.actor_id();
+        if !self.has_maintenance_access(&current_actor_id) {
+            return Err(DriveAccessError::AccessDenied(
+                "must be able to record changes to rotate the filesystem key",
+            ));
+        }
+
+        if self.filesystem_key.is_none() {
+            return Err(DriveAccessError::AccessDenied(
+                "must hold the filesystem key to rotate it",
+            ));
+        }
+
+        for settings in self.actor_settings.values_mut() {
+            let access = settings.access();
+
+            if access.is_historical() {
+                settings.clear_filesystem_key();
+            } else if access.has_filesystem_key() {
+                settings
+                    .grant_filesystem_key(rng, new_key)
+                    .map_err(DriveAccessError::GrantFailed)?;
+            }
+        }
+
+        self.filesystem_key = Some(new_key.clone());
+
+        Ok(())
+    }
+
     /// Returns all the available [`ActorSettings`] associated with the current drive instance
     /// sorted by each configured actor's [`ActorId`]. We ultimately want this sorted by [`KeyId`]
     /// for consistent encoding which requires sorting by [`KeyId`], by sorting on the full
@@ -451,6 +809,93 @@ impl DriveAccess {
         Ok(())
     }
 
+    /// Behaves the same as [`DriveAccess::unlock_keys`], but falls back to the actor's entry in
+    /// a backup made with [`DriveAccess::export_key_escrow`] when the actor's keys escrowed in the
+    /// drive can't be unlocked or are missing keys the entry holds. The keys from the entry have
+    /// to unlock, match the entry's check value, and match any of the actor's keys that could be
+    /// unlocked from the drive. They then replace the actor's escrowed keys so they're present the
+    /// next time the drive is encoded.
+    ///
+    /// Entries are never used for historical actors, a backup made before an actor was removed
+    /// must not restore its access.
+    pub(crate) fn unlock_keys_with_escrow(
+        &mut self,
+        actor_key: &SigningKey,
+        escrow: &[WrappedKeyEntry],
+    ) -> Result<(), DriveAccessError> {
+        let actor_id = actor_key.actor_id();
+        let entry = escrow
+            .iter()
+            .find(|entry| entry.verifying_key().actor_id() == actor_id);
+
+        let unlock_result = self.unlock_keys(actor_key);
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return unlock_result,
+        };
+
+        let keys_missing = [
+            (entry.filesystem_key(), &self.filesystem_key),
+            (entry.data_key(), &self.data_key),
+            (entry.maintenance_key(), &self.maintenance_key),
+        ]
+        .iter()
+        .any(|(escrowed_key, held_key)| escrowed_key.is_some() && held_key.is_none());
+
+        if unlock_result.is_ok() && !keys_missing {
+            return Ok(());
+        }
+
+        if self.is_historical(&actor_id) {
+            return unlock_result;
+        }
+
+        let unlock = |key: Option<&AsymLockedAccessKey>| {
+            key.map(|key| key.unlock(actor_key))
+                .transpose()
+                .map_err(|_| {
+                    DriveAccessError::InvalidKeyEscrow("escrowed keys couldn't be unlocked")
+                })
+        };
+        let filesystem_key = unlock(entry.filesystem_key())?;
+        let data_key = unlock(entry.data_key())?;
+        let maintenance_key = unlock(entry.maintenance_key())?;
+
+        let key_check = WrappedKeyEntry::key_check_for(
+            filesystem_key.as_ref(),
+            data_key.as_ref(),
+            maintenance_key.as_ref(),
+        );
+        if &key_check != entry.key_check() {
+            return Err(DriveAccessError::InvalidKeyEscrow(
+                "escrowed keys don't match their check value",
+            ));
+        }
+
+        // Any keys that could still be unlocked from the drive confirm the entry belongs to it
+        let unlocked_keys = [
+            (filesystem_key, &self.filesystem_key),
+            (data_key, &self.data_key),
+            (maintenance_key, &self.maintenance_key),
+        ];
+        for (escrowed_key, held_key) in unlocked_keys {
+            if let (Some(escrowed_key), Some(held_key)) = (escrowed_key, held_key) {
+                if &escrowed_key != held_key {
+                    return Err(DriveAccessError::InvalidKeyEscrow(
+                        "escrowed keys don't belong to this drive",
+                    ));
+                }
+            }
+        }
+
+        let settings = self
+            .actor_settings
+            .get_mut(&actor_id)
+            .ok_or(DriveAccessError::UnknownActorId(actor_id))?;
+        settings.replace_wrapped_keys(entry);
+        self.unlock_keys(actor_key)
+    }
+
     pub const fn size() -> usize {
         KeyId::size() + ActorSettings::size()
     }
@@ -464,12 +909,21 @@ pub enum DriveAccessError {
     #[error("access mask was invalid: {0}")]
     InvalidAccessMask(#[from] AccessMaskError),
 
+    #[error("capability token was invalid: {0}")]
+    InvalidCapability(&'static str),
+
+    #[error("escrowed keys were invalid: {0}")]
+    InvalidKeyEscrow(&'static str),
+
     #[error("attempted to add an actor that already has access")]
     ActorAlreadyPresent,
 
     #[error("failed to grant actor permission key: {0}")]
     GrantFailed(ActorSettingsError),
 
+    #[error("actor's access to the drive has been revoked: {}", .0.as_hex())]
+    HistoricalActor(ActorId),
+
     #[error("attempted to grant permission key the actor doesn't have access to")]
     PermissionEscalation,
 
@@ -581,6 +1035,195 @@ mod tests {
         assert!(access.is_protected(&actor_id));
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_access_summary_matches_individual_checks() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = SigningKey::generate(&mut rng);
+        let verifying_key = key.verifying_key();
+        let actor_clock = VectorClockActor::initialize(verifying_key.actor_id()).as_snapshot();
+
+        let owner_id = verifying_key.actor_id();
+        let mut access = DriveAccess::initialize(&mut rng, verifying_key, actor_clock).unwrap();
+
+        let second_key = SigningKey::generate(&mut rng);
+        let second_verifying_key = second_key.verifying_key();
+        let second_id = second_verifying_key.actor_id();
+
+        let access_mask = AccessMaskBuilder::structural().build().unwrap();
+        access
+            .register_actor(&mut rng, second_verifying_key, access_mask, actor_clock)
+            .unwrap();
+
+        let owner_summary = access.access_summary(&owner_id);
+        assert!(owner_summary.has_read_access());
+        assert!(owner_summary.has_write_access());
+        assert!(owner_summary.has_maintenance_access());
+        assert!(owner_summary.holds_filesystem_key());
+        assert!(owner_summary.holds_data_key());
+
+        let second_summary = access.access_summary(&second_id);
+        assert_eq!(
+            second_summary.has_read_access(),
+            access.has_read_access(&second_id)
+        );
+        assert_eq!(
+            second_summary.has_write_access(),
+            access.has_write_access(&second_id)
+        );
+        assert!(second_summary.holds_filesystem_key());
+        assert!(!second_summary.holds_data_key());
+
+        let unknown_id = SigningKey::generate(&mut rng).actor_id();
+        assert_eq!(access.access_summary(&unknown_id), AccessSummary::default());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_key_escrow_export_restores_missing_keys() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = SigningKey::generate(&mut rng);
+        let verifying_key = key.verifying_key();
+        let actor_clock = VectorClockActor::initialize(verifying_key.actor_id()).as_snapshot();
+
+        let owner_id = verifying_key.actor_id();
+        let mut access = DriveAccess::initialize(&mut rng, verifying_key, actor_clock).unwrap();
+
+        let second_key = SigningKey::generate(&mut rng);
+        let second_id = second_key.actor_id();
+        let access_mask = AccessMaskBuilder::full_access().build().unwrap();
+        access
+            .register_actor(
+                &mut rng,
+                second_key.verifying_key(),
+                access_mask,
+                actor_clock,
+            )
+            .unwrap();
+
+        let entries = access.export_key_escrow();
+        assert_eq!(entries.len(), 2);
+
+        let mut encoded = Vec::new();
+        for entry in entries.iter() {
+            assert_eq!(
+                entry.encode(&mut encoded).await.unwrap(),
+                WrappedKeyEntry::size()
+            );
+        }
+
+        let mut remaining = Stream::new(encoded.as_slice());
+        let mut parsed = Vec::new();
+        while !remaining.is_empty() {
+            let (input, entry) = WrappedKeyEntry::parse(remaining).unwrap();
+            remaining = input;
+            parsed.push(entry);
+        }
+
+        // Simulate the escrowed keys being lost from the drive
+        for settings in access.actor_settings.values_mut() {
+            settings.clear_data_key();
+        }
+        assert!(access.clone().unlock_keys(&key).is_ok());
+        assert!(!access.has_data_access(&owner_id));
+        assert!(!access.has_data_access(&second_id));
+
+        let unknown_key = SigningKey::generate(&mut rng);
+        assert!(matches!(
+            access.import_key_escrow(&unknown_key, &parsed),
+            Err(DriveAccessError::AccessDenied(_))
+        ));
+
+        assert_eq!(access.import_key_escrow(&key, &parsed).unwrap(), 2);
+        assert!(access.has_data_access(&owner_id));
+        assert!(access.has_data_access(&second_id));
+
+        let mut second_access = access.clone();
+        second_access.unlock_keys(&second_key).unwrap();
+        assert_eq!(second_access.data_key(), access.data_key());
+
+        // Restoring again is a no-op, and another drive's keys are never accepted
+        assert_eq!(access.import_key_escrow(&key, &parsed).unwrap(), 0);
+
+        let mut tampered = Vec::new();
+        parsed[1].encode(&mut tampered).await.unwrap();
+        *tampered.last_mut().unwrap() ^= 0x01;
+        let (_, tampered) = WrappedKeyEntry::parse(Stream::new(&tampered)).unwrap();
+        assert!(matches!(
+            access.import_key_escrow(&key, &[tampered]),
+            Err(DriveAccessError::InvalidKeyEscrow(_))
+        ));
+
+        let other_clock = VectorClockActor::initialize(owner_id).as_snapshot();
+        let other_access =
+            DriveAccess::initialize(&mut rng, key.verifying_key(), other_clock).unwrap();
+        assert!(matches!(
+            access.import_key_escrow(&key, &other_access.export_key_escrow()),
+            Err(DriveAccessError::InvalidKeyEscrow(_))
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_loading_falls_back_to_key_escrow_backup() {
+        use std::sync::Arc;
+
+        use crate::codec::header::ContentOptions;
+        use crate::filesystem::{Drive, DriveLoader, DriveLoaderError};
+
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let owner_id = key.actor_id();
+        let drive = Drive::initialize_private(&mut rng, key.clone()).unwrap();
+        let escrow = drive.export_key_escrow().await;
+
+        // Simulate the owner's escrowed data key being lost from the drive
+        drive
+            .inner
+            .write()
+            .await
+            .access_mut()
+            .actor_settings
+            .get_mut(&owner_id)
+            .unwrap()
+            .clear_data_key();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+
+        let loaded = DriveLoader::new(&key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert!(loaded.inner.read().await.access().data_key().is_none());
+
+        let recovered = DriveLoader::new(&key)
+            .with_key_escrow(escrow)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        let inner_read = recovered.inner.read().await;
+        let access = inner_read.access();
+        assert!(access.data_key().is_some());
+        assert!(access.actor_access(&owner_id).unwrap().has_data_key());
+        drop(inner_read);
+
+        let other_drive = Drive::initialize_private(&mut rng, key.clone()).unwrap();
+        let result = DriveLoader::new(&key)
+            .with_key_escrow(other_drive.export_key_escrow().await)
+            .from_reader(encoded.as_slice())
+            .await;
+        assert!(matches!(
+            result,
+            Err(DriveLoaderError::PermissionKeysUnavailable(
+                DriveAccessError::InvalidKeyEscrow(_)
+            ))
+        ));
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
     async fn test_cant_remove_self() {
@@ -669,6 +1312,81 @@ mod tests {
 
         let actor2_access = access.actor_access(&actor2_id).unwrap();
         assert!(actor2_access.is_historical());
+        assert!(access.is_historical(&actor2_id));
+        assert_eq!(access.actor_status(&actor2_id), ActorStatus::Historical);
+
+        let actor1_id = actor1_key.actor_id();
+        assert_eq!(
+            access.actor_status(&actor1_id),
+            ActorStatus::Active(access.actor_access(&actor1_id).unwrap())
+        );
+
+        let unknown_id = SigningKey::generate(&mut rng).actor_id();
+        assert_eq!(access.actor_status(&unknown_id), ActorStatus::Unknown);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_revoke_all_except_owners() {
+        let mut rng = crate::utils::crypto_rng();
+
+        let owner_key = SigningKey::generate(&mut rng);
+        let owner_verifying_key = owner_key.verifying_key();
+        let owner_actor_clock =
+            VectorClockActor::initialize(owner_verifying_key.actor_id()).as_snapshot();
+
+        let mut access =
+            DriveAccess::initialize(&mut rng, owner_verifying_key, owner_actor_clock).unwrap();
+
+        let masks = [
+            AccessMaskBuilder::full_access().build().unwrap(),
+            AccessMaskBuilder::full_access().build().unwrap(),
+            AccessMaskBuilder::full_access().owner().build().unwrap(),
+            AccessMaskBuilder::full_access()
+                .protected()
+                .build()
+                .unwrap(),
+        ];
+
+        let mut actor_keys = Vec::new();
+        for access_mask in masks {
+            let key = SigningKey::generate(&mut rng);
+            let verifying_key = key.verifying_key();
+            let actor_clock = VectorClockActor::initialize(verifying_key.actor_id()).as_snapshot();
+
+            access
+                .register_actor(&mut rng, verifying_key, access_mask, actor_clock)
+                .unwrap();
+            actor_keys.push(key);
+        }
+
+        let actor_ids: Vec<ActorId> = actor_keys.iter().map(|k| k.actor_id()).collect();
+
+        // Collaborators without ownership can't lock everyone else out
+        let err = access.revoke_all_except_owners(&actor_keys[0]).unwrap_err();
+        assert!(matches!(err, DriveAccessError::AccessDenied(_)));
+        assert!(access.active_actor_access(&actor_ids[1]).is_some());
+
+        let mut expected = vec![actor_ids[0], actor_ids[1]];
+        expected.sort();
+        assert_eq!(
+            access.revoke_all_except_owners(&owner_key).unwrap(),
+            expected
+        );
+
+        let still_active: Vec<bool> = actor_ids
+            .iter()
+            .map(|id| access.active_actor_access(id).is_some())
+            .collect();
+        assert_eq!(still_active, vec![false, false, true, true]);
+        assert!(access.actor_access(&actor_ids[0]).unwrap().is_historical());
+        assert!(access.is_owner(&owner_key.actor_id()));
+
+        // Nothing is left to revoke
+        assert!(access
+            .revoke_all_except_owners(&owner_key)
+            .unwrap()
+            .is_empty());
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
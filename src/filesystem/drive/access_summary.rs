@@ -0,0 +1,57 @@
+/// A snapshot of everything a single actor is able to do with a drive, produced by
+/// [`crate::filesystem::Drive::access_summary`]. This is primarily intended for gating interface
+/// elements without having to query each level of access individually. Historical actors and
+/// actors unknown to the drive have no access at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccessSummary {
+    read: bool,
+    write: bool,
+    maintenance: bool,
+
+    filesystem_key: bool,
+    data_key: bool,
+}
+
+impl AccessSummary {
+    /// See [`crate::filesystem::DriveAccess::has_maintenance_access`]
+    pub fn has_maintenance_access(&self) -> bool {
+        self.maintenance
+    }
+
+    /// See [`crate::filesystem::DriveAccess::has_read_access`]
+    pub fn has_read_access(&self) -> bool {
+        self.read
+    }
+
+    /// See [`crate::filesystem::DriveAccess::has_write_access`]
+    pub fn has_write_access(&self) -> bool {
+        self.write
+    }
+
+    /// Whether the actor holds the key needed to decrypt the file data keys referenced in the
+    /// filesystem.
+    pub fn holds_data_key(&self) -> bool {
+        self.data_key
+    }
+
+    /// Whether the actor holds the key needed to decrypt the structure of the filesystem.
+    pub fn holds_filesystem_key(&self) -> bool {
+        self.filesystem_key
+    }
+
+    pub(crate) fn new(
+        read: bool,
+        write: bool,
+        maintenance: bool,
+        filesystem_key: bool,
+        data_key: bool,
+    ) -> Self {
+        Self {
+            read,
+            write,
+            maintenance,
+            filesystem_key,
+            data_key,
+        }
+    }
+}
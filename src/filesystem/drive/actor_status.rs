@@ -0,0 +1,16 @@
+use crate::codec::header::AccessMask;
+
+/// Where an actor stands with a drive, produced by [`crate::filesystem::Drive::actor_status`].
+/// Primarily useful before authorizing a key to catch keys that were previously revoked, as
+/// re-authorizing them restores access to an actor other members may have considered removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActorStatus {
+    /// The actor has never been registered with the drive.
+    Unknown,
+
+    /// The actor is registered with the drive and currently holds the provided access.
+    Active(AccessMask),
+
+    /// The actor was registered with the drive but has since had its access revoked.
+    Historical,
+}
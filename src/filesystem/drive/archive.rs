@@ -0,0 +1,373 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use bytes::Bytes;
+use futures::future::ready;
+use futures::stream::{self, LocalBoxStream};
+use futures::{StreamExt, TryStreamExt};
+
+use crate::codec::filesystem::NodeKind;
+use crate::filesystem::drive::directory_handle::ReadableContent;
+use crate::filesystem::drive::{InnerDrive, OperationError};
+use crate::filesystem::nodes::{NodeData, NodeId, NodeName};
+use crate::stores::DataStore;
+
+const BLOCK_SIZE: usize = 512;
+
+/// Name used by GNU tar for the pseudo-entry that carries a path too long for the header itself.
+const LONG_NAME_MARKER: &[u8] = b"././@LongLink";
+
+const NAME_FIELD_SIZE: usize = 100;
+
+const TYPE_DIRECTORY: u8 = b'5';
+
+const TYPE_FILE: u8 = b'0';
+
+const TYPE_LONG_NAME: u8 = b'L';
+
+/// The largest value that fits in the 11 octal digits available to a numeric header field, larger
+/// sizes fall back to the GNU base-256 encoding.
+const MAX_OCTAL_VALUE: u64 = 0o77777777777;
+
+/// A single file or directory that will be written out as part of an archive. Everything needed
+/// to produce the entry is collected up front so the drive doesn't need to remain locked while the
+/// archive is streamed.
+pub(crate) struct ArchiveEntry {
+    node_id: NodeId,
+    path: String,
+    modified_at: i64,
+    kind: ArchiveEntryKind,
+}
+
+enum ArchiveEntryKind {
+    Directory,
+    File { size: u64, content: ReadableContent },
+}
+
+impl ArchiveEntry {
+    /// Collects the entries for the node and everything underneath it, parents are always listed
+    /// before their children. Entries are named relative to the parent of the provided node, when
+    /// the root of the drive is provided only its contents are included. Names are sanitized with
+    /// [`sanitize_sibling_names`] so every entry within a directory keeps a distinct name.
+    ///
+    /// Callers are responsible for checking the current actor is able to read the drive.
+    pub(crate) fn collect(
+        inner: &InnerDrive,
+        node_id: NodeId,
+    ) -> Result<Vec<ArchiveEntry>, OperationError> {
+        let path = match inner.by_id(node_id)?.name() {
+            NodeName::Root => String::new(),
+            NodeName::Named(name) => sanitize_name(&name),
+        };
+
+        let mut entries = Vec::new();
+        let mut pending = vec![(node_id, path)];
+
+        while let Some((node_id, path)) = pending.pop() {
+            let node = inner.by_id(node_id)?;
+
+            match node.data() {
+                NodeData::Directory { .. } => {
+                    let child_path = if path.is_empty() {
+                        path.clone()
+                    } else {
+                        format!("{path}/")
+                    };
+
+                    if !path.is_empty() {
+                        entries.push(ArchiveEntry {
+                            node_id,
+                            path: child_path.clone(),
+                            modified_at: node.modified_at(),
+                            kind: ArchiveEntryKind::Directory,
+                        });
+                    }
+
+                    let mut children = Vec::new();
+                    for child_pid in node.data().ordered_child_pids() {
+                        let child = inner.by_perm_id(&child_pid)?;
+                        if !matches!(child.kind(), NodeKind::Directory | NodeKind::File) {
+                            continue;
+                        }
+
+                        if let NodeName::Named(name) = child.name() {
+                            children.push((child.id(), name));
+                        }
+                    }
+
+                    let names: Vec<String> =
+                        children.iter().map(|(_, name)| name.clone()).collect();
+                    let child_names = sanitize_sibling_names(&names);
+
+                    // Reversed so the children come off the stack in their original order
+                    for ((child_id, _), name) in children.into_iter().zip(child_names).rev() {
+                        pending.push((child_id, format!("{child_path}{name}")));
+                    }
+                }
+                NodeData::File { content, .. } => {
+                    let size = content.size();
+                    let content = ReadableContent::from_node(inner, node_id)?;
+                    if matches!(content, ReadableContent::External { .. }) {
+                        return Err(OperationError::ExternalFetcherRequired);
+                    }
+
+                    entries.push(ArchiveEntry {
+                        node_id,
+                        path,
+                        modified_at: node.modified_at(),
+                        kind: ArchiveEntryKind::File { size, content },
+                    });
+                }
+                NodeData::AssociatedData { .. } => return Err(OperationError::NotReadable),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let (type_flag, mode, size) = match &self.kind {
+            ArchiveEntryKind::Directory => (TYPE_DIRECTORY, 0o755, 0),
+            ArchiveEntryKind::File { size, .. } => (TYPE_FILE, 0o644, *size),
+        };
+
+        // Timestamps within the drive are in milliseconds, tar only tracks seconds
+        let mtime = (self.modified_at.max(0) / 1_000) as u64;
+        let path = self.path.as_bytes();
+
+        let mut header = Vec::with_capacity(BLOCK_SIZE);
+        if path.len() > NAME_FIELD_SIZE {
+            let mut long_name = path.to_vec();
+            long_name.push(0);
+
+            let long_name_size = long_name.len() as u64;
+            header.extend_from_slice(&header_block(
+                LONG_NAME_MARKER,
+                TYPE_LONG_NAME,
+                0o644,
+                long_name_size,
+                0,
+            ));
+
+            long_name.resize(long_name.len() + padding_size(long_name_size), 0);
+            header.extend_from_slice(&long_name);
+        }
+
+        header.extend_from_slice(&header_block(path, type_flag, mode, size, mtime));
+        header
+    }
+
+    fn into_stream<S: DataStore>(
+        self,
+        store: &S,
+    ) -> Result<LocalBoxStream<'_, Result<Bytes, OperationError>>, OperationError> {
+        let header = stream::once(ready(Ok(Bytes::from(self.header()))));
+
+        let (size, content) = match self.kind {
+            ArchiveEntryKind::Directory => return Ok(header.boxed_local()),
+            ArchiveEntryKind::File { size, content } => (size, content),
+        };
+
+        let written = Rc::new(Cell::new(0u64));
+        let counter = written.clone();
+        let data = content.into_stream(store)?.map_ok(move |chunk| {
+            counter.set(counter.get() + chunk.len() as u64);
+            Bytes::from(chunk)
+        });
+
+        // The size in the header has already been written, content that doesn't match it would
+        // silently corrupt every entry that follows.
+        let node_id = self.node_id;
+        let padding = stream::once(async move {
+            if written.get() != size {
+                return Err(OperationError::InternalCorruption(
+                    node_id,
+                    "file content didn't match its recorded size",
+                ));
+            }
+
+            Ok(Bytes::from(vec![0u8; padding_size(size)]))
+        });
+
+        Ok(header.chain(data).chain(padding).boxed_local())
+    }
+}
+
+/// Produces a tar archive containing each of the entries in turn. Only the data block currently
+/// being read is held in memory. The stream ends after the first error.
+pub(crate) fn archive_stream<S: DataStore>(
+    store: &S,
+    entries: Vec<ArchiveEntry>,
+) -> LocalBoxStream<'_, Result<Bytes, OperationError>> {
+    let end_of_archive = stream::once(ready(Ok(Bytes::from(vec![0u8; BLOCK_SIZE * 2]))));
+
+    stream::iter(entries)
+        .map(move |entry| match entry.into_stream(store) {
+            Ok(entry_stream) => entry_stream,
+            Err(err) => stream::once(ready(Err(err))).boxed_local(),
+        })
+        .flatten()
+        .chain(end_of_archive)
+        .filter(|item| ready(!matches!(item, Ok(bytes) if bytes.is_empty())))
+        .scan(false, |failed, item| {
+            if *failed {
+                return ready(None);
+            }
+
+            *failed = item.is_err();
+            ready(Some(item))
+        })
+        .boxed_local()
+}
+
+/// Builds a GNU flavored tar header. The name is truncated to fit the header, longer names need to
+/// be preceded by a long name entry.
+fn header_block(name: &[u8], type_flag: u8, mode: u64, size: u64, mtime: u64) -> [u8; BLOCK_SIZE] {
+    let mut block = [0u8; BLOCK_SIZE];
+
+    let name_len = name.len().min(NAME_FIELD_SIZE);
+    block[..name_len].copy_from_slice(&name[..name_len]);
+
+    write_numeric(&mut block[100..108], mode);
+    write_numeric(&mut block[108..116], 0);
+    write_numeric(&mut block[116..124], 0);
+    write_numeric(&mut block[124..136], size);
+    write_numeric(&mut block[136..148], mtime);
+    block[156] = type_flag;
+    block[257..265].copy_from_slice(b"ustar  \0");
+
+    // The checksum is calculated with the checksum field itself filled with spaces
+    block[148..156].fill(b' ');
+    let checksum = block.iter().map(|b| *b as u64).sum::<u64>();
+    let checksum_field = format!("{checksum:06o}\0 ");
+    block[148..156].copy_from_slice(checksum_field.as_bytes());
+
+    block
+}
+
+fn padding_size(size: u64) -> usize {
+    let remainder = (size % BLOCK_SIZE as u64) as usize;
+    (BLOCK_SIZE - remainder) % BLOCK_SIZE
+}
+
+/// Replaces anything in a node name that tar or the systems extracting the archive would treat
+/// specially. Node names can't contain slashes but may contain backslashes and control characters,
+/// which are replaced with underscores. The traversal names are already rejected by the drive but
+/// are handled here as well so a malformed drive can't produce an archive that escapes its
+/// extraction directory.
+fn sanitize_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    match sanitized.as_str() {
+        "" => "_".to_string(),
+        "." | ".." => sanitized.replace('.', "_"),
+        _ => sanitized,
+    }
+}
+
+/// Sanitizes the names of the entries within a single directory. Sanitizing can turn distinct
+/// names into the same one, such as `a\b` and `a_b`. Names that don't need sanitizing are kept as
+/// they are, any other name that would collide has a numeric suffix added before its extension
+/// (`a_b~1`) to keep the entries from overwriting each other when the archive is extracted.
+fn sanitize_sibling_names(names: &[String]) -> Vec<String> {
+    let sanitized: Vec<String> = names.iter().map(|name| sanitize_name(name)).collect();
+
+    let mut taken: HashSet<String> = names
+        .iter()
+        .zip(sanitized.iter())
+        .filter(|(name, sanitized)| name == sanitized)
+        .map(|(_, sanitized)| sanitized.clone())
+        .collect();
+
+    names
+        .iter()
+        .zip(sanitized)
+        .map(|(name, sanitized)| {
+            if *name == sanitized {
+                return sanitized;
+            }
+
+            let mut candidate = sanitized.clone();
+            let mut suffix = 1;
+            while !taken.insert(candidate.clone()) {
+                candidate = match sanitized.rfind('.') {
+                    Some(idx) if idx > 0 => {
+                        format!("{}~{suffix}{}", &sanitized[..idx], &sanitized[idx..])
+                    }
+                    _ => format!("{sanitized}~{suffix}"),
+                };
+                suffix += 1;
+            }
+
+            candidate
+        })
+        .collect()
+}
+
+/// Writes a zero padded octal number terminated by a NUL into the field. Values too large for the
+/// field use the GNU base-256 encoding instead.
+fn write_numeric(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+
+    if field.len() == 12 && value > MAX_OCTAL_VALUE {
+        field.fill(0);
+        field[0] = 0x80;
+        field[4..].copy_from_slice(&value.to_be_bytes());
+        return;
+    }
+
+    let encoded = format!("{value:0digits$o}\0");
+    field.copy_from_slice(encoded.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_block_checksum_and_fields() {
+        let block = header_block(b"photos/cat.jpg", TYPE_FILE, 0o644, 1_234, 1_700_000_000);
+
+        assert_eq!(&block[..14], b"photos/cat.jpg");
+        assert_eq!(&block[124..136], b"00000002322\0");
+        assert_eq!(&block[257..265], b"ustar  \0");
+
+        let recorded = std::str::from_utf8(&block[148..154]).unwrap();
+        let recorded = u64::from_str_radix(recorded, 8).unwrap();
+
+        let mut unsigned = block;
+        unsigned[148..156].fill(b' ');
+        let expected = unsigned.iter().map(|b| *b as u64).sum::<u64>();
+        assert_eq!(recorded, expected);
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("notes.txt"), "notes.txt");
+        assert_eq!(sanitize_name("back\\slash"), "back_slash");
+        assert_eq!(sanitize_name("line\nbreak"), "line_break");
+        assert_eq!(sanitize_name(".."), "__");
+        assert_eq!(sanitize_name("."), "_");
+    }
+
+    #[test]
+    fn test_sanitize_sibling_names_disambiguates_collisions() {
+        let names: Vec<String> = ["a\\b.txt", "a_b.txt", "c\nd", "c_d", "c\td", "e\nf"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        assert_eq!(
+            sanitize_sibling_names(&names),
+            vec!["a_b~1.txt", "a_b.txt", "c_d~1", "c_d", "c_d~2", "e_f"]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use elliptic_curve::rand_core::CryptoRngCore;
+use futures::AsyncWrite;
+
+use crate::codec::crypto::{AccessKey, AsymLockedAccessKey, Signature, SigningKey, VerifyingKey};
+use crate::codec::header::AccessMask;
+use crate::codec::meta::FilesystemId;
+use crate::codec::{ParserResult, Stream};
+use crate::filesystem::drive::DriveAccessError;
+
+/// A standalone, transferable grant of access to a single drive for a single recipient key. The
+/// token is minted by an existing actor through [`crate::filesystem::Drive::grant_capability`]
+/// and contains each of the permission keys covered by its [`AccessMask`] escrowed to the
+/// recipient's [`VerifyingKey`], the same way they would be if the recipient had been added
+/// directly with [`crate::filesystem::Drive::authorize_key`].
+///
+/// The entire token is signed by the issuing actor. When it's redeemed with
+/// [`crate::filesystem::Drive::redeem_capability`] that signature is checked against the actors
+/// present in the drive, and the recipient proves ownership of their key by unlocking the
+/// escrowed permission keys.
+#[derive(Clone, Debug)]
+pub struct CapabilityToken {
+    body: CapabilityBody,
+    signature: Signature,
+}
+
+impl CapabilityToken {
+    pub fn access_mask(&self) -> AccessMask {
+        self.body.access_mask
+    }
+
+    pub async fn encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let mut written_bytes = 0;
+
+        written_bytes += self.body.encode(writer).await?;
+        written_bytes += self.signature.encode(writer).await?;
+
+        Ok(written_bytes)
+    }
+
+    pub fn filesystem_id(&self) -> FilesystemId {
+        self.body.filesystem_id
+    }
+
+    /// Produces a new signed token. The caller is responsible for ensuring the issuing key is
+    /// allowed to grant the permissions and for providing the unlocked copies of each permission
+    /// key covered by the access mask in the order filesystem, data, and maintenance.
+    pub(crate) async fn issue(
+        rng: &mut impl CryptoRngCore,
+        filesystem_id: FilesystemId,
+        issuer_key: &SigningKey,
+        recipient: VerifyingKey,
+        access_mask: AccessMask,
+        keys: [Option<&AccessKey>; 3],
+    ) -> Result<Self, DriveAccessError> {
+        let mut locked_keys = Vec::with_capacity(keys.len());
+        for key in keys {
+            let locked_key = key
+                .map(|k| k.lock_for(rng, &recipient))
+                .transpose()
+                .map_err(|_| DriveAccessError::InvalidCapability("failed to escrow key"))?;
+            locked_keys.push(locked_key);
+        }
+
+        let mut locked_keys = locked_keys.into_iter();
+        let body = CapabilityBody {
+            filesystem_id,
+            issuer: issuer_key.verifying_key(),
+            recipient,
+            access_mask,
+
+            filesystem_key: locked_keys.next().flatten(),
+            data_key: locked_keys.next().flatten(),
+            maintenance_key: locked_keys.next().flatten(),
+        };
+
+        let mut signed_bytes = Vec::new();
+        body.encode(&mut signed_bytes)
+            .await
+            .map_err(|_| DriveAccessError::InvalidCapability("failed to encode token"))?;
+        let signature = issuer_key.sign(rng, &signed_bytes);
+
+        Ok(Self { body, signature })
+    }
+
+    /// The key of the actor that minted and signed this token
+    pub fn issuer(&self) -> &VerifyingKey {
+        &self.body.issuer
+    }
+
+    pub fn parse(input: Stream) -> ParserResult<Self> {
+        let (input, body) = CapabilityBody::parse(input)?;
+        let (input, signature) = Signature::parse(input)?;
+
+        Ok((input, Self { body, signature }))
+    }
+
+    /// The key this token grants access to, only the matching [`SigningKey`] is able to redeem
+    /// it.
+    pub fn recipient(&self) -> &VerifyingKey {
+        &self.body.recipient
+    }
+
+    /// Unlocks each of the escrowed permission keys using the recipient's key. The returned keys
+    /// are in the order filesystem, data, and maintenance.
+    pub(crate) fn unlock_keys(
+        &self,
+        recipient_key: &SigningKey,
+    ) -> Result<[Option<AccessKey>; 3], DriveAccessError> {
+        if recipient_key.verifying_key() != self.body.recipient {
+            return Err(DriveAccessError::InvalidCapability(
+                "token was issued to a different key",
+            ));
+        }
+
+        let unlock = |locked: &Option<AsymLockedAccessKey>| {
+            locked
+                .as_ref()
+                .map(|key| key.unlock(recipient_key))
+                .transpose()
+                .map_err(|_| DriveAccessError::InvalidCapability("unable to unlock escrowed key"))
+        };
+
+        Ok([
+            unlock(&self.body.filesystem_key)?,
+            unlock(&self.body.data_key)?,
+            unlock(&self.body.maintenance_key)?,
+        ])
+    }
+
+    /// Confirms the token's contents have not been modified since they were signed by the
+    /// issuer. This does not confirm the issuer has any access to the drive.
+    pub(crate) async fn verify_signature(&self) -> Result<(), DriveAccessError> {
+        let mut signed_bytes = Vec::new();
+        self.body
+            .encode(&mut signed_bytes)
+            .await
+            .map_err(|_| DriveAccessError::InvalidCapability("failed to encode token"))?;
+
+        self.signature
+            .verify(&self.body.issuer, &signed_bytes)
+            .map_err(|_| DriveAccessError::InvalidCapability("signature verification failed"))
+    }
+}
+
+/// The signed portion of a [`CapabilityToken`]
+#[derive(Clone, Debug)]
+struct CapabilityBody {
+    filesystem_id: FilesystemId,
+    issuer: VerifyingKey,
+    recipient: VerifyingKey,
+    access_mask: AccessMask,
+
+    filesystem_key: Option<AsymLockedAccessKey>,
+    data_key: Option<AsymLockedAccessKey>,
+    maintenance_key: Option<AsymLockedAccessKey>,
+}
+
+impl CapabilityBody {
+    async fn encode<W: AsyncWrite + Unpin + Send>(&self, writer: &mut W) -> std::io::Result<usize> {
+        let mut written_bytes = 0;
+
+        written_bytes += self.filesystem_id.encode(writer).await?;
+        written_bytes += self.issuer.encode(writer).await?;
+        written_bytes += self.recipient.encode(writer).await?;
+        written_bytes += self.access_mask.encode(writer).await?;
+
+        for locked_key in [&self.filesystem_key, &self.data_key, &self.maintenance_key]
+            .into_iter()
+            .flatten()
+        {
+            written_bytes += locked_key.encode(writer).await?;
+        }
+
+        Ok(written_bytes)
+    }
+
+    fn parse(input: Stream) -> ParserResult<Self> {
+        let (input, filesystem_id) = FilesystemId::parse(input)?;
+        let (input, issuer) = VerifyingKey::parse(input)?;
+        let (input, recipient) = VerifyingKey::parse(input)?;
+        let (input, access_mask) = AccessMask::parse(input)?;
+
+        let (input, filesystem_key) = parse_locked_key(input, access_mask.has_filesystem_key())?;
+        let (input, data_key) = parse_locked_key(input, access_mask.has_data_key())?;
+        let (input, maintenance_key) = parse_locked_key(input, access_mask.has_maintenance_key())?;
+
+        let body = Self {
+            filesystem_id,
+            issuer,
+            recipient,
+            access_mask,
+
+            filesystem_key,
+            data_key,
+            maintenance_key,
+        };
+
+        Ok((input, body))
+    }
+}
+
+fn parse_locked_key(input: Stream, present: bool) -> ParserResult<Option<AsymLockedAccessKey>> {
+    if !present {
+        return Ok((input, None));
+    }
+
+    let (input, key) = AsymLockedAccessKey::parse(input)?;
+    Ok((input, Some(key)))
+}
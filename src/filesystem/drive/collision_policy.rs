@@ -0,0 +1,52 @@
+use crate::filesystem::drive::OperationError;
+use crate::filesystem::nodes::{Node, NodeName, NodeNameError};
+
+/// Limit on the encoded length of a [`NodeName`], names are truncated to make room for the suffix
+/// added by [`CollisionPolicy::Rename`].
+const MAX_NAME_LENGTH: usize = 255;
+
+/// How an operation creating a node should behave when the name it was asked to use is already
+/// taken in the parent directory, see [`crate::filesystem::DirectoryHandle::mkdir_with_policy`]
+/// and [`crate::filesystem::DirectoryHandle::write_with_policy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Reject the operation with [`OperationError::Exists`].
+    #[default]
+    Error,
+
+    /// Make use of the existing node. Files have their content replaced while existing
+    /// directories are used as they are. A file never replaces a directory, or the other way
+    /// around, these are still rejected with [`OperationError::Exists`].
+    Overwrite,
+
+    /// Use the first available name formed by appending " (2)", " (3)", and so on to the
+    /// requested name. The requested name is shortened when needed to keep the result within the
+    /// limits of a valid name.
+    Rename,
+}
+
+/// Finds the first name derived from `name` as described by [`CollisionPolicy::Rename`] that
+/// isn't in use by any child of `parent`.
+pub(crate) fn available_name(parent: &Node, name: &NodeName) -> Result<NodeName, OperationError> {
+    let base_name = match name {
+        NodeName::Named(name) => name.as_str(),
+        NodeName::Root => return Err(NodeNameError::ReservedRoot.into()),
+    };
+
+    let mut counter = 2u64;
+    loop {
+        let suffix = format!(" ({counter})");
+
+        let mut base_length = base_name.len().min(MAX_NAME_LENGTH - suffix.len());
+        while !base_name.is_char_boundary(base_length) {
+            base_length -= 1;
+        }
+
+        let candidate = NodeName::named(format!("{}{suffix}", &base_name[..base_length]))?;
+        if parent.data().child_permanent_id(&candidate).is_none() {
+            return Ok(candidate);
+        }
+
+        counter += 1;
+    }
+}
@@ -1,7 +1,9 @@
-use crate::codec::filesystem::NodeKind;
-use crate::codec::PermanentId;
+use std::collections::HashMap;
+
+use crate::codec::filesystem::{NodeKind, Permissions};
+use crate::codec::{PermanentId, VectorClockNodeSnapshot};
 use crate::filesystem::drive::OperationError;
-use crate::filesystem::nodes::{Node, NodeName};
+use crate::filesystem::nodes::{MetadataKey, Node, NodeName};
 
 /// An immutable view of one of the children of a directory in the filesystem, gets returned by `DirectoryHandle::ls()`
 #[derive(Debug)]
@@ -14,10 +16,14 @@ pub struct DirectoryEntry {
     name: NodeName,
     kind: NodeKind,
 
+    metadata: HashMap<MetadataKey, Vec<u8>>,
+    permissions: Option<Permissions>,
+
     #[cfg(feature = "mime-type")]
     mime_type: Option<mime::MediaType>,
 
     size: u64,
+    vector_clock: VectorClockNodeSnapshot,
 }
 
 impl DirectoryEntry {
@@ -31,6 +37,21 @@ impl DirectoryEntry {
         self.kind.clone()
     }
 
+    /// Whether the entry is a directory, and can be traversed with `DirectoryHandle::cd()`
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory)
+    }
+
+    /// Whether the entry is a file
+    pub fn is_file(&self) -> bool {
+        matches!(self.kind, NodeKind::File)
+    }
+
+    /// Entry's metadata attributes, see `DirectoryHandle::set_attribute()`
+    pub fn metadata(&self) -> &HashMap<MetadataKey, Vec<u8>> {
+        &self.metadata
+    }
+
     /// Entry's last modification timestamp
     pub fn modified_at(&self) -> i64 {
         self.modified_at
@@ -46,12 +67,22 @@ impl DirectoryEntry {
         self.permanent_id
     }
 
+    /// Entry's permissions, see `DirectoryHandle::chmod()`
+    pub fn permissions(&self) -> Option<Permissions> {
+        self.permissions
+    }
+
     /// Entry's content size
     /// (i.e. for a File the size of all file content, for a subdirectory the size of the files it and it sub-directories hold)
     pub fn size(&self) -> u64 {
         self.size
     }
 
+    /// Entry's vector clock, used to determine the causal ordering of different versions of it
+    pub fn vector_clock(&self) -> VectorClockNodeSnapshot {
+        self.vector_clock
+    }
+
     #[cfg(feature = "mime-type")]
     pub fn mime_type(&self) -> Option<mime::MediaType> {
         match self.kind {
@@ -78,10 +109,14 @@ impl TryFrom<&Node> for DirectoryEntry {
             name: node.name().clone(),
             kind: node.kind().clone(),
 
+            metadata: node.metadata().clone(),
+            permissions: node.permissions(),
+
             #[cfg(feature = "mime-type")]
             mime_type: node.mime_type(),
 
             size: node.size(),
+            vector_clock: node.vector_clock(),
         })
     }
 }
@@ -1,27 +1,52 @@
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+#[cfg(feature = "local-store")]
+use std::path::Path;
 use std::sync::Arc;
 
 use async_std::sync::RwLock;
 use elliptic_curve::rand_core::CryptoRngCore;
 use futures::future::BoxFuture;
-use futures::FutureExt;
+use futures::stream::{self, LocalBoxStream};
+use futures::{AsyncRead, AsyncReadExt, FutureExt, StreamExt, TryStreamExt};
 use tracing::{debug, instrument, trace, Instrument, Level};
 
 use crate::codec::filesystem::NodeKind;
 use crate::codec::*;
 
 use crate::codec::crypto::{AccessKey, SigningKey};
-use crate::codec::data_storage::{data_chunk::DataChunk, DataBlock};
+use crate::codec::data_storage::encrypted_data_chunk::EncryptedDataChunkError;
+use crate::codec::data_storage::{
+    data_chunk::DataChunk, data_options::DataOptions, ChunkSizePolicy, DataBlock,
+};
 use crate::codec::filesystem::BlockKind;
-use crate::filesystem::drive::{DirectoryEntry, InnerDrive, OperationError, WalkState};
+use crate::filesystem::drive::collision_policy::available_name;
+#[cfg(feature = "local-store")]
+use crate::filesystem::drive::directory_import::{
+    ImportError, ImportLog, ImportSource, ImportSummary,
+};
+use crate::filesystem::drive::{
+    CollisionPolicy, DirectoryEntry, InnerDrive, OperationError, WalkState, WriteStreamOptions,
+};
+use crate::filesystem::nodes::metadata::MetadataKey;
 #[cfg(feature = "mime-type")]
-use crate::filesystem::nodes::metadata::{MetadataKey, MimeGuesser};
-use crate::filesystem::nodes::{Node, NodeData, NodeId, NodeName};
-use crate::filesystem::{ContentLocation, ContentReference, FileContent, NodeBuilder};
-use crate::stores::DataStore;
+use crate::filesystem::nodes::metadata::{MimeGuesser, MIME_SNIFF_LENGTH};
+#[cfg(feature = "mime-type")]
+use crate::filesystem::nodes::Node;
+use crate::filesystem::nodes::{
+    ChildMapEntry, NodeData, NodeDataError, NodeId, NodeName, MAX_METADATA_ENTRIES,
+};
+use crate::filesystem::{
+    ContentLocation, ContentReference, FileContent, FileContentError, NodeBuilder,
+};
+use crate::stores::{DataStore, ExternalFetcher};
 
 use self::filesystem::Permissions;
 
-const MAX_PATH_DEPTH: usize = 32;
+pub(crate) const MAX_PATH_DEPTH: usize = 32;
+
+/// Reserved name of the associated data entry holding a file's thumbnail.
+const THUMBNAIL_NAME: &str = "thumbnail";
 
 /// A handle on a specific directory, used to perform most operations on the filesystem itself.
 /// Instances of these are safe to clone but each one will track its own current working directory.
@@ -31,6 +56,7 @@ pub struct DirectoryHandle {
     pub(crate) current_key: Arc<SigningKey>,
     pub(crate) cwd_id: NodeId,
     pub(crate) inner: Arc<RwLock<InnerDrive>>,
+    pub(crate) chunk_size_policy: ChunkSizePolicy,
 }
 
 impl DirectoryHandle {
@@ -61,14 +87,93 @@ impl DirectoryHandle {
             current_key: self.current_key.clone(),
             cwd_id: target_directory_id,
             inner: self.inner.clone(),
+            chunk_size_policy: self.chunk_size_policy,
         };
 
         Ok(directory)
     }
 
-    /// Changes the permission on the target node. Currently not implemented.
-    pub async fn chmod(&self, _path: &[&str], _owner: Permissions) -> Result<(), OperationError> {
-        unimplemented!()
+    /// Produces the CID of the node at the provided path, an empty path refers to the current
+    /// working directory. A directory's CID covers the CIDs of all of its children so it changes
+    /// whenever anything beneath it does, making it usable as a rollup hash of the entire subtree:
+    /// two subtrees with equal CIDs are guaranteed to be identical, allowing comparisons between
+    /// drives to skip them without walking their contents.
+    ///
+    /// The reverse doesn't hold. The CID includes node metadata such as timestamps and owners, so
+    /// subtrees holding the same files may still produce different CIDs and need to be walked to
+    /// be compared.
+    pub async fn content_hash(&self, path: &[&str]) -> Result<Cid, OperationError> {
+        let node_id = self.target_node_id(path).await?;
+
+        let inner_read = self.inner.read().await;
+        inner_read.by_id(node_id)?.cid().await
+    }
+
+    /// Changes the permissions of the target node. An empty path changes the permissions of the
+    /// current working directory, including the root directory of the drive.
+    pub async fn chmod(
+        &self,
+        path: &[&str],
+        permissions: Permissions,
+    ) -> Result<(), OperationError> {
+        let node_id = self.target_node_id(path).await?;
+
+        let mut inner_write = self.inner.write().await;
+        if !inner_write
+            .access()
+            .has_write_access(&self.current_key.actor_id())
+        {
+            return Err(OperationError::AccessDenied);
+        }
+        inner_write
+            .by_id_mut(node_id)
+            .await?
+            .set_permissions(permissions)
+            .await?;
+        inner_write.clean_drive().await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the permissions of the target node. An empty path targets the current working
+    /// directory. See [`Permissions`] for the flags that are available.
+    pub async fn permissions(&self, path: &[&str]) -> Result<Permissions, OperationError> {
+        let node_id = self.target_node_id(path).await?;
+
+        let inner_read = self.inner.read().await;
+        let permissions = inner_read
+            .by_id(node_id)?
+            .permissions()
+            .ok_or(NodeDataError::PermissionsUnsupported)?;
+
+        Ok(permissions)
+    }
+
+    /// Sets or clears the executable flag on the target node leaving the rest of its permissions
+    /// untouched. An empty path targets the current working directory.
+    pub async fn set_executable(
+        &self,
+        path: &[&str],
+        executable: bool,
+    ) -> Result<(), OperationError> {
+        let node_id = self.target_node_id(path).await?;
+
+        let mut inner_write = self.inner.write().await;
+        if !inner_write
+            .access()
+            .has_write_access(&self.current_key.actor_id())
+        {
+            return Err(OperationError::AccessDenied);
+        }
+        let node = inner_write.by_id_mut(node_id).await?;
+        let permissions = node
+            .permissions()
+            .ok_or(NodeDataError::PermissionsUnsupported)?
+            .with_executable(executable);
+        node.set_permissions(permissions).await?;
+        inner_write.clean_drive().await?;
+
+        Ok(())
     }
 
     /// Changes the owner of the target node. Currently not implemented
@@ -76,6 +181,52 @@ impl DirectoryHandle {
         unimplemented!()
     }
 
+    /// Attaches a metadata attribute to the target node, returning the previous value if the
+    /// attribute was already set. An empty path targets the current working directory which can
+    /// be used to attach drive-wide attributes (such as a label) to the root directory. Keys and
+    /// values are limited to 255 bytes each and a node can hold at most `MAX_METADATA_ENTRIES`
+    /// (65535) attributes.
+    pub async fn set_attribute(
+        &mut self,
+        path: &[&str],
+        key: MetadataKey,
+        value: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, OperationError> {
+        if key.as_bytes().len() > u8::MAX as usize {
+            return Err(OperationError::InvalidMetadata("key too long"));
+        }
+
+        if value.len() > u8::MAX as usize {
+            return Err(OperationError::InvalidMetadata("value too long"));
+        }
+
+        #[cfg(feature = "banyan-api")]
+        crate::filesystem::drive::platform_attributes::validate(&key, &value)?;
+
+        let node_id = self.target_node_id(path).await?;
+
+        let mut inner_write = self.inner.write().await;
+        if !inner_write
+            .access()
+            .has_write_access(&self.current_key.actor_id())
+        {
+            return Err(OperationError::AccessDenied);
+        }
+        let node = inner_write.by_id(node_id)?;
+        if !node.metadata().contains_key(&key) && node.metadata().len() >= MAX_METADATA_ENTRIES {
+            return Err(OperationError::InvalidMetadata("too many attributes"));
+        }
+
+        let previous = inner_write
+            .by_id_mut(node_id)
+            .await?
+            .set_attribute(key, value)
+            .await;
+        inner_write.clean_drive().await?;
+
+        Ok(previous)
+    }
+
     /// Retrieve the contents of a directory as a Vector of `DirectoryEntry`
     /// Passed in path is relative to the current working directory, if path is empty it will
     /// list contents of current working directory
@@ -102,6 +253,7 @@ impl DirectoryHandle {
         } else {
             let node_id = match walk_path(&self.inner, self.cwd_id, path, 0).await {
                 Ok(WalkState::FoundNode { node_id }) => node_id,
+                Err(err @ OperationError::SubtreeNotLoaded(_)) => return Err(err),
                 _ => return Err(OperationError::NotTraversable),
             };
 
@@ -130,6 +282,171 @@ impl DirectoryHandle {
         Ok(entries)
     }
 
+    /// Lists everything beneath the directory at the provided path, depth first, with each entry
+    /// accompanied by its path relative to the current working directory. Children are listed
+    /// directly after their directory. An empty path lists the current working directory, while a
+    /// path to a file produces the single entry for it.
+    ///
+    /// A `max_depth` bounds how many levels of subdirectories are descended into, a depth of zero
+    /// lists the same entries as [`DirectoryHandle::ls`] and `None` lists the entire subtree.
+    #[instrument(level = Level::DEBUG, skip(self))]
+    pub async fn ls_recursive(
+        &self,
+        path: &[&str],
+        max_depth: Option<usize>,
+    ) -> Result<Vec<(Vec<String>, DirectoryEntry)>, OperationError> {
+        trace!(cwd_id = self.cwd_id, "directory::ls_recursive");
+
+        let node_id = if path.is_empty() {
+            self.cwd_id
+        } else {
+            match walk_path(&self.inner, self.cwd_id, path, 0).await {
+                Ok(WalkState::FoundNode { node_id }) => node_id,
+                _ => return Err(OperationError::NotTraversable),
+            }
+        };
+
+        let inner_read = self.inner.read().await;
+        let listed_node = inner_read.by_id(node_id)?;
+        let base_path: Vec<String> = path.iter().map(|name| name.to_string()).collect();
+
+        if !matches!(listed_node.data(), NodeData::Directory { .. }) {
+            let entry = DirectoryEntry::try_from(listed_node)?;
+            return Ok(vec![(base_path, entry)]);
+        }
+
+        // The tree shouldn't contain any cycles but a corrupted one would otherwise never finish
+        // being listed.
+        let mut visited = HashSet::from([listed_node.permanent_id()]);
+        let mut pending: Vec<(Vec<String>, PermanentId, usize)> = Vec::new();
+
+        // Children are queued in reverse name order so they're listed in name order
+        let queue_children =
+            |pending: &mut Vec<_>, parent_path: &[String], data: &NodeData, depth| {
+                let NodeData::Directory { children, .. } = data else {
+                    return;
+                };
+
+                let mut named_children: Vec<_> = children
+                    .iter()
+                    .filter_map(|(name, child)| match name {
+                        NodeName::Named(name) => Some((name.clone(), *child.permanent_id())),
+                        NodeName::Root => None,
+                    })
+                    .collect();
+                named_children.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+                for (name, perm_id) in named_children {
+                    let mut child_path = parent_path.to_vec();
+                    child_path.push(name);
+                    pending.push((child_path, perm_id, depth));
+                }
+            };
+        queue_children(&mut pending, &base_path, listed_node.data(), 0);
+
+        let mut entries = Vec::new();
+        while let Some((entry_path, perm_id, depth)) = pending.pop() {
+            if !visited.insert(perm_id) {
+                tracing::warn!(?perm_id, "skipping node that was already listed");
+                continue;
+            }
+
+            let node = inner_read.by_perm_id(&perm_id)?;
+            if max_depth.is_none_or(|max_depth| depth < max_depth) {
+                queue_children(&mut pending, &entry_path, node.data(), depth + 1);
+            }
+
+            entries.push((entry_path, DirectoryEntry::try_from(node)?));
+        }
+
+        trace!(count = entries.len(), "directory::ls_recursive::success");
+
+        Ok(entries)
+    }
+
+    /// Lists the same entries as [`DirectoryHandle::ls`] along with the CID and size of each as
+    /// they're recorded in the parent directory. The child nodes themselves aren't consulted so
+    /// this is cheaper than listing when only those details are needed, such as when building
+    /// proofs of the filesystem's structure. Listing a file produces the single entry for it.
+    pub async fn ls_detailed(
+        &self,
+        path: &[&str],
+    ) -> Result<Vec<(NodeName, PermanentId, Cid, u64)>, OperationError> {
+        trace!(cwd_id = self.cwd_id, "directory::ls_detailed");
+
+        let node_id = if path.is_empty() {
+            self.cwd_id
+        } else {
+            match walk_path(&self.inner, self.cwd_id, path, 0).await {
+                Ok(WalkState::FoundNode { node_id }) => node_id,
+                _ => return Err(OperationError::NotTraversable),
+            }
+        };
+
+        let inner_read = self.inner.read().await;
+        let listed_node = inner_read.by_id(node_id)?;
+
+        let detail = |name: &NodeName, entry: &ChildMapEntry| {
+            (
+                name.clone(),
+                *entry.permanent_id(),
+                entry.cid().clone(),
+                entry.size(),
+            )
+        };
+
+        if let NodeData::Directory { children, .. } = listed_node.data() {
+            return Ok(children
+                .iter()
+                .map(|(name, entry)| detail(name, entry))
+                .collect());
+        }
+
+        let parent_id = listed_node
+            .parent_id()
+            .ok_or(OperationError::InternalCorruption(
+                node_id,
+                "non-directory node without a parent",
+            ))?;
+
+        let name = listed_node.name();
+        let parent_node = inner_read.by_perm_id(&parent_id)?;
+        match parent_node.data() {
+            NodeData::Directory { children, .. } => match children.get(&name) {
+                Some(entry) => Ok(vec![detail(&name, entry)]),
+                None => Err(OperationError::InternalCorruption(
+                    node_id,
+                    "node missing from its parent's children",
+                )),
+            },
+            _ => Err(OperationError::InternalCorruption(
+                node_id,
+                "parent of node is not a directory",
+            )),
+        }
+    }
+
+    /// Resolves a path relative to the current working directory, an empty path refers to the
+    /// current working directory itself.
+    async fn target_node_id(&self, path: &[&str]) -> Result<NodeId, OperationError> {
+        if path.is_empty() {
+            return Ok(self.cwd_id);
+        }
+
+        match walk_path(&self.inner, self.cwd_id, path, 0).await {
+            Ok(WalkState::FoundNode { node_id }) => Ok(node_id),
+            _ => Err(OperationError::PathNotFound),
+        }
+    }
+
+    /// Uses the provided policy to decide the size of the chunks the content of files written
+    /// through this handle is split into, handles start out with [`ChunkSizePolicy::default`].
+    /// Handles produced from this one, such as with [`DirectoryHandle::cd`], keep the policy.
+    pub fn with_chunk_size_policy(mut self, policy: ChunkSizePolicy) -> Self {
+        self.chunk_size_policy = policy;
+        self
+    }
+
     #[instrument(level = Level::TRACE, skip(current_key, inner))]
     pub(crate) async fn new(
         current_key: Arc<SigningKey>,
@@ -142,56 +459,117 @@ impl DirectoryHandle {
             current_key,
             cwd_id,
             inner,
+            chunk_size_policy: ChunkSizePolicy::default(),
         }
     }
 
-    #[instrument(level = tracing::Level::TRACE, skip_all)]
-    async fn insert_node<'a, 'b, R, F, Fut>(
-        &'b mut self,
-        rng: &'a mut R,
-        parent_permanent_id: PermanentId,
-        build_node: F,
-    ) -> Result<PermanentId, OperationError>
-    where
-        R: CryptoRngCore,
-        F: FnOnce(&'a mut R, NodeId, PermanentId, ActorId) -> Fut,
-        Fut: std::future::Future<Output = Result<Node, OperationError>>,
-    {
-        trace!("directory::insert_node");
+    /// Creates the directory at the provided path. When `recursive` is set any missing parent
+    /// directories are created as well. The entire path is created while holding the lock on the
+    /// drive, so if the returned future is dropped before it completes none of the directories
+    /// will have been created.
+    ///
+    /// An existing directory at the path is left as is, matching [`CollisionPolicy::Overwrite`].
+    #[instrument(skip(self, rng))]
+    pub async fn mkdir(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        path: &[&str],
+        recursive: bool,
+    ) -> Result<(), OperationError> {
+        self.mkdir_with_policy(rng, path, recursive, CollisionPolicy::Overwrite)
+            .await?;
 
-        let mut inner_write = self.inner.write().in_current_span().await;
+        Ok(())
+    }
 
-        let owner_id = self.current_key.actor_id();
-        let new_permanent_id = inner_write
-            .create_node(rng, owner_id, parent_permanent_id, build_node)
+    /// Creates the directory at the provided path along with any missing parents, the same as a
+    /// recursive [`DirectoryHandle::mkdir`], and returns a handle on it. Directories that already
+    /// exist are reused, so this can also be used to get a handle on a directory that may not
+    /// exist yet.
+    #[instrument(skip(self, rng))]
+    pub async fn mkdir_p(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        path: &[&str],
+    ) -> Result<DirectoryHandle, OperationError> {
+        let (directory_id, _) = self
+            .create_directory(rng, path, true, CollisionPolicy::Overwrite)
             .await?;
-        inner_write.clean_drive().await?;
-        Ok(new_permanent_id)
+
+        let directory = DirectoryHandle {
+            current_key: self.current_key.clone(),
+            cwd_id: directory_id,
+            inner: self.inner.clone(),
+            chunk_size_policy: self.chunk_size_policy,
+        };
+
+        Ok(directory)
     }
 
+    /// Behaves the same as [`DirectoryHandle::mkdir`] with the provided policy deciding what
+    /// happens when the final component of the path already exists. Intermediate directories
+    /// created by a recursive call are unaffected by the policy. The name the directory ended up
+    /// with is returned, which only differs from the one requested with
+    /// [`CollisionPolicy::Rename`].
     #[instrument(skip(self, rng))]
-    pub async fn mkdir(
+    pub async fn mkdir_with_policy(
         &mut self,
         rng: &mut impl CryptoRngCore,
         path: &[&str],
         recursive: bool,
-    ) -> Result<(), OperationError> {
+        policy: CollisionPolicy,
+    ) -> Result<NodeName, OperationError> {
+        let (_, directory_name) = self.create_directory(rng, path, recursive, policy).await?;
+
+        Ok(directory_name)
+    }
+
+    /// Shared implementation of the `mkdir` variants, producing the ID and name of the directory
+    /// at the end of the path.
+    async fn create_directory(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        path: &[&str],
+        recursive: bool,
+        policy: CollisionPolicy,
+    ) -> Result<(NodeId, NodeName), OperationError> {
         if path.is_empty() {
             return Err(OperationError::UnexpectedEmptyPath);
         }
 
-        for _ in 0..MAX_PATH_DEPTH {
-            match walk_path(&self.inner.clone(), self.cwd_id, path, 0).await? {
+        if path.len() > MAX_PATH_DEPTH {
+            return Err(OperationError::PathTooDeep);
+        }
+
+        let mut inner_write = self.inner.write().in_current_span().await;
+        let owner_id = self.current_key.actor_id();
+
+        let directory_pid = loop {
+            let (parent_permanent_id, directory_name, is_final) = match inner_write
+                .walk_path(self.cwd_id, path)?
+            {
                 WalkState::FoundNode { node_id } => {
                     debug!(node_id, "drive::mkdir::already_exists");
 
-                    let inner_read = self.inner.read().await;
-                    let node = inner_read.by_id(node_id)?;
-
-                    match node.kind() {
-                        NodeKind::Directory => return Ok(()),
-                        NodeKind::File => return Err(OperationError::Exists(node_id)),
-                        _ => unimplemented!(),
+                    let node = inner_write.by_id(node_id)?;
+
+                    match (policy, node.kind()) {
+                        (CollisionPolicy::Overwrite, NodeKind::Directory) => {
+                            break node.permanent_id()
+                        }
+                        (CollisionPolicy::Rename, _) => {
+                            let parent_permanent_id = node
+                                .parent_id()
+                                .ok_or(OperationError::OrphanNode(node.permanent_id()))?;
+                            let parent_node = inner_write.by_perm_id(&parent_permanent_id)?;
+
+                            (
+                                parent_permanent_id,
+                                available_name(parent_node, &node.name())?,
+                                true,
+                            )
+                        }
+                        _ => return Err(OperationError::Exists(node_id)),
                     }
                 }
                 WalkState::MissingComponent {
@@ -201,41 +579,47 @@ impl DirectoryHandle {
                 } => {
                     trace!(cwd_id = working_directory_id, name = ?missing_name, "drive::mkdir::node_missing");
 
-                    // When we're not recursing and there are more path components left, we have to
-                    // abort early
+                    // When we're not recursing and there are more path components left, we
+                    // have to abort early
                     if !recursive && !remaining_path.is_empty() {
                         trace!(?remaining_path, "drive::mkdir::not_recursive");
                         return Err(OperationError::PathNotFound);
                     }
 
-                    let inner_read = self.inner.read().await;
                     let parent_permanent_id =
-                        inner_read.by_id(working_directory_id)?.permanent_id();
-                    drop(inner_read);
-
-                    self.insert_node(
-                        &mut *rng,
-                        parent_permanent_id,
-                        |rng, new_node_id, parent_id, actor_id| async move {
-                            NodeBuilder::directory(missing_name)
-                                .with_parent(parent_id)
-                                .with_id(new_node_id)
-                                .with_owner(actor_id)
-                                .build(rng)
-                                .map_err(OperationError::CreationFailed)
-                        },
-                    )
-                    .await?;
+                        inner_write.by_id(working_directory_id)?.permanent_id();
 
-                    if remaining_path.is_empty() {
-                        trace!("drive::mkdir::complete");
-                        return Ok(());
-                    }
+                    (parent_permanent_id, missing_name, remaining_path.is_empty())
                 }
+            };
+
+            let permanent_id = inner_write
+                .create_node(
+                    &mut *rng,
+                    owner_id,
+                    parent_permanent_id,
+                    |rng, new_node_id, parent_id, actor_id| async move {
+                        NodeBuilder::directory(directory_name)
+                            .with_parent(parent_id)
+                            .with_id(new_node_id)
+                            .with_owner(actor_id)
+                            .build(rng)
+                            .map_err(OperationError::CreationFailed)
+                    },
+                )
+                .await?;
+
+            if is_final {
+                break permanent_id;
             }
-        }
+        };
 
-        Err(OperationError::PathTooDeep)
+        let directory = inner_write.by_perm_id(&directory_pid)?;
+        let created = (directory.id(), directory.name());
+
+        inner_write.clean_drive().await?;
+        trace!("drive::mkdir::complete");
+        Ok(created)
     }
 
     #[instrument(level = Level::DEBUG, skip(self, _rng))]
@@ -304,47 +688,15 @@ impl DirectoryHandle {
                 }
             };
 
+        // All of the checks that could cause the move to fail are performed before any changes
+        // are made, so a failed move leaves the drive untouched.
         let mut inner_write = self.inner.write().await;
-        let src_node_name = inner_write.by_id(src_node_id)?.name();
-        let src_node_cid = inner_write.by_id(src_node_id)?.cid().await?;
-        let src_node_size = inner_write.by_id(src_node_id)?.size();
         let src_node_perm_id = inner_write.by_id(src_node_id)?.permanent_id();
-        let src_parent_perm_id = inner_write.by_id(src_node_id)?.parent_id().ok_or(
-            OperationError::InternalCorruption(src_node_id, "src node has no parent"),
-        )?;
-        let src_parent_node = inner_write.by_perm_id_mut(&src_parent_perm_id).await?;
-
-        // Remove target node from its current location by removing it as a child from its parent
-        src_parent_node
-            .remove_child(&src_node_name)
-            .await
-            .map_err(|_| {
-                OperationError::InternalCorruption(
-                    src_parent_node.id(),
-                    "Could not remove target from parent",
-                )
-            })?;
-
-        // A Failure from here on would leave the child orphaned or inconsistent, I don't think there is a
-        // good way to make the move an atomic operation though...
-        // The borrow checker won't let us get mutable references to all three nodes at once (src, dst, src_parent)
-        // We could maybe extend `DriveInner` to do this operation internally to make it more atomic
-        let dst_parent_node = inner_write.by_id_mut(dst_parent_id).await?;
-        let dst_parent_node_perm_id = dst_parent_node.permanent_id();
-
-        dst_parent_node
-            .add_child(
-                new_dst_name.clone(),
-                src_node_perm_id,
-                src_node_cid,
-                src_node_size,
-            )
+        let dst_parent_perm_id = inner_write.by_id(dst_parent_id)?.permanent_id();
+        inner_write
+            .move_node(src_node_perm_id, dst_parent_perm_id, new_dst_name)
             .await?;
 
-        let src_node = inner_write.by_id_mut(src_node_id).await?;
-        src_node.set_parent_id(dst_parent_node_perm_id).await;
-        src_node.set_name(new_dst_name).await;
-
         inner_write.clean_drive().await?;
 
         Ok(())
@@ -369,16 +721,85 @@ impl DirectoryHandle {
         let target_node = inner_write.by_id(target_node_id)?;
         let target_perm_id = target_node.permanent_id();
 
-        if let Some(removed_data_cids) = inner_write.remove_node(target_perm_id).await? {
-            for cid in removed_data_cids.into_iter() {
-                store.remove(cid, true).await?;
-            }
+        let removed_data_cids = inner_write.remove_node(target_perm_id).await?;
+        inner_write.clean_drive().await?;
+        drop(inner_write);
+
+        // The drive is consistent before any blocks are removed, interrupting the removal at
+        // this point at worst leaves some unreferenced blocks in the store.
+        for cid in removed_data_cids.into_iter().flatten() {
+            store.remove(cid, true).await?;
         }
 
-        inner_write.clean_drive().await?;
         Ok(())
     }
 
+    /// Locates the file at the provided path, creating a new empty file node if one doesn't
+    /// already exist, and returns its [`PermanentId`]. The parent directory must already exist.
+    /// When a node is already present at the path the policy decides whether it is reused or a
+    /// new file is created next to it. The caller is expected to be holding the write lock on the
+    /// drive for the duration of the whole operation the node is needed for.
+    async fn file_permanent_id(
+        &self,
+        inner: &mut InnerDrive,
+        rng: &mut impl CryptoRngCore,
+        path: &[&str],
+        data_size: u64,
+        policy: CollisionPolicy,
+    ) -> Result<PermanentId, OperationError> {
+        let (parent_id, file_name) = match inner.walk_path(self.cwd_id, path)? {
+            WalkState::FoundNode { node_id } => {
+                let node = inner.by_id(node_id)?;
+
+                match (policy, node.kind()) {
+                    (CollisionPolicy::Overwrite, NodeKind::File) => return Ok(node.permanent_id()),
+                    (CollisionPolicy::Rename, _) => {
+                        let parent_pid = node
+                            .parent_id()
+                            .ok_or(OperationError::OrphanNode(node.permanent_id()))?;
+                        let parent_node = inner.by_perm_id(&parent_pid)?;
+
+                        (parent_node.id(), available_name(parent_node, &node.name())?)
+                    }
+                    _ => return Err(OperationError::Exists(node_id)),
+                }
+            }
+            WalkState::MissingComponent {
+                working_directory_id,
+                missing_name,
+                remaining_path,
+            } => {
+                if !remaining_path.is_empty() {
+                    return Err(OperationError::PathNotFound);
+                }
+
+                (working_directory_id, missing_name)
+            }
+        };
+
+        tracing::info!(?path, ?file_name, ?parent_id, "drive::write");
+
+        let parent_perm_id = inner.by_id(parent_id)?.permanent_id();
+        let owner_id = self.current_key.actor_id();
+
+        inner
+            .create_node(
+                rng,
+                owner_id,
+                parent_perm_id,
+                |rng, new_node_id, parent_id, actor_id| async move {
+                    NodeBuilder::file(file_name)
+                        .with_parent(parent_id)
+                        .with_id(new_node_id)
+                        .with_owner(actor_id)
+                        .with_size_hint(data_size)
+                        .build(rng)
+                        .map_err(OperationError::CreationFailed)
+                },
+            )
+            .await
+    }
+
     #[instrument(level = Level::DEBUG, skip(self))]
     pub async fn size(&self) -> Result<u64, OperationError> {
         //let inner_read = self.inner.read().await;
@@ -396,6 +817,111 @@ impl DirectoryHandle {
         store: &impl DataStore,
         path: &[&str],
     ) -> Result<Vec<u8>, OperationError> {
+        self.read_content(store, None, path).await
+    }
+
+    /// Behaves the same as [`DirectoryHandle::read`] but additionally supports reading files
+    /// whose content lives outside of BanyanFS using the provided [`ExternalFetcher`]. Content
+    /// retrieved by the fetcher is verified against the CID recorded when the file was written.
+    pub async fn read_with_fetcher(
+        &self,
+        store: &impl DataStore,
+        fetcher: &impl ExternalFetcher,
+        path: &[&str],
+    ) -> Result<Vec<u8>, OperationError> {
+        self.read_content(store, Some(fetcher), path).await
+    }
+
+    /// Streams the contents of the file at the provided path one decrypted chunk at a time
+    /// instead of buffering the entire file in memory. Each chunk's authentication tag is
+    /// verified as it is decrypted. If a block has been tampered with the stream produces a
+    /// [`OperationError::BlockAuthenticationFailed`] in place of the offending chunk and ends,
+    /// none of the tampered data is ever yielded.
+    ///
+    /// Files whose content lives outside of BanyanFS can't be streamed and need to be read using
+    /// [`DirectoryHandle::read_with_fetcher`].
+    pub async fn read_stream<'a, S: DataStore>(
+        &self,
+        store: &'a S,
+        path: &[&str],
+    ) -> Result<LocalBoxStream<'a, Result<Vec<u8>, OperationError>>, OperationError> {
+        self.readable_content(path).await?.into_stream(store)
+    }
+
+    /// Provides the contents of the file at the provided path as an [`AsyncRead`], for consumers
+    /// that work with readers rather than streams. Blocks are retrieved from the store and
+    /// decrypted lazily as the reader is consumed, at most one decrypted chunk is held at a time
+    /// in addition to the data block it came from. This behaves the same as
+    /// [`DirectoryHandle::read_stream`] otherwise, failures retrieving or decrypting blocks
+    /// (including [`crate::stores::DataStoreError`]s) are produced by the reader as
+    /// [`std::io::Error`]s wrapping the [`OperationError`].
+    pub async fn reader<'a, S: DataStore>(
+        &self,
+        store: &'a S,
+        path: &[&str],
+    ) -> Result<Box<dyn AsyncRead + Unpin + 'a>, OperationError> {
+        let chunks = self.read_stream(store, path).await?;
+        Ok(Box::new(
+            chunks.map_err(std::io::Error::from).into_async_read(),
+        ))
+    }
+
+    /// Reads part of the contents of the file at the provided path, starting `offset` bytes into
+    /// the file and continuing for `len` bytes (or until the end of the file when `None`). Ranges
+    /// extending past the end of the file are cut short, an offset past the end of the file is
+    /// rejected with [`OperationError::OffsetOutOfRange`].
+    ///
+    /// Only the data blocks holding part of the range are retrieved and decrypted. Files made up
+    /// of raw blocks not produced by BanyanFS are the exception, the sizes of their blocks aren't
+    /// recorded so every block preceding the range needs to be retrieved as well. Files whose
+    /// content lives outside of BanyanFS need to be read using
+    /// [`DirectoryHandle::read_with_fetcher`].
+    pub async fn read_range(
+        &self,
+        store: &impl DataStore,
+        path: &[&str],
+        offset: u64,
+        len: Option<u64>,
+    ) -> Result<Vec<u8>, OperationError> {
+        let (readable, size) = self.sized_readable_content(path).await?;
+        if offset > size {
+            return Err(OperationError::OffsetOutOfRange { offset, size });
+        }
+
+        let end = match len {
+            Some(len) => size.min(offset.saturating_add(len)),
+            None => size,
+        };
+
+        readable.read_range(store, offset, end).await
+    }
+
+    async fn read_content(
+        &self,
+        store: &impl DataStore,
+        fetcher: Option<&dyn ExternalFetcher>,
+        path: &[&str],
+    ) -> Result<Vec<u8>, OperationError> {
+        self.readable_content(path)
+            .await?
+            .read(store, fetcher)
+            .await
+    }
+
+    /// Performs the access checks needed to read the file at the provided path and collects
+    /// everything needed to retrieve its content so the drive doesn't need to remain locked while
+    /// the content is read.
+    async fn readable_content(&self, path: &[&str]) -> Result<ReadableContent, OperationError> {
+        let (readable, _size) = self.sized_readable_content(path).await?;
+        Ok(readable)
+    }
+
+    /// Behaves the same as [`DirectoryHandle::readable_content`] while also producing the size of
+    /// the file's content.
+    async fn sized_readable_content(
+        &self,
+        path: &[&str],
+    ) -> Result<(ReadableContent, u64), OperationError> {
         if path.is_empty() {
             return Err(OperationError::UnexpectedEmptyPath);
         }
@@ -413,91 +939,89 @@ impl DirectoryHandle {
         };
 
         let inner_read = self.inner.read().await;
+        let size = inner_read.by_id(target_node_id)?.data().content_size();
+        let readable = ReadableContent::from_node(&inner_read, target_node_id)?;
 
-        let read_node = inner_read.by_id(target_node_id)?;
-        let node_content = match read_node.data() {
-            NodeData::File { content, .. } => content,
-            NodeData::AssociatedData { content, .. } => content,
-            _ => return Err(OperationError::NotReadable),
-        };
+        Ok((readable, size))
+    }
 
-        if node_content.is_stub() {
-            return Err(OperationError::NotAvailable);
-        }
-
-        if node_content.is_encrypted() {
-            let locked_key = node_content
-                .data_key()
-                .map_err(|_| OperationError::AccessDenied)?;
-
-            let data_key = match inner_read.access().data_key() {
-                Some(data_key) => data_key,
-                None => return Err(OperationError::AccessDenied),
-            };
-
-            let unlocked_key = locked_key
-                .unlock(data_key)
-                .map_err(|_| OperationError::AccessDenied)?;
-
-            let mut file_data = Vec::new();
-
-            for content_ref in node_content.content_references()? {
-                if !store.contains_cid(content_ref.data_block_cid()).await? {
-                    return Err(OperationError::BlockUnavailable(
-                        content_ref.data_block_cid(),
-                    ));
-                }
-
-                let data_chunk = store.retrieve(content_ref.data_block_cid()).await?;
-
-                let (_remaining, block) = DataBlock::parse_with_magic(Stream::new(&data_chunk))
-                    .map_err(|err| {
-                        tracing::error!("parsing of data block failed: {err:?}");
-                        OperationError::BlockCorrupted(content_ref.data_block_cid())
-                    })?;
-                // todo(sstelfox): still stuff remaining which means this decoder is sloppy
-                //tracing::info!(?remaining, "drive::read::remaining");
-                //debug_assert!(remaining.is_empty(), "no extra data should be present");
+    #[instrument(level = Level::DEBUG, skip(self, rng, store))]
+    pub async fn write(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        data: &[u8],
+    ) -> Result<(), OperationError> {
+        self.write_checked(rng, store, path, data, None, CollisionPolicy::Overwrite)
+            .await?;
 
-                for location in content_ref.chunks() {
-                    if !matches!(location.block_kind(), BlockKind::Data) {
-                        unimplemented!("indirect reference loading");
-                    }
+        Ok(())
+    }
 
-                    let encrypted_chunk = block
-                        .get_chunk(location.block_index() as usize)
-                        .map_err(|err| {
-                            tracing::error!("failed to retrieve block chunk: {err:?}");
-                            OperationError::BlockCorrupted(content_ref.data_block_cid())
-                        })?;
-                    let chunk = encrypted_chunk
-                        .decrypt(&block.data_options(), &unlocked_key)
-                        .map_err(|_| {
-                            OperationError::BlockCorrupted(content_ref.data_block_cid())
-                        })?;
-
-                    // &unlocked_key,
-                    // &verifying_key,
-
-                    file_data.extend_from_slice(chunk.data());
-                }
-            }
+    /// Behaves the same as [`DirectoryHandle::write`] but only replaces the content of the file
+    /// when its current CID still matches `expected_cid`, such as one previously retrieved using
+    /// [`DirectoryHandle::ls_detailed`]. The check is repeated while holding the write lock the
+    /// new content is applied under, if the file changed in the meantime the write is rejected
+    /// with [`OperationError::Conflict`] carrying the file's current CID.
+    ///
+    /// The file must already exist. Blocks written out for content that ends up being rejected
+    /// are not removed from the store.
+    #[instrument(level = Level::DEBUG, skip(self, rng, store))]
+    pub async fn write_if_unchanged(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        data: &[u8],
+        expected_cid: Cid,
+    ) -> Result<(), OperationError> {
+        self.write_checked(
+            rng,
+            store,
+            path,
+            data,
+            Some(&expected_cid),
+            CollisionPolicy::Overwrite,
+        )
+        .await?;
 
-            Ok(file_data)
-        } else if node_content.is_empty() {
-            Ok(Vec::new())
-        } else {
-            unimplemented!()
-        }
+        Ok(())
     }
 
+    /// Behaves the same as [`DirectoryHandle::write`] with the provided policy deciding what
+    /// happens when a node already exists at the path, [`DirectoryHandle::write`] itself
+    /// overwrites existing files. The name the file ended up with is returned, which only
+    /// differs from the one requested with [`CollisionPolicy::Rename`].
     #[instrument(level = Level::DEBUG, skip(self, rng, store))]
-    pub async fn write(
+    pub async fn write_with_policy(
         &mut self,
         rng: &mut impl CryptoRngCore,
         store: &mut impl DataStore,
         path: &[&str],
         data: &[u8],
+        policy: CollisionPolicy,
+    ) -> Result<NodeName, OperationError> {
+        self.write_checked(rng, store, path, data, None, policy)
+            .await
+    }
+
+    /// Behaves the same as [`DirectoryHandle::write`] but reads the content of the file from
+    /// `reader` as it is stored, rather than needing all of it in memory at once. Only a single
+    /// block's worth of content is buffered at a time, as the total size isn't known up front the
+    /// blocks are sized using [`ChunkSizePolicy::streaming_data_options`]. How the mime type of
+    /// the file is decided is controlled by the provided [`WriteStreamOptions`].
+    ///
+    /// The drive is only changed once the reader has been fully consumed. If reading fails part
+    /// way through the blocks already written out are left in the store.
+    #[instrument(level = Level::DEBUG, skip(self, rng, store, reader))]
+    pub async fn write_stream(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        mut reader: impl AsyncRead + Unpin,
+        options: WriteStreamOptions,
     ) -> Result<(), OperationError> {
         if path.is_empty() {
             return Err(OperationError::UnexpectedEmptyPath);
@@ -514,642 +1038,2985 @@ impl DirectoryHandle {
             None => return Err(OperationError::AccessDenied),
         };
 
+        if let WalkState::MissingComponent { remaining_path, .. } =
+            inner_read.walk_path(self.cwd_id, path)?
+        {
+            if !remaining_path.is_empty() {
+                return Err(OperationError::PathNotFound);
+            }
+        }
+
         drop(inner_read);
 
-        let existing_file = match walk_path(&self.inner, self.cwd_id, path, 0).await {
-            Ok(WalkState::FoundNode { node_id }) => Some(node_id),
-            _ => None,
-        };
+        #[cfg(not(feature = "mime-type"))]
+        let _ = options;
 
-        let data_size = data.len() as u64;
-        let new_permanent_id = match existing_file {
-            Some(existing_file) => {
-                let inner_read = self.inner.read().await;
-                let node = inner_read.by_id(existing_file)?;
-                node.permanent_id()
-            }
-            None => {
-                let (parent_path, name) = path.split_at(path.len() - 1);
-                let file_name = NodeName::try_from(name[0]).map_err(OperationError::InvalidName)?;
+        let node_data_key = AccessKey::generate(rng);
+        let mut hasher = blake3::Hasher::new();
+        let mut content_references = Vec::new();
+        let mut data_size = 0u64;
 
-                tracing::info!(?path, ?file_name, "drive::write");
+        // Only the leading bytes are kept around for sniffing, see `MimeGuesser`
+        #[cfg(feature = "mime-type")]
+        let mut leading_data = Vec::new();
+
+        loop {
+            let data_options = self.chunk_size_policy.streaming_data_options(data_size);
+            let mut block_data = vec![0u8; data_options.block_data_size()];
+
+            let mut filled = 0;
+            while filled < block_data.len() {
+                match reader
+                    .read(&mut block_data[filled..])
+                    .await
+                    .map_err(OperationError::ContentReadFailed)?
+                {
+                    0 => break,
+                    read => filled += read,
+                }
+            }
 
-                let parent_id = match walk_path(&self.inner, self.cwd_id, parent_path, 0).await? {
-                    WalkState::FoundNode { node_id } => node_id,
-                    WalkState::MissingComponent { .. } => return Err(OperationError::PathNotFound),
-                };
+            if filled == 0 {
+                break;
+            }
+            block_data.truncate(filled);
 
-                tracing::info!(?parent_id, ?parent_path, "drive::write::parent_id");
+            #[cfg(feature = "mime-type")]
+            if options.sniff_content() {
+                let wanted = MIME_SNIFF_LENGTH.saturating_sub(leading_data.len());
+                leading_data.extend_from_slice(&block_data[..filled.min(wanted)]);
+            }
 
-                let inner_read = self.inner.read().await;
-                let parent_node = inner_read.by_id(parent_id)?;
-                let parent_perm_id = parent_node.permanent_id();
-                drop(inner_read);
+            hasher.update(&block_data);
+            content_references.extend(
+                encrypt_blocks_with_options(rng, store, &node_data_key, data_options, &block_data)
+                    .await?,
+            );
+            data_size += filled as u64;
 
-                let node_name = file_name.clone();
-                self.insert_node(
-                    rng,
-                    parent_perm_id,
-                    |rng, new_node_id, parent_id, actor_id| async move {
-                        NodeBuilder::file(node_name)
-                            .with_parent(parent_id)
-                            .with_id(new_node_id)
-                            .with_owner(actor_id)
-                            .with_size_hint(data_size)
-                            .build(rng)
-                            .map_err(OperationError::CreationFailed)
-                    },
-                )
-                .await?
+            if content_references.len() > u8::MAX as usize {
+                return Err(FileContentError::TooManyReferences(content_references.len()).into());
             }
-        };
-
-        if data.is_empty() {
-            let mut inner_write = self.inner.write().await;
-            let node = inner_write.by_perm_id_mut(&new_permanent_id).await?;
-            let node_data = node.data_mut().await;
-            *node_data = NodeData::empty_file();
-            return Ok(());
         }
 
-        let small_block_threshold: usize = DataBlock::small_encrypted_data_size() * 8;
-        let block_creator = if data_size > small_block_threshold as u64 {
-            || {
-                DataBlock::small().map_err(|err| {
-                    tracing::error!("failed to create data block: {:?}", err);
-                    OperationError::Other("data block failed")
-                })
-            }
+        let file_data = if data_size == 0 {
+            NodeData::empty_file()
         } else {
-            || {
-                DataBlock::standard().map_err(|err| {
-                    tracing::error!("failed to create data block: {:?}", err);
-                    OperationError::Other("data block failed")
-                })
-            }
+            let locked_key = node_data_key
+                .lock_with(rng, &data_key)
+                .map_err(|_| OperationError::Other("failed to seal node data key"))?;
+            let content_cid = Cid::from(<[u8; 32]>::from(hasher.finalize()));
+
+            NodeData::full_file(FileContent::encrypted(
+                locked_key,
+                content_cid,
+                data_size,
+                content_references,
+            ))
         };
 
-        // todo(sstelfox): bit lazy here, should calculate this as I stream it but speed right
-        // now...
-        let plaintext_cid = crate::utils::calculate_cid(data);
-
-        let mut remaining_data = data;
-        let mut active_block = block_creator()?;
-        let active_block_chunk_size = active_block.data_options().chunk_data_size();
-        let node_data_key = AccessKey::generate(rng);
-        let mut content_references = Vec::new();
-        let mut content_indexes = Vec::new();
-
-        while !remaining_data.is_empty() {
-            let data_to_read = std::cmp::min(remaining_data.len(), active_block_chunk_size);
-            let (chunk_data, next_data) = remaining_data.split_at(data_to_read);
-            remaining_data = next_data;
-
-            let chunk = DataChunk::from_slice(chunk_data, &active_block.data_options())
-                .map_err(|err| {
-                    tracing::error!("failed to push chunk: {:?}", err);
-                    OperationError::Other("expected remaining capacity")
-                })?
-                .encrypt(rng, &active_block.data_options(), &node_data_key)
-                .await
-                .map_err(|err| {
-                    tracing::error!("Failed to encrypt chunk: {:?}", err);
-                    OperationError::Other("Error encrypting chunk")
-                })?;
-
-            content_indexes.push(active_block.push_chunk(chunk).map_err(|err| {
-                tracing::error!("failed to push chunk: {:?}", err);
-                OperationError::Other("expected remaining capacity")
-            })?);
-
-            if active_block.is_full() {
-                let mut sealed_block = Vec::new();
+        let mut inner_write = self.inner.write().await;
+        let permanent_id = self
+            .file_permanent_id(
+                &mut inner_write,
+                rng,
+                path,
+                data_size,
+                CollisionPolicy::Overwrite,
+            )
+            .await?;
+        let node = inner_write.by_perm_id_mut(&permanent_id).await?;
 
-                let (_, cids) =
-                    active_block
-                        .encode(rng, &mut sealed_block)
-                        .await
-                        .map_err(|err| {
-                            tracing::error!("failed to encode block: {:?}", err);
-                            OperationError::Other("failed to encode block")
-                        })?;
+        #[cfg(feature = "mime-type")]
+        if data_size != 0 {
+            set_mime_type(&leading_data, node).await;
+        }
 
-                let cid = active_block
-                    .cid()
-                    .map_err(|_| OperationError::Other("unable to access block cid"))?;
+        *node.data_mut().await = file_data;
 
-                store.store(cid.clone(), sealed_block, false).await?;
+        inner_write.clean_drive().await?;
+        Ok(())
+    }
 
-                let locations = content_indexes
-                    .iter()
-                    .map(|i| ContentLocation::data(cids[*i].clone(), *i as u64))
-                    .collect::<Vec<_>>();
-                content_indexes.clear();
+    /// Appends data to the end of the file at the provided path. When the file doesn't exist yet
+    /// it is created, behaving the same as [`DirectoryHandle::write`]. The appended data is
+    /// encrypted under the file's existing key into new blocks, the blocks already holding the
+    /// file's content are left untouched and remain referenced as they are. The exception is a
+    /// partially filled final chunk, which is written out again in front of the appended data.
+    ///
+    /// The CID of a file covers all of its content, so every append retrieves, decrypts, and
+    /// hashes all of the existing content to calculate the new one. Building a file up through
+    /// many appends takes time quadratic in its final size. If the file changes while the data is
+    /// being appended the append is rejected with [`OperationError::Conflict`].
+    ///
+    /// The content of a file is limited to 255 blocks and every append stores at least one new
+    /// block, taking up one of those slots unless the previous final block held nothing but a
+    /// partially filled chunk. Once the limit is reached appends are rejected with
+    /// [`FileContentError::TooManyReferences`]. Content that wasn't encrypted by BanyanFS (such
+    /// as content written with [`DirectoryHandle::write_external`]) can't be appended to.
+    #[instrument(level = Level::DEBUG, skip(self, rng, store, data))]
+    pub async fn write_append(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        data: &[u8],
+    ) -> Result<(), OperationError> {
+        if path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
 
-                let content_ref =
-                    ContentReference::new(cid, active_block.data_options(), locations);
-                content_references.push(content_ref);
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_write_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
 
-                active_block = block_creator()?;
+        let node_id = match inner_read.walk_path(self.cwd_id, path)? {
+            WalkState::FoundNode { node_id } => node_id,
+            WalkState::MissingComponent { .. } => {
+                drop(inner_read);
+                return self.write(rng, store, path, data).await;
             }
-        }
+        };
 
-        if !active_block.is_empty() {
-            tracing::info!("writing trailing block");
+        let node = inner_read.by_id(node_id)?;
+        if node.kind() != NodeKind::File {
+            return Err(OperationError::Exists(node_id));
+        }
 
-            // todo(sstelfox): this is duplicated, need to extract it
-            let mut sealed_block = Vec::new();
+        if data.is_empty() {
+            return Ok(());
+        }
 
-            let (_, cids) = active_block
-                .encode(rng, &mut sealed_block)
-                .await
-                .map_err(|err| {
-                    tracing::error!("failed to encode block: {:?}", err);
-                    OperationError::Other("failed to encode block")
-                })?;
+        let existing_cid = node.cid().await?;
+        let existing_size = node.data().content_size();
+        let existing_content = ReadableContent::from_node(&inner_read, node_id)?;
+        drop(inner_read);
 
-            let cid = active_block
-                .cid()
-                .map_err(|_| OperationError::Other("unable to access block cid"))?;
+        let (file_key, mut content_references) = match existing_content {
+            ReadableContent::Empty => {
+                self.write_checked(
+                    rng,
+                    store,
+                    path,
+                    data,
+                    Some(&existing_cid),
+                    CollisionPolicy::Overwrite,
+                )
+                .await?;
 
-            store.store(cid.clone(), sealed_block, false).await?;
+                return Ok(());
+            }
+            ReadableContent::Encrypted {
+                data_key,
+                content_references,
+            } => (data_key, content_references),
+            _ => return Err(OperationError::NotAppendable),
+        };
 
-            let locations = content_indexes
-                .iter()
-                .map(|i| ContentLocation::data(cids[*i].clone(), *i as u64))
-                .collect::<Vec<_>>();
-            content_indexes.clear();
+        let mut hasher = blake3::Hasher::new();
+        let mut last_chunk = Vec::new();
+        let mut chunks =
+            ChunkReader::new(&*store, file_key.clone(), content_references.clone()).into_stream();
+        while let Some(chunk) = chunks.next().await {
+            last_chunk = chunk?;
+            hasher.update(&last_chunk);
+        }
+        drop(chunks);
+        hasher.update(data);
+        let content_cid = Cid::from(<[u8; 32]>::from(hasher.finalize()));
+
+        // Ranges of the content are located assuming every chunk other than the last one is full,
+        // a partially filled final chunk is written out again ahead of the appended data rather
+        // than being left in the middle of the content.
+        let mut appended_data = Cow::Borrowed(data);
+        if let Some(last_reference) = content_references.pop() {
+            let chunk_capacity = last_reference.data_options().chunk_data_size();
+
+            match last_reference.chunks().split_last() {
+                Some((_, kept_chunks)) if last_chunk.len() < chunk_capacity => {
+                    if !kept_chunks.is_empty() {
+                        content_references.push(ContentReference::new(
+                            last_reference.data_block_cid(),
+                            *last_reference.data_options(),
+                            kept_chunks.to_vec(),
+                        ));
+                    }
 
-            let content_ref = ContentReference::new(cid, active_block.data_options(), locations);
-            content_references.push(content_ref);
+                    last_chunk.extend_from_slice(data);
+                    appended_data = Cow::Owned(last_chunk);
+                }
+                _ => content_references.push(last_reference),
+            }
         }
 
-        let locked_key = node_data_key
-            .lock_with(rng, &data_key)
-            .map_err(|_| OperationError::Other("failed to seal node data key"))?;
+        let appended_references = encrypt_blocks(
+            rng,
+            store,
+            &file_key,
+            self.chunk_size_policy,
+            &appended_data,
+        )
+        .await?;
+        content_references.extend(appended_references);
+        if content_references.len() > u8::MAX as usize {
+            return Err(FileContentError::TooManyReferences(content_references.len()).into());
+        }
 
         let mut inner_write = self.inner.write().await;
-        let node = inner_write.by_perm_id_mut(&new_permanent_id).await?;
-
-        #[cfg(feature = "mime-type")]
-        set_mime_type(data, node).await;
+        self.verify_unchanged(&inner_write, path, &existing_cid)
+            .await?;
 
-        let node_data = node.data_mut().await;
-        let file_content =
-            FileContent::encrypted(locked_key, plaintext_cid, data_size, content_references);
-        *node_data = NodeData::full_file(file_content);
+        let node = inner_write.by_id_mut(node_id).await?;
+        if let NodeData::File { content, .. } = node.data_mut().await {
+            let locked_key = content.data_key()?.clone();
+            *content = FileContent::encrypted(
+                locked_key,
+                content_cid,
+                existing_size + data.len() as u64,
+                content_references,
+            );
+        }
 
         inner_write.clean_drive().await?;
         Ok(())
     }
-}
-
-// todo: should these operations be using the permanent ids? Is that worth the extra
-// level of indirection? As long as we remain consistent it should be fine.
-#[instrument(level = Level::TRACE, skip(inner, path))]
-fn walk_path<'a>(
-    inner: &'a Arc<RwLock<InnerDrive>>,
-    working_directory_id: NodeId,
-    path: &'a [&'a str],
-    depth: usize,
-) -> BoxFuture<'a, Result<WalkState<'a>, OperationError>> {
-    trace!("directory::walk_directory");
 
-    async move {
-        let inner_read = inner.read().await;
+    /// Copies the contents of a directory on the local disk into the current working directory,
+    /// recording the progress in a manifest at `manifest_path` as each file completes (see
+    /// [`crate::filesystem::ImportManifest`]). Files are streamed into the drive one at a time
+    /// using [`DirectoryHandle::write_stream`], with their mime types sniffed from their content.
+    /// Existing files at the same paths are overwritten and symbolic links are skipped.
+    ///
+    /// The manifest should be kept outside of both the source directory and the drive. An
+    /// existing manifest is never replaced, an interrupted import should instead be continued
+    /// using [`DirectoryHandle::resume_import`].
+    #[cfg(feature = "local-store")]
+    #[instrument(level = Level::DEBUG, skip(self, rng, store))]
+    pub async fn import_directory(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        source: &Path,
+        manifest_path: &Path,
+    ) -> Result<ImportSummary, ImportError> {
+        let import_log = ImportLog::create(manifest_path)?;
+        self.import_with_log(rng, store, source, import_log).await
+    }
 
-        let (raw_child_name, remaining_path) = match path.split_first() {
-            Some(pair) => pair,
-            // We've reached the end of the path, our current node is the target
-            None => return Ok(WalkState::found(working_directory_id)),
-        };
+    /// Continues an import started by [`DirectoryHandle::import_directory`] using the manifest it
+    /// left behind. Files the manifest records as completed are skipped as long as the file they
+    /// produced is still present in the drive with the CID recorded for it. A drive that wasn't
+    /// persisted after those files were written won't contain them, and files changed since may
+    /// no longer match, in either case they're imported again.
+    #[cfg(feature = "local-store")]
+    #[instrument(level = Level::DEBUG, skip(self, rng, store))]
+    pub async fn resume_import(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        source: &Path,
+        manifest_path: &Path,
+    ) -> Result<ImportSummary, ImportError> {
+        let import_log = ImportLog::resume(manifest_path)?;
+        self.import_with_log(rng, store, source, import_log).await
+    }
 
-        let child_name = NodeName::try_from(*raw_child_name)?;
-        let current_node = inner_read.by_id(working_directory_id)?;
+    #[cfg(feature = "local-store")]
+    async fn import_with_log(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        source: &Path,
+        mut import_log: ImportLog,
+    ) -> Result<ImportSummary, ImportError> {
+        let mut summary = ImportSummary::default();
 
-        let child_map = match current_node.data() {
-            NodeData::Directory { children, .. } => children,
-            NodeData::File { associated_data, .. } => associated_data,
-            _ => return Err(OperationError::NotTraversable),
-        };
+        for import_source in ImportSource::collect(source)? {
+            let path = import_source.drive_path();
 
-        let perm_id = match child_map.get(&child_name) {
-            Some(entry) => entry.permanent_id(),
-            None => {
-                return Ok(WalkState::MissingComponent {
-                    working_directory_id,
-                    missing_name: child_name,
-                    remaining_path,
-                });
+            if import_source.is_directory {
+                self.mkdir(rng, &path, true).await?;
+                continue;
             }
-        };
 
-        let next_node = inner_read.by_perm_id(perm_id)?;
-        let next_node_id = next_node.id();
-        trace!(node_id = ?next_node_id, next_node_kind = ?next_node.kind(), "drive::walk_directory::next_node");
+            let relative_path = import_source.relative_path.as_str();
+            let manifest = import_log.manifest();
+            if let (Some(permanent_id), Some(recorded_cid)) = (
+                manifest.permanent_id(relative_path),
+                manifest.cid(relative_path),
+            ) {
+                let inner_read = self.inner.read().await;
+                if let Ok(node) = inner_read.by_perm_id(&permanent_id) {
+                    if &node.cid().await? == recorded_cid {
+                        summary.record_skipped();
+                        continue;
+                    }
+                }
+            }
 
-        if !next_node.supports_children() {
-            return Err(OperationError::NotTraversable);
-        }
+            let source_file = async_std::fs::File::open(&import_source.source_path).await?;
+            let options = WriteStreamOptions::default().with_content_sniffing();
+            self.write_stream(rng, store, &path, source_file, options)
+                .await?;
 
-        drop(inner_read);
+            let inner_read = self.inner.read().await;
+            let node = match inner_read.walk_path(self.cwd_id, &path)? {
+                WalkState::FoundNode { node_id } => inner_read.by_id(node_id)?,
+                WalkState::MissingComponent { .. } => {
+                    return Err(OperationError::PathNotFound.into())
+                }
+            };
+            let (permanent_id, cid) = (node.permanent_id(), node.cid().await?);
+            drop(inner_read);
 
-        if depth >= MAX_PATH_DEPTH {
-            return Err(OperationError::PathTooDeep);
+            import_log.record(relative_path, permanent_id, cid)?;
+            summary.record_imported();
         }
 
-        walk_path(inner, next_node_id, remaining_path, depth + 1).await
+        Ok(summary)
     }
-    .boxed()
-}
 
-#[cfg(feature = "mime-type")]
-async fn set_mime_type(data: &[u8], node: &mut Node) {
-    if let Some(mime_type) = MimeGuesser::default()
-        .with_name(node.name().clone())
-        .with_data(data)
-        .guess_mime_type()
-    {
-        node.set_attribute(MetadataKey::MimeType, mime_type.to_string().into())
-            .await;
-    }
-}
+    async fn write_checked(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        data: &[u8],
+        expected_cid: Option<&Cid>,
+        policy: CollisionPolicy,
+    ) -> Result<NodeName, OperationError> {
+        if path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
 
-#[cfg(test)]
-mod test {
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_write_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
+
+        let data_key = match inner_read.access().data_key() {
+            Some(data_key) => data_key.clone(),
+            None => return Err(OperationError::AccessDenied),
+        };
+
+        // Catch missing parent directories and rejected collisions before any data is written
+        // out to the store
+        match inner_read.walk_path(self.cwd_id, path)? {
+            WalkState::MissingComponent { remaining_path, .. } if !remaining_path.is_empty() => {
+                return Err(OperationError::PathNotFound);
+            }
+            WalkState::FoundNode { node_id } if policy == CollisionPolicy::Error => {
+                return Err(OperationError::Exists(node_id));
+            }
+            _ => {}
+        }
+
+        if let Some(expected_cid) = expected_cid {
+            self.verify_unchanged(&inner_read, path, expected_cid)
+                .await?;
+        }
+
+        drop(inner_read);
+
+        // The content is stored before the drive is locked for the change, if this gets
+        // interrupted the drive is left untouched.
+        let file_data = if data.is_empty() {
+            NodeData::empty_file()
+        } else {
+            NodeData::full_file(
+                encrypt_content(rng, store, &data_key, self.chunk_size_policy, data).await?,
+            )
+        };
+
+        let data_size = data.len() as u64;
+        let mut inner_write = self.inner.write().await;
+        if let Some(expected_cid) = expected_cid {
+            self.verify_unchanged(&inner_write, path, expected_cid)
+                .await?;
+        }
+
+        let permanent_id = self
+            .file_permanent_id(&mut inner_write, rng, path, data_size, policy)
+            .await?;
+        let node = inner_write.by_perm_id_mut(&permanent_id).await?;
+
+        #[cfg(feature = "mime-type")]
+        if !data.is_empty() {
+            set_mime_type(data, node).await;
+        }
+
+        *node.data_mut().await = file_data;
+        let file_name = node.name();
+
+        inner_write.clean_drive().await?;
+        Ok(file_name)
+    }
+
+    /// Records a file whose content lives entirely outside of BanyanFS at the provided URL. No
+    /// data is copied into the filesystem, instead the provided CID (calculated over the
+    /// plaintext content using [`crate::utils::calculate_cid`]) and size are recorded and used to
+    /// verify the content when it is later read with [`DirectoryHandle::read_with_fetcher`].
+    #[instrument(level = Level::DEBUG, skip(self, rng))]
+    pub async fn write_external(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        path: &[&str],
+        url: &str,
+        cid: Cid,
+        data_size: u64,
+    ) -> Result<(), OperationError> {
+        if path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
+
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_write_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
+        drop(inner_read);
+
+        let file_content = FileContent::external(url.to_string(), cid, data_size)?;
+
+        let mut inner_write = self.inner.write().await;
+        let permanent_id = self
+            .file_permanent_id(
+                &mut inner_write,
+                rng,
+                path,
+                data_size,
+                CollisionPolicy::Overwrite,
+            )
+            .await?;
+        let node = inner_write.by_perm_id_mut(&permanent_id).await?;
+        let node_data = node.data_mut().await;
+        *node_data = NodeData::full_file(file_content);
+
+        inner_write.clean_drive().await?;
+        Ok(())
+    }
+
+    /// Records a file whose content is made up of blocks already present in a data store, such as
+    /// when migrating data from another system. No data is read or stored, the file records each
+    /// of the provided block CIDs in order and its content is the concatenation of the raw blocks.
+    /// These blocks aren't encrypted by BanyanFS, anyone able to retrieve them is able to read
+    /// the file's content.
+    ///
+    /// The sizes of the blocks need to add up to `total_size` and at most 255 blocks can be
+    /// referenced. When a store is provided, each block is confirmed to be present in it before
+    /// the file is written. As the content itself isn't read, the content CID recorded for the
+    /// file is calculated over the ordered block CIDs. Reading the file checks each block's
+    /// content against its CID and that the blocks add up to `total_size`.
+    #[instrument(level = Level::DEBUG, skip(self, rng, store, refs))]
+    pub async fn write_from_refs(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: Option<&dyn DataStore>,
+        path: &[&str],
+        refs: Vec<(Cid, u64)>,
+        total_size: u64,
+    ) -> Result<(), OperationError> {
+        if path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
+
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_write_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
+        drop(inner_read);
+
+        let actual_size = refs
+            .iter()
+            .try_fold(0u64, |total, (_, size)| total.checked_add(*size))
+            .unwrap_or(u64::MAX);
+        if actual_size != total_size {
+            return Err(OperationError::ContentSizeMismatch {
+                expected: total_size,
+                actual: actual_size,
+            });
+        }
+
+        let block_cids: Vec<Cid> = refs.into_iter().map(|(cid, _)| cid).collect();
+
+        if let Some(store) = store {
+            let presence = store.contains_cids(&block_cids).await?;
+            let missing = block_cids
+                .iter()
+                .find(|cid| !presence.get(*cid).copied().unwrap_or(false));
+
+            if let Some(missing) = missing {
+                return Err(OperationError::BlockUnavailable(missing.clone()));
+            }
+        }
+
+        let file_content = if total_size == 0 {
+            FileContent::EmptyFile
+        } else {
+            let cid_bytes: Vec<u8> = block_cids
+                .iter()
+                .flat_map(|cid| cid.as_bytes().to_vec())
+                .collect();
+            let content_cid = crate::utils::calculate_cid(&cid_bytes);
+            let content = block_cids
+                .into_iter()
+                .map(ContentReference::raw_block)
+                .collect();
+
+            FileContent::public(content_cid, total_size, content)?
+        };
+
+        let mut inner_write = self.inner.write().await;
+        let permanent_id = self
+            .file_permanent_id(
+                &mut inner_write,
+                rng,
+                path,
+                total_size,
+                CollisionPolicy::Overwrite,
+            )
+            .await?;
+        let node = inner_write.by_perm_id_mut(&permanent_id).await?;
+        let node_data = node.data_mut().await;
+        *node_data = NodeData::full_file(file_content);
+
+        inner_write.clean_drive().await?;
+        Ok(())
+    }
+
+    /// Attaches a preview (such as a thumbnail of an image or video) to the file at the provided
+    /// path. The preview is encrypted the same way regular file content is and stored as
+    /// associated data of the file under a reserved name, alongside the provided content type.
+    /// Any thumbnail previously attached to the file is replaced.
+    #[instrument(level = Level::DEBUG, skip(self, rng, store, data))]
+    pub async fn set_thumbnail(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        data: &[u8],
+        content_type: &str,
+    ) -> Result<(), OperationError> {
+        if content_type.len() > u8::MAX as usize {
+            return Err(OperationError::InvalidMetadata("value too long"));
+        }
+
+        self.store_associated(rng, store, path, thumbnail_name(), data, Some(content_type))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Attaches sidecar data (such as extracted text or an alternate rendition) to the file at the
+    /// provided path, stored as an associated data child of the file under the provided name.
+    /// The data is encrypted the same way regular file content is. Data previously associated
+    /// with the file under the same name is replaced. Produces the permanent ID of the associated
+    /// data node.
+    ///
+    /// The name `thumbnail` is used by [`DirectoryHandle::set_thumbnail`], writing under it here
+    /// replaces the file's thumbnail and clears the content type recorded for it.
+    #[instrument(level = Level::DEBUG, skip(self, rng, store, data))]
+    pub async fn write_associated(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        name: &str,
+        data: &[u8],
+    ) -> Result<PermanentId, OperationError> {
+        let name = NodeName::try_from(name)?;
+        self.store_associated(rng, store, path, name, data, None)
+            .await
+    }
+
+    /// Retrieves the data previously attached to the file at the provided path under the provided
+    /// name with [`DirectoryHandle::write_associated`]. Files without associated data by that name
+    /// produce `None`.
+    pub async fn read_associated(
+        &self,
+        store: &impl DataStore,
+        path: &[&str],
+        name: &str,
+    ) -> Result<Option<Vec<u8>>, OperationError> {
+        let name = NodeName::try_from(name)?;
+
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_read_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
+
+        let associated_perm_id = match self.associated_ids(&inner_read, path, &name)? {
+            (_, Some(perm_id)) => perm_id,
+            (_, None) => return Ok(None),
+        };
+
+        let associated_node = inner_read.by_perm_id(&associated_perm_id)?;
+        let readable = ReadableContent::from_node(&inner_read, associated_node.id())?;
+        drop(inner_read);
+
+        let data = readable.read(store, None).await?;
+        Ok(Some(data))
+    }
+
+    /// Encrypts the data and stores it in the associated data child of the file at the provided
+    /// path with the provided name, creating the child if it doesn't exist yet.
+    async fn store_associated(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        store: &mut impl DataStore,
+        path: &[&str],
+        name: NodeName,
+        data: &[u8],
+        content_type: Option<&str>,
+    ) -> Result<PermanentId, OperationError> {
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_write_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
+
+        let data_key = match inner_read.access().data_key() {
+            Some(data_key) => data_key.clone(),
+            None => return Err(OperationError::AccessDenied),
+        };
+
+        // Confirm there is a file to attach the data to before storing anything
+        self.associated_ids(&inner_read, path, &name)?;
+        drop(inner_read);
+
+        let file_content =
+            encrypt_content(rng, store, &data_key, self.chunk_size_policy, data).await?;
+
+        let mut inner_write = self.inner.write().await;
+        let (file_perm_id, existing_perm_id) = self.associated_ids(&inner_write, path, &name)?;
+
+        let associated_perm_id = match existing_perm_id {
+            Some(perm_id) => perm_id,
+            None => {
+                let data_size = data.len() as u64;
+                inner_write
+                    .create_node(
+                        rng,
+                        actor_id,
+                        file_perm_id,
+                        |rng, new_node_id, parent_id, actor_id| async move {
+                            NodeBuilder::associated_data(name)
+                                .with_parent(parent_id)
+                                .with_id(new_node_id)
+                                .with_owner(actor_id)
+                                .with_size_hint(data_size)
+                                .build(rng)
+                                .map_err(OperationError::CreationFailed)
+                        },
+                    )
+                    .await?
+            }
+        };
+
+        let node = inner_write.by_perm_id_mut(&associated_perm_id).await?;
+        // Data replaced without a content type shouldn't keep the one recorded for the old data
+        match content_type {
+            Some(content_type) => {
+                node.set_attribute(MetadataKey::MimeType, content_type.as_bytes().to_vec())
+                    .await;
+            }
+            None => {
+                node.remove_attribute(&MetadataKey::MimeType).await;
+            }
+        }
+        *node.data_mut().await = NodeData::AssociatedData {
+            content: file_content,
+        };
+
+        inner_write.clean_drive().await?;
+        Ok(associated_perm_id)
+    }
+
+    /// Retrieves the preview previously attached to the file at the provided path using
+    /// [`DirectoryHandle::set_thumbnail`] along with its content type. Files without a thumbnail
+    /// produce `None`.
+    pub async fn get_thumbnail(
+        &self,
+        store: &impl DataStore,
+        path: &[&str],
+    ) -> Result<Option<(Vec<u8>, String)>, OperationError> {
+        let inner_read = self.inner.read().await;
+        let actor_id = self.current_key.actor_id();
+        if !inner_read.access().has_read_access(&actor_id) {
+            return Err(OperationError::AccessDenied);
+        }
+
+        let thumbnail_perm_id = match self.associated_ids(&inner_read, path, &thumbnail_name())? {
+            (_, Some(perm_id)) => perm_id,
+            (_, None) => return Ok(None),
+        };
+
+        let thumbnail_node = inner_read.by_perm_id(&thumbnail_perm_id)?;
+        let content_type = thumbnail_node
+            .metadata()
+            .get(&MetadataKey::MimeType)
+            .map(|raw| String::from_utf8_lossy(raw).to_string())
+            .unwrap_or_default();
+        let readable = ReadableContent::from_node(&inner_read, thumbnail_node.id())?;
+        drop(inner_read);
+
+        let data = readable.read(store, None).await?;
+        Ok(Some((data, content_type)))
+    }
+
+    /// Confirms the node at the provided path currently has the expected CID.
+    async fn verify_unchanged(
+        &self,
+        inner: &InnerDrive,
+        path: &[&str],
+        expected_cid: &Cid,
+    ) -> Result<(), OperationError> {
+        let node_id = match inner.walk_path(self.cwd_id, path)? {
+            WalkState::FoundNode { node_id } => node_id,
+            WalkState::MissingComponent { .. } => return Err(OperationError::PathNotFound),
+        };
+
+        let current = inner.by_id(node_id)?.cid().await?;
+        if &current != expected_cid {
+            return Err(OperationError::Conflict { current });
+        }
+
+        Ok(())
+    }
+
+    /// Locates the file at the provided path, producing its permanent ID and the permanent ID of
+    /// its associated data with the provided name if it exists.
+    fn associated_ids(
+        &self,
+        inner: &InnerDrive,
+        path: &[&str],
+        name: &NodeName,
+    ) -> Result<(PermanentId, Option<PermanentId>), OperationError> {
+        if path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
+
+        let file_node_id = match inner.walk_path(self.cwd_id, path)? {
+            WalkState::FoundNode { node_id } => node_id,
+            WalkState::MissingComponent { .. } => return Err(OperationError::PathNotFound),
+        };
+
+        let file_node = inner.by_id(file_node_id)?;
+        if file_node.kind() != NodeKind::File {
+            return Err(OperationError::NotAFile);
+        }
+
+        let associated_perm_id = file_node.data().child_permanent_id(name);
+        Ok((file_node.permanent_id(), associated_perm_id))
+    }
+}
+
+/// Everything needed to retrieve the content of a file once the drive itself is no longer locked.
+pub(super) enum ReadableContent {
+    Empty,
+    Encrypted {
+        data_key: AccessKey,
+        content_references: Vec<ContentReference>,
+    },
+    External {
+        url: String,
+        cid: Cid,
+    },
+    Raw {
+        content_references: Vec<ContentReference>,
+        size: u64,
+    },
+}
+
+impl ReadableContent {
+    /// Collects everything needed to read the content of the provided node. Callers are
+    /// responsible for performing any access checks on the path leading to the node.
+    pub(super) fn from_node(inner: &InnerDrive, node_id: NodeId) -> Result<Self, OperationError> {
+        let read_node = inner.by_id(node_id)?;
+        let node_content = match read_node.data() {
+            NodeData::File { content, .. } => content,
+            NodeData::AssociatedData { content, .. } => content,
+            _ => return Err(OperationError::NotReadable),
+        };
+
+        if node_content.is_stub() {
+            return Err(OperationError::NotAvailable);
+        }
+
+        if let FileContent::External { url, cid, .. } = node_content {
+            return Ok(Self::External {
+                url: url.clone(),
+                cid: cid.clone(),
+            });
+        }
+
+        if node_content.is_encrypted() {
+            let locked_key = node_content
+                .data_key()
+                .map_err(|_| OperationError::AccessDenied)?;
+
+            let data_key = match inner.access().data_key() {
+                Some(data_key) => data_key,
+                None => return Err(OperationError::AccessDenied),
+            };
+
+            let unlocked_key = locked_key
+                .unlock(data_key)
+                .map_err(|_| OperationError::AccessDenied)?;
+
+            Ok(Self::Encrypted {
+                data_key: unlocked_key,
+                content_references: node_content.content_references()?.to_vec(),
+            })
+        } else if node_content.is_empty() {
+            Ok(Self::Empty)
+        } else {
+            Ok(Self::Raw {
+                content_references: node_content.content_references()?.to_vec(),
+                size: node_content.size(),
+            })
+        }
+    }
+
+    /// Streams the content one decrypted chunk at a time, see [`DirectoryHandle::read_stream`].
+    pub(super) fn into_stream<S: DataStore>(
+        self,
+        store: &S,
+    ) -> Result<LocalBoxStream<'_, Result<Vec<u8>, OperationError>>, OperationError> {
+        match self {
+            Self::Empty => Ok(stream::empty().boxed_local()),
+            Self::Encrypted {
+                data_key,
+                content_references,
+            } => Ok(ChunkReader::new(store, data_key, content_references).into_stream()),
+            Self::External { .. } => Err(OperationError::ExternalFetcherRequired),
+            Self::Raw {
+                content_references,
+                size,
+            } => Ok(raw_block_stream(store, content_references, size)),
+        }
+    }
+
+    /// Reads the content between the `start` and `end` offsets, see
+    /// [`DirectoryHandle::read_range`]. The offsets need to be within the content.
+    pub(super) async fn read_range(
+        self,
+        store: &impl DataStore,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<u8>, OperationError> {
+        let (mut chunks, mut position) = match self {
+            Self::Empty => return Ok(Vec::new()),
+            Self::Encrypted {
+                data_key,
+                content_references,
+            } => {
+                let mut reader = ChunkReader::new(store, data_key, content_references);
+                let position = reader.restrict_to_range(start, end);
+                (reader.into_stream(), position)
+            }
+            Self::External { .. } => return Err(OperationError::ExternalFetcherRequired),
+            Self::Raw {
+                content_references,
+                size,
+            } => (raw_block_stream(store, content_references, size), 0),
+        };
+
+        let mut range_data = Vec::with_capacity((end - start) as usize);
+        while position < end {
+            let chunk = match chunks.next().await {
+                Some(chunk) => chunk?,
+                None => break,
+            };
+
+            let chunk_start = position;
+            position += chunk.len() as u64;
+            if position <= start {
+                continue;
+            }
+
+            let skip = start.saturating_sub(chunk_start) as usize;
+            let take = (end.min(position) - chunk_start) as usize;
+            range_data.extend_from_slice(&chunk[skip..take]);
+        }
+
+        Ok(range_data)
+    }
+
+    pub(super) async fn read(
+        self,
+        store: &impl DataStore,
+        fetcher: Option<&dyn ExternalFetcher>,
+    ) -> Result<Vec<u8>, OperationError> {
+        match self {
+            Self::Empty => Ok(Vec::new()),
+            Self::Encrypted {
+                data_key,
+                content_references,
+            } => {
+                let mut file_data = Vec::new();
+
+                let mut chunks =
+                    ChunkReader::new(store, data_key, content_references).into_stream();
+                while let Some(chunk) = chunks.next().await {
+                    file_data.extend_from_slice(&chunk?);
+                }
+
+                Ok(file_data)
+            }
+            Self::Raw {
+                content_references,
+                size,
+            } => {
+                let mut file_data = Vec::new();
+
+                let mut blocks = raw_block_stream(store, content_references, size);
+                while let Some(block) = blocks.next().await {
+                    file_data.extend_from_slice(&block?);
+                }
+
+                Ok(file_data)
+            }
+            Self::External { url, cid } => {
+                let fetcher = fetcher.ok_or(OperationError::ExternalFetcherRequired)?;
+
+                let data = fetcher.fetch(&url).await?;
+                if crate::utils::calculate_cid(&data) != cid {
+                    return Err(OperationError::ExternalContentMismatch);
+                }
+
+                Ok(data)
+            }
+        }
+    }
+}
+
+/// Retrieves each of the referenced blocks in order, yielding their content as is. Used for
+/// unencrypted content made up of blocks that weren't produced by BanyanFS. Nothing authenticates
+/// these blocks, so each one is checked against its CID before it is yielded and the blocks need
+/// to add up to the `size` recorded for the content. The stream ends after the first error.
+fn raw_block_stream<S: DataStore>(
+    store: &S,
+    content_references: Vec<ContentReference>,
+    size: u64,
+) -> LocalBoxStream<'_, Result<Vec<u8>, OperationError>> {
+    stream::try_unfold(
+        (content_references.into_iter(), 0u64),
+        move |(mut pending, position)| async move {
+            let block_cid = match pending.next() {
+                Some(content_ref) => content_ref.data_block_cid(),
+                None if position == size => return Ok(None),
+                None => {
+                    return Err(OperationError::ContentSizeMismatch {
+                        expected: size,
+                        actual: position,
+                    })
+                }
+            };
+
+            if !store.contains_cid(block_cid.clone()).await? {
+                return Err(OperationError::BlockUnavailable(block_cid));
+            }
+
+            let block_data = store.retrieve(block_cid.clone()).await?;
+            if crate::utils::calculate_cid(&block_data) != block_cid {
+                return Err(OperationError::BlockCorrupted(block_cid));
+            }
+
+            let position = position + block_data.len() as u64;
+            if position > size {
+                return Err(OperationError::ContentSizeMismatch {
+                    expected: size,
+                    actual: position,
+                });
+            }
+
+            Ok(Some((block_data, (pending, position))))
+        },
+    )
+    .boxed_local()
+}
+
+/// Retrieves and decrypts the chunks of a file in order, holding on to only the data block that
+/// is currently being read.
+struct ChunkReader<'a, S: DataStore> {
+    store: &'a S,
+    data_key: AccessKey,
+
+    /// The chunks still to be read, along with the capacity of each chunk.
+    pending: VecDeque<(Cid, ContentLocation, u64)>,
+    current_block: Option<(Cid, DataBlock)>,
+}
+
+impl<'a, S: DataStore> ChunkReader<'a, S> {
+    fn new(store: &'a S, data_key: AccessKey, content_references: Vec<ContentReference>) -> Self {
+        let pending = content_references
+            .iter()
+            .flat_map(|content_ref| {
+                let block_cid = content_ref.data_block_cid();
+                let chunk_size = content_ref.data_options().chunk_data_size() as u64;
+                content_ref
+                    .chunks()
+                    .iter()
+                    .map(move |location| (block_cid.clone(), location.clone(), chunk_size))
+            })
+            .collect();
+
+        Self {
+            store,
+            data_key,
+            pending,
+            current_block: None,
+        }
+    }
+
+    /// Drops the chunks that don't hold any of the content between the `start` and `end` offsets,
+    /// producing the offset of the first remaining chunk. Every chunk other than the last one of
+    /// the content is filled to the chunk size of its block (appends write a partially filled
+    /// final chunk out again, see [`DirectoryHandle::write_append`]), so the chunks can be located
+    /// without retrieving any of their blocks.
+    fn restrict_to_range(&mut self, start: u64, end: u64) -> u64 {
+        let mut position = 0;
+        let mut first_position = None;
+
+        self.pending.retain(|(_, _, chunk_size)| {
+            let chunk_start = position;
+            position += chunk_size;
+
+            let intersects = position > start && chunk_start < end;
+            if intersects && first_position.is_none() {
+                first_position = Some(chunk_start);
+            }
+
+            intersects
+        });
+
+        first_position.unwrap_or(start)
+    }
+
+    /// The stream ends after the first error, a corrupted block can't be skipped without losing
+    /// the position of the data within the file.
+    fn into_stream(self) -> LocalBoxStream<'a, Result<Vec<u8>, OperationError>> {
+        stream::try_unfold(self, |mut reader| async move {
+            let chunk = reader.next_chunk().await?;
+            Ok(chunk.map(|data| (data, reader)))
+        })
+        .boxed_local()
+    }
+
+    async fn load_block(&mut self, block_cid: &Cid) -> Result<&DataBlock, OperationError> {
+        let loaded = matches!(&self.current_block, Some((cid, _)) if cid == block_cid);
+
+        if !loaded {
+            if !self.store.contains_cid(block_cid.clone()).await? {
+                return Err(OperationError::BlockUnavailable(block_cid.clone()));
+            }
+
+            let block_data = self.store.retrieve(block_cid.clone()).await?;
+            let (_remaining, block) = DataBlock::parse_with_magic(Stream::new(&block_data))
+                .map_err(|err| {
+                    tracing::error!("parsing of data block failed: {err:?}");
+                    OperationError::BlockCorrupted(block_cid.clone())
+                })?;
+            // todo(sstelfox): still stuff remaining which means this decoder is sloppy
+            //tracing::info!(?remaining, "drive::read::remaining");
+            //debug_assert!(remaining.is_empty(), "no extra data should be present");
+
+            self.current_block = Some((block_cid.clone(), block));
+        }
+
+        match &self.current_block {
+            Some((_, block)) => Ok(block),
+            None => unreachable!("block was loaded above"),
+        }
+    }
+
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, OperationError> {
+        let (block_cid, location, _) = match self.pending.pop_front() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+
+        if !matches!(location.block_kind(), BlockKind::Data) {
+            unimplemented!("indirect reference loading");
+        }
+
+        let data_key = self.data_key.clone();
+        let block = self.load_block(&block_cid).await?;
+
+        let encrypted_chunk = block
+            .get_chunk(location.block_index() as usize)
+            .map_err(|err| {
+                tracing::error!("failed to retrieve block chunk: {err:?}");
+                OperationError::BlockCorrupted(block_cid.clone())
+            })?;
+
+        let chunk = encrypted_chunk
+            .decrypt(&block.data_options(), &data_key)
+            .map_err(|err| match err {
+                EncryptedDataChunkError::DecryptError => {
+                    OperationError::BlockAuthenticationFailed {
+                        cid: block_cid.clone(),
+                    }
+                }
+                _ => OperationError::BlockCorrupted(block_cid.clone()),
+            })?;
+
+        Ok(Some(chunk.data().to_vec()))
+    }
+}
+
+// todo: should these operations be using the permanent ids? Is that worth the extra
+// level of indirection? As long as we remain consistent it should be fine.
+#[instrument(level = Level::TRACE, skip(inner, path))]
+fn walk_path<'a>(
+    inner: &'a Arc<RwLock<InnerDrive>>,
+    working_directory_id: NodeId,
+    path: &'a [&'a str],
+    depth: usize,
+) -> BoxFuture<'a, Result<WalkState<'a>, OperationError>> {
+    trace!("directory::walk_directory");
+
+    async move {
+        let inner_read = inner.read().await;
+
+        let (raw_child_name, remaining_path) = match path.split_first() {
+            Some(pair) => pair,
+            // We've reached the end of the path, our current node is the target
+            None => return Ok(WalkState::found(working_directory_id)),
+        };
+
+        let child_name = NodeName::try_from(*raw_child_name)?;
+        let current_node = inner_read.by_id(working_directory_id)?;
+
+        let child_map = match current_node.data() {
+            NodeData::Directory { children, .. } => children,
+            NodeData::File { associated_data, .. } => associated_data,
+            _ => return Err(OperationError::NotTraversable),
+        };
+
+        let perm_id = match child_map.get(&child_name) {
+            Some(entry) => entry.permanent_id(),
+            None => {
+                return Ok(WalkState::MissingComponent {
+                    working_directory_id,
+                    missing_name: child_name,
+                    remaining_path,
+                });
+            }
+        };
+
+        let next_node = inner_read.by_perm_id(perm_id)?;
+        let next_node_id = next_node.id();
+        trace!(node_id = ?next_node_id, next_node_kind = ?next_node.kind(), "drive::walk_directory::next_node");
+
+        if !next_node.supports_children() {
+            return Err(OperationError::NotTraversable);
+        }
+
+        drop(inner_read);
+
+        if depth >= MAX_PATH_DEPTH {
+            return Err(OperationError::PathTooDeep);
+        }
+
+        walk_path(inner, next_node_id, remaining_path, depth + 1).await
+    }
+    .boxed()
+}
+
+fn thumbnail_name() -> NodeName {
+    NodeName::try_from(THUMBNAIL_NAME).expect("reserved name to be valid")
+}
+
+/// Splits the provided data into encrypted chunks under a freshly generated key, storing the
+/// sealed data blocks in the provided store. The size of the chunks and blocks is chosen by the
+/// policy based on the size of the data. The returned content has its key locked with the drive's
+/// data key.
+async fn encrypt_content(
+    rng: &mut impl CryptoRngCore,
+    store: &mut impl DataStore,
+    data_key: &AccessKey,
+    chunk_size_policy: ChunkSizePolicy,
+    data: &[u8],
+) -> Result<FileContent, OperationError> {
+    let data_size = data.len() as u64;
+
+    // todo(sstelfox): bit lazy here, should calculate this as I stream it but speed right
+    // now...
+    let plaintext_cid = crate::utils::calculate_cid(data);
+
+    let node_data_key = AccessKey::generate(rng);
+    let content_references =
+        encrypt_blocks(rng, store, &node_data_key, chunk_size_policy, data).await?;
+
+    let locked_key = node_data_key
+        .lock_with(rng, data_key)
+        .map_err(|_| OperationError::Other("failed to seal node data key"))?;
+
+    Ok(FileContent::encrypted(
+        locked_key,
+        plaintext_cid,
+        data_size,
+        content_references,
+    ))
+}
+
+/// Encrypts the provided data under a file's own key into newly created data blocks, storing the
+/// sealed blocks in the provided store. Every chunk other than the last one is filled completely.
+async fn encrypt_blocks(
+    rng: &mut impl CryptoRngCore,
+    store: &mut impl DataStore,
+    node_data_key: &AccessKey,
+    chunk_size_policy: ChunkSizePolicy,
+    data: &[u8],
+) -> Result<Vec<ContentReference>, OperationError> {
+    let data_options = chunk_size_policy.data_options(data.len() as u64);
+    encrypt_blocks_with_options(rng, store, node_data_key, data_options, data).await
+}
+
+/// Encrypts the provided data into data blocks created with the provided options, see
+/// [`encrypt_blocks`].
+async fn encrypt_blocks_with_options(
+    rng: &mut impl CryptoRngCore,
+    store: &mut impl DataStore,
+    node_data_key: &AccessKey,
+    data_options: DataOptions,
+    data: &[u8],
+) -> Result<Vec<ContentReference>, OperationError> {
+    let block_creator = || {
+        DataBlock::with_data_options(data_options).map_err(|err| {
+            tracing::error!("failed to create data block: {:?}", err);
+            OperationError::Other("data block failed")
+        })
+    };
+
+    let mut remaining_data = data;
+    let mut active_block = block_creator()?;
+    let active_block_chunk_size = active_block.data_options().chunk_data_size();
+    let mut content_references = Vec::new();
+    let mut content_indexes = Vec::new();
+
+    while !remaining_data.is_empty() {
+        let data_to_read = std::cmp::min(remaining_data.len(), active_block_chunk_size);
+        let (chunk_data, next_data) = remaining_data.split_at(data_to_read);
+        remaining_data = next_data;
+
+        let chunk = DataChunk::from_slice(chunk_data, &active_block.data_options())
+            .map_err(|err| {
+                tracing::error!("failed to push chunk: {:?}", err);
+                OperationError::Other("expected remaining capacity")
+            })?
+            .encrypt(rng, &active_block.data_options(), node_data_key)
+            .await
+            .map_err(|err| {
+                tracing::error!("Failed to encrypt chunk: {:?}", err);
+                OperationError::Other("Error encrypting chunk")
+            })?;
+
+        content_indexes.push(active_block.push_chunk(chunk).map_err(|err| {
+            tracing::error!("failed to push chunk: {:?}", err);
+            OperationError::Other("expected remaining capacity")
+        })?);
+
+        if active_block.is_full() {
+            let mut sealed_block = Vec::new();
+
+            let (_, cids) = active_block
+                .encode(rng, &mut sealed_block)
+                .await
+                .map_err(|err| {
+                    tracing::error!("failed to encode block: {:?}", err);
+                    OperationError::Other("failed to encode block")
+                })?;
+
+            let cid = active_block
+                .cid()
+                .map_err(|_| OperationError::Other("unable to access block cid"))?;
+
+            store.store(cid.clone(), sealed_block, false).await?;
+
+            let locations = content_indexes
+                .iter()
+                .map(|i| ContentLocation::data(cids[*i].clone(), *i as u64))
+                .collect::<Vec<_>>();
+            content_indexes.clear();
+
+            let content_ref = ContentReference::new(cid, active_block.data_options(), locations);
+            content_references.push(content_ref);
+
+            active_block = block_creator()?;
+        }
+    }
+
+    if !active_block.is_empty() {
+        tracing::info!("writing trailing block");
+
+        // todo(sstelfox): this is duplicated, need to extract it
+        let mut sealed_block = Vec::new();
+
+        let (_, cids) = active_block
+            .encode(rng, &mut sealed_block)
+            .await
+            .map_err(|err| {
+                tracing::error!("failed to encode block: {:?}", err);
+                OperationError::Other("failed to encode block")
+            })?;
+
+        let cid = active_block
+            .cid()
+            .map_err(|_| OperationError::Other("unable to access block cid"))?;
+
+        store.store(cid.clone(), sealed_block, false).await?;
+
+        let locations = content_indexes
+            .iter()
+            .map(|i| ContentLocation::data(cids[*i].clone(), *i as u64))
+            .collect::<Vec<_>>();
+        content_indexes.clear();
+
+        let content_ref = ContentReference::new(cid, active_block.data_options(), locations);
+        content_references.push(content_ref);
+    }
+
+    Ok(content_references)
+}
+
+#[cfg(feature = "mime-type")]
+async fn set_mime_type(data: &[u8], node: &mut Node) {
+    if let Some(mime_type) = MimeGuesser::default()
+        .with_name(node.name().clone())
+        .with_data(data)
+        .guess_mime_type()
+    {
+        node.set_attribute(MetadataKey::MimeType, mime_type.to_string().into())
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod test {
     use super::*;
+    use crate::codec::crypto::AuthenticationTag;
     use crate::filesystem::drive::inner::test::build_interesting_inner;
+    use crate::prelude::MemoryDataStore;
+    use crate::stores::DataStoreError;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_dir_from_dir_to_cwd_specify_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(&mut rng, &["dir_1", "dir_2"], &["dir_2_new"])
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("dir_2_new").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_dir_from_dir_to_dir_specify_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(
+                &mut rng,
+                &["dir_1", "dir_2", "dir_3"],
+                &["dir_1", "dir_3_new"],
+            )
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("dir_3_new").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_file_from_dir_to_cwd_specify_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(
+                &mut rng,
+                &["dir_1", "dir_2", "dir_3", "file_3"],
+                &["file_3_new"],
+            )
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("file_3_new").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_file_from_dir_to_dir_specify_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(
+                &mut rng,
+                &["dir_1", "dir_2", "dir_3", "file_3"],
+                &["dir_1", "file_3_new"],
+            )
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("file_3_new").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_dir_from_dir_to_cwd_no_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle.mv(&mut rng, &["dir_1", "dir_2"], &[]).await.unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("dir_2").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_dir_from_dir_to_dir_no_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(&mut rng, &["dir_1", "dir_2", "dir_3"], &["dir_1"])
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("dir_3").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_file_from_dir_to_cwd_no_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(&mut rng, &["dir_1", "dir_2", "dir_3", "file_3"], &[])
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("file_3").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn mv_file_from_dir_to_dir_no_name() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut handle = interesting_handle(None).await;
+        handle
+            .mv(&mut rng, &["dir_1", "dir_2", "dir_3", "file_3"], &["dir_1"])
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from("file_3").unwrap())
+                .count(),
+            1
+        );
+    }
+
+    async fn interesting_handle(current_key: Option<SigningKey>) -> DirectoryHandle {
+        //           -----file_1
+        //         /
+        // root ---------file_2
+        //         \
+        //          --------- dir_1 ----- dir_2 ---- dir_3 ---- file_3
+        //                         \
+        //                           --- file_4
+        //                            \
+        //                              ----file_5
+        let mut rng = crate::utils::crypto_rng();
+        let inner = build_interesting_inner(current_key.clone()).await;
+        let root_id = inner.root_node().unwrap().id();
+        let inner = Arc::new(RwLock::new(inner));
+        DirectoryHandle {
+            current_key: Arc::new(current_key.unwrap_or_else(|| SigningKey::generate(&mut rng))),
+            inner,
+            cwd_id: root_id,
+            chunk_size_policy: ChunkSizePolicy::default(),
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_stream_stores_content_across_blocks() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+
+        let data: Vec<u8> = (0..300_000).map(|idx| (idx % 251) as u8).collect();
+        handle
+            .write_stream(
+                &mut rng,
+                &mut store,
+                &["streamed.bin"],
+                futures::io::Cursor::new(data.clone()),
+                WriteStreamOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let file_data = handle.read(&store, &["streamed.bin"]).await.unwrap();
+        assert_eq!(file_data, data);
+
+        let inner = handle.inner.read().await;
+        let node_id = match inner.walk_path(handle.cwd_id, &["streamed.bin"]).unwrap() {
+            WalkState::FoundNode { node_id } => node_id,
+            _ => panic!("expected the streamed file to exist"),
+        };
+        match inner.by_id(node_id).unwrap().data() {
+            NodeData::File { content, .. } => {
+                assert_eq!(content.cid(), Some(crate::utils::calculate_cid(&data)));
+            }
+            _ => panic!("expected file data"),
+        }
+        drop(inner);
+
+        match handle.readable_content(&["streamed.bin"]).await.unwrap() {
+            ReadableContent::Encrypted {
+                content_references, ..
+            } => assert!(content_references.len() > 1),
+            _ => panic!("expected encrypted content"),
+        }
+    }
+
+    #[cfg(feature = "mime-type")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_stream_sniffs_only_leading_content() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+
+        // An MP4 is only recognized when its whole leading box is available to the sniffer
+        let mp4_content = |box_size: u32| {
+            let mut data = box_size.to_be_bytes().to_vec();
+            data.extend_from_slice(b"ftypmp42");
+            data.resize(4096, 0);
+            data
+        };
+
+        let test_cases = [
+            (mp4_content(1000), "short_box.bin", true, "video/mp4"),
+            (
+                mp4_content(MIME_SNIFF_LENGTH as u32 + 555),
+                "long_box.bin",
+                true,
+                "application/octet-stream",
+            ),
+            (
+                mp4_content(1000),
+                "unsniffed.bin",
+                false,
+                "application/octet-stream",
+            ),
+        ];
+
+        for (data, file_name, sniff, expected) in test_cases {
+            let options = if sniff {
+                WriteStreamOptions::default().with_content_sniffing()
+            } else {
+                WriteStreamOptions::default()
+            };
+
+            handle
+                .write_stream(
+                    &mut rng,
+                    &mut store,
+                    &[file_name],
+                    futures::io::Cursor::new(data),
+                    options,
+                )
+                .await
+                .unwrap();
+
+            let name = NodeName::try_from(file_name).unwrap();
+            let cwd_ls = handle.ls(&[]).await.unwrap();
+            let entry = cwd_ls.iter().find(|entry| entry.name() == name).unwrap();
+            assert_eq!(entry.mime_type().unwrap(), expected, "{file_name}");
+        }
+    }
+
+    #[cfg(feature = "mime-type")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn sniff_html_mime_type() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+
+        let test_cases = vec![
+            (b"<html><head><title>Test File</title></head><body><h1>Hello World!</h1></body></html>".to_vec(), "test.html"),
+            (b"<HTML><HEAD><TITLE>Test File</TITLE></HEAD><BODY><H1>Hello World!</H1></BODY></HTML>".to_vec(), "TEST.HTML"),
+            (b"<h1>Heading</h1><p>Paragraph</p>".to_vec(), "file.htm"),
+            (b"<div><span>Some text</span></div>".to_vec(), "page.php"),
+            (
+                b"<!docTYPE html><html><body>Content</body></html>".to_vec(),
+                "invalid_file_name",
+            ),
+        ];
+        for (data, file_name) in test_cases {
+            handle
+                .write(&mut rng, &mut store, &[file_name], &data)
+                .await
+                .unwrap();
+
+            let cwd_ls = handle.ls(&[]).await.unwrap();
+            assert_eq!(
+                cwd_ls
+                    .iter()
+                    .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+                    .count(),
+                1
+            );
+
+            let file_entry = cwd_ls
+                .iter()
+                .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+                .unwrap();
+
+            assert_eq!(file_entry.kind(), NodeKind::File);
+
+            let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
+            assert_eq!(file_data.as_slice(), data);
+
+            let mime_type = file_entry.mime_type().unwrap();
+            assert_eq!(mime_type, "text/html");
+        }
+    }
+
     #[cfg(feature = "mime-type")]
-    use crate::prelude::MemoryDataStore;
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn sniff_mp3_file_mime_type() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+        let mp3_test_case: &[u8] = &[
+            0x49, 0x44, 0x33, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x22, 0x54, 0x53, 0x53, 0x45,
+            0x00, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x03, 0x4c, 0x61, 0x76, 0x66, 0x36, 0x30, 0x2e,
+            0x33, 0x2e, 0x31, 0x30, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0xff, 0xfb, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let file_name = "the_audio.mp4";
+        handle
+            .write(&mut rng, &mut store, &[file_name], mp3_test_case)
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+                .count(),
+            1
+        );
+
+        let file_entry = cwd_ls
+            .iter()
+            .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+            .unwrap();
+
+        assert_eq!(file_entry.kind(), NodeKind::File);
+
+        let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
+        assert_eq!(file_data.as_slice(), mp3_test_case);
+
+        let mime_type = file_entry.mime_type().unwrap();
+        assert_eq!(mime_type, "audio/mpeg");
+    }
+
+    #[cfg(feature = "mime-type")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn sniff_mp4_file_mime_type() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+        let mp4_test_case: &[u8] = &[
+            0x00, 0x00, 0x00, 0x1c, 0x66, 0x74, 0x79, 0x70, 0x69, 0x73, 0x6f, 0x6d, 0x00, 0x00,
+            0x02, 0x00, 0x69, 0x73, 0x6f, 0x6d, 0x69, 0x73, 0x6f, 0x32, 0x6d, 0x70, 0x34, 0x31,
+            0x00, 0x00, 0x00, 0x08,
+        ];
+        let file_name = "the_audio.mp3";
+        handle
+            .write(&mut rng, &mut store, &[file_name], mp4_test_case)
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+                .count(),
+            1
+        );
+
+        let file_entry = cwd_ls
+            .iter()
+            .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+            .unwrap();
+
+        assert_eq!(file_entry.kind(), NodeKind::File);
+
+        let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
+        assert_eq!(file_data.as_slice(), mp4_test_case);
+
+        let mime_type = file_entry.mime_type().unwrap();
+        assert_eq!(mime_type, "video/mp4");
+    }
+
+    #[cfg(feature = "mime-type")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn sniff_webm_file_mime_type() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+        let webm_test_case: &[u8] = &[
+            0x1a, 0x45, 0xdf, 0xa3, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x42, 0x86,
+            0x81, 0x01, 0x42, 0xf7, 0x81, 0x01, 0x42, 0xf2, 0x81, 0x04, 0x42, 0xf3, 0x81, 0x08,
+            0x42, 0x82, 0x84, 0x77, 0x65, 0x62, 0x6d, 0x42, 0x87, 0x81, 0x02, 0x42, 0x85, 0x81,
+            0x02, 0x18, 0x53, 0x80, 0x67, 0x01, 0x00, 0x00, 0x00, 0x00, 0x0d, 0xc0, 0x0a, 0x11,
+            0x4d, 0x9b, 0x74, 0x40, 0x3c, 0x4d, 0xbb, 0x8b, 0x53, 0xab, 0x84, 0x15, 0x49, 0xa9,
+            0x66, 0x53, 0xac, 0x81, 0xe5, 0x4d, 0xbb, 0x8c, 0x53, 0xab,
+        ];
+        let file_name = "the_audio.mp4";
+        handle
+            .write(&mut rng, &mut store, &[file_name], webm_test_case)
+            .await
+            .unwrap();
+
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!(
+            cwd_ls
+                .iter()
+                .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+                .count(),
+            1
+        );
+
+        let file_entry = cwd_ls
+            .iter()
+            .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+            .unwrap();
+
+        assert_eq!(file_entry.kind(), NodeKind::File);
+
+        let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
+        assert_eq!(file_data.as_slice(), webm_test_case);
+
+        let mime_type = file_entry.mime_type().unwrap();
+        assert_eq!(mime_type, "video/webm");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn ls_entry_kind_predicates() {
+        let handle = interesting_handle(None).await;
+        let cwd_ls = handle.ls(&[]).await.unwrap();
+
+        let dir_entry = cwd_ls
+            .iter()
+            .find(|entry| entry.name() == NodeName::try_from("dir_1").unwrap())
+            .unwrap();
+        assert!(dir_entry.is_dir());
+        assert!(!dir_entry.is_file());
+
+        let file_entry = cwd_ls
+            .iter()
+            .find(|entry| entry.name() == NodeName::try_from("file_1").unwrap())
+            .unwrap();
+        assert!(file_entry.is_file());
+        assert!(!file_entry.is_dir());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn ls_detailed_matches_ls() {
+        let handle = interesting_handle(None).await;
+
+        let mut listed = handle
+            .ls(&["dir_1"])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.name(), entry.permanent_id()))
+            .collect::<Vec<_>>();
+        let mut detailed = handle
+            .ls_detailed(&["dir_1"])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(name, pid, _, _)| (name, pid))
+            .collect::<Vec<_>>();
+
+        listed.sort_by_key(|(_, pid)| *pid);
+        detailed.sort_by_key(|(_, pid)| *pid);
+        assert_eq!(listed, detailed);
+
+        let file_detail = handle.ls_detailed(&["file_1"]).await.unwrap();
+        assert_eq!(file_detail.len(), 1);
+        assert_eq!(file_detail[0].0, NodeName::try_from("file_1").unwrap());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn ls_recursive_lists_depth_first() {
+        let handle = interesting_handle(None).await;
+        let paths = |listing: Vec<(Vec<String>, DirectoryEntry)>| {
+            listing
+                .into_iter()
+                .map(|(path, _)| path.join("/"))
+                .collect::<Vec<_>>()
+        };
+
+        let listing = handle.ls_recursive(&[], None).await.unwrap();
+        assert_eq!(
+            paths(listing),
+            vec![
+                "dir_1",
+                "dir_1/dir_2",
+                "dir_1/dir_2/dir_3",
+                "dir_1/dir_2/dir_3/file_3",
+                "dir_1/dir_2/file_4",
+                "dir_1/dir_2/file_5",
+                "file_1",
+                "file_2",
+            ]
+        );
+
+        let listing = handle.ls_recursive(&["dir_1"], Some(1)).await.unwrap();
+        assert_eq!(
+            paths(listing),
+            vec![
+                "dir_1/dir_2",
+                "dir_1/dir_2/dir_3",
+                "dir_1/dir_2/file_4",
+                "dir_1/dir_2/file_5",
+            ]
+        );
+
+        // A depth of zero matches a plain listing
+        let listing = handle
+            .ls_recursive(&["dir_1", "dir_2"], Some(0))
+            .await
+            .unwrap();
+        let mut listed = listing
+            .into_iter()
+            .map(|(_, entry)| entry.permanent_id())
+            .collect::<Vec<_>>();
+        let mut expected = handle
+            .ls(&["dir_1", "dir_2"])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.permanent_id())
+            .collect::<Vec<_>>();
+        listed.sort();
+        expected.sort();
+        assert_eq!(listed, expected);
+
+        let listing = handle.ls_recursive(&["file_1"], None).await.unwrap();
+        assert_eq!(paths(listing), vec!["file_1"]);
+    }
+
+    struct TestFetcher(Vec<u8>);
+
+    #[async_trait::async_trait(?Send)]
+    impl ExternalFetcher for TestFetcher {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>, DataStoreError> {
+            assert_eq!(url, "https://example.com/object");
+            Ok(self.0.clone())
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn external_content_read_verified() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let store = MemoryDataStore::default();
+        let mut handle = interesting_handle(Some(current_key)).await;
+
+        let external_data = b"content stored somewhere else".to_vec();
+        let cid = crate::utils::calculate_cid(&external_data);
+
+        handle
+            .write_external(
+                &mut rng,
+                &["dir_1", "external"],
+                "https://example.com/object",
+                cid,
+                external_data.len() as u64,
+            )
+            .await
+            .unwrap();
+
+        let result = handle.read(&store, &["dir_1", "external"]).await;
+        assert!(matches!(
+            result,
+            Err(OperationError::ExternalFetcherRequired)
+        ));
+
+        let fetcher = TestFetcher(external_data.clone());
+        let file_data = handle
+            .read_with_fetcher(&store, &fetcher, &["dir_1", "external"])
+            .await
+            .unwrap();
+        assert_eq!(file_data, external_data);
+
+        let tampered_fetcher = TestFetcher(b"not the original content".to_vec());
+        let result = handle
+            .read_with_fetcher(&store, &tampered_fetcher, &["dir_1", "external"])
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationError::ExternalContentMismatch)
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_from_refs_reads_existing_blocks() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let mut handle = interesting_handle(Some(current_key)).await;
+
+        let mut refs = Vec::new();
+        for block in [b"imported ".to_vec(), b"from elsewhere".to_vec()] {
+            let cid = crate::utils::calculate_cid(&block);
+            refs.push((cid.clone(), block.len() as u64));
+            store.store(cid, block, false).await.unwrap();
+        }
+
+        let result = handle
+            .write_from_refs(&mut rng, Some(&store), &["imported"], refs.clone(), 22)
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationError::ContentSizeMismatch {
+                expected: 22,
+                actual: 23
+            })
+        ));
+
+        let missing_cid = crate::utils::calculate_cid(b"never stored");
+        let mut missing_refs = refs.clone();
+        missing_refs.push((missing_cid.clone(), 4));
+        let result = handle
+            .write_from_refs(&mut rng, Some(&store), &["imported"], missing_refs, 27)
+            .await;
+        assert!(matches!(result, Err(OperationError::BlockUnavailable(cid)) if cid == missing_cid));
+
+        handle
+            .write_from_refs(&mut rng, Some(&store), &["imported"], refs, 23)
+            .await
+            .unwrap();
+
+        let file_data = handle.read(&store, &["imported"]).await.unwrap();
+        assert_eq!(file_data, b"imported from elsewhere");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_from_refs_blocks_are_verified_when_read() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let mut handle = interesting_handle(Some(current_key)).await;
+
+        let block = b"imported".to_vec();
+        let cid = crate::utils::calculate_cid(&block);
+        store.store(cid.clone(), block, false).await.unwrap();
+
+        // The declared sizes can't be checked until the blocks are read
+        handle
+            .write_from_refs(&mut rng, None, &["short"], vec![(cid.clone(), 20)], 20)
+            .await
+            .unwrap();
+        assert!(matches!(
+            handle.read(&store, &["short"]).await,
+            Err(OperationError::ContentSizeMismatch {
+                expected: 20,
+                actual: 8
+            })
+        ));
+
+        handle
+            .write_from_refs(&mut rng, None, &["imported"], vec![(cid.clone(), 8)], 8)
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.read(&store, &["imported"]).await.unwrap(),
+            b"imported"
+        );
+
+        store.remove(cid.clone(), false).await.unwrap();
+        store
+            .store(cid.clone(), b"tampered".to_vec(), false)
+            .await
+            .unwrap();
+        assert!(matches!(
+            handle.read(&store, &["imported"]).await,
+            Err(OperationError::BlockCorrupted(corrupted)) if corrupted == cid
+        ));
+    }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_dir_from_dir_to_cwd_specify_name() {
+    async fn read_stream_stops_at_tampered_block() {
         let mut rng = crate::utils::crypto_rng();
-        let mut handle = interesting_handle(None).await;
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let chunk_size_policy = ChunkSizePolicy::fixed(4 * 1024).unwrap();
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(chunk_size_policy);
+
+        // Large enough to be split across several blocks of small chunks
+        let file_data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        let data_options = chunk_size_policy.data_options(file_data.len() as u64);
         handle
-            .mv(&mut rng, &["dir_1", "dir_2"], &["dir_2_new"])
+            .write(&mut rng, &mut store, &["large_file"], &file_data)
             .await
             .unwrap();
 
-        let cwd_ls = handle.ls(&[]).await.unwrap();
+        let mut chunks = handle.read_stream(&store, &["large_file"]).await.unwrap();
+        let mut streamed = Vec::new();
+        while let Some(chunk) = chunks.next().await {
+            streamed.extend_from_slice(&chunk.unwrap());
+        }
+        drop(chunks);
+        assert_eq!(streamed, file_data);
+
+        let content_references = match handle.readable_content(&["large_file"]).await.unwrap() {
+            ReadableContent::Encrypted {
+                content_references, ..
+            } => content_references,
+            _ => panic!("expected encrypted content"),
+        };
+        assert!(content_references.len() > 3);
+
+        // Flip a bit in the ciphertext of the second chunk of the third block, the remaining
+        // chunks, the chunk CID trailer, and the chunk's authentication tag follow it at the end
+        // of the block.
+        let tampered_cid = content_references[2].data_block_cid();
+        let mut block_data = store.retrieve(tampered_cid.clone()).await.unwrap();
+        let chunk_count = data_options.chunk_count() as usize;
+        let tamper_idx = block_data.len()
+            - chunk_count * Cid::size()
+            - (chunk_count - 2) * data_options.chunk_size() as usize
+            - AuthenticationTag::size()
+            - 100;
+        block_data[tamper_idx] ^= 0x01;
+        store.remove(tampered_cid.clone(), false).await.unwrap();
+        store
+            .store(tampered_cid.clone(), block_data, true)
+            .await
+            .unwrap();
+
+        let untampered_chunks = content_references[..2]
+            .iter()
+            .map(|content_ref| content_ref.chunks().len())
+            .sum::<usize>()
+            + 1;
+
+        let mut chunks = handle.read_stream(&store, &["large_file"]).await.unwrap();
+        for _ in 0..untampered_chunks {
+            assert!(chunks.next().await.unwrap().is_ok());
+        }
+
+        let result = chunks.next().await.unwrap();
+        assert!(matches!(
+            result,
+            Err(OperationError::BlockAuthenticationFailed { cid }) if cid == tampered_cid
+        ));
+        assert!(chunks.next().await.is_none());
+        drop(chunks);
+
+        let result = handle.read(&store, &["large_file"]).await;
+        assert!(matches!(
+            result,
+            Err(OperationError::BlockAuthenticationFailed { .. })
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn read_range_only_retrieves_intersecting_blocks() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(ChunkSizePolicy::fixed(4 * 1024).unwrap());
+
+        let file_data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        handle
+            .write(&mut rng, &mut store, &["large_file"], &file_data)
+            .await
+            .unwrap();
+
+        let read_range = |offset, len| handle.read_range(&store, &["large_file"], offset, len);
+        assert_eq!(read_range(0, Some(10)).await.unwrap(), file_data[..10]);
         assert_eq!(
-            cwd_ls
+            read_range(4_000, Some(10_000)).await.unwrap(),
+            file_data[4_000..14_000]
+        );
+        assert_eq!(
+            read_range(299_990, Some(100)).await.unwrap(),
+            file_data[299_990..]
+        );
+        assert_eq!(
+            read_range(150_000, None).await.unwrap(),
+            file_data[150_000..]
+        );
+        assert!(read_range(300_000, None).await.unwrap().is_empty());
+        assert!(matches!(
+            read_range(300_001, None).await,
+            Err(OperationError::OffsetOutOfRange {
+                offset: 300_001,
+                size: 300_000
+            })
+        ));
+
+        // Ranges outside of the first block are still readable once it's gone
+        let content_references = match handle.readable_content(&["large_file"]).await.unwrap() {
+            ReadableContent::Encrypted {
+                content_references, ..
+            } => content_references,
+            _ => panic!("expected encrypted content"),
+        };
+        let first_cid = content_references[0].data_block_cid();
+        store.remove(first_cid.clone(), false).await.unwrap();
+
+        let result = handle
+            .read_range(&store, &["large_file"], 299_000, None)
+            .await;
+        assert_eq!(result.unwrap(), file_data[299_000..]);
+
+        let result = handle.read_range(&store, &["large_file"], 0, Some(1)).await;
+        assert!(matches!(result, Err(OperationError::BlockUnavailable(cid)) if cid == first_cid));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn reader_produces_file_contents_lazily() {
+        use futures::AsyncReadExt;
+
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(ChunkSizePolicy::fixed(4 * 1024).unwrap());
+
+        let file_data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        handle
+            .write(&mut rng, &mut store, &["large_file"], &file_data)
+            .await
+            .unwrap();
+
+        let mut reader = handle.reader(&store, &["large_file"]).await.unwrap();
+        let mut first = [0u8; 100];
+        reader.read_exact(&mut first).await.unwrap();
+        assert_eq!(first, file_data[..100]);
+
+        let mut remaining = Vec::new();
+        reader.read_to_end(&mut remaining).await.unwrap();
+        assert_eq!(remaining, file_data[100..]);
+        drop(reader);
+
+        // Blocks are only retrieved once the reader reaches them
+        let content_references = match handle.readable_content(&["large_file"]).await.unwrap() {
+            ReadableContent::Encrypted {
+                content_references, ..
+            } => content_references,
+            _ => panic!("expected encrypted content"),
+        };
+        let last_cid = content_references.last().unwrap().data_block_cid();
+        store.remove(last_cid, false).await.unwrap();
+
+        let mut reader = handle.reader(&store, &["large_file"]).await.unwrap();
+        reader.read_exact(&mut first).await.unwrap();
+        assert_eq!(first, file_data[..100]);
+
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        let inner = err
+            .into_inner()
+            .unwrap()
+            .downcast::<OperationError>()
+            .unwrap();
+        assert!(matches!(*inner, OperationError::BlockUnavailable(_)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_append_keeps_existing_blocks() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(ChunkSizePolicy::fixed(4 * 1024).unwrap());
+
+        let entry_size = |entries: Vec<DirectoryEntry>, name: &str| {
+            let name = NodeName::try_from(name).unwrap();
+            entries.iter().find(|e| e.name() == name).unwrap().size()
+        };
+        // Appending to a missing file creates it
+        let path = ["dir_1", "log"];
+        let first: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        handle
+            .write_append(&mut rng, &mut store, &path, &first)
+            .await
+            .unwrap();
+        assert_eq!(handle.read(&store, &path).await.unwrap(), first);
+
+        let block_cids = |content: ReadableContent| match content {
+            ReadableContent::Encrypted {
+                content_references, ..
+            } => content_references
                 .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("dir_2_new").unwrap())
-                .count(),
-            1
+                .map(|reference| reference.data_block_cid())
+                .collect::<Vec<_>>(),
+            _ => panic!("expected encrypted content"),
+        };
+        let existing_cids = block_cids(handle.readable_content(&path).await.unwrap());
+        let initial_file_size = entry_size(handle.ls(&["dir_1"]).await.unwrap(), "log");
+        let initial_dir_size = entry_size(handle.ls(&[]).await.unwrap(), "dir_1");
+
+        let second: Vec<u8> = (0..5_000u32).map(|i| (i % 13) as u8).collect();
+        handle
+            .write_append(&mut rng, &mut store, &path, &second)
+            .await
+            .unwrap();
+        handle
+            .write_append(&mut rng, &mut store, &path, b"tail")
+            .await
+            .unwrap();
+
+        let mut expected = first.clone();
+        expected.extend_from_slice(&second);
+        expected.extend_from_slice(b"tail");
+        assert_eq!(handle.read(&store, &path).await.unwrap(), expected);
+
+        let block_cids = block_cids(handle.readable_content(&path).await.unwrap());
+        assert!(block_cids.starts_with(&existing_cids));
+        assert!(block_cids.len() > existing_cids.len());
+
+        // Sizes grow by the appended data, including the size reported for the parent
+        let appended_size = (expected.len() - first.len()) as u64;
+        assert_eq!(
+            entry_size(handle.ls(&["dir_1"]).await.unwrap(), "log"),
+            initial_file_size + appended_size
+        );
+        assert_eq!(
+            entry_size(handle.ls(&[]).await.unwrap(), "dir_1"),
+            initial_dir_size + appended_size
         );
+
+        // Ranges crossing the boundaries between the appended pieces are still correct
+        let result = handle
+            .read_range(&store, &path, 9_000, Some(2_000))
+            .await
+            .unwrap();
+        assert_eq!(result, expected[9_000..11_000]);
+        let result = handle
+            .read_range(&store, &path, 14_990, None)
+            .await
+            .unwrap();
+        assert_eq!(result, expected[14_990..]);
+
+        // Directories can't be appended to
+        let result = handle
+            .write_append(&mut rng, &mut store, &["dir_1"], b"data")
+            .await;
+        assert!(matches!(result, Err(OperationError::Exists(_))));
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_dir_from_dir_to_dir_specify_name() {
+    async fn write_append_repacks_a_partial_final_chunk() {
         let mut rng = crate::utils::crypto_rng();
-        let mut handle = interesting_handle(None).await;
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let policy = ChunkSizePolicy::fixed(4 * 1024).unwrap();
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(policy);
+
+        let chunk_size = policy.data_options(1).chunk_data_size();
+        let first: Vec<u8> = (0..chunk_size - 1).map(|i| (i % 251) as u8).collect();
+        let second: Vec<u8> = (0..chunk_size).map(|i| (i % 13) as u8).collect();
+
+        let path = ["log"];
         handle
-            .mv(
+            .write(&mut rng, &mut store, &path, &first)
+            .await
+            .unwrap();
+        handle
+            .write_append(&mut rng, &mut store, &path, &second)
+            .await
+            .unwrap();
+
+        let mut expected = first.clone();
+        expected.extend_from_slice(&second);
+        assert_eq!(handle.read(&store, &path).await.unwrap(), expected);
+
+        // The byte right after the first chunk's capacity comes from the appended data
+        let offset = chunk_size as u64;
+        let result = handle
+            .read_range(&store, &path, offset, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(result, expected[chunk_size..chunk_size + 1]);
+
+        for offset in [0, chunk_size - 2, chunk_size - 1, chunk_size + 1] {
+            let result = handle
+                .read_range(&store, &path, offset as u64, Some(3))
+                .await
+                .unwrap();
+            assert_eq!(result, expected[offset..(offset + 3).min(expected.len())]);
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_append_rejects_content_past_the_reference_limit() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut store = MemoryDataStore::default();
+        let policy = ChunkSizePolicy::fixed(4 * 1024).unwrap();
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(policy);
+
+        // Exactly fills a single block, leaving no partial chunk to write out again
+        let block_size = 2 * policy.data_options(1).chunk_data_size();
+        let block_data: Vec<u8> = (0..block_size).map(|i| (i % 251) as u8).collect();
+        let path = ["log"];
+        handle
+            .write(&mut rng, &mut store, &path, &block_data)
+            .await
+            .unwrap();
+
+        // Fill every reference slot by repeating the block rather than appending 255 times
+        let full_data = block_data.repeat(u8::MAX as usize);
+        let mut inner_write = handle.inner.write().await;
+        let node_id = match inner_write.walk_path(handle.cwd_id, &path).unwrap() {
+            WalkState::FoundNode { node_id } => node_id,
+            _ => panic!("expected the file to exist"),
+        };
+        let node = inner_write.by_id_mut(node_id).await.unwrap();
+        if let NodeData::File { content, .. } = node.data_mut().await {
+            let reference = content.content_references().unwrap()[0].clone();
+            *content = FileContent::encrypted(
+                content.data_key().unwrap().clone(),
+                crate::utils::calculate_cid(&full_data),
+                full_data.len() as u64,
+                vec![reference; u8::MAX as usize],
+            );
+        }
+        inner_write.clean_drive().await.unwrap();
+        drop(inner_write);
+        assert_eq!(handle.read(&store, &path).await.unwrap(), full_data);
+
+        let result = handle
+            .write_append(&mut rng, &mut store, &path, b"more")
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationError::FileContentError(
+                FileContentError::TooManyReferences(256)
+            ))
+        ));
+        assert_eq!(handle.read(&store, &path).await.unwrap(), full_data);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn write_if_unchanged_rejects_stale_cids() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+
+        handle
+            .write(&mut rng, &mut store, &["notes.txt"], b"first")
+            .await
+            .unwrap();
+        let (_, _, first_cid, _) = handle.ls_detailed(&["notes.txt"]).await.unwrap()[0].clone();
+
+        handle
+            .write_if_unchanged(
                 &mut rng,
-                &["dir_1", "dir_2", "dir_3"],
-                &["dir_1", "dir_3_new"],
+                &mut store,
+                &["notes.txt"],
+                b"second",
+                first_cid.clone(),
             )
             .await
             .unwrap();
+        let (_, _, second_cid, _) = handle.ls_detailed(&["notes.txt"]).await.unwrap()[0].clone();
 
-        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
+        let result = handle
+            .write_if_unchanged(&mut rng, &mut store, &["notes.txt"], b"third", first_cid)
+            .await;
+        match result {
+            Err(OperationError::Conflict { current }) => assert_eq!(current, second_cid),
+            other => panic!("expected a conflict, got {other:?}"),
+        }
         assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("dir_3_new").unwrap())
-                .count(),
-            1
+            handle.read(&store, &["notes.txt"]).await.unwrap(),
+            b"second"
         );
+
+        let result = handle
+            .write_if_unchanged(&mut rng, &mut store, &["missing.txt"], b"data", second_cid)
+            .await;
+        assert!(matches!(result, Err(OperationError::PathNotFound)));
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_file_from_dir_to_cwd_specify_name() {
+    async fn content_hash_rolls_up_subtree_changes() {
         let mut rng = crate::utils::crypto_rng();
-        let mut handle = interesting_handle(None).await;
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+
+        // The fixture skips propagating child CIDs, every public mutation does so
+        handle.inner.write().await.clean_drive().await.unwrap();
+
+        let root_before = handle.content_hash(&[]).await.unwrap();
+        let dir_1_before = handle.content_hash(&["dir_1"]).await.unwrap();
+        let file_1_before = handle.content_hash(&["file_1"]).await.unwrap();
+
+        // The hash of a directory is the CID its parent records for it
+        let recorded_cid = handle
+            .ls_detailed(&[])
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(name, ..)| name == &NodeName::Named("dir_1".into()))
+            .map(|(_, _, cid, _)| cid)
+            .unwrap();
+        assert_eq!(dir_1_before, recorded_cid);
+
         handle
-            .mv(
+            .write(
                 &mut rng,
-                &["dir_1", "dir_2", "dir_3", "file_3"],
-                &["file_3_new"],
+                &mut store,
+                &["dir_1", "dir_2", "new.txt"],
+                b"content",
             )
             .await
             .unwrap();
 
-        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_ne!(handle.content_hash(&[]).await.unwrap(), root_before);
+        assert_ne!(handle.content_hash(&["dir_1"]).await.unwrap(), dir_1_before);
         assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("file_3_new").unwrap())
-                .count(),
-            1
+            handle.content_hash(&["file_1"]).await.unwrap(),
+            file_1_before
+        );
+
+        let result = handle.content_hash(&["missing"]).await;
+        assert!(matches!(result, Err(OperationError::PathNotFound)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn collision_policies_resolve_existing_names() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+        let name = |name: &str| NodeName::try_from(name).unwrap();
+
+        handle
+            .write(&mut rng, &mut store, &["notes.txt"], b"first")
+            .await
+            .unwrap();
+
+        let result = handle
+            .write_with_policy(
+                &mut rng,
+                &mut store,
+                &["notes.txt"],
+                b"second",
+                CollisionPolicy::Error,
+            )
+            .await;
+        assert!(matches!(result, Err(OperationError::Exists(_))));
+
+        for expected in ["notes.txt (2)", "notes.txt (3)"] {
+            let written = handle
+                .write_with_policy(
+                    &mut rng,
+                    &mut store,
+                    &["notes.txt"],
+                    b"copy",
+                    CollisionPolicy::Rename,
+                )
+                .await
+                .unwrap();
+            assert_eq!(written, name(expected));
+        }
+        assert_eq!(handle.read(&store, &["notes.txt"]).await.unwrap(), b"first");
+
+        let written = handle
+            .write_with_policy(
+                &mut rng,
+                &mut store,
+                &["notes.txt"],
+                b"second",
+                CollisionPolicy::Overwrite,
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, name("notes.txt"));
+        assert_eq!(
+            handle.read(&store, &["notes.txt"]).await.unwrap(),
+            b"second"
         );
+
+        // Files never replace directories
+        let result = handle
+            .write_with_policy(
+                &mut rng,
+                &mut store,
+                &["dir_1"],
+                b"data",
+                CollisionPolicy::Overwrite,
+            )
+            .await;
+        assert!(matches!(result, Err(OperationError::Exists(_))));
+
+        let result = handle
+            .mkdir_with_policy(&mut rng, &["dir_1"], false, CollisionPolicy::Error)
+            .await;
+        assert!(matches!(result, Err(OperationError::Exists(_))));
+
+        let created = handle
+            .mkdir_with_policy(&mut rng, &["dir_1"], false, CollisionPolicy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(created, name("dir_1"));
+
+        let created = handle
+            .mkdir_with_policy(&mut rng, &["dir_1"], false, CollisionPolicy::Rename)
+            .await
+            .unwrap();
+        assert_eq!(created, name("dir_1 (2)"));
+        assert!(handle.ls(&["dir_1 (2)"]).await.unwrap().is_empty());
+
+        // Names at the length limit are shortened to fit the suffix
+        let long_name = "a".repeat(255);
+        handle.mkdir(&mut rng, &[&long_name], false).await.unwrap();
+        let created = handle
+            .mkdir_with_policy(&mut rng, &[&long_name], false, CollisionPolicy::Rename)
+            .await
+            .unwrap();
+        assert_eq!(created, name(&format!("{} (2)", "a".repeat(251))));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn set_executable_updates_permissions_and_cid() {
+        let mut rng = crate::utils::crypto_rng();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = MemoryDataStore::default();
+
+        handle
+            .write(&mut rng, &mut store, &["run.sh"], b"#!/bin/sh")
+            .await
+            .unwrap();
+        handle
+            .chmod(&["run.sh"], Permissions::default().with_immutable(true))
+            .await
+            .unwrap();
+        let (_, original_cid) = drive_snapshot(&handle).await;
+
+        handle.set_executable(&["run.sh"], true).await.unwrap();
+
+        let permissions = handle.permissions(&["run.sh"]).await.unwrap();
+        assert!(permissions.executable());
+        assert!(permissions.immutable());
+        assert!(!permissions.owner_write_only());
+
+        let (_, updated_cid) = drive_snapshot(&handle).await;
+        assert_ne!(original_cid, updated_cid);
+
+        handle.set_executable(&["run.sh"], false).await.unwrap();
+        assert!(!handle.permissions(&["run.sh"]).await.unwrap().executable());
+
+        let result = handle.set_executable(&["missing.sh"], true).await;
+        assert!(matches!(result, Err(OperationError::PathNotFound)));
+    }
+
+    /// Completes on the second poll, standing in for any data store I/O that isn't immediately
+    /// ready.
+    struct PendOnce(bool);
+
+    impl std::future::Future for PendOnce {
+        type Output = ();
+
+        fn poll(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<()> {
+            if self.0 {
+                return std::task::Poll::Ready(());
+            }
+
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    }
+
+    /// Pends once on every block stored, counting the blocks that made it into the store.
+    #[derive(Default)]
+    struct SlowDataStore(MemoryDataStore, usize);
+
+    #[async_trait::async_trait(?Send)]
+    impl DataStore for SlowDataStore {
+        async fn contains_cid(&self, cid: Cid) -> Result<bool, DataStoreError> {
+            self.0.contains_cid(cid).await
+        }
+
+        async fn remove(&mut self, cid: Cid, recursive: bool) -> Result<(), DataStoreError> {
+            self.0.remove(cid, recursive).await
+        }
+
+        async fn retrieve(&self, cid: Cid) -> Result<Vec<u8>, DataStoreError> {
+            self.0.retrieve(cid).await
+        }
+
+        async fn store(
+            &mut self,
+            cid: Cid,
+            data: Vec<u8>,
+            immediate: bool,
+        ) -> Result<(), DataStoreError> {
+            PendOnce(false).await;
+            self.0.store(cid, data, immediate).await?;
+            self.1 += 1;
+            Ok(())
+        }
+    }
+
+    async fn drive_snapshot(handle: &DirectoryHandle) -> (usize, Cid) {
+        let inner_read = handle.inner.read().await;
+        let node_count = inner_read.node_iter().count();
+        let root_cid = inner_read.root_node().unwrap().cid().await.unwrap();
+        (node_count, root_cid)
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_file_from_dir_to_dir_specify_name() {
+    async fn cancelled_mkdir_leaves_drive_unchanged() {
         let mut rng = crate::utils::crypto_rng();
         let mut handle = interesting_handle(None).await;
+        let before = drive_snapshot(&handle).await;
+
+        // A concurrent reader keeps the mkdir waiting on the drive lock when it gets dropped
+        let shared_inner = handle.inner.clone();
+        let reader = shared_inner.read().await;
+        let mut mkdir = Box::pin(handle.mkdir(&mut rng, &["new_1", "new_2", "new_3"], true));
+        assert!(futures::poll!(mkdir.as_mut()).is_pending());
+        drop(mkdir);
+        drop(reader);
+
+        assert_eq!(drive_snapshot(&handle).await, before);
+        assert!(matches!(
+            handle.ls(&["new_1"]).await,
+            Err(OperationError::NotTraversable)
+        ));
+
         handle
-            .mv(
-                &mut rng,
-                &["dir_1", "dir_2", "dir_3", "file_3"],
-                &["dir_1", "file_3_new"],
-            )
+            .mkdir(&mut rng, &["new_1", "new_2", "new_3"], true)
             .await
             .unwrap();
-
-        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
-        assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("file_3_new").unwrap())
-                .count(),
-            1
-        );
+        assert_eq!(drive_snapshot(&handle).await.0, before.0 + 3);
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_dir_from_dir_to_cwd_no_name() {
+    async fn cancelled_write_leaves_drive_unchanged() {
         let mut rng = crate::utils::crypto_rng();
-        let mut handle = interesting_handle(None).await;
-        handle.mv(&mut rng, &["dir_1", "dir_2"], &[]).await.unwrap();
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key)).await;
+        let mut store = SlowDataStore::default();
+        let before = drive_snapshot(&handle).await;
 
-        let cwd_ls = handle.ls(&[]).await.unwrap();
-        assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("dir_2").unwrap())
-                .count(),
-            1
-        );
-    }
+        let mut write = Box::pin(handle.write(&mut rng, &mut store, &["new_file"], b"content"));
+        assert!(futures::poll!(write.as_mut()).is_pending());
+        drop(write);
+
+        assert_eq!(drive_snapshot(&handle).await, before);
 
-    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
-    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_dir_from_dir_to_dir_no_name() {
-        let mut rng = crate::utils::crypto_rng();
-        let mut handle = interesting_handle(None).await;
         handle
-            .mv(&mut rng, &["dir_1", "dir_2", "dir_3"], &["dir_1"])
+            .write(&mut rng, &mut store, &["new_file"], b"content")
             .await
             .unwrap();
-
-        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
         assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("dir_3").unwrap())
-                .count(),
-            1
+            handle.read(&store, &["new_file"]).await.unwrap(),
+            b"content"
         );
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_file_from_dir_to_cwd_no_name() {
+    async fn write_cancelled_mid_transfer_leaves_drive_unchanged() {
         let mut rng = crate::utils::crypto_rng();
-        let mut handle = interesting_handle(None).await;
+        let current_key = SigningKey::generate(&mut rng);
+        let mut handle = interesting_handle(Some(current_key))
+            .await
+            .with_chunk_size_policy(ChunkSizePolicy::fixed(4 * 1024).unwrap());
+        let mut store = SlowDataStore::default();
+        let before = drive_snapshot(&handle).await;
+
+        // Large enough to be split across several blocks of small chunks, each poll after the
+        // first completes storing one block and pends on the next
+        let file_data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        let mut write = Box::pin(handle.write(&mut rng, &mut store, &["new_file"], &file_data));
+        for _ in 0..3 {
+            assert!(futures::poll!(write.as_mut()).is_pending());
+        }
+        drop(write);
+
+        assert_eq!(store.1, 2);
+        assert_eq!(drive_snapshot(&handle).await, before);
+
         handle
-            .mv(&mut rng, &["dir_1", "dir_2", "dir_3", "file_3"], &[])
+            .write(&mut rng, &mut store, &["new_file"], &file_data)
             .await
             .unwrap();
-
-        let cwd_ls = handle.ls(&[]).await.unwrap();
-        assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("file_3").unwrap())
-                .count(),
-            1
-        );
+        assert_eq!(handle.read(&store, &["new_file"]).await.unwrap(), file_data);
     }
 
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn mv_file_from_dir_to_dir_no_name() {
+    async fn mkdir_p_returns_handle_on_leaf() {
         let mut rng = crate::utils::crypto_rng();
         let mut handle = interesting_handle(None).await;
-        handle
-            .mv(&mut rng, &["dir_1", "dir_2", "dir_3", "file_3"], &["dir_1"])
+
+        let mut leaf = handle
+            .mkdir_p(&mut rng, &["dir_1", "new_1", "new_2"])
             .await
             .unwrap();
+        assert!(leaf.ls(&[]).await.unwrap().is_empty());
 
-        let cwd_ls = handle.ls(&["dir_1"]).await.unwrap();
-        assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from("file_3").unwrap())
-                .count(),
-            1
-        );
-    }
+        leaf.mkdir(&mut rng, &["inside"], false).await.unwrap();
+        let listing = handle.ls(&["dir_1", "new_1", "new_2"]).await.unwrap();
+        assert_eq!(listing.len(), 1);
+        assert_eq!(listing[0].name(), NodeName::try_from("inside").unwrap());
 
-    async fn interesting_handle(current_key: Option<SigningKey>) -> DirectoryHandle {
-        //           -----file_1
-        //         /
-        // root ---------file_2
-        //         \
-        //          --------- dir_1 ----- dir_2 ---- dir_3 ---- file_3
-        //                         \
-        //                           --- file_4
-        //                            \
-        //                              ----file_5
-        let mut rng = crate::utils::crypto_rng();
-        let inner = build_interesting_inner(current_key.clone()).await;
-        let root_id = inner.root_node().unwrap().id();
-        let inner = Arc::new(RwLock::new(inner));
-        DirectoryHandle {
-            current_key: Arc::new(current_key.unwrap_or_else(|| SigningKey::generate(&mut rng))),
-            inner,
-            cwd_id: root_id,
-        }
+        // Existing directories are reused rather than replaced
+        let again = handle
+            .mkdir_p(&mut rng, &["dir_1", "new_1", "new_2"])
+            .await
+            .unwrap();
+        assert_eq!(again.cwd_id, leaf.cwd_id);
     }
 
-    #[cfg(feature = "mime-type")]
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn sniff_html_mime_type() {
+    async fn content_round_trips_with_any_chunk_size() {
         let mut rng = crate::utils::crypto_rng();
         let current_key = SigningKey::generate(&mut rng);
-        let mut handle = interesting_handle(Some(current_key)).await;
         let mut store = MemoryDataStore::default();
 
-        let test_cases = vec![
-            (b"<html><head><title>Test File</title></head><body><h1>Hello World!</h1></body></html>".to_vec(), "test.html"),
-            (b"<HTML><HEAD><TITLE>Test File</TITLE></HEAD><BODY><H1>Hello World!</H1></BODY></HTML>".to_vec(), "TEST.HTML"),
-            (b"<h1>Heading</h1><p>Paragraph</p>".to_vec(), "file.htm"),
-            (b"<div><span>Some text</span></div>".to_vec(), "page.php"),
-            (
-                b"<!docTYPE html><html><body>Content</body></html>".to_vec(),
-                "invalid_file_name",
-            ),
+        let policies = [
+            ChunkSizePolicy::default(),
+            ChunkSizePolicy::fixed(4 * 1024).unwrap(),
+            ChunkSizePolicy::new(8 * 1024, 64 * 1024).unwrap(),
         ];
-        for (data, file_name) in test_cases {
-            handle
-                .write(&mut rng, &mut store, &[file_name], &data)
-                .await
-                .unwrap();
-
-            let cwd_ls = handle.ls(&[]).await.unwrap();
-            assert_eq!(
-                cwd_ls
-                    .iter()
-                    .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-                    .count(),
-                1
-            );
 
-            let file_entry = cwd_ls
-                .iter()
-                .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-                .unwrap();
+        for (idx, policy) in policies.into_iter().enumerate() {
+            let mut handle = interesting_handle(Some(current_key.clone()))
+                .await
+                .with_chunk_size_policy(policy);
 
-            assert_eq!(file_entry.kind(), NodeKind::File);
+            for size in [1, 4 * 1024, 300 * 1024 + 7] {
+                let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+                let name = format!("file_{idx}_{size}");
 
-            let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
-            assert_eq!(file_data.as_slice(), data);
+                handle
+                    .write(&mut rng, &mut store, &[&name], &data)
+                    .await
+                    .unwrap();
 
-            let mime_type = file_entry.mime_type().unwrap();
-            assert_eq!(mime_type, "text/html");
+                // Handles derived from this one keep the policy
+                let reader = handle.cd(&[]).await.unwrap();
+                assert_eq!(reader.chunk_size_policy, policy);
+                assert_eq!(reader.read(&store, &[&name]).await.unwrap(), data);
+            }
         }
     }
 
-    #[cfg(feature = "mime-type")]
-    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
-    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn sniff_mp3_file_mime_type() {
-        let mut rng = crate::utils::crypto_rng();
-        let current_key = SigningKey::generate(&mut rng);
-        let mut handle = interesting_handle(Some(current_key)).await;
-        let mut store = MemoryDataStore::default();
-        let mp3_test_case: &[u8] = &[
-            0x49, 0x44, 0x33, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x22, 0x54, 0x53, 0x53, 0x45,
-            0x00, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x03, 0x4c, 0x61, 0x76, 0x66, 0x36, 0x30, 0x2e,
-            0x33, 0x2e, 0x31, 0x30, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0xff, 0xfb, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ];
-        let file_name = "the_audio.mp4";
-        handle
-            .write(&mut rng, &mut store, &[file_name], mp3_test_case)
-            .await
-            .unwrap();
-
-        let cwd_ls = handle.ls(&[]).await.unwrap();
-        assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-                .count(),
-            1
-        );
-
-        let file_entry = cwd_ls
-            .iter()
-            .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-            .unwrap();
+    #[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn import_resumes_from_manifest() {
+        use elliptic_curve::rand_core::RngCore;
+        use std::io::Write;
 
-        assert_eq!(file_entry.kind(), NodeKind::File);
+        use crate::filesystem::drive::directory_import::ImportManifest;
 
-        let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
-        assert_eq!(file_data.as_slice(), mp3_test_case);
+        let mut rng = crate::utils::crypto_rng();
+        let scratch_dir =
+            std::env::temp_dir().join(format!("banyanfs-import-{:016x}", rng.next_u64()));
+        let source = scratch_dir.join("source");
+        let manifest_path = scratch_dir.join("import.manifest");
 
-        let mime_type = file_entry.mime_type().unwrap();
-        assert_eq!(mime_type, "audio/mpeg");
-    }
+        std::fs::create_dir_all(source.join("photos/empty")).unwrap();
+        std::fs::write(source.join("notes.txt"), b"notes").unwrap();
+        std::fs::write(source.join("photos/cat.jpg"), b"meow").unwrap();
 
-    #[cfg(feature = "mime-type")]
-    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
-    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn sniff_mp4_file_mime_type() {
-        let mut rng = crate::utils::crypto_rng();
         let current_key = SigningKey::generate(&mut rng);
         let mut handle = interesting_handle(Some(current_key)).await;
         let mut store = MemoryDataStore::default();
-        let mp4_test_case: &[u8] = &[
-            0x00, 0x00, 0x00, 0x1c, 0x66, 0x74, 0x79, 0x70, 0x69, 0x73, 0x6f, 0x6d, 0x00, 0x00,
-            0x02, 0x00, 0x69, 0x73, 0x6f, 0x6d, 0x69, 0x73, 0x6f, 0x32, 0x6d, 0x70, 0x34, 0x31,
-            0x00, 0x00, 0x00, 0x08,
-        ];
-        let file_name = "the_audio.mp3";
-        handle
-            .write(&mut rng, &mut store, &[file_name], mp4_test_case)
+
+        let summary = handle
+            .import_directory(&mut rng, &mut store, &source, &manifest_path)
             .await
             .unwrap();
-
-        let cwd_ls = handle.ls(&[]).await.unwrap();
+        assert_eq!((summary.imported(), summary.skipped()), (2, 0));
         assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-                .count(),
-            1
+            handle.read(&store, &["photos", "cat.jpg"]).await.unwrap(),
+            b"meow"
         );
+        assert!(handle.cd(&["photos", "empty"]).await.is_ok());
 
-        let file_entry = cwd_ls
-            .iter()
-            .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-            .unwrap();
-
-        assert_eq!(file_entry.kind(), NodeKind::File);
+        let manifest = ImportManifest::load(&manifest_path).unwrap();
+        let cat_pid = manifest.permanent_id("photos/cat.jpg").unwrap();
 
-        let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
-        assert_eq!(file_data.as_slice(), mp4_test_case);
+        // Starting over would lose track of the earlier progress
+        let restarted = handle
+            .import_directory(&mut rng, &mut store, &source, &manifest_path)
+            .await;
+        assert!(matches!(restarted, Err(ImportError::ManifestExists(_))));
 
-        let mime_type = file_entry.mime_type().unwrap();
-        assert_eq!(mime_type, "video/mp4");
-    }
+        // Simulate an interruption part way through recording a new file
+        std::fs::write(source.join("photos/dog.jpg"), b"woof").unwrap();
+        let mut manifest_file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&manifest_path)
+            .unwrap();
+        manifest_file.write_all(&[0xff; 5]).unwrap();
+        drop(manifest_file);
 
-    #[cfg(feature = "mime-type")]
-    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
-    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
-    async fn sniff_webm_file_mime_type() {
-        let mut rng = crate::utils::crypto_rng();
-        let current_key = SigningKey::generate(&mut rng);
-        let mut handle = interesting_handle(Some(current_key)).await;
-        let mut store = MemoryDataStore::default();
-        let webm_test_case: &[u8] = &[
-            0x1a, 0x45, 0xdf, 0xa3, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x42, 0x86,
-            0x81, 0x01, 0x42, 0xf7, 0x81, 0x01, 0x42, 0xf2, 0x81, 0x04, 0x42, 0xf3, 0x81, 0x08,
-            0x42, 0x82, 0x84, 0x77, 0x65, 0x62, 0x6d, 0x42, 0x87, 0x81, 0x02, 0x42, 0x85, 0x81,
-            0x02, 0x18, 0x53, 0x80, 0x67, 0x01, 0x00, 0x00, 0x00, 0x00, 0x0d, 0xc0, 0x0a, 0x11,
-            0x4d, 0x9b, 0x74, 0x40, 0x3c, 0x4d, 0xbb, 0x8b, 0x53, 0xab, 0x84, 0x15, 0x49, 0xa9,
-            0x66, 0x53, 0xac, 0x81, 0xe5, 0x4d, 0xbb, 0x8c, 0x53, 0xab,
-        ];
-        let file_name = "the_audio.mp4";
-        handle
-            .write(&mut rng, &mut store, &[file_name], webm_test_case)
+        let summary = handle
+            .resume_import(&mut rng, &mut store, &source, &manifest_path)
             .await
             .unwrap();
+        assert_eq!((summary.imported(), summary.skipped()), (1, 2));
 
-        let cwd_ls = handle.ls(&[]).await.unwrap();
+        let manifest = ImportManifest::load(&manifest_path).unwrap();
+        assert_eq!(manifest.len(), 3);
+        assert_eq!(manifest.permanent_id("photos/cat.jpg"), Some(cat_pid));
         assert_eq!(
-            cwd_ls
-                .iter()
-                .filter(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
-                .count(),
-            1
+            handle.read(&store, &["photos", "dog.jpg"]).await.unwrap(),
+            b"woof"
         );
 
-        let file_entry = cwd_ls
-            .iter()
-            .find(|entry| entry.name() == NodeName::try_from(file_name).unwrap())
+        // Files changed in the drive since they were imported no longer match the recorded CID
+        handle
+            .write(&mut rng, &mut store, &["notes.txt"], b"edited")
+            .await
             .unwrap();
+        let summary = handle
+            .resume_import(&mut rng, &mut store, &source, &manifest_path)
+            .await
+            .unwrap();
+        assert_eq!((summary.imported(), summary.skipped()), (1, 2));
+        assert_eq!(handle.read(&store, &["notes.txt"]).await.unwrap(), b"notes");
+
+        // A drive that never received the files imports them again
+        let fresh_key = SigningKey::generate(&mut rng);
+        let mut fresh_handle = interesting_handle(Some(fresh_key)).await;
+        let summary = fresh_handle
+            .resume_import(&mut rng, &mut store, &source, &manifest_path)
+            .await
+            .unwrap();
+        assert_eq!((summary.imported(), summary.skipped()), (3, 0));
 
-        assert_eq!(file_entry.kind(), NodeKind::File);
-
-        let file_data = handle.read(&mut store, &[file_name]).await.unwrap();
-        assert_eq!(file_data.as_slice(), webm_test_case);
-
-        let mime_type = file_entry.mime_type().unwrap();
-        assert_eq!(mime_type, "video/webm");
+        std::fs::remove_dir_all(scratch_dir).unwrap();
     }
 }
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use winnow::binary::le_u16;
+use winnow::token::{literal, take};
+use winnow::Parser;
+
+use crate::codec::{Cid, ParserResult, PermanentId, Stream};
+use crate::filesystem::drive::OperationError;
+
+const IMPORT_MANIFEST_MAGIC: &[u8] = b"BFSIMPORT1";
+
+/// The progress of a directory import started with
+/// [`crate::filesystem::DirectoryHandle::import_directory`]. The manifest records each source file
+/// that has been completely written to the drive, keyed by its path relative to the imported
+/// directory (always separated by `/`), along with the [`PermanentId`] of the file it produced and
+/// the CID that file had once it was written.
+///
+/// Entries are appended and flushed to disk as each file completes. An import interrupted part way
+/// through a write leaves at most a partial final entry behind, which is ignored and discarded when
+/// the import is resumed.
+#[derive(Clone, Debug, Default)]
+pub struct ImportManifest {
+    completed: HashMap<String, (PermanentId, Cid)>,
+    valid_length: u64,
+}
+
+impl ImportManifest {
+    /// The CID the file produced from the source file at the provided relative path had when it
+    /// was written, if it has been completed.
+    pub fn cid(&self, source_path: &str) -> Option<&Cid> {
+        self.completed.get(source_path).map(|(_, cid)| cid)
+    }
+
+    /// Whether no files have been completed yet.
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+    }
+
+    /// The number of source files that have been completed.
+    pub fn len(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Reads the manifest at the provided location without modifying it.
+    pub fn load(manifest_path: &Path) -> Result<Self, ImportError> {
+        let data = std::fs::read(manifest_path)?;
+
+        let header: ParserResult<_> = literal(IMPORT_MANIFEST_MAGIC).parse_peek(Stream::new(&data));
+        let (mut input, _magic) = header.map_err(|_| ImportError::InvalidManifest)?;
+
+        let mut manifest = Self {
+            completed: HashMap::new(),
+            valid_length: IMPORT_MANIFEST_MAGIC.len() as u64,
+        };
+
+        // Anything that doesn't form a complete entry is the remains of an interrupted write
+        while let Ok((remaining, (source_path, permanent_id, cid))) = parse_entry(input) {
+            manifest.valid_length += (input.len() - remaining.len()) as u64;
+            manifest.completed.insert(source_path, (permanent_id, cid));
+            input = remaining;
+        }
+
+        Ok(manifest)
+    }
+
+    /// The ID of the file produced from the source file at the provided relative path, if it has
+    /// been completed.
+    pub fn permanent_id(&self, source_path: &str) -> Option<PermanentId> {
+        self.completed
+            .get(source_path)
+            .map(|(permanent_id, _)| *permanent_id)
+    }
+}
+
+fn parse_entry(input: Stream) -> ParserResult<(String, PermanentId, Cid)> {
+    let (input, permanent_id) = PermanentId::parse(input)?;
+    let (input, cid) = Cid::parse(input)?;
+    let (input, path_length) = le_u16.parse_peek(input)?;
+    let (input, path_bytes) = take(path_length).parse_peek(input)?;
+
+    let source_path = String::from_utf8(path_bytes.to_vec()).map_err(|_| {
+        winnow::error::ErrMode::Cut(winnow::error::ParserError::from_error_kind(
+            &input,
+            winnow::error::ErrorKind::Verify,
+        ))
+    })?;
+
+    Ok((input, (source_path, permanent_id, cid)))
+}
+
+/// An [`ImportManifest`] open for recording further progress.
+pub(crate) struct ImportLog {
+    file: File,
+    manifest: ImportManifest,
+}
+
+impl ImportLog {
+    /// Starts a new manifest, an existing one is never replaced as it may hold the progress of an
+    /// import that still needs to be resumed.
+    pub(crate) fn create(manifest_path: &Path) -> Result<Self, ImportError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(manifest_path)
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::AlreadyExists => {
+                    ImportError::ManifestExists(manifest_path.to_path_buf())
+                }
+                _ => ImportError::Io(err),
+            })?;
+
+        file.write_all(IMPORT_MANIFEST_MAGIC)?;
+        file.sync_all()?;
+
+        let manifest = ImportManifest {
+            completed: HashMap::new(),
+            valid_length: IMPORT_MANIFEST_MAGIC.len() as u64,
+        };
+
+        Ok(Self { file, manifest })
+    }
+
+    pub(crate) fn manifest(&self) -> &ImportManifest {
+        &self.manifest
+    }
+
+    /// Opens an existing manifest to continue recording progress after the last complete entry.
+    pub(crate) fn resume(manifest_path: &Path) -> Result<Self, ImportError> {
+        let manifest = ImportManifest::load(manifest_path)?;
+
+        let file = OpenOptions::new().append(true).open(manifest_path)?;
+        file.set_len(manifest.valid_length)?;
+
+        Ok(Self { file, manifest })
+    }
+
+    /// Durably records the source file as completed before returning.
+    pub(crate) fn record(
+        &mut self,
+        source_path: &str,
+        permanent_id: PermanentId,
+        cid: Cid,
+    ) -> Result<(), ImportError> {
+        let path_length = u16::try_from(source_path.len())
+            .map_err(|_| ImportError::UnsupportedPath(PathBuf::from(source_path)))?;
+
+        let mut entry =
+            Vec::with_capacity(PermanentId::size() + Cid::size() + 2 + source_path.len());
+        entry.extend_from_slice(permanent_id.as_bytes());
+        entry.extend_from_slice(cid.as_bytes());
+        entry.extend_from_slice(&path_length.to_le_bytes());
+        entry.extend_from_slice(source_path.as_bytes());
+
+        self.file.write_all(&entry)?;
+        self.file.sync_data()?;
+
+        self.manifest.valid_length += entry.len() as u64;
+        self.manifest
+            .completed
+            .insert(source_path.to_string(), (permanent_id, cid));
+
+        Ok(())
+    }
+}
+
+/// A directory or regular file found within the source of an import.
+pub(crate) struct ImportSource {
+    pub(crate) is_directory: bool,
+    pub(crate) relative_path: String,
+    pub(crate) source_path: PathBuf,
+}
+
+impl ImportSource {
+    /// Lists everything underneath the provided directory, parents are always listed before
+    /// their children and entries are sorted by name so imports proceed in a consistent order.
+    /// Only the paths are collected, file contents are read as they are imported. Symbolic links
+    /// and special files are skipped.
+    pub(crate) fn collect(source: &Path) -> Result<Vec<ImportSource>, ImportError> {
+        let mut sources = Vec::new();
+        let mut pending = vec![(source.to_path_buf(), String::new())];
+
+        while let Some((directory, parent_path)) = pending.pop() {
+            let mut entries = std::fs::read_dir(&directory)?.collect::<Result<Vec<_>, _>>()?;
+            entries.sort_by_key(|entry| entry.file_name());
+
+            let mut child_directories = Vec::new();
+            for entry in entries {
+                let source_path = entry.path();
+                let name = entry
+                    .file_name()
+                    .into_string()
+                    .map_err(|_| ImportError::UnsupportedPath(source_path.clone()))?;
+                let relative_path = format!("{parent_path}{name}");
+                if relative_path.len() > u16::MAX as usize {
+                    return Err(ImportError::UnsupportedPath(source_path));
+                }
+
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    child_directories.push((source_path.clone(), format!("{relative_path}/")));
+                } else if !file_type.is_file() {
+                    tracing::warn!(?source_path, "skipping unsupported file type during import");
+                    continue;
+                }
+
+                sources.push(ImportSource {
+                    is_directory: file_type.is_dir(),
+                    relative_path,
+                    source_path,
+                });
+            }
+
+            // Reversed so the directories come off the stack in their sorted order
+            pending.extend(child_directories.into_iter().rev());
+        }
+
+        Ok(sources)
+    }
+
+    pub(crate) fn drive_path(&self) -> Vec<&str> {
+        self.relative_path.split('/').collect()
+    }
+}
+
+/// The outcome of a completed directory import.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    imported: usize,
+    skipped: usize,
+}
+
+impl ImportSummary {
+    pub(crate) fn record_imported(&mut self) {
+        self.imported += 1;
+    }
+
+    pub(crate) fn record_skipped(&mut self) {
+        self.skipped += 1;
+    }
+
+    /// The number of files written to the drive by this run of the import.
+    pub fn imported(&self) -> usize {
+        self.imported
+    }
+
+    /// The number of files skipped as the manifest showed an earlier run already completed them
+    /// and the files they produced haven't changed since.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("failed to access the import source or manifest: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("import manifest is not valid")]
+    InvalidManifest,
+
+    #[error("an import manifest already exists at {0:?}, the import should be resumed instead")]
+    ManifestExists(PathBuf),
+
+    #[error("drive operation failed during import: {0}")]
+    Operation(#[from] OperationError),
+
+    #[error("source path can't be represented in the drive: {0:?}")]
+    UnsupportedPath(PathBuf),
+}
@@ -0,0 +1,48 @@
+/// Totals describing the contents of a drive, produced by [`crate::filesystem::Drive::stats`].
+/// Only the drive's metadata is consulted to produce these, no blocks are retrieved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DriveStats {
+    file_count: usize,
+    directory_count: usize,
+    total_size: u64,
+    data_cid_count: usize,
+}
+
+impl DriveStats {
+    /// The number of distinct data blocks referenced by the drive's files and their associated
+    /// data. Blocks shared between several files are only counted once.
+    pub fn data_cid_count(&self) -> usize {
+        self.data_cid_count
+    }
+
+    /// The number of directories in the drive, including the root directory.
+    pub fn directory_count(&self) -> usize {
+        self.directory_count
+    }
+
+    /// The number of files in the drive. Associated data attached to files, such as thumbnails,
+    /// isn't counted.
+    pub fn file_count(&self) -> usize {
+        self.file_count
+    }
+
+    /// The combined size of the content of every file in the drive as it would be read, before
+    /// any encryption or compression. Files sharing the same content each contribute their size.
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    pub(crate) fn new(
+        file_count: usize,
+        directory_count: usize,
+        total_size: u64,
+        data_cid_count: usize,
+    ) -> Self {
+        Self {
+            file_count,
+            directory_count,
+            total_size,
+            data_cid_count,
+        }
+    }
+}
@@ -7,10 +7,13 @@ use tracing::instrument;
 use winnow::{binary::le_u64, Parser};
 
 use crate::{
-    codec::*,
+    codec::{crypto::AccessKey, filesystem::NodeKind, *},
     filesystem::{
-        drive::{DriveAccess, VectorClockFilesystem},
-        nodes::{Node, NodeBuilder, NodeId},
+        drive::{
+            directory_handle::MAX_PATH_DEPTH, DriveAccess, DriveStats, PathResolution,
+            PathResolutionFailure, ResolvedComponent, VectorClockFilesystem, WalkState,
+        },
+        nodes::{Node, NodeBuilder, NodeData, NodeId, NodeName},
     },
     utils::std_io_err,
 };
@@ -27,10 +30,29 @@ pub(crate) struct InnerDrive {
     vector_clock_actor: VectorClockActor,
     root_pid: PermanentId,
 
+    // todo: every node is held in memory for the lifetime of the drive. Very large drives on
+    // memory constrained targets would benefit from evicting cold nodes into the DataStore (keyed
+    // by their CID) and reloading them on demand. That can't be done behind the current lookups as
+    // `by_perm_id` and friends synchronously hand out borrowed `&Node`s while the DataStore is
+    // async. Lookups need to become async and return owned or guarded nodes first.
+    //
+    // Encoded nodes hold plaintext metadata (names, structure, data references with their
+    // per-file keys) and DataStores are commonly backed by local disks shared with other users or
+    // processes. Anything spilled out of memory must be treated as visible to anyone who can read
+    // the store: when this is implemented spilled nodes have to be encrypted unconditionally, with
+    // no plaintext option, under a key derived from the filesystem key (`Drive::encode_shards`
+    // seals shards with the filesystem key itself) so only holders of the drive's keys can read
+    // them back. The shard cache on `Drive` already only ever holds shards in encrypted form.
     nodes: Slab<Node>,
     permanent_id_map: HashMap<PermanentId, NodeId>,
 
     dirty_nodes: Vec<NodeId>,
+
+    /// Top level directories whose shard hasn't been decrypted yet when only part of a sharded
+    /// drive was loaded, keyed by the directory's permanent ID along with the CID of the
+    /// directory (which is also the source CID of its shard). None of the nodes in these
+    /// subtrees are present until the shard is loaded.
+    unloaded_shards: HashMap<PermanentId, Cid>,
 }
 
 impl InnerDrive {
@@ -111,8 +133,9 @@ impl InnerDrive {
     }
 
     pub(crate) async fn clean_drive(&mut self) -> Result<(), OperationError> {
-        // Take the dirty node list (replacing it with an empty Vec)
-        let mut dirty = std::mem::take(&mut self.dirty_nodes);
+        // The dirty node list is only cleared once every node has been updated, if this gets
+        // interrupted the next call will pick up the same work again.
+        let mut dirty = self.dirty_nodes.clone();
 
         // Popping from back of the dirty list, push NodeIds into `node_list`
         // Only pushing if the list does not already contain that nodeId.
@@ -131,6 +154,12 @@ impl InnerDrive {
             let child_pids = node_mut.data().ordered_child_pids();
             let mut child_data = Vec::new();
             for pid in child_pids {
+                // Subtrees that haven't been loaded can't have changed, their recorded CID and
+                // size are still accurate
+                if self.unloaded_shards.contains_key(&pid) {
+                    continue;
+                }
+
                 child_data.push((
                     pid,
                     self.by_perm_id_mut_untracked(&pid)?.cid().await?,
@@ -140,12 +169,11 @@ impl InnerDrive {
 
             let node_mut = self.by_id_mut_untracked(node_id)?;
             for child in child_data {
-                node_mut
-                    .data_mut()
-                    .await
-                    .update_child(&child.0, child.1, child.2)?;
+                node_mut.update_child(&child.0, child.1, child.2).await?;
             }
         }
+
+        self.dirty_nodes.clear();
         Ok(())
     }
 
@@ -199,8 +227,7 @@ impl InnerDrive {
             return Err(OperationError::ParentMustBeDirectory);
         }
 
-        let node_entry = self.nodes.vacant_entry();
-        let node_id = node_entry.key();
+        let node_id = self.nodes.vacant_key();
 
         let node = build_node(rng, node_id, parent_permanent_id, owner_id).await?;
         let cid = node.cid().await?;
@@ -209,59 +236,48 @@ impl InnerDrive {
         let name = node.name();
         let permanent_id = node.permanent_id();
 
-        node_entry.insert(node);
-
-        self.permanent_id_map.insert(permanent_id, node_id);
-
+        // The node only gets tracked once the parent has accepted it as a child, a name collision
+        // or a cancelled future leaves no trace of the new node behind.
         let parent_node = self.by_perm_id_mut(&parent_permanent_id).await?;
         parent_node.add_child(name, permanent_id, cid, size).await?;
 
+        let inserted_id = self.nodes.insert(node);
+        debug_assert_eq!(inserted_id, node_id);
+        self.permanent_id_map.insert(permanent_id, node_id);
+
         Ok(permanent_id)
     }
 
+    /// Produces an independent copy of the filesystem structure by round-tripping it through its
+    /// encoded form. Every node (including its permanent ID and its own vector clock) is
+    /// preserved, while the filesystem and actor clocks are replaced with the ones provided.
+    pub(crate) async fn deep_copy(
+        &self,
+        vector_clocks: VectorClockFilesystemActorSnapshot,
+    ) -> Result<Self, OperationError> {
+        let mut encoded = Vec::new();
+        self.encode(&mut encoded).await.map_err(|err| {
+            tracing::error!("failed to encode filesystem for copying: {err}");
+            OperationError::Other("unable to copy filesystem")
+        })?;
+
+        let (_, copy) = Self::parse(Stream::new(&encoded), self.access.clone(), vector_clocks)
+            .map_err(|err| {
+                tracing::error!("failed to parse copied filesystem: {err:?}");
+                OperationError::Other("unable to copy filesystem")
+            })?;
+
+        Ok(copy)
+    }
+
     pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
         &self,
         writer: &mut W,
     ) -> std::io::Result<usize> {
         let mut written_bytes = 0;
 
-        // We want to walk the nodes in a consistent depth first order to provide a total ordering
-        // of the internal nodes. This is important as once we get to encoding we want to ensure
-        // that any node that has children is encoded after its children. We accomplish this by
-        // reversing the list from our walked tree and encoding in that order.
-        //
-        // This will silently discard any disconnected leaf nodes. Loops are tolerated by
-        // deduplication of unique identifiers.
-        let mut ordered_ids = Vec::new();
-        let mut seen_ids = HashSet::new();
-        let mut outstanding_ids = vec![self.root_pid];
-
-        while let Some(node_pid) = outstanding_ids.pop() {
-            let node = self
-                .by_perm_id(&node_pid)
-                .map_err(|_| std_io_err("missing node PID"))?;
-
-            let permanent_id = node.permanent_id();
-            if seen_ids.contains(&permanent_id) {
-                // We've already seen this ID, but it now needs to appear earlier in our encoding
-                // so we have to append to the end of our list.
-                let existing_pos = ordered_ids
-                    .iter()
-                    .position(|&pid| pid == permanent_id)
-                    .ok_or(std_io_err("expected PID to already be present"))?;
-
-                ordered_ids.remove(existing_pos);
-                ordered_ids.push(permanent_id);
-
-                // We don't need to do anything else for nodes we've already seen
-                continue;
-            }
-
-            seen_ids.insert(permanent_id);
-            ordered_ids.push(permanent_id);
-
-            outstanding_ids.extend(node.ordered_child_pids());
-        }
+        let mut ordered_ids = self.encoding_order(self.root_pid, &HashSet::new())?;
+        let node_count = ordered_ids.len() as u64;
 
         // `Vec::pop` pops from the back of the Vec so we will implicitly be getting the correct ordering as
         // we pop elements in the reverse order we found them in our DFS
@@ -274,7 +290,7 @@ impl InnerDrive {
                 .by_perm_id(&node_pid)
                 .map_err(|_| std_io_err("missing node PID"))?;
 
-            node.encode(&mut node_buffer).await?;
+            node.encode_cached(&mut node_buffer).await?;
 
             if let Some(data_cids) = node.data_cids() {
                 for cid in data_cids {
@@ -298,7 +314,6 @@ impl InnerDrive {
         let encoded_len = self.root_pid.encode(writer).await?;
         written_bytes += encoded_len;
 
-        let node_count = seen_ids.len() as u64;
         let node_count_bytes = node_count.to_le_bytes();
         writer.write_all(&node_count_bytes).await?;
         written_bytes += node_count_bytes.len();
@@ -309,13 +324,128 @@ impl InnerDrive {
         Ok(written_bytes)
     }
 
+    /// Encodes the subtree starting at the provided node as an independent shard of the
+    /// filesystem. Any nodes listed in `excluded` (and everything underneath them) are left out so
+    /// they can be encoded into their own shards.
+    pub(crate) async fn encode_shard<W: AsyncWrite + Unpin + Send>(
+        &self,
+        shard_pid: PermanentId,
+        excluded: &HashSet<PermanentId>,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let mut ordered_ids = self.encoding_order(shard_pid, excluded)?;
+
+        let node_count_bytes = (ordered_ids.len() as u64).to_le_bytes();
+        writer.write_all(&node_count_bytes).await?;
+        let mut written_bytes = node_count_bytes.len();
+
+        while let Some(node_pid) = ordered_ids.pop() {
+            let node = self
+                .by_perm_id(&node_pid)
+                .map_err(|_| std_io_err("missing node PID"))?;
+
+            written_bytes += node.encode_cached(writer).await?;
+        }
+
+        Ok(written_bytes)
+    }
+
+    /// Lists the nodes that each head their own shard when the filesystem is encoded as multiple
+    /// shards. Every directory directly beneath the root gets a shard of its own, with the root
+    /// itself being the last entry. The root's shard holds any nodes not covered by the others.
+    pub(crate) fn shard_roots(&self) -> Result<Vec<PermanentId>, OperationError> {
+        let root_node = self.root_node()?;
+
+        let mut shard_pids = Vec::new();
+        for child_pid in root_node.ordered_child_pids() {
+            if self.by_perm_id(&child_pid)?.kind() == NodeKind::Directory {
+                shard_pids.push(child_pid);
+            }
+        }
+        shard_pids.push(self.root_pid);
+
+        Ok(shard_pids)
+    }
+
+    /// We want to walk the nodes in a consistent depth first order to provide a total ordering
+    /// of the internal nodes. This is important as once we get to encoding we want to ensure
+    /// that any node that has children is encoded after its children. Callers accomplish this by
+    /// popping the IDs off the end of the returned list.
+    ///
+    /// This will silently discard any disconnected leaf nodes. Loops are tolerated by
+    /// deduplication of unique identifiers. The walk will not descend into any of the nodes
+    /// present in `excluded`.
+    fn encoding_order(
+        &self,
+        start_pid: PermanentId,
+        excluded: &HashSet<PermanentId>,
+    ) -> std::io::Result<Vec<PermanentId>> {
+        let mut ordered_ids = Vec::new();
+        let mut seen_ids = HashSet::new();
+        let mut outstanding_ids = vec![start_pid];
+
+        while let Some(node_pid) = outstanding_ids.pop() {
+            if excluded.contains(&node_pid) {
+                continue;
+            }
+
+            if self.unloaded_shards.contains_key(&node_pid) {
+                return Err(std_io_err("subtree hasn't been loaded"));
+            }
+
+            let node = self
+                .by_perm_id(&node_pid)
+                .map_err(|_| std_io_err("missing node PID"))?;
+
+            let permanent_id = node.permanent_id();
+            if seen_ids.contains(&permanent_id) {
+                // We've already seen this ID, but it now needs to appear earlier in our encoding
+                // so we have to append to the end of our list.
+                let existing_pos = ordered_ids
+                    .iter()
+                    .position(|&pid| pid == permanent_id)
+                    .ok_or(std_io_err("expected PID to already be present"))?;
+
+                ordered_ids.remove(existing_pos);
+                ordered_ids.push(permanent_id);
+
+                // We don't need to do anything else for nodes we've already seen
+                continue;
+            }
+
+            seen_ids.insert(permanent_id);
+            ordered_ids.push(permanent_id);
+
+            outstanding_ids.extend(node.ordered_child_pids());
+        }
+
+        Ok(ordered_ids)
+    }
+
     pub(crate) fn initialize(
         rng: &mut impl CryptoRngCore,
         actor_id: ActorId,
         access: DriveAccess,
         vector_clock_actor: VectorClockActor,
     ) -> Result<Self, OperationError> {
-        let vector_clock_filesystem = VectorClockFilesystem::initialize();
+        let vector_clocks = VectorClockFilesystemActorSnapshot::new(
+            VectorClockFilesystem::initialize().as_snapshot(),
+            vector_clock_actor.as_snapshot(),
+        );
+
+        Self::initialize_with_clocks(rng, actor_id, access, vector_clocks)
+    }
+
+    /// Sets up an empty filesystem whose vector clocks start from the provided values rather than
+    /// their initial state. See [`crate::filesystem::Drive::initialize_private_with_clocks`] for
+    /// the constraints on the values.
+    pub(crate) fn initialize_with_clocks(
+        rng: &mut impl CryptoRngCore,
+        actor_id: ActorId,
+        access: DriveAccess,
+        vector_clocks: VectorClockFilesystemActorSnapshot,
+    ) -> Result<Self, OperationError> {
+        let (vector_clock_filesystem, vector_clock_actor) = vector_clocks.reanimate();
 
         let mut nodes = Slab::with_capacity(32);
         let mut permanent_id_map = HashMap::new();
@@ -340,6 +470,7 @@ impl InnerDrive {
             root_pid,
             permanent_id_map,
             dirty_nodes: Vec::new(),
+            unloaded_shards: HashMap::new(),
         };
 
         Ok(inner)
@@ -349,10 +480,59 @@ impl InnerDrive {
         &self,
         perm_id: &PermanentId,
     ) -> Result<NodeId, OperationError> {
-        self.permanent_id_map
-            .get(perm_id)
-            .copied()
-            .ok_or(OperationError::MissingPermanentId(*perm_id))
+        match self.permanent_id_map.get(perm_id) {
+            Some(node_id) => Ok(*node_id),
+            None if self.unloaded_shards.contains_key(perm_id) => {
+                Err(OperationError::SubtreeNotLoaded(*perm_id))
+            }
+            None => Err(OperationError::MissingPermanentId(*perm_id)),
+        }
+    }
+
+    /// Counts the nodes referencing each data block, a node referencing the same block more than
+    /// once is only counted once. Blocks with a count above one are shared between nodes and
+    /// need to be kept until the last of them is removed.
+    pub(crate) fn block_reference_counts(&self) -> HashMap<Cid, usize> {
+        let mut reference_counts = HashMap::new();
+
+        for node in self.node_iter() {
+            let data_cids = match node.data_cids() {
+                Some(data_cids) => data_cids.into_iter().collect::<HashSet<_>>(),
+                None => continue,
+            };
+
+            for cid in data_cids {
+                *reference_counts.entry(cid).or_insert(0) += 1;
+            }
+        }
+
+        reference_counts
+    }
+
+    /// Totals the nodes and content of the drive in a single pass over its nodes, see
+    /// [`crate::filesystem::Drive::stats`].
+    pub(crate) fn stats(&self) -> DriveStats {
+        let mut file_count = 0;
+        let mut directory_count = 0;
+        let mut total_size = 0;
+        let mut data_cids = HashSet::new();
+
+        for node in self.node_iter() {
+            match node.data() {
+                NodeData::File { content, .. } => {
+                    file_count += 1;
+                    total_size += content.size();
+                }
+                NodeData::Directory { .. } => directory_count += 1,
+                NodeData::AssociatedData { .. } => {}
+            }
+
+            if let Some(cids) = node.data_cids() {
+                data_cids.extend(cids);
+            }
+        }
+
+        DriveStats::new(file_count, directory_count, total_size, data_cids.len())
     }
 
     /// Returns an iterator of immutable references to every [`Node`] in this [`InnerDrive`]
@@ -372,15 +552,77 @@ impl InnerDrive {
         let (remaining, root_pid) = PermanentId::parse(input)?;
         let bytes_read = input.len() - remaining.len();
 
-        let (remaining, node_count) = le_u64.parse_peek(remaining)?;
-        let bytes_read = input.len() - remaining.len() - bytes_read;
-        tracing::trace!(node_count, bytes_read,
-            remaining_len = ?remaining.len(),
-            "inner_drive::parse::node_count");
+        tracing::trace!(bytes_read, "inner_drive::parse::root_pid");
 
         let mut nodes = Slab::new();
         let mut permanent_id_map = HashMap::new();
 
+        let (node_input, _) = Self::parse_nodes(remaining, &mut nodes, &mut permanent_id_map)?;
+
+        let root_node_id = *permanent_id_map.get(&root_pid).ok_or_else(|| {
+            winnow::error::ErrMode::Cut(winnow::error::ParserError::from_error_kind(
+                &node_input,
+                winnow::error::ErrorKind::Verify,
+            ))
+        })?;
+
+        tracing::trace!(?root_node_id, "inner_drive::parse::complete");
+
+        let inner_drive = InnerDrive {
+            access: drive_access,
+            vector_clock_actor,
+            vector_clock_filesystem,
+            root_pid,
+            nodes,
+            permanent_id_map,
+            dirty_nodes: Vec::new(),
+            unloaded_shards: HashMap::new(),
+        };
+
+        Ok((node_input, inner_drive))
+    }
+
+    /// Assembles a drive from nodes that have already been parsed, such as those collected from
+    /// each shard of a sharded filesystem by [`InnerDrive::parse_nodes`]. Returns `None` if the
+    /// root node isn't among them.
+    pub(crate) fn from_parsed_nodes(
+        root_pid: PermanentId,
+        nodes: Slab<Node>,
+        permanent_id_map: HashMap<PermanentId, NodeId>,
+        drive_access: DriveAccess,
+        vector_clocks: VectorClockFilesystemActorSnapshot,
+    ) -> Option<Self> {
+        if !permanent_id_map.contains_key(&root_pid) {
+            return None;
+        }
+
+        let (vector_clock_filesystem, vector_clock_actor) = vector_clocks.reanimate();
+
+        Some(InnerDrive {
+            access: drive_access,
+            vector_clock_actor,
+            vector_clock_filesystem,
+            root_pid,
+            nodes,
+            permanent_id_map,
+            dirty_nodes: Vec::new(),
+            unloaded_shards: HashMap::new(),
+        })
+    }
+
+    /// Parses a node count followed by that many encoded nodes, inserting each of them into the
+    /// provided collections. Returns the number of nodes that were parsed. A node that is already
+    /// present (which may happen when it is reachable from multiple shards) is only kept once.
+    pub(crate) fn parse_nodes<'a>(
+        input: Stream<'a>,
+        nodes: &mut Slab<Node>,
+        permanent_id_map: &mut HashMap<PermanentId, NodeId>,
+    ) -> ParserResult<'a, u64> {
+        let (remaining, node_count) = le_u64.parse_peek(input)?;
+        tracing::trace!(node_count,
+            remaining_len = ?remaining.len(),
+            "inner_drive::parse::node_count");
+
         let mut node_input = remaining;
         for _ in 0..node_count {
             let entry = nodes.vacant_entry();
@@ -390,6 +632,11 @@ impl InnerDrive {
             node_input = remaining;
             let permanent_id = node.permanent_id();
 
+            if permanent_id_map.contains_key(&permanent_id) {
+                tracing::warn!(?permanent_id, "encountered duplicate node, ignoring it");
+                continue;
+            }
+
             for pid in node.ordered_child_pids() {
                 if !permanent_id_map.contains_key(&pid) {
                     tracing::warn!(?permanent_id, child_pid = ?pid, "encountered child PID before parent");
@@ -413,26 +660,338 @@ impl InnerDrive {
             entry.insert(node);
         }
 
-        let root_node_id = *permanent_id_map.get(&root_pid).ok_or_else(|| {
-            winnow::error::ErrMode::Cut(winnow::error::ParserError::from_error_kind(
-                &node_input,
-                winnow::error::ErrorKind::Verify,
-            ))
-        })?;
+        Ok((node_input, node_count))
+    }
 
-        tracing::trace!(?root_node_id, "inner_drive::parse::complete");
+    /// Moves the node with the provided permanent ID underneath a new parent using the provided
+    /// name. The destination is checked for name collisions and to ensure a node isn't being
+    /// moved inside of itself before any changes are made so a failed move leaves the drive
+    /// untouched.
+    pub(crate) async fn move_node(
+        &mut self,
+        node_pid: PermanentId,
+        new_parent_pid: PermanentId,
+        new_name: NodeName,
+    ) -> Result<(), OperationError> {
+        let new_parent = self.by_perm_id(&new_parent_pid)?;
+        if new_parent.kind() != NodeKind::Directory {
+            return Err(OperationError::ParentMustBeDirectory);
+        }
 
-        let inner_drive = InnerDrive {
-            access: drive_access,
-            vector_clock_actor,
-            vector_clock_filesystem,
-            root_pid,
-            nodes,
-            permanent_id_map,
-            dirty_nodes: Vec::new(),
+        if let Some(existing_pid) = new_parent.data().child_permanent_id(&new_name) {
+            if existing_pid != node_pid {
+                let existing_id = self.lookup_internal_id(&existing_pid)?;
+                return Err(OperationError::Exists(existing_id));
+            }
+        }
+
+        let mut ancestor_pid = Some(new_parent_pid);
+        while let Some(pid) = ancestor_pid {
+            if pid == node_pid {
+                return Err(OperationError::RecursiveMove);
+            }
+
+            ancestor_pid = self.by_perm_id(&pid)?.parent_id();
+        }
+
+        let node = self.by_perm_id(&node_pid)?;
+        let current_name = node.name();
+        let current_parent_pid = node
+            .parent_id()
+            .ok_or(OperationError::OrphanNode(node_pid))?;
+        let node_cid = node.cid().await?;
+        let node_size = node.size();
+
+        self.by_perm_id_mut(&current_parent_pid)
+            .await?
+            .remove_child(&current_name)
+            .await?;
+
+        self.by_perm_id_mut(&new_parent_pid)
+            .await?
+            .add_child(new_name.clone(), node_pid, node_cid, node_size)
+            .await?;
+
+        let node = self.by_perm_id_mut(&node_pid).await?;
+        node.set_parent_id(new_parent_pid).await;
+        node.set_name(new_name).await;
+
+        Ok(())
+    }
+
+    /// Checks that a batch of moves, each a source and destination path as accepted by
+    /// [`InnerDrive::move_path`], can be applied in order without changing anything. Every move is
+    /// checked against the tree as the moves before it in the batch would leave it, returning the
+    /// error the first failing move would produce.
+    pub(crate) fn check_moves(
+        &self,
+        moves: &[(Vec<&str>, Vec<&str>)],
+    ) -> Result<(), OperationError> {
+        // The parent and name of every node moved so far, along with the directory entries those
+        // moves have removed (`None`) or added
+        let mut locations: HashMap<PermanentId, (PermanentId, NodeName)> = HashMap::new();
+        let mut entries: HashMap<(PermanentId, NodeName), Option<PermanentId>> = HashMap::new();
+
+        let root_id = self.root_node()?.id();
+
+        for (src_path, dst_path) in moves {
+            if src_path.is_empty() {
+                return Err(OperationError::UnexpectedEmptyPath);
+            }
+
+            let child_lookup = |node: &Node, name: &NodeName| match entries
+                .get(&(node.permanent_id(), name.clone()))
+            {
+                Some(entry) => *entry,
+                None => node.data().child_permanent_id(name),
+            };
+
+            let src_pid = match self.walk_path_with(root_id, src_path, &child_lookup)? {
+                WalkState::FoundNode { node_id } => self.by_id(node_id)?.permanent_id(),
+                WalkState::MissingComponent { .. } => return Err(OperationError::PathNotFound),
+            };
+
+            let (original_parent_pid, original_name) = match locations.get(&src_pid) {
+                Some(location) => location.clone(),
+                None => {
+                    let src_node = self.by_perm_id(&src_pid)?;
+                    let parent_pid = src_node
+                        .parent_id()
+                        .ok_or(OperationError::OrphanNode(src_pid))?;
+                    (parent_pid, src_node.name())
+                }
+            };
+
+            let (dst_parent_pid, new_name) =
+                self.resolve_destination_with(dst_path, original_name.clone(), &child_lookup)?;
+
+            // The same checks [`InnerDrive::move_node`] performs before changing anything
+            let dst_parent = self.by_perm_id(&dst_parent_pid)?;
+            if dst_parent.kind() != NodeKind::Directory {
+                return Err(OperationError::ParentMustBeDirectory);
+            }
+
+            if let Some(existing_pid) = child_lookup(dst_parent, &new_name) {
+                if existing_pid != src_pid {
+                    let existing_id = self.lookup_internal_id(&existing_pid)?;
+                    return Err(OperationError::Exists(existing_id));
+                }
+            }
+
+            let mut ancestor_pid = Some(dst_parent_pid);
+            while let Some(pid) = ancestor_pid {
+                if pid == src_pid {
+                    return Err(OperationError::RecursiveMove);
+                }
+
+                ancestor_pid = match locations.get(&pid) {
+                    Some((parent_pid, _)) => Some(*parent_pid),
+                    None => self.by_perm_id(&pid)?.parent_id(),
+                };
+            }
+
+            entries.insert((original_parent_pid, original_name), None);
+            entries.insert((dst_parent_pid, new_name.clone()), Some(src_pid));
+            locations.insert(src_pid, (dst_parent_pid, new_name));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the source and destination paths relative to the root of the drive and performs
+    /// the move between them following the same rules as
+    /// [`crate::filesystem::DirectoryHandle::mv`]. On success the source node's original parent
+    /// and name are returned so the move can be reverted if needed.
+    pub(crate) async fn move_path(
+        &mut self,
+        src_path: &[&str],
+        dst_path: &[&str],
+    ) -> Result<(PermanentId, PermanentId, NodeName), OperationError> {
+        if src_path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
+
+        let root_id = self.root_node()?.id();
+
+        let src_node = match self.walk_path(root_id, src_path)? {
+            WalkState::FoundNode { node_id } => self.by_id(node_id)?,
+            WalkState::MissingComponent { .. } => return Err(OperationError::PathNotFound),
         };
 
-        Ok((node_input, inner_drive))
+        let src_pid = src_node.permanent_id();
+        let original_name = src_node.name();
+        let original_parent_pid = src_node
+            .parent_id()
+            .ok_or(OperationError::OrphanNode(src_pid))?;
+
+        let (dst_parent_pid, new_name) =
+            self.resolve_destination(dst_path, original_name.clone())?;
+
+        self.move_node(src_pid, dst_parent_pid, new_name).await?;
+
+        Ok((src_pid, original_parent_pid, original_name))
+    }
+
+    /// Resolves a destination path relative to the root of the drive into the parent directory
+    /// and name a node should be placed at. When the path points at an existing node the node is
+    /// placed inside of it keeping `current_name`, otherwise the final component of the path is
+    /// used as the new name. Intermediate directories are never created.
+    fn resolve_destination(
+        &self,
+        dst_path: &[&str],
+        current_name: NodeName,
+    ) -> Result<(PermanentId, NodeName), OperationError> {
+        self.resolve_destination_with(dst_path, current_name, &|node, name| {
+            node.data().child_permanent_id(name)
+        })
+    }
+
+    fn resolve_destination_with(
+        &self,
+        dst_path: &[&str],
+        current_name: NodeName,
+        child_lookup: &impl Fn(&Node, &NodeName) -> Option<PermanentId>,
+    ) -> Result<(PermanentId, NodeName), OperationError> {
+        let root_id = self.root_node()?.id();
+
+        match self.walk_path_with(root_id, dst_path, child_lookup)? {
+            WalkState::FoundNode { node_id } => {
+                Ok((self.by_id(node_id)?.permanent_id(), current_name))
+            }
+            WalkState::MissingComponent {
+                working_directory_id,
+                missing_name,
+                remaining_path,
+            } => {
+                // We won't create any intermediate directories, only the final component of the
+                // destination is allowed to be missing.
+                if !remaining_path.is_empty() {
+                    return Err(OperationError::PathNotFound);
+                }
+
+                Ok((
+                    self.by_id(working_directory_id)?.permanent_id(),
+                    missing_name,
+                ))
+            }
+        }
+    }
+
+    /// Copies the subtree rooted at `source_pid` in another drive into this one, placing it at
+    /// `dst_path` following the same rules as [`InnerDrive::move_path`]. Every copied node
+    /// receives a new permanent ID and is owned by `owner_id`. The data blocks of any file are
+    /// referenced as they are, only the file keys get re-sealed with this drive's data key, so
+    /// both drives need to be backed by the same data store.
+    ///
+    /// Nothing in this drive is changed if the copy fails. On success the permanent ID of the
+    /// copy of `source_pid` is returned. The caller is responsible for cleaning the drive.
+    pub(crate) async fn graft_subtree(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        source: &InnerDrive,
+        source_pid: PermanentId,
+        dst_path: &[&str],
+        owner_id: ActorId,
+    ) -> Result<PermanentId, OperationError> {
+        let (source_data_key, data_key) = match (source.access.data_key(), self.access.data_key()) {
+            (Some(source_key), Some(key)) => (source_key.clone(), key.clone()),
+            _ => return Err(OperationError::AccessDenied),
+        };
+
+        let source_node = source.by_perm_id(&source_pid)?;
+        let (parent_pid, name) = self.resolve_destination(dst_path, source_node.name())?;
+
+        let parent_node = self.by_perm_id(&parent_pid)?;
+        if parent_node.kind() != NodeKind::Directory {
+            return Err(OperationError::ParentMustBeDirectory);
+        }
+
+        if let Some(existing_pid) = parent_node.data().child_permanent_id(&name) {
+            let existing_id = self.lookup_internal_id(&existing_pid)?;
+            return Err(OperationError::Exists(existing_id));
+        }
+
+        // Parents always appear before their children
+        let mut ordered_pids = vec![source_pid];
+        let mut position = 0;
+        while let Some(pid) = ordered_pids.get(position) {
+            let child_pids = source.by_perm_id(pid)?.ordered_child_pids();
+            ordered_pids.extend(child_pids);
+            position += 1;
+        }
+
+        let new_ids: HashMap<PermanentId, PermanentId> = ordered_pids
+            .iter()
+            .map(|pid| (*pid, PermanentId::generate(rng)))
+            .collect();
+
+        let mut inserted_pids = Vec::new();
+        let result = async {
+            // Children are copied first so their CIDs are known when their parent is copied
+            for pid in ordered_pids.iter().rev() {
+                let source_node = source.by_perm_id(pid)?;
+
+                let new_parent_pid = if *pid == source_pid {
+                    parent_pid
+                } else {
+                    let original_parent_pid = source_node
+                        .parent_id()
+                        .ok_or(OperationError::OrphanNode(*pid))?;
+                    new_ids[&original_parent_pid]
+                };
+
+                let node_id = self.nodes.vacant_key();
+                let mut node = source_node
+                    .transplant(node_id, new_ids[pid], new_parent_pid, owner_id)
+                    .await?;
+
+                if *pid == source_pid {
+                    node.set_name(name.clone()).await;
+                }
+
+                let node_data = node.data_mut().await;
+                node_data.relock_data_key(rng, &source_data_key, &data_key)?;
+
+                for child_pid in source_node.ordered_child_pids() {
+                    let new_child_pid = new_ids[&child_pid];
+                    let child_node = self.by_perm_id(&new_child_pid)?;
+
+                    node_data.replace_child_id(
+                        &child_pid,
+                        new_child_pid,
+                        child_node.cid().await?,
+                        child_node.size(),
+                    )?;
+                }
+
+                let inserted_id = self.nodes.insert(node);
+                debug_assert_eq!(inserted_id, node_id);
+                self.permanent_id_map.insert(new_ids[pid], node_id);
+                inserted_pids.push(new_ids[pid]);
+            }
+
+            let new_pid = new_ids[&source_pid];
+            let new_node = self.by_perm_id(&new_pid)?;
+            let (cid, size) = (new_node.cid().await?, new_node.size());
+
+            self.by_perm_id_mut(&parent_pid)
+                .await?
+                .add_child(name, new_pid, cid, size)
+                .await?;
+
+            Ok(new_pid)
+        }
+        .await;
+
+        if result.is_err() {
+            for pid in inserted_pids {
+                if let Some(node_id) = self.permanent_id_map.remove(&pid) {
+                    self.nodes.try_remove(node_id);
+                }
+            }
+        }
+
+        result
     }
 
     pub(crate) async fn remove_node(
@@ -482,6 +1041,51 @@ impl InnerDrive {
         }
     }
 
+    /// Reseals the key of every file and associated data node holding encrypted content, moving
+    /// it from `current_key` to `new_key`. Every key is resealed before any node is changed so
+    /// the content either moves to the new key in its entirety or is left as it was. Returns the
+    /// CIDs of the data blocks referenced by the resealed content.
+    pub(crate) async fn relock_data_keys(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        current_key: &AccessKey,
+        new_key: &AccessKey,
+    ) -> Result<HashSet<Cid>, OperationError> {
+        let mut relocked = Vec::new();
+
+        for (node_id, node) in self.nodes.iter() {
+            let content = match node.data() {
+                NodeData::File { content, .. } | NodeData::AssociatedData { content }
+                    if content.is_encrypted() =>
+                {
+                    content
+                }
+                _ => continue,
+            };
+
+            let mut content = content.clone();
+            content.relock_data_key(rng, current_key, new_key)?;
+            relocked.push((node_id, content));
+        }
+
+        let mut data_cids = HashSet::new();
+        for (node_id, relocked_content) in relocked {
+            if let Some(cids) = relocked_content.data_cids() {
+                data_cids.extend(cids);
+            }
+
+            let node = self.by_id_mut(node_id).await?;
+            match node.data_mut().await {
+                NodeData::File { content, .. } | NodeData::AssociatedData { content } => {
+                    *content = relocked_content;
+                }
+                NodeData::Directory { .. } => {}
+            }
+        }
+
+        Ok(data_cids)
+    }
+
     pub(crate) fn root_node(&self) -> Result<&Node, OperationError> {
         self.by_perm_id(&self.root_pid)
     }
@@ -490,6 +1094,157 @@ impl InnerDrive {
         self.root_pid
     }
 
+    /// Parses the nodes of a decrypted shard of a partially loaded drive into the drive, making
+    /// the subtree under `shard_pid` available.
+    pub(crate) fn load_shard(
+        &mut self,
+        shard_pid: &PermanentId,
+        shard_buffer: &[u8],
+    ) -> Result<(), OperationError> {
+        Self::parse_nodes(
+            Stream::new(shard_buffer),
+            &mut self.nodes,
+            &mut self.permanent_id_map,
+        )
+        .map_err(|_| OperationError::Other("unable to parse subtree shard"))?;
+
+        self.unloaded_shards.remove(shard_pid);
+
+        Ok(())
+    }
+
+    /// The top level directories whose subtree hasn't been loaded, along with the CID of the
+    /// shard source for each.
+    pub(crate) fn unloaded_shards(&self) -> &HashMap<PermanentId, Cid> {
+        &self.unloaded_shards
+    }
+
+    pub(crate) fn with_unloaded_shards(
+        mut self,
+        unloaded_shards: HashMap<PermanentId, Cid>,
+    ) -> Self {
+        self.unloaded_shards = unloaded_shards;
+        self
+    }
+
+    /// Walks the provided path from the working directory while the caller is holding a lock on
+    /// the drive. Follows the same traversal rules as the walk performed by
+    /// [`crate::filesystem::DirectoryHandle`].
+    pub(crate) fn walk_path<'a>(
+        &self,
+        working_directory_id: NodeId,
+        path: &'a [&'a str],
+    ) -> Result<WalkState<'a>, OperationError> {
+        self.walk_path_with(working_directory_id, path, &|node, name| {
+            node.data().child_permanent_id(name)
+        })
+    }
+
+    /// Walks the path the same way as [`InnerDrive::walk_path`] but looks up the children of each
+    /// directory through `child_lookup`, allowing a path to be walked against changes that
+    /// haven't been applied to the drive yet.
+    fn walk_path_with<'a>(
+        &self,
+        working_directory_id: NodeId,
+        path: &'a [&'a str],
+        child_lookup: &impl Fn(&Node, &NodeName) -> Option<PermanentId>,
+    ) -> Result<WalkState<'a>, OperationError> {
+        let mut current_id = working_directory_id;
+        let mut current_path = path;
+        let mut depth = 0;
+
+        while let Some((raw_child_name, remaining_path)) = current_path.split_first() {
+            let child_name = NodeName::try_from(*raw_child_name)?;
+            let current_node = self.by_id(current_id)?;
+
+            if !current_node.supports_children() {
+                return Err(OperationError::NotTraversable);
+            }
+
+            let child_pid = match child_lookup(current_node, &child_name) {
+                Some(pid) => pid,
+                None => {
+                    return Ok(WalkState::MissingComponent {
+                        working_directory_id: current_id,
+                        missing_name: child_name,
+                        remaining_path,
+                    });
+                }
+            };
+
+            let next_node = self.by_perm_id(&child_pid)?;
+            if !next_node.supports_children() {
+                return Err(OperationError::NotTraversable);
+            }
+
+            if depth >= MAX_PATH_DEPTH {
+                return Err(OperationError::PathTooDeep);
+            }
+
+            current_id = next_node.id();
+            current_path = remaining_path;
+            depth += 1;
+        }
+
+        Ok(WalkState::found(current_id))
+    }
+
+    /// Resolves each component of the path in turn from the working directory, recording what
+    /// every component resolved to and stopping at the first one that couldn't be. Unlike
+    /// [`InnerDrive::walk_path`] the final component may be a node without children, allowing
+    /// the paths of files to be resolved as well.
+    pub(crate) fn resolve_path(
+        &self,
+        working_directory_id: NodeId,
+        path: &[&str],
+    ) -> PathResolution {
+        let mut resolution = PathResolution::new();
+        let mut current_id = working_directory_id;
+
+        for (depth, raw_child_name) in path.iter().enumerate() {
+            let child_name = match NodeName::try_from(*raw_child_name) {
+                Ok(name) => name,
+                Err(_) => {
+                    return resolution.fail(raw_child_name, PathResolutionFailure::InvalidName)
+                }
+            };
+
+            let current_node = match self.by_id(current_id) {
+                Ok(node) => node,
+                Err(_) => {
+                    return resolution
+                        .fail(raw_child_name, PathResolutionFailure::InconsistentDrive)
+                }
+            };
+
+            if !current_node.supports_children() {
+                return resolution.fail(raw_child_name, PathResolutionFailure::NotTraversable);
+            }
+
+            if depth >= MAX_PATH_DEPTH {
+                return resolution.fail(raw_child_name, PathResolutionFailure::TooDeep);
+            }
+
+            let child_pid = match current_node.data().child_permanent_id(&child_name) {
+                Some(pid) => pid,
+                None => return resolution.fail(raw_child_name, PathResolutionFailure::Missing),
+            };
+
+            let child = match self.by_perm_id(&child_pid) {
+                Ok(node) => node,
+                Err(_) => {
+                    return resolution
+                        .fail(raw_child_name, PathResolutionFailure::InconsistentDrive)
+                }
+            };
+
+            resolution.push(ResolvedComponent::new(raw_child_name, child));
+            current_id = child.id();
+        }
+
+        resolution
+    }
+
     pub fn vector_clock(&self) -> VectorClockFilesystemActorSnapshot {
         VectorClockFilesystemActorSnapshot::new(
             self.vector_clock_filesystem.as_snapshot(),
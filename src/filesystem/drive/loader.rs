@@ -1,43 +1,274 @@
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use async_std::sync::RwLock;
 use futures::{AsyncRead, AsyncReadExt};
+use slab::Slab;
 use tracing::{debug, trace};
 use winnow::binary::le_u64;
 use winnow::error::ErrMode;
+use winnow::token::take;
 use winnow::Parser;
 
-use crate::codec::crypto::{AuthenticationTag, EncryptedBuffer, Nonce, SigningKey};
+use crate::codec::crypto::{
+    AccessKey, AuthenticationTag, EncryptedBuffer, KeyId, Nonce, SigningKey,
+};
 use crate::codec::header::{ContentOptions, IdentityHeader, KeyCount, PublicSettings};
-use crate::codec::meta::{FilesystemId, MetaKey};
+use crate::codec::meta::{
+    FilesystemId, IntegrityManifest, IntegrityManifestError, JournalCheckpoint, MetaKey,
+    WrappedKeyEntry,
+};
 use crate::codec::parser::{
     ParserResult, ParserStateMachine, ProgressType, SegmentStreamer, StateError, StateResult,
 };
-use crate::codec::Stream;
-use crate::filesystem::drive::{VectorClockFilesystemActorSnapshot, VectorClockFilesystemSnapshot};
-use crate::filesystem::{Drive, DriveAccess, InnerDrive};
+use crate::codec::{Cid, Stream};
+use crate::filesystem::drive::directory_handle::MAX_PATH_DEPTH;
+use crate::filesystem::drive::{
+    MetadataEncoding, ShardTable, VectorClockFilesystemActorSnapshot, VectorClockFilesystemSnapshot,
+};
+use crate::filesystem::nodes::{NodeName, NodeNameError};
+use crate::filesystem::{ActorStatus, Drive, DriveAccess, DriveAccessError, InnerDrive};
+
+/// The largest encoded drive [`DriveLoader::parse_untrusted`] will attempt to parse.
+pub const MAX_UNTRUSTED_DRIVE_SIZE: usize = 64 * 1024 * 1024;
+
+/// How far below the root of an untrusted drive a node may be. This covers the deepest path a
+/// drive allows along with the file and associated data nodes that may sit beneath it.
+const MAX_UNTRUSTED_NODE_DEPTH: usize = MAX_PATH_DEPTH + 2;
 
 pub struct DriveLoader<'a> {
     signing_key: &'a SigningKey,
     state: DriveLoaderState,
+    content_hasher: blake3::Hasher,
 
     filesystem_id: Option<FilesystemId>,
     public_settings: Option<PublicSettings>,
     drive_access: Option<DriveAccess>,
+
+    key_escrow: Vec<WrappedKeyEntry>,
+    previous_shards: HashMap<Cid, Vec<u8>>,
+    subtree: Option<Option<NodeName>>,
+    untrusted: bool,
 }
 
 impl<'a> DriveLoader<'a> {
     pub fn new(signing_key: &'a SigningKey) -> Self {
         Self {
             signing_key,
-            state: DriveLoaderState::IdentityHeader,
+            state: DriveLoaderState::Preamble,
+            content_hasher: blake3::Hasher::new(),
 
             filesystem_id: None,
             public_settings: None,
             drive_access: None,
+
+            key_escrow: Vec::new(),
+            previous_shards: HashMap::new(),
+            subtree: None,
+            untrusted: false,
         }
     }
 
+    /// Provides a backup of the drive's escrowed keys made with [`Drive::export_key_escrow`]. It's
+    /// only consulted when the signing key's permission keys escrowed in the drive can't be
+    /// unlocked or are missing, the matching entry is then checked and used in their place. The
+    /// drive's header still needs to be readable with the signing key for the drive to load.
+    pub fn with_key_escrow(mut self, entries: Vec<WrappedKeyEntry>) -> Self {
+        self.key_escrow = entries;
+        self
+    }
+
+    /// Makes the shards of a previous version of the drive available while loading. This is
+    /// required to load a version produced by [`Drive::encode_delta`], which only includes the
+    /// shards that changed and references the rest by the CID of their encrypted form.
+    pub async fn with_previous_version(mut self, previous: &Drive) -> Self {
+        self.previous_shards = previous
+            .shard_cache
+            .read()
+            .await
+            .values()
+            .map(|encrypted_shard| {
+                let shard_cid = crate::utils::calculate_cid(encrypted_shard);
+                (shard_cid, encrypted_shard.clone())
+            })
+            .collect();
+        self
+    }
+
+    /// Parses a complete encoded drive that came from an untrusted source, such as a file uploaded
+    /// by a user. This is the entry point intended for fuzzing and is expected to return an error,
+    /// rather than panic or allocate without bound, for any input.
+    ///
+    /// Compared to [`DriveLoader::from_reader`] this:
+    ///
+    /// * Rejects input larger than [`MAX_UNTRUSTED_DRIVE_SIZE`]. No allocation made while parsing
+    ///   is larger than the input itself.
+    /// * Requires the input to hold exactly one drive, truncated input and trailing data are both
+    ///   errors.
+    /// * Confirms every node is connected to the root of the drive through its parents without
+    ///   cycles and within the nesting depth a drive allows, so later walks of the drive can't
+    ///   loop or recurse without bound.
+    /// * Checks the [`IntegrityManifest`] of drives encoded with one as soon as the header
+    ///   indicates it is present, rejecting truncated or modified drives before the filesystem is
+    ///   decrypted.
+    ///
+    /// Everything after the escrowed keys is encrypted and authenticated, only content that was
+    /// produced by a holder of the drive's keys is parsed beyond that point.
+    pub fn parse_untrusted(mut self, data: &[u8]) -> Result<Drive, DriveLoaderError> {
+        if data.len() > MAX_UNTRUSTED_DRIVE_SIZE {
+            return Err(DriveLoaderError::InputTooLarge(data.len()));
+        }
+
+        self.untrusted = true;
+        let mut remaining = data;
+
+        loop {
+            match self.parse(Stream::new(remaining)) {
+                Ok(ProgressType::Advance(bytes_read)) => {
+                    remaining = remaining
+                        .get(bytes_read..)
+                        .ok_or(DriveLoaderError::UnexpectedStreamEnd)?;
+
+                    if let DriveLoaderState::PrivateContent(content_options, _) = &self.state {
+                        if content_options.include_integrity_manifest() {
+                            let manifest = IntegrityManifest::from_trailing(data)?;
+                            check_manifest_signer(&manifest, self.drive_access.as_ref())?;
+                        }
+                    }
+                }
+                Ok(ProgressType::Ready(bytes_read, drive)) => {
+                    if bytes_read != remaining.len() {
+                        return Err(DriveLoaderError::ParserFailure(format!(
+                            "{} bytes of trailing data after the drive",
+                            remaining.len().saturating_sub(bytes_read)
+                        )));
+                    }
+
+                    return Ok(drive);
+                }
+                Err(err) if err.needs_more_data() => {
+                    return Err(DriveLoaderError::UnexpectedStreamEnd)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Checks the structure of the escrow section of a complete encoded drive, the copies of the
+    /// drive's header key locked to each of its actors. Only the entries addressed to the loader's
+    /// key can be unlocked, so the rest of the entries are checked against the actors recorded in
+    /// the drive's header instead:
+    ///
+    /// * The section holds at least one entry and all of the entries it declares.
+    /// * Every entry is well formed.
+    /// * An entry addressed to the loader's key unlocks with it and the unlocked key decrypts the
+    ///   drive's header.
+    /// * Every entry belongs to one of the drive's actors, with exactly one entry per actor.
+    ///
+    /// The first problem found is reported, intended for diagnosing drives that fail to load or
+    /// catching bugs in how drives are encoded.
+    pub fn verify_escrow(&self, data: &[u8]) -> Result<(), EscrowError> {
+        let (mut input, (_, public_settings, key_count)) =
+            parse_preamble(Stream::new(data)).map_err(EscrowError::invalid_preamble)?;
+
+        if !public_settings.private() {
+            return Err(EscrowError::NotPrivate);
+        }
+
+        let key_count = *key_count;
+        if key_count == 0 {
+            return Err(EscrowError::Empty);
+        }
+
+        let mut entries = Vec::with_capacity(key_count as usize);
+        for index in 0..key_count as usize {
+            let (remaining, entry) =
+                MetaKey::parse_escrow_entry(input).map_err(|err| match err {
+                    ErrMode::Incomplete(_) => EscrowError::Truncated {
+                        expected: key_count as usize,
+                        found: index,
+                    },
+                    _ => EscrowError::MalformedEntry { index },
+                })?;
+
+            input = remaining;
+            entries.push(entry);
+        }
+
+        let meta_key = match MetaKey::unlock_escrow(&entries, self.signing_key) {
+            Some(meta_key) => meta_key,
+            None => {
+                let signing_key_id = self.signing_key.key_id();
+                return match entries
+                    .iter()
+                    .filter(|(key_id, _)| *key_id == signing_key_id)
+                    .count()
+                {
+                    0 => Err(EscrowError::NoEntryForKey),
+                    attempts => Err(EscrowError::UnlockFailed(attempts)),
+                };
+            }
+        };
+
+        let (_, header_buffer) =
+            decrypt_header(input, key_count, &meta_key).map_err(|err| match err {
+                ErrMode::Incomplete(_) => EscrowError::InvalidHeader("header is truncated".into()),
+                _ => EscrowError::HeaderUndecryptable,
+            })?;
+
+        let (access, _, _) = parse_header(&header_buffer, key_count)
+            .map_err(|err| EscrowError::InvalidHeader(err.to_string()))?;
+
+        let mut unmatched_actors: BTreeMap<KeyId, usize> = BTreeMap::new();
+        for settings in access.sorted_actor_settings() {
+            *unmatched_actors
+                .entry(settings.verifying_key().key_id())
+                .or_default() += 1;
+        }
+
+        // Both sections hold the same number of entries, when every escrowed key matches one of
+        // the actors every actor has been matched as well.
+        for (index, (key_id, _)) in entries.into_iter().enumerate() {
+            match unmatched_actors.get_mut(&key_id) {
+                Some(remaining) if *remaining > 0 => *remaining -= 1,
+                _ => {
+                    return Err(EscrowError::UnknownEntry {
+                        index,
+                        key_id: *key_id,
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads a drive encoded with [`ContentOptions::sharded_metadata`] while only decrypting the
+    /// shards needed to reach `path`: the shard holding the root of the drive and the shard of the
+    /// top level directory `path` falls under. An empty path only loads the root shard.
+    ///
+    /// The remaining shards are kept in their encrypted form and each of them can be loaded on
+    /// demand with [`Drive::load_subtree`]. Operations covering the entire drive, such as
+    /// encoding it, load everything that is outstanding first. Reaching into a subtree that is
+    /// still outstanding through a [`crate::filesystem::DirectoryHandle`] fails with
+    /// [`crate::filesystem::OperationError::SubtreeNotLoaded`]. Drives that weren't sharded are
+    /// loaded in full.
+    pub async fn load_subtree<R: AsyncRead + AsyncReadExt + Unpin>(
+        mut self,
+        reader: R,
+        path: &[&str],
+    ) -> Result<Drive, DriveLoaderError> {
+        let top_level = match path.first() {
+            Some(name) => Some(
+                NodeName::named(name.to_string()).map_err(DriveLoaderError::InvalidSubtreePath)?,
+            ),
+            None => None,
+        };
+
+        self.subtree = Some(top_level);
+        self.from_reader(reader).await
+    }
+
     pub async fn from_reader<R: AsyncRead + AsyncReadExt + Unpin>(
         self,
         mut reader: R,
@@ -65,77 +296,80 @@ impl<'a> DriveLoader<'a> {
             };
         }
     }
+
+    /// Behaves the same as [`DriveLoader::from_reader`] for a drive that was compressed with the
+    /// provided encoding, such as a response body with a `Content-Encoding` header. The content is
+    /// decompressed as it is read rather than all at once.
+    pub async fn from_compressed_reader<R: AsyncRead + Unpin>(
+        self,
+        reader: R,
+        encoding: MetadataEncoding,
+    ) -> Result<Drive, DriveLoaderError> {
+        let reader = encoding.decoding_reader(reader)?;
+        self.from_reader(reader).await
+    }
 }
 
 impl ParserStateMachine<Drive> for DriveLoader<'_> {
     type Error = DriveLoaderError;
 
     fn parse(&mut self, buffer: Stream) -> StateResult<Drive, Self::Error> {
-        match &self.state {
-            DriveLoaderState::IdentityHeader => {
-                let (input, id_header) = IdentityHeader::parse_with_magic(buffer)?;
-                let bytes_read = buffer.len() - input.len();
-
-                trace!(bytes_read, ?id_header, "drive_loader::identity_header");
+        let progress = self.parse_state(buffer)?;
 
-                self.state = DriveLoaderState::FilesystemId;
-
-                Ok(ProgressType::Advance(bytes_read))
-            }
-            DriveLoaderState::FilesystemId => {
-                let (input, filesystem_id) = FilesystemId::parse(buffer)?;
-                let bytes_read = buffer.len() - input.len();
-
-                trace!(bytes_read, ?filesystem_id, "drive_loader::filesystem_id");
+        if let ProgressType::Advance(bytes_read) = &progress {
+            self.content_hasher
+                .update(&buffer.into_inner()[..*bytes_read]);
+        }
 
-                self.filesystem_id = Some(filesystem_id);
-                self.state = DriveLoaderState::PublicSettings;
+        Ok(progress)
+    }
+}
 
-                Ok(ProgressType::Advance(bytes_read))
-            }
-            DriveLoaderState::PublicSettings => {
-                let (input, public_settings) = PublicSettings::parse(buffer)?;
+impl DriveLoader<'_> {
+    fn parse_state(&mut self, buffer: Stream) -> StateResult<Drive, DriveLoaderError> {
+        match &self.state {
+            DriveLoaderState::Preamble => {
+                let (input, (filesystem_id, public_settings, key_count)) = parse_preamble(buffer)?;
                 let bytes_read = buffer.len() - input.len();
 
                 trace!(
                     bytes_read,
+                    ?filesystem_id,
                     ?public_settings,
-                    "drive_loader::public_settings"
+                    ?key_count,
+                    "drive_loader::preamble"
                 );
 
-                self.public_settings = Some(public_settings);
-                self.state = DriveLoaderState::KeyCount;
-
-                Ok(ProgressType::Advance(bytes_read))
-            }
-            DriveLoaderState::KeyCount => {
-                let (input, key_count) = KeyCount::parse(buffer)?;
-                let bytes_read = buffer.len() - input.len();
-
-                trace!(bytes_read, ?key_count, "drive_loader::key_count");
-
-                if self
-                    .public_settings
-                    .as_ref()
-                    .expect("to have been set")
-                    .private()
-                {
-                    self.state = DriveLoaderState::EscrowedAccessKeys(key_count);
-                } else {
-                    unimplemented!("public filesystems not yet available");
+                if !public_settings.private() {
+                    return Err(DriveLoaderError::Unsupported(
+                        "public filesystems not yet available",
+                    ));
                 }
 
+                self.filesystem_id = Some(filesystem_id);
+                self.public_settings = Some(public_settings);
+                self.state = DriveLoaderState::EscrowedAccessKeys(key_count);
+
                 Ok(ProgressType::Advance(bytes_read))
             }
             DriveLoaderState::EscrowedAccessKeys(key_count) => {
                 // todo(sstelfox): switch to EncryptedBuffer
-                let (input, meta_key) =
-                    MetaKey::parse_escrow(buffer, **key_count, self.signing_key)?;
+                let (input, locked_keys) = MetaKey::parse_escrow_entries(buffer, **key_count)?;
 
                 let bytes_read = buffer.len() - input.len();
                 trace!(bytes_read, ?key_count, "drive_loader::escrowed_access_keys");
 
-                let meta_key = match meta_key {
+                // Checked before attempting to unlock anything so a key that was never granted
+                // access is reported as such rather than as a failure to decrypt
+                let signing_key_id = self.signing_key.key_id();
+                if !locked_keys
+                    .iter()
+                    .any(|(key_id, _)| *key_id == signing_key_id)
+                {
+                    return Err(DriveLoaderError::KeyNotAuthorized);
+                }
+
+                let meta_key = match MetaKey::unlock_escrow(&locked_keys, self.signing_key) {
                     Some(mk) => mk,
                     None => return Err(DriveLoaderError::AccessUnavailable),
                 };
@@ -146,33 +380,21 @@ impl ParserStateMachine<Drive> for DriveLoader<'_> {
                 Ok(ProgressType::Advance(bytes_read))
             }
             DriveLoaderState::EncryptedHeader(key_count, meta_key) => {
-                let payload_size = (**key_count as usize * DriveAccess::size())
-                    + ContentOptions::size()
-                    + VectorClockFilesystemSnapshot::size();
-
-                let (input, header_buffer) =
-                    EncryptedBuffer::parse_and_decrypt(buffer, payload_size, &[], meta_key)?;
+                let (input, header_buffer) = decrypt_header(buffer, **key_count, meta_key)?;
                 let encrypted_size = buffer.len() - input.len();
                 trace!(
                     encrypted_size,
-                    payload_size,
+                    payload_size = header_buffer.len(),
                     "drive_loader::encrypted_header"
                 );
 
-                let hdr_stream = Stream::new(header_buffer.as_slice());
-
-                let (hdr_stream, access) =
-                    DriveAccess::parse(hdr_stream, **key_count, self.signing_key)?;
+                let (mut access, content_options, vector_clock) =
+                    parse_header(&header_buffer, **key_count)?;
+                access
+                    .unlock_keys_with_escrow(self.signing_key, &self.key_escrow)
+                    .map_err(DriveLoaderError::PermissionKeysUnavailable)?;
                 trace!("drive_loader::encrypted_header::drive_access");
 
-                let (hdr_stream, content_options) = ContentOptions::parse(hdr_stream)?;
-                trace!("drive_loader::encrypted_header::content_options");
-
-                let (hdr_stream, vector_clock) = VectorClockFilesystemSnapshot::parse(hdr_stream)?;
-                trace!("drive_loader::encrypted_header::vector_clock");
-
-                debug_assert!(hdr_stream.is_empty());
-
                 self.drive_access = Some(access);
                 self.state = DriveLoaderState::PrivateContent(content_options, vector_clock);
 
@@ -183,11 +405,6 @@ impl ParserStateMachine<Drive> for DriveLoader<'_> {
             }
             DriveLoaderState::PrivateContent(content_options, filesystem_clock) => {
                 if content_options.include_filesystem() {
-                    let (input, encrypted_size) = content_length(buffer)?;
-
-                    let encrypted_size = encrypted_size as usize;
-                    let payload_size = encrypted_size - (Nonce::size() + AuthenticationTag::size());
-
                     let drive_access = match &self.drive_access {
                         Some(da) => da,
                         None => {
@@ -199,6 +416,64 @@ impl ParserStateMachine<Drive> for DriveLoader<'_> {
                         .filesystem_key()
                         .ok_or(DriveLoaderError::KeyNotAvailable("filesystem key missing"))?;
 
+                    let actor_id = self.signing_key.actor_id();
+                    let actor_clock = drive_access
+                        .actor_vector_clock(&actor_id)
+                        .ok_or(DriveLoaderError::ActorIdNotAvailable)?;
+                    let vector_clocks =
+                        VectorClockFilesystemActorSnapshot::new(*filesystem_clock, actor_clock);
+
+                    if content_options.sharded_filesystem() {
+                        let (input, inner_drive, shard_cache) = parse_sharded_filesystem(
+                            buffer,
+                            content_options,
+                            &self.previous_shards,
+                            self.subtree.as_ref(),
+                            filesystem_key,
+                            drive_access.clone(),
+                            vector_clocks,
+                        )?;
+
+                        if self.untrusted {
+                            check_node_ancestry(&inner_drive)?;
+                        }
+
+                        let (input, journal) =
+                            parse_journal(input, content_options, filesystem_key)?;
+
+                        let buffered = &buffer.into_inner()[..buffer.len() - input.len()];
+                        let input = parse_integrity_manifest(
+                            input,
+                            content_options,
+                            drive_access,
+                            &inner_drive,
+                            &self.content_hasher,
+                            buffered,
+                        )?;
+
+                        let drive = Drive {
+                            current_key: Arc::new(self.signing_key.clone()),
+                            filesystem_id: self.filesystem_id.expect("to have been set"),
+                            private: true,
+                            inner: Arc::new(RwLock::new(inner_drive)),
+                            shard_cache: Arc::new(RwLock::new(shard_cache)),
+                            journal: Arc::new(RwLock::new(journal)),
+                        };
+
+                        let bytes_read = buffer.len() - input.len();
+                        trace!(bytes_read, "drive_loader::sharded_payload::complete");
+
+                        return Ok(ProgressType::Ready(bytes_read, drive));
+                    }
+
+                    let (input, encrypted_size) = content_length(buffer)?;
+
+                    let payload_size = (encrypted_size as usize)
+                        .checked_sub(Nonce::size() + AuthenticationTag::size())
+                        .ok_or_else(|| {
+                            DriveLoaderError::ParserFailure("filesystem is too small".into())
+                        })?;
+
                     // todo(sstelfox): we ideally want to stream this data and selectively parse
                     // things, but that has impacts on the encryption which would need to be managed
                     // carefully. Since this only covers the realized view of the filesystem (the
@@ -220,13 +495,6 @@ impl ParserStateMachine<Drive> for DriveLoader<'_> {
                         "drive_loader::private_content::decrypt_successful"
                     );
 
-                    let actor_id = self.signing_key.actor_id();
-                    let actor_clock = drive_access
-                        .actor_vector_clock(&actor_id)
-                        .ok_or(DriveLoaderError::ActorIdNotAvailable)?;
-                    let vector_clocks =
-                        VectorClockFilesystemActorSnapshot::new(*filesystem_clock, actor_clock);
-
                     let (remaining, inner_drive) = InnerDrive::parse(
                         Stream::new(fs_buffer.as_slice()),
                         drive_access.clone(),
@@ -241,17 +509,37 @@ impl ParserStateMachine<Drive> for DriveLoader<'_> {
                         ),
                         e => e,
                     })?;
-                    debug_assert!(remaining.is_empty());
+                    if !remaining.is_empty() {
+                        return Err(DriveLoaderError::ParserFailure(
+                            "filesystem contained unexpected data after its nodes".into(),
+                        ));
+                    }
+
+                    if self.untrusted {
+                        check_node_ancestry(&inner_drive)?;
+                    }
+
+                    let (input, journal) = parse_journal(input, content_options, filesystem_key)?;
+
+                    let buffered = &buffer.into_inner()[..buffer.len() - input.len()];
+                    let input = parse_integrity_manifest(
+                        input,
+                        content_options,
+                        drive_access,
+                        &inner_drive,
+                        &self.content_hasher,
+                        buffered,
+                    )?;
 
                     let drive = Drive {
                         current_key: Arc::new(self.signing_key.clone()),
                         filesystem_id: self.filesystem_id.expect("to have been set"),
                         private: true,
                         inner: Arc::new(RwLock::new(inner_drive)),
+                        shard_cache: Arc::new(RwLock::new(HashMap::new())),
+                        journal: Arc::new(RwLock::new(journal)),
                     };
 
-                    // todo handle journal entries
-
                     let bytes_read = buffer.len() - input.len();
                     trace!(bytes_read, "drive_loader::encrypted_payload::complete");
 
@@ -260,16 +548,432 @@ impl ParserStateMachine<Drive> for DriveLoader<'_> {
 
                 // todo handle data segments
 
-                unimplemented!("further content");
+                Err(DriveLoaderError::Unsupported(
+                    "drives without filesystem content",
+                ))
             }
         }
     }
 }
 
+/// Confirms each node's chain of parents leads back to the root of the drive within
+/// [`MAX_UNTRUSTED_NODE_DEPTH`] steps. This rules out cycles and detached nodes which the rest of
+/// the library assumes can't exist.
+fn check_node_ancestry(inner_drive: &InnerDrive) -> Result<(), DriveLoaderError> {
+    let root_pid = inner_drive.root_pid();
+
+    for node in inner_drive.node_iter() {
+        let mut current = node;
+
+        for _ in 0..=MAX_UNTRUSTED_NODE_DEPTH {
+            let parent_pid = match current.parent_id() {
+                Some(parent_pid) => parent_pid,
+                None if current.permanent_id() == root_pid => break,
+                None => {
+                    return Err(DriveLoaderError::ParserFailure(
+                        "node is not attached to the drive's root".into(),
+                    ))
+                }
+            };
+
+            current = inner_drive.by_perm_id(&parent_pid).map_err(|_| {
+                DriveLoaderError::ParserFailure("node refers to a missing parent".into())
+            })?;
+        }
+
+        if current.permanent_id() != root_pid || current.parent_id().is_some() {
+            return Err(DriveLoaderError::ParserFailure(
+                "node is nested too deeply or its parents form a cycle".into(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn content_length(input: Stream) -> ParserResult<u64> {
     le_u64.parse_peek(input)
 }
 
+/// The remaining input, the parsed drive, and the encrypted shards keyed by the CID of their root
+/// node.
+type ShardedFilesystem<'a> = (Stream<'a>, InnerDrive, HashMap<Cid, Vec<u8>>);
+
+fn encrypted_shard(input: Stream<'_>, encrypted_len: usize) -> ParserResult<'_, &[u8]> {
+    take(encrypted_len).parse_peek(input)
+}
+
+fn shard_flags(input: Stream<'_>, shard_count: usize) -> ParserResult<'_, &[u8]> {
+    take(shard_count).parse_peek(input)
+}
+
+/// Parses a filesystem that was encoded as multiple independently encrypted shards. Along with
+/// the drive, this returns the encrypted form of each shard keyed by the CID of the node at its
+/// root so unchanged shards can be reused when the drive is encoded again.
+///
+/// Deltas flag each shard after the shard table, shards that weren't included are taken from the
+/// previous version of the drive instead.
+///
+/// When a `subtree` is provided only the root shard and the shard of the named top level
+/// directory are decrypted, the others are recorded in the drive as unloaded.
+fn parse_sharded_filesystem<'a>(
+    input: Stream<'a>,
+    content_options: &ContentOptions,
+    previous_shards: &HashMap<Cid, Vec<u8>>,
+    subtree: Option<&Option<NodeName>>,
+    filesystem_key: &AccessKey,
+    drive_access: DriveAccess,
+    vector_clocks: VectorClockFilesystemActorSnapshot,
+) -> Result<ShardedFilesystem<'a>, DriveLoaderError> {
+    let encryption_overhead = Nonce::size() + AuthenticationTag::size();
+
+    let (input, table_size) = content_length(input)?;
+    let table_payload_size = (table_size as usize)
+        .checked_sub(encryption_overhead)
+        .ok_or_else(|| DriveLoaderError::ParserFailure("shard table is too small".into()))?;
+
+    let (mut input, table_buffer) =
+        EncryptedBuffer::parse_and_decrypt(input, table_payload_size, &[], filesystem_key)?;
+    let (table_remaining, table) =
+        ShardTable::parse(Stream::new(table_buffer.as_slice())).map_err(complete_buffer_error)?;
+    trace!(
+        shard_count = table.entries().len(),
+        "drive_loader::shard_table"
+    );
+
+    let included: Vec<bool> = if content_options.delta_filesystem() {
+        let (_, flags) =
+            shard_flags(table_remaining, table.entries().len()).map_err(complete_buffer_error)?;
+        flags.iter().map(|flag| *flag != 0).collect()
+    } else {
+        vec![true; table.entries().len()]
+    };
+
+    let mut shard_cache = HashMap::new();
+
+    for (entry, included) in table.entries().iter().zip(included) {
+        let encrypted_len = entry.encrypted_len() as usize;
+
+        let encrypted_shard = if included {
+            let (remaining, encrypted_shard) = encrypted_shard(input, encrypted_len)?;
+            input = remaining;
+            encrypted_shard
+        } else {
+            previous_shards
+                .get(entry.cid())
+                .map(Vec::as_slice)
+                .ok_or_else(|| DriveLoaderError::MissingPreviousShard(entry.cid().clone()))?
+        };
+
+        if &crate::utils::calculate_cid(encrypted_shard) != entry.cid() {
+            return Err(DriveLoaderError::ParserFailure(
+                "shard content did not match its recorded CID".into(),
+            ));
+        }
+
+        shard_cache.insert(entry.source_cid().clone(), encrypted_shard.to_vec());
+    }
+
+    let mut nodes = Slab::new();
+    let mut permanent_id_map = HashMap::new();
+
+    let load_shard = |source_cid: &Cid, nodes: &mut _, permanent_id_map: &mut _| {
+        let encrypted_shard = shard_cache
+            .get(source_cid)
+            .expect("every shard in the table was cached");
+        let shard_buffer = decrypt_shard(encrypted_shard, filesystem_key)?;
+
+        let (_, node_count) = InnerDrive::parse_nodes(
+            Stream::new(shard_buffer.as_slice()),
+            nodes,
+            permanent_id_map,
+        )
+        .map_err(complete_buffer_error)?;
+        trace!(node_count, "drive_loader::shard");
+
+        Ok::<_, DriveLoaderError>(())
+    };
+
+    let unloaded_shards = match subtree {
+        None => {
+            for entry in table.entries() {
+                load_shard(entry.source_cid(), &mut nodes, &mut permanent_id_map)?;
+            }
+
+            HashMap::new()
+        }
+        Some(top_level) => {
+            // The root shard is always the last one in the table
+            let root_entry = table
+                .entries()
+                .last()
+                .ok_or_else(|| DriveLoaderError::ParserFailure("shard table is empty".into()))?;
+            load_shard(root_entry.source_cid(), &mut nodes, &mut permanent_id_map)?;
+
+            let root_node = permanent_id_map
+                .get(&table.root_pid())
+                .and_then(|node_id| nodes.get(*node_id))
+                .ok_or_else(|| {
+                    DriveLoaderError::ParserFailure("root node missing from shards".into())
+                })?;
+
+            let requested_pid = top_level
+                .as_ref()
+                .and_then(|name| root_node.data().child_permanent_id(name));
+
+            let mut unloaded_shards = HashMap::new();
+            let mut requested_cid = None;
+
+            for child in root_node.data().child_entries() {
+                if !shard_cache.contains_key(child.cid()) {
+                    continue;
+                }
+
+                if Some(*child.permanent_id()) == requested_pid {
+                    requested_cid = Some(child.cid().clone());
+                } else {
+                    unloaded_shards.insert(*child.permanent_id(), child.cid().clone());
+                }
+            }
+
+            if let Some(requested_cid) = requested_cid {
+                load_shard(&requested_cid, &mut nodes, &mut permanent_id_map)?;
+            }
+
+            unloaded_shards
+        }
+    };
+
+    let inner_drive = InnerDrive::from_parsed_nodes(
+        table.root_pid(),
+        nodes,
+        permanent_id_map,
+        drive_access,
+        vector_clocks,
+    )
+    .ok_or_else(|| DriveLoaderError::ParserFailure("root node missing from shards".into()))?
+    .with_unloaded_shards(unloaded_shards);
+
+    Ok((input, inner_drive, shard_cache))
+}
+
+/// Decrypts a single filesystem shard, producing the encoded nodes it contains.
+pub(crate) fn decrypt_shard(
+    encrypted_shard: &[u8],
+    filesystem_key: &AccessKey,
+) -> Result<Vec<u8>, DriveLoaderError> {
+    let payload_size = encrypted_shard
+        .len()
+        .checked_sub(Nonce::size() + AuthenticationTag::size())
+        .ok_or_else(|| DriveLoaderError::ParserFailure("shard is too small".into()))?;
+
+    let (_, shard_buffer) = EncryptedBuffer::parse_and_decrypt(
+        Stream::new(encrypted_shard),
+        payload_size,
+        &[],
+        filesystem_key,
+    )
+    .map_err(complete_buffer_error)?;
+
+    Ok(shard_buffer)
+}
+
+/// Parses the encrypted journal of checkpoints that follows the filesystem when the content
+/// options indicate one is present, otherwise the drive starts with an empty journal.
+fn parse_journal<'a>(
+    input: Stream<'a>,
+    content_options: &ContentOptions,
+    filesystem_key: &AccessKey,
+) -> Result<(Stream<'a>, Vec<JournalCheckpoint>), DriveLoaderError> {
+    if !content_options.include_journal() {
+        return Ok((input, Vec::new()));
+    }
+
+    let (input, journal_size) = content_length(input)?;
+    let payload_size = (journal_size as usize)
+        .checked_sub(Nonce::size() + AuthenticationTag::size())
+        .ok_or_else(|| DriveLoaderError::ParserFailure("journal is too small".into()))?;
+
+    if payload_size % JournalCheckpoint::size() != 0 {
+        return Err(DriveLoaderError::ParserFailure(
+            "journal size is not a whole number of checkpoints".into(),
+        ));
+    }
+
+    let (input, journal_buffer) =
+        EncryptedBuffer::parse_and_decrypt(input, payload_size, &[], filesystem_key)?;
+
+    let checkpoint_count = payload_size / JournalCheckpoint::size();
+    let mut journal = Vec::with_capacity(checkpoint_count);
+    let mut journal_stream = Stream::new(journal_buffer.as_slice());
+
+    for _ in 0..checkpoint_count {
+        let (remaining, checkpoint) =
+            JournalCheckpoint::parse(journal_stream).map_err(complete_buffer_error)?;
+        journal_stream = remaining;
+        journal.push(checkpoint);
+    }
+
+    trace!(checkpoint_count, "drive_loader::journal");
+
+    Ok((input, journal))
+}
+
+/// Confirms the key that signed an [`IntegrityManifest`] belongs to an actor currently holding
+/// access to the drive. Anyone can sign a manifest, this is what ties it to the drive.
+fn check_manifest_signer(
+    manifest: &IntegrityManifest,
+    drive_access: Option<&DriveAccess>,
+) -> Result<(), DriveLoaderError> {
+    let drive_access =
+        drive_access.ok_or(DriveLoaderError::KeyNotAvailable("drive access missing"))?;
+
+    match drive_access.actor_status(&manifest.verifying_key().actor_id()) {
+        ActorStatus::Active(_) => Ok(()),
+        _ => Err(IntegrityManifestError::UnknownSigner.into()),
+    }
+}
+
+/// Parses and checks the [`IntegrityManifest`] that follows the rest of the drive when the content
+/// options indicate one is present. The bytes of the drive that came before the manifest are the
+/// ones already fed to `content_hasher` followed by the `buffered` bytes of the current buffer.
+fn parse_integrity_manifest<'a>(
+    input: Stream<'a>,
+    content_options: &ContentOptions,
+    drive_access: &DriveAccess,
+    inner_drive: &InnerDrive,
+    content_hasher: &blake3::Hasher,
+    buffered: &[u8],
+) -> Result<Stream<'a>, DriveLoaderError> {
+    if !content_options.include_integrity_manifest() {
+        return Ok(input);
+    }
+
+    let (input, manifest) = IntegrityManifest::parse(input)?;
+
+    let mut content_hasher = content_hasher.clone();
+    content_hasher.update(buffered);
+    let preceding_length = content_hasher.count();
+
+    manifest.verify(preceding_length, content_hasher.finalize().as_bytes())?;
+    check_manifest_signer(&manifest, Some(drive_access))?;
+
+    let root_cid = inner_drive
+        .root_node()
+        .ok()
+        .and_then(|root| root.try_cid())
+        .ok_or(IntegrityManifestError::RootMismatch)?;
+
+    if &root_cid != manifest.root_cid() {
+        return Err(IntegrityManifestError::RootMismatch.into());
+    }
+
+    trace!(preceding_length, "drive_loader::integrity_manifest");
+
+    Ok(input)
+}
+
+/// Parses the unencrypted start of a drive: its identity header, filesystem ID, public settings,
+/// and the number of entries in the escrow section that follows.
+fn parse_preamble(input: Stream) -> ParserResult<(FilesystemId, PublicSettings, KeyCount)> {
+    let (input, _) = IdentityHeader::parse_with_magic(input)?;
+    let (input, filesystem_id) = FilesystemId::parse(input)?;
+    let (input, public_settings) = PublicSettings::parse(input)?;
+    let (input, key_count) = KeyCount::parse(input)?;
+
+    Ok((input, (filesystem_id, public_settings, key_count)))
+}
+
+/// Decrypts the header following the escrow section of a drive with `key_count` actors using the
+/// key unlocked from the escrow section.
+fn decrypt_header<'a>(
+    input: Stream<'a>,
+    key_count: u8,
+    meta_key: &MetaKey,
+) -> ParserResult<'a, Vec<u8>> {
+    let payload_size = (key_count as usize * DriveAccess::size())
+        + ContentOptions::size()
+        + VectorClockFilesystemSnapshot::size();
+
+    EncryptedBuffer::parse_and_decrypt(input, payload_size, &[], meta_key)
+}
+
+/// Parses the decrypted header of a drive, see [`decrypt_header`]. The permission keys of the
+/// returned access are still locked.
+fn parse_header(
+    header_buffer: &[u8],
+    key_count: u8,
+) -> Result<(DriveAccess, ContentOptions, VectorClockFilesystemSnapshot), DriveLoaderError> {
+    let hdr_stream = Stream::new(header_buffer);
+
+    let (hdr_stream, access) =
+        DriveAccess::parse_locked(hdr_stream, key_count).map_err(complete_buffer_error)?;
+    let (hdr_stream, content_options) =
+        ContentOptions::parse(hdr_stream).map_err(complete_buffer_error)?;
+    let (hdr_stream, vector_clock) =
+        VectorClockFilesystemSnapshot::parse(hdr_stream).map_err(complete_buffer_error)?;
+
+    if !hdr_stream.is_empty() {
+        return Err(DriveLoaderError::ParserFailure(
+            "encrypted header contained unexpected data".into(),
+        ));
+    }
+
+    Ok((access, content_options, vector_clock))
+}
+
+/// Buffers that have already been fully read into memory can't be completed by waiting for more
+/// data, running out of data within them is a parse failure rather than a request for more.
+fn complete_buffer_error<E: std::fmt::Debug>(err: ErrMode<E>) -> DriveLoaderError {
+    match err {
+        ErrMode::Incomplete(_) => {
+            DriveLoaderError::ParserFailure("unexpected end of decrypted buffer".into())
+        }
+        err => DriveLoaderError::from(err),
+    }
+}
+
+/// Structural problems with the escrow section of an encoded drive found by
+/// [`DriveLoader::verify_escrow`].
+#[derive(Debug, thiserror::Error)]
+pub enum EscrowError {
+    #[error("the escrow section doesn't hold any keys")]
+    Empty,
+
+    #[error("the escrowed key doesn't decrypt the drive's header")]
+    HeaderUndecryptable,
+
+    #[error("the drive's header is invalid: {0}")]
+    InvalidHeader(String),
+
+    #[error("the drive's identity and settings preceding the escrow section are invalid: {0}")]
+    InvalidPreamble(String),
+
+    #[error("escrowed key entry {index} is malformed")]
+    MalformedEntry { index: usize },
+
+    #[error("none of the escrowed key entries are addressed to the provided key")]
+    NoEntryForKey,
+
+    #[error("public drives don't have an escrow section")]
+    NotPrivate,
+
+    #[error("the escrow section declares {expected} keys but only {found} are present")]
+    Truncated { expected: usize, found: usize },
+
+    #[error("escrowed key entry {index} with key ID {key_id} doesn't belong to any of the drive's actors")]
+    UnknownEntry { index: usize, key_id: u16 },
+
+    #[error("none of the {0} escrowed key entries addressed to the provided key could be unlocked with it")]
+    UnlockFailed(usize),
+}
+
+impl EscrowError {
+    fn invalid_preamble<E: std::fmt::Debug>(err: ErrMode<E>) -> Self {
+        Self::InvalidPreamble(complete_buffer_error(err).to_string())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum DriveLoaderError {
     #[error("the provided signing key does not have access to this encrypted filesystem")]
@@ -278,12 +982,21 @@ pub enum DriveLoaderError {
     #[error("additional data needed to continue parsing")]
     Incomplete(Option<usize>),
 
+    #[error("the path of the subtree to load was invalid: {0}")]
+    InvalidSubtreePath(NodeNameError),
+
+    #[error("input of {0} bytes is larger than an untrusted drive is allowed to be")]
+    InputTooLarge(usize),
+
     #[error("failed to decrypt internal data with associated key: {0}")]
     InternalKeyError(&'static str),
 
     #[error("an I/O error occurred: {0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("the provided signing key is not authorized to open this drive")]
+    KeyNotAuthorized,
+
     #[error("key expected to be available was missing when it was needed: {0}")]
     KeyNotAvailable(&'static str),
 
@@ -293,8 +1006,23 @@ pub enum DriveLoaderError {
     #[error("unexpected end of stream")]
     UnexpectedStreamEnd,
 
+    #[error("drive uses a feature that is not supported yet: {0}")]
+    Unsupported(&'static str),
+
+    #[error("drive was encoded with an unsupported content encoding: {0}")]
+    UnsupportedEncoding(String),
+
     #[error("Actor Id not available in Drive Access")]
     ActorIdNotAvailable,
+
+    #[error("shard {0:?} was left out of the delta and is not part of the previous version")]
+    MissingPreviousShard(Cid),
+
+    #[error("drive failed its integrity manifest check: {0}")]
+    IntegrityCheckFailed(#[from] IntegrityManifestError),
+
+    #[error("the signing key's permission keys couldn't be unlocked: {0}")]
+    PermissionKeysUnavailable(DriveAccessError),
 }
 
 impl StateError for DriveLoaderError {
@@ -327,11 +1055,7 @@ impl<E: std::fmt::Debug> From<winnow::error::ErrMode<E>> for DriveLoaderError {
 
 #[derive(Debug)]
 enum DriveLoaderState {
-    IdentityHeader,
-    FilesystemId,
-    PublicSettings,
-
-    KeyCount,
+    Preamble,
 
     EscrowedAccessKeys(KeyCount),
     EncryptedHeader(KeyCount, MetaKey),
@@ -0,0 +1,351 @@
+use bytes::Bytes;
+use futures::io::{AsyncRead, BufReader};
+
+#[cfg(feature = "compression")]
+use std::collections::VecDeque;
+#[cfg(feature = "compression")]
+use std::pin::Pin;
+#[cfg(feature = "compression")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "compression")]
+use async_compression::futures::bufread;
+#[cfg(feature = "compression")]
+use bytes::Buf;
+#[cfg(feature = "compression")]
+use futures::io::{AsyncBufRead, AsyncReadExt};
+
+use crate::filesystem::drive::DriveLoaderError;
+
+/// The value of the `Accept-Encoding` header sent when requesting metadata that may be
+/// compressed.
+#[cfg(feature = "compression")]
+pub(crate) const ACCEPTED_ENCODINGS: &str = "gzip, zstd";
+
+/// How an encoded drive was compressed for transfer, usually taken from the `Content-Encoding`
+/// header of the response it was received in. Decompressing anything other than
+/// [`MetadataEncoding::Identity`] requires the `compression` feature, without it loading such a
+/// drive fails with [`DriveLoaderError::UnsupportedEncoding`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetadataEncoding {
+    /// The drive isn't compressed.
+    #[default]
+    Identity,
+
+    Gzip,
+
+    Zstd,
+}
+
+impl MetadataEncoding {
+    /// Determines the encoding from the value of a `Content-Encoding` header, a missing header
+    /// means the content wasn't compressed.
+    pub fn from_content_encoding(value: Option<&str>) -> Result<Self, DriveLoaderError> {
+        let value = match value {
+            Some(value) => value.trim(),
+            None => return Ok(Self::Identity),
+        };
+
+        match value.to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(Self::Identity),
+            "gzip" | "x-gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(DriveLoaderError::UnsupportedEncoding(value.to_string())),
+        }
+    }
+
+    /// Creates a [`ChunkDecoder`] for content arriving in pieces with this encoding.
+    pub(crate) fn chunk_decoder(self) -> Result<ChunkDecoder, DriveLoaderError> {
+        let state = match self {
+            Self::Identity => ChunkDecoderState::Identity,
+            #[cfg(feature = "compression")]
+            Self::Gzip => {
+                let mut decoder = bufread::GzipDecoder::new(ChunkInput::default());
+                decoder.multiple_members(true);
+                ChunkDecoderState::Gzip(decoder)
+            }
+            #[cfg(feature = "compression")]
+            Self::Zstd => {
+                let mut decoder = bufread::ZstdDecoder::new(ChunkInput::default());
+                decoder.multiple_members(true);
+                ChunkDecoderState::Zstd(decoder)
+            }
+            #[cfg(not(feature = "compression"))]
+            _ => return Err(self.unsupported()),
+        };
+
+        Ok(ChunkDecoder(state))
+    }
+
+    /// Wraps the reader so everything read from it comes out decompressed.
+    pub(crate) fn decoding_reader<'a, R: AsyncRead + Unpin + 'a>(
+        self,
+        reader: R,
+    ) -> Result<Box<dyn AsyncRead + Unpin + 'a>, DriveLoaderError> {
+        let reader = BufReader::new(reader);
+
+        match self {
+            Self::Identity => Ok(Box::new(reader)),
+            #[cfg(feature = "compression")]
+            Self::Gzip => {
+                let mut decoder = bufread::GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Ok(Box::new(decoder))
+            }
+            #[cfg(feature = "compression")]
+            Self::Zstd => {
+                let mut decoder = bufread::ZstdDecoder::new(reader);
+                decoder.multiple_members(true);
+                Ok(Box::new(decoder))
+            }
+            #[cfg(not(feature = "compression"))]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn unsupported(&self) -> DriveLoaderError {
+        DriveLoaderError::UnsupportedEncoding(self.name().to_string())
+    }
+}
+
+impl std::fmt::Display for MetadataEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Decompresses content that is received as a sequence of chunks, such as a response body. Each
+/// chunk produces whatever output could be decoded so far, the decoder keeps its state between
+/// chunks so content arriving over several responses can be fed through the same decoder. Once
+/// all the content has been received [`ChunkDecoder::finish`] needs to be called to decode the
+/// remainder and check the content wasn't cut short.
+pub(crate) struct ChunkDecoder(ChunkDecoderState);
+
+enum ChunkDecoderState {
+    Identity,
+    #[cfg(feature = "compression")]
+    Gzip(bufread::GzipDecoder<ChunkInput>),
+    #[cfg(feature = "compression")]
+    Zstd(bufread::ZstdDecoder<ChunkInput>),
+}
+
+impl ChunkDecoder {
+    pub(crate) async fn decode(&mut self, chunk: Bytes) -> Result<Bytes, DriveLoaderError> {
+        match &mut self.0 {
+            ChunkDecoderState::Identity => Ok(chunk),
+            #[cfg(feature = "compression")]
+            ChunkDecoderState::Gzip(decoder) => {
+                decoder.get_mut().chunks.push_back(chunk);
+                Ok(decode_available(decoder)?)
+            }
+            #[cfg(feature = "compression")]
+            ChunkDecoderState::Zstd(decoder) => {
+                decoder.get_mut().chunks.push_back(chunk);
+                Ok(decode_available(decoder)?)
+            }
+        }
+    }
+
+    /// Decodes whatever remains once all the content has been received. Fails if the content
+    /// ended part way through a compressed stream or a stream's trailer doesn't match the content
+    /// decoded from it.
+    pub(crate) async fn finish(self) -> Result<Bytes, DriveLoaderError> {
+        match self.0 {
+            ChunkDecoderState::Identity => Ok(Bytes::new()),
+            #[cfg(feature = "compression")]
+            ChunkDecoderState::Gzip(mut decoder) => {
+                decoder.get_mut().finished = true;
+                Ok(decode_remaining(decoder).await?)
+            }
+            #[cfg(feature = "compression")]
+            ChunkDecoderState::Zstd(mut decoder) => {
+                decoder.get_mut().finished = true;
+                Ok(decode_remaining(decoder).await?)
+            }
+        }
+    }
+}
+
+/// The input of a [`ChunkDecoder`], holding the chunks that haven't been decoded yet. Running out
+/// of chunks is reported as pending rather than the end of the input until the input is marked as
+/// finished. The decoder is only polled directly by [`decode_available`], so no waker needs to be
+/// registered.
+#[cfg(feature = "compression")]
+#[derive(Default)]
+struct ChunkInput {
+    chunks: VecDeque<Bytes>,
+    finished: bool,
+}
+
+#[cfg(feature = "compression")]
+impl AsyncRead for ChunkInput {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let available = match self.as_mut().poll_fill_buf(cx) {
+            Poll::Ready(Ok(available)) => available,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+
+        Poll::Ready(Ok(read))
+    }
+}
+
+#[cfg(feature = "compression")]
+impl AsyncBufRead for ChunkInput {
+    fn poll_fill_buf(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+        while this.chunks.front().is_some_and(Bytes::is_empty) {
+            this.chunks.pop_front();
+        }
+
+        match this.chunks.front() {
+            Some(chunk) => Poll::Ready(Ok(chunk.as_ref())),
+            None if this.finished => Poll::Ready(Ok(&[])),
+            None => Poll::Pending,
+        }
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        if let Some(chunk) = self.chunks.front_mut() {
+            chunk.advance(amt);
+        }
+    }
+}
+
+/// Reads everything the decoder is able to produce from the chunks it has received so far.
+#[cfg(feature = "compression")]
+fn decode_available<R: AsyncRead + Unpin>(decoder: &mut R) -> std::io::Result<Bytes> {
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    let mut output = Vec::new();
+    let mut buffer = [0u8; 8 * 1024];
+
+    loop {
+        match Pin::new(&mut *decoder).poll_read(&mut cx, &mut buffer) {
+            Poll::Ready(Ok(0)) | Poll::Pending => return Ok(Bytes::from(output)),
+            Poll::Ready(Ok(read)) => output.extend_from_slice(&buffer[..read]),
+            Poll::Ready(Err(err)) => return Err(err),
+        }
+    }
+}
+
+/// Reads the decoder to the end of its input, which needs to have been marked as finished.
+#[cfg(feature = "compression")]
+async fn decode_remaining<R: AsyncRead + Unpin>(mut decoder: R) -> std::io::Result<Bytes> {
+    let mut output = Vec::new();
+    decoder.read_to_end(&mut output).await?;
+
+    Ok(Bytes::from(output))
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+
+    use futures::AsyncReadExt;
+
+    async fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        bufread::GzipEncoder::new(data)
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+        compressed
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_chunk_decoder_handles_split_input() {
+        let original: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| (i % 251).to_le_bytes())
+            .collect();
+        let compressed = gzip(&original).await;
+
+        let mut decoder = MetadataEncoding::Gzip.chunk_decoder().unwrap();
+        let mut decoded = Vec::new();
+        for chunk in compressed.chunks(7) {
+            let output = decoder.decode(Bytes::copy_from_slice(chunk)).await.unwrap();
+            decoded.extend_from_slice(&output);
+        }
+        decoded.extend_from_slice(&decoder.finish().await.unwrap());
+
+        assert_eq!(decoded, original);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_chunk_decoder_handles_multiple_members() {
+        let mut compressed = gzip(b"first member, ").await;
+        compressed.extend_from_slice(&gzip(b"second member").await);
+
+        let mut decoder = MetadataEncoding::Gzip.chunk_decoder().unwrap();
+        let mut decoded = Vec::new();
+        for chunk in compressed.chunks(5) {
+            let output = decoder.decode(Bytes::copy_from_slice(chunk)).await.unwrap();
+            decoded.extend_from_slice(&output);
+        }
+        decoded.extend_from_slice(&decoder.finish().await.unwrap());
+
+        assert_eq!(decoded, b"first member, second member");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_chunk_decoder_rejects_truncated_streams() {
+        let original = vec![0x42; 4096];
+        let compressed = gzip(&original).await;
+
+        // Cutting off the trailer leaves all of the content decodable, only finishing notices
+        let truncated = &compressed[..compressed.len() - 4];
+        let mut decoder = MetadataEncoding::Gzip.chunk_decoder().unwrap();
+        decoder
+            .decode(Bytes::copy_from_slice(truncated))
+            .await
+            .unwrap();
+        assert!(decoder.finish().await.is_err());
+
+        let mut zstd_compressed = Vec::new();
+        bufread::ZstdEncoder::new(original.as_slice())
+            .read_to_end(&mut zstd_compressed)
+            .await
+            .unwrap();
+
+        let truncated = &zstd_compressed[..zstd_compressed.len() / 2];
+        let mut decoder = MetadataEncoding::Zstd.chunk_decoder().unwrap();
+        decoder
+            .decode(Bytes::copy_from_slice(truncated))
+            .await
+            .unwrap();
+        assert!(decoder.finish().await.is_err());
+    }
+
+    #[test]
+    fn test_content_encoding_parsing() {
+        let parse = MetadataEncoding::from_content_encoding;
+
+        assert_eq!(parse(None).unwrap(), MetadataEncoding::Identity);
+        assert_eq!(parse(Some("identity")).unwrap(), MetadataEncoding::Identity);
+        assert_eq!(parse(Some(" GZIP ")).unwrap(), MetadataEncoding::Gzip);
+        assert_eq!(parse(Some("zstd")).unwrap(), MetadataEncoding::Zstd);
+        assert!(matches!(
+            parse(Some("br")),
+            Err(DriveLoaderError::UnsupportedEncoding(_))
+        ));
+    }
+}
@@ -1,34 +1,77 @@
 mod access;
+mod access_summary;
+mod actor_status;
+mod archive;
+mod capability_token;
+mod collision_policy;
 mod directory_entry;
 mod directory_handle;
+#[cfg(feature = "local-store")]
+mod directory_import;
+mod drive_stats;
 mod inner;
 mod loader;
+mod metadata_encoding;
 mod operations;
+mod path_pattern;
+mod path_resolution;
+#[cfg(feature = "banyan-api")]
+mod platform_attributes;
+mod shard_table;
+mod snapshot;
 mod walk_state;
+mod write_stream_options;
 
 pub use access::{DriveAccess, DriveAccessError};
+pub use access_summary::AccessSummary;
+pub use actor_status::ActorStatus;
+pub use capability_token::CapabilityToken;
+pub use collision_policy::CollisionPolicy;
 pub use directory_entry::DirectoryEntry;
 pub use directory_handle::DirectoryHandle;
-pub use loader::{DriveLoader, DriveLoaderError};
+#[cfg(feature = "local-store")]
+pub use directory_import::{ImportError, ImportManifest, ImportSummary};
+pub use drive_stats::DriveStats;
+pub use loader::{DriveLoader, DriveLoaderError, EscrowError, MAX_UNTRUSTED_DRIVE_SIZE};
+pub use metadata_encoding::MetadataEncoding;
 pub use operations::OperationError;
+pub use path_resolution::{PathResolution, PathResolutionFailure, ResolvedComponent};
+pub use snapshot::DriveSnapshot;
+pub use write_stream_options::WriteStreamOptions;
 
 pub(crate) use inner::InnerDrive;
+pub(crate) use metadata_encoding::ChunkDecoder;
+#[cfg(feature = "compression")]
+pub(crate) use metadata_encoding::ACCEPTED_ENCODINGS;
+pub(crate) use shard_table::{ShardEntry, ShardTable};
 pub(crate) use walk_state::WalkState;
 
+use std::collections::{HashMap, HashSet};
 use std::io::{Error as StdError, ErrorKind as StdErrorKind};
-use std::ops::Deref;
+use std::ops::{ControlFlow, Deref, RangeBounds};
 use std::sync::Arc;
 
 use async_std::sync::RwLock;
+use bytes::Bytes;
 use elliptic_curve::rand_core::CryptoRngCore;
 use futures::io::{AsyncWrite, AsyncWriteExt};
+use futures::TryFutureExt;
 use tracing::trace;
 
+#[cfg(feature = "banyan-api")]
+use crate::api::platform::{DriveKind, StorageClass};
 use crate::codec::crypto::*;
+use crate::codec::filesystem::NodeKind;
 use crate::codec::header::*;
+use crate::codec::utils::CountingWriter;
 use crate::codec::*;
 
-use crate::filesystem::nodes::{Node, NodeBuilderError, NodeName};
+use crate::filesystem::nodes::{MetadataKey, Node, NodeBuilderError, NodeName};
+use crate::stores::DataStore;
+use crate::utils::std_io_err;
+
+use self::archive::ArchiveEntry;
+use self::path_pattern::PathPattern;
 
 /// The core entry point of the library, a `Drive` is the means through which the BanyanFS
 /// filesystem's public or private data is accessed. Initial creation of a new drive requires a
@@ -58,28 +101,110 @@ pub struct Drive {
 
     current_key: Arc<SigningKey>,
     inner: Arc<RwLock<InnerDrive>>,
+
+    /// The encrypted form of each filesystem shard from the last time this drive was loaded or
+    /// encoded as shards, keyed by the CID of the node at the root of the shard.
+    shard_cache: Arc<RwLock<HashMap<Cid, Vec<u8>>>>,
+
+    /// Checkpoints for each version of the filesystem that has been encoded, oldest first.
+    journal: Arc<RwLock<Vec<JournalCheckpoint>>>,
 }
 
 impl Drive {
+    /// Encodes the drive with the provided content options. When the options include the journal
+    /// (see [`ContentOptions::with_journal`]) a checkpoint for the current version of the
+    /// filesystem is added to the drive's journal before it is written, unless the latest
+    /// checkpoint already covers that version.
     pub async fn encode<W: AsyncWrite + Unpin + Send>(
         &self,
         rng: &mut impl CryptoRngCore,
         content_options: ContentOptions,
         writer: &mut W,
-    ) -> std::io::Result<usize> {
+    ) -> Result<usize, DriveEncodeError> {
         if self.private {
-            self.encode_private(rng, content_options, writer).await
+            self.encode_private(rng, content_options, None, writer)
+                .await
         } else {
-            unimplemented!("public encoding not implemented")
+            Err(DriveEncodeError::PublicEncodingUnsupported)
+        }
+    }
+
+    /// Encodes the drive with [`ContentOptions::delta_metadata`], leaving out the shards of the
+    /// filesystem that are already present in a previous version of the drive. The previous
+    /// version is identified by the CIDs of its encrypted shards as reported by
+    /// [`Drive::shard_cids`]. This is the metadata counterpart to only uploading new data blocks,
+    /// small changes to a large drive only need to push the shards that were affected.
+    ///
+    /// A shard is only left out when the previous version holds exactly the same encrypted
+    /// shard, any other shards are included as usual. The result can only be loaded with the
+    /// previous version on hand, see [`DriveLoader::with_previous_version`].
+    pub async fn encode_delta<W: AsyncWrite + Unpin + Send>(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        previous_shards: &HashSet<Cid>,
+        writer: &mut W,
+    ) -> Result<usize, DriveEncodeError> {
+        if !self.private {
+            return Err(DriveEncodeError::PublicEncodingUnsupported);
         }
+
+        let content_options = ContentOptions::delta_metadata();
+        self.encode_private(rng, content_options, Some(previous_shards), writer)
+            .await
     }
 
     async fn encode_private<W: AsyncWrite + Unpin + Send>(
         &self,
         rng: &mut impl CryptoRngCore,
         content_options: ContentOptions,
+        previous_shards: Option<&HashSet<Cid>>,
         writer: &mut W,
-    ) -> std::io::Result<usize> {
+    ) -> Result<usize, DriveEncodeError> {
+        if !content_options.include_integrity_manifest() {
+            return self
+                .encode_private_contents(rng, content_options, previous_shards, writer)
+                .await;
+        }
+
+        // The manifest records the length and hash of the bytes that precede it, both are taken
+        // from what actually reaches the writer.
+        let mut counting_writer = CountingWriter::new(writer);
+        self.encode_private_contents(rng, content_options, previous_shards, &mut counting_writer)
+            .await?;
+        let total_length = counting_writer.written();
+        let content_hash = counting_writer.hash();
+
+        let root_cid = {
+            let inner_read = self.inner.read().await;
+            let root_node = inner_read
+                .root_node()
+                .map_err(DriveEncodeError::ManifestRootUnavailable)?;
+
+            root_node
+                .cid()
+                .await
+                .map_err(DriveEncodeError::ManifestRootUnavailable)?
+        };
+
+        let manifest =
+            IntegrityManifest::sign(rng, &self.current_key, total_length, content_hash, root_cid);
+        let manifest_size = manifest.encode(writer).await?;
+        tracing::trace!(total_length, "drive::encode_private::integrity_manifest");
+
+        Ok(total_length as usize + manifest_size)
+    }
+
+    async fn encode_private_contents<W: AsyncWrite + Unpin + Send>(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        content_options: ContentOptions,
+        previous_shards: Option<&HashSet<Cid>>,
+        writer: &mut W,
+    ) -> Result<usize, DriveEncodeError> {
+        self.load_remaining_subtrees()
+            .await
+            .map_err(DriveEncodeError::SubtreeUnavailable)?;
+
         let mut written_bytes = 0;
 
         written_bytes += IdentityHeader::encode(&IdentityHeader, writer).await?;
@@ -98,8 +223,13 @@ impl Drive {
 
         let mut inner_header_size = inner_read.access().encode(rng, &mut *header_buffer).await?;
         inner_header_size += content_options.encode(&mut *header_buffer).await?;
+        // The header only records the filesystem's vector clock, which is all the loader reads
+        // back. Each actor's clock is already recorded in their access settings. Drives encoded
+        // before this also wrote the current actor's clock here, leaving the header longer than
+        // the loader expects.
         inner_header_size += inner_read
             .vector_clock()
+            .filesystem()
             .encode(&mut *header_buffer)
             .await?;
 
@@ -111,31 +241,426 @@ impl Drive {
         tracing::trace!(payload_size = ?inner_header_size, encrypted_size = ?hdr_len, "drive::encode_private::header");
 
         if content_options.include_filesystem() {
-            let mut fs_buffer = EncryptedBuffer::default();
-
             let filesystem_key = inner_read
                 .access()
                 .filesystem_key()
-                .ok_or(StdError::new(StdErrorKind::Other, "no filesystem key"))?;
+                .ok_or(DriveEncodeError::MissingFilesystemKey)?;
+
+            if content_options.sharded_filesystem() {
+                // Without a previous version every shard needs to be included
+                let no_previous_shards = HashSet::new();
+                let previous_shards = content_options
+                    .delta_filesystem()
+                    .then(|| previous_shards.unwrap_or(&no_previous_shards));
+
+                written_bytes += self
+                    .encode_shards(rng, &inner_read, filesystem_key, previous_shards, writer)
+                    .await?;
+            } else {
+                // Only the encrypted form of the buffer reaches the writer, what's encoded into it
+                // doesn't count towards the bytes written
+                let mut fs_buffer = EncryptedBuffer::default();
+                inner_read.encode(&mut *fs_buffer).await?;
+
+                // todo(sstelfox): use filesystem ID and encoded length bytes as AD, but this is a
+                // breaking change...
+
+                let buffer_length = fs_buffer.encrypted_len() as u64;
+                let length_bytes = buffer_length.to_le_bytes();
+                writer.write_all(&length_bytes).await?;
+                written_bytes += length_bytes.len();
+
+                written_bytes += fs_buffer
+                    .encrypt_and_encode(rng, writer, &[], filesystem_key)
+                    .await?;
+            }
+
+            if content_options.include_journal() {
+                written_bytes += self
+                    .encode_journal(rng, &inner_read, filesystem_key, writer)
+                    .await?;
+            }
+        }
+
+        Ok(written_bytes)
+    }
+
+    /// Records a checkpoint for the current version of the filesystem if it has changed since the
+    /// last one was recorded, then encodes the complete journal encrypted with the filesystem key.
+    async fn encode_journal<W: AsyncWrite + Unpin + Send>(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        inner: &InnerDrive,
+        filesystem_key: &AccessKey,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let mut journal = self.journal.write().await;
+        if let Some(checkpoint) = pending_checkpoint(inner, &journal).await? {
+            journal.push(checkpoint);
+        }
+
+        let mut journal_buffer = EncryptedBuffer::default();
+        for checkpoint in journal.iter() {
+            checkpoint.encode(&mut *journal_buffer).await?;
+        }
+
+        let length_bytes = (journal_buffer.encrypted_len() as u64).to_le_bytes();
+        writer.write_all(&length_bytes).await?;
+        let mut written_bytes = length_bytes.len();
+
+        written_bytes += journal_buffer
+            .encrypt_and_encode(rng, writer, &[], filesystem_key)
+            .await?;
+
+        tracing::trace!(
+            checkpoint_count = journal.len(),
+            "drive::encode_private::journal"
+        );
+
+        Ok(written_bytes)
+    }
+
+    /// Encodes the filesystem as an encrypted [`ShardTable`] followed by each of the independently
+    /// encrypted shards it lists. Shards whose root node is unchanged since the drive was last
+    /// loaded or encoded reuse their existing encrypted form, only the shards that have changed
+    /// are encoded and encrypted again.
+    ///
+    /// When encoding a delta, the shard table is followed by a flag for each shard indicating
+    /// whether it is included. Shards whose encrypted form is present in the previous version are
+    /// left out.
+    async fn encode_shards<W: AsyncWrite + Unpin + Send>(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        inner: &InnerDrive,
+        filesystem_key: &AccessKey,
+        previous_shards: Option<&HashSet<Cid>>,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let root_pid = inner.root_pid();
+        let shard_pids = inner
+            .shard_roots()
+            .map_err(|_| std_io_err("unable to determine filesystem shards"))?;
+
+        // The root shard needs to stop at each of the other shards, everything else runs to the
+        // bottom of its subtree.
+        let root_exclusions: HashSet<PermanentId> = shard_pids
+            .iter()
+            .copied()
+            .filter(|pid| *pid != root_pid)
+            .collect();
+        let no_exclusions = HashSet::new();
+
+        let mut shard_cache = self.shard_cache.write().await;
+        let mut updated_cache = HashMap::with_capacity(shard_pids.len());
+        let mut entries = Vec::with_capacity(shard_pids.len());
 
-            written_bytes += inner_read.encode(&mut *fs_buffer).await?;
+        for shard_pid in shard_pids {
+            let source_cid = inner
+                .by_perm_id(&shard_pid)
+                .map_err(|_| std_io_err("missing shard root PID"))?
+                .cid()
+                .await
+                .map_err(|_| std_io_err("unable to calculate shard root CID"))?;
 
-            // todo(sstelfox): use filesystem ID and encoded length bytes as AD, but this is a
-            // breaking change...
+            let encrypted_shard = match shard_cache.remove(&source_cid) {
+                Some(encrypted_shard) => encrypted_shard,
+                None => {
+                    let exclusions = if shard_pid == root_pid {
+                        &root_exclusions
+                    } else {
+                        &no_exclusions
+                    };
 
-            let buffer_length = fs_buffer.encrypted_len() as u64;
-            let length_bytes = buffer_length.to_le_bytes();
-            writer.write_all(&length_bytes).await?;
-            written_bytes += length_bytes.len();
+                    let mut shard_buffer = EncryptedBuffer::default();
+                    inner
+                        .encode_shard(shard_pid, exclusions, &mut *shard_buffer)
+                        .await?;
 
-            written_bytes += fs_buffer
-                .encrypt_and_encode(rng, writer, &[], filesystem_key)
-                .await?;
+                    let mut encrypted_shard = Vec::with_capacity(shard_buffer.encrypted_len());
+                    shard_buffer
+                        .encrypt_and_encode(rng, &mut encrypted_shard, &[], filesystem_key)
+                        .await?;
+
+                    encrypted_shard
+                }
+            };
+
+            let shard_cid = crate::utils::calculate_cid(&encrypted_shard);
+            let encrypted_len = encrypted_shard.len() as u64;
+            entries.push(ShardEntry::new(
+                source_cid.clone(),
+                shard_cid,
+                encrypted_len,
+            ));
+
+            updated_cache.insert(source_cid, encrypted_shard);
+        }
+
+        let table = ShardTable::new(root_pid, entries);
+        let mut table_buffer = EncryptedBuffer::default();
+        table.encode(&mut *table_buffer).await?;
+
+        let included: Vec<bool> = table
+            .entries()
+            .iter()
+            .map(|entry| match previous_shards {
+                Some(previous_shards) => !previous_shards.contains(entry.cid()),
+                None => true,
+            })
+            .collect();
+
+        if previous_shards.is_some() {
+            let flags = included.iter().map(|included| *included as u8);
+            table_buffer.extend(flags);
+        }
+
+        let length_bytes = (table_buffer.encrypted_len() as u64).to_le_bytes();
+        writer.write_all(&length_bytes).await?;
+        let mut written_bytes = length_bytes.len();
+
+        written_bytes += table_buffer
+            .encrypt_and_encode(rng, writer, &[], filesystem_key)
+            .await?;
+
+        for (entry, included) in table.entries().iter().zip(included) {
+            if !included {
+                continue;
+            }
+
+            let encrypted_shard = updated_cache
+                .get(entry.source_cid())
+                .ok_or(std_io_err("encoded shard went missing"))?;
+
+            writer.write_all(encrypted_shard).await?;
+            written_bytes += encrypted_shard.len();
         }
 
+        tracing::trace!(
+            shard_count = table.entries().len(),
+            "drive::encode_private::shards"
+        );
+
+        *shard_cache = updated_cache;
+
         Ok(written_bytes)
     }
 
+    /// Estimates the number of bytes [`Drive::encode`] will produce with the provided content
+    /// options without encoding or encrypting anything. This is the sum of the header and key
+    /// escrow overhead for every registered actor along with the encoded size of each of the
+    /// filesystem's nodes, intended for things like progress reporting and sizing buffers before
+    /// the drive is encoded.
+    ///
+    /// This is only an estimate. Pushing the drive to the platform should use the actual size of
+    /// the encoded drive once it is available.
+    pub async fn estimated_encoded_size(&self, content_options: ContentOptions) -> u64 {
+        self.load_remaining_subtrees_or_warn().await;
+        let inner_read = self.inner.read().await;
+
+        let encryption_overhead = Nonce::size() + AuthenticationTag::size();
+        let actor_count = inner_read.access().sorted_actor_settings().len();
+
+        let mut estimated_size = IdentityHeader::size()
+            + FilesystemId::size()
+            + PublicSettings::size()
+            + KeyCount::size()
+            + actor_count * (KeyId::size() + AsymLockedAccessKey::size());
+
+        estimated_size += encryption_overhead
+            + actor_count * (KeyId::size() + ActorSettings::size())
+            + ContentOptions::size()
+            + VectorClockFilesystemSnapshot::size();
+
+        if !content_options.include_filesystem() {
+            return estimated_size as u64;
+        }
+
+        let nodes_size: u64 = inner_read.node_iter().map(Node::encoded_size).sum();
+
+        if content_options.sharded_filesystem() {
+            let shard_count = inner_read.shard_roots().map_or(1, |roots| roots.len());
+            let table_size = PermanentId::size() + 2 + shard_count * (2 * Cid::size() + 8);
+
+            // Each shard is encrypted on its own and carries its own node count
+            estimated_size += 8 + encryption_overhead + table_size;
+            estimated_size += shard_count * (encryption_overhead + 8);
+
+            // Deltas flag whether each shard is included, every shard is counted as included
+            if content_options.delta_filesystem() {
+                estimated_size += shard_count;
+            }
+        } else {
+            estimated_size += 8 + encryption_overhead + PermanentId::size() + 8;
+        }
+
+        if content_options.include_journal() {
+            let journal = self.journal.read().await;
+            let pending = pending_checkpoint(&inner_read, &journal)
+                .await
+                .map_or(true, |checkpoint| checkpoint.is_some());
+            let checkpoint_count = journal.len() + usize::from(pending);
+
+            estimated_size +=
+                8 + encryption_overhead + checkpoint_count * JournalCheckpoint::size();
+        }
+
+        if content_options.include_integrity_manifest() {
+            estimated_size += IntegrityManifest::size();
+        }
+
+        estimated_size as u64 + nodes_size
+    }
+
+    /// Summarizes the access the provided actor has to this drive, combining
+    /// [`Drive::has_read_access`], [`Drive::has_write_access`], [`Drive::has_maintenance_access`],
+    /// and which of the drive's keys the actor holds into a single check.
+    pub async fn access_summary(&self, actor_id: &ActorId) -> AccessSummary {
+        let inner = self.inner.read().await;
+        inner.access().access_summary(actor_id)
+    }
+
+    /// Reports whether the provided key belongs to an actor that currently has access to the
+    /// drive, one whose access has been revoked, or one the drive has never known about.
+    pub async fn actor_status(&self, key: &VerifyingKey) -> ActorStatus {
+        let inner = self.inner.read().await;
+        inner.access().actor_status(&key.actor_id())
+    }
+
+    /// Produces a complete and independent copy of this drive under a newly generated
+    /// [`FilesystemId`], intended for using an existing drive as a template. The copy keeps the
+    /// entire tree, the actors with access, and the permission keys of the original. The
+    /// filesystem and actor vector clocks restart from their initial state as the copy has no
+    /// shared history with the original.
+    ///
+    /// [`PermanentId`]s and the vector clocks of individual nodes are preserved. These are only
+    /// meaningful within a single filesystem so reuse between the two drives is harmless, but
+    /// the platform sees the copy as an unrelated drive: none of the original's metadata versions
+    /// or block references carry over and the copy needs to be pushed as a new drive of its own.
+    /// As the permission keys are shared, any actor able to read one of the drives is able to
+    /// read the data blocks of both and blocks already present in the data store can be reused by
+    /// the copy without being re-uploaded.
+    ///
+    /// Drives opened with [`DriveLoader::load_subtree`] have their remaining subtrees loaded
+    /// first so the copy is always complete.
+    pub async fn clone_with_new_id(
+        &self,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Self, DriveError> {
+        self.load_remaining_subtrees().await?;
+
+        let actor_id = self.current_key.actor_id();
+        let vector_clocks = VectorClockFilesystemActorSnapshot::new(
+            VectorClockFilesystem::initialize().as_snapshot(),
+            VectorClockActor::initialize(actor_id).as_snapshot(),
+        );
+
+        let inner_read = self.inner.read().await;
+        let inner = inner_read.deep_copy(vector_clocks).await?;
+        drop(inner_read);
+
+        let drive = Self {
+            filesystem_id: FilesystemId::generate(rng),
+            private: self.private,
+            current_key: self.current_key.clone(),
+            inner: Arc::new(RwLock::new(inner)),
+            shard_cache: Arc::new(RwLock::new(HashMap::new())),
+            journal: Arc::new(RwLock::new(Vec::new())),
+        };
+
+        Ok(drive)
+    }
+
+    /// The CIDs of each of the filesystem's encrypted shards from when the drive was last loaded
+    /// or encoded with sharded metadata. Used to identify the version being built upon when
+    /// encoding a later version with [`Drive::encode_delta`].
+    pub async fn shard_cids(&self) -> HashSet<Cid> {
+        self.shard_cache
+            .read()
+            .await
+            .values()
+            .map(|encrypted_shard| crate::utils::calculate_cid(encrypted_shard))
+            .collect()
+    }
+
+    /// The checkpoints recorded each time a new version of the filesystem was encoded, ordered
+    /// from oldest to newest. Each one identifies the root of the filesystem at that version
+    /// along with the filesystem's vector clock and when it was recorded, which is enough to
+    /// present a timeline of the drive and pick out a version to compare against or restore.
+    ///
+    /// Checkpoints are only recorded and persisted when the drive is encoded with content options
+    /// that include the journal, see [`ContentOptions::with_journal`]. Drives loaded from an
+    /// encoding without a journal start with an empty one.
+    pub async fn checkpoints(&self) -> Vec<JournalCheckpoint> {
+        self.journal.read().await.clone()
+    }
+
+    /// The CIDs of every data block referenced by the files in the drive, these are the blocks
+    /// that need to be held by a data store for all of the drive's content to be readable. Only
+    /// the drive's metadata is consulted, no blocks are retrieved. Blocks making up the encoded
+    /// drive itself, such as [`Drive::shard_cids`], are not included.
+    pub async fn all_cids(&self) -> HashSet<Cid> {
+        self.block_reference_counts().await.into_keys().collect()
+    }
+
+    /// Counts how many nodes reference each of the data blocks in the drive. Blocks with a count
+    /// above one are shared between files, such as copies referencing the same written content,
+    /// and are only stored once. Only the drive's metadata is consulted, no blocks are retrieved.
+    pub async fn block_reference_counts(&self) -> HashMap<Cid, usize> {
+        self.load_remaining_subtrees_or_warn().await;
+        self.inner.read().await.block_reference_counts()
+    }
+
+    /// Counts the files and directories in the drive along with the total size of their content
+    /// and the number of distinct data blocks it's stored in. Only the drive's metadata is
+    /// consulted, no blocks are retrieved. Every subtree of a partially loaded drive is loaded
+    /// first, failing to load one is reported rather than producing totals for part of the drive.
+    pub async fn stats(&self) -> Result<DriveStats, OperationError> {
+        self.load_remaining_subtrees().await?;
+        Ok(self.inner.read().await.stats())
+    }
+
+    /// Groups the files in the drive that reference identical content, identified by having the
+    /// same ordered list of data block CIDs. Only the drive's metadata is consulted, no blocks are
+    /// retrieved. Each group contains at least two files ordered by their [`PermanentId`] and the
+    /// groups are ordered by their first file.
+    ///
+    /// Content is encrypted with a key generated each time a file is written, so files only share
+    /// blocks when they reference the same written content rather than having been written with
+    /// the same data separately. Empty files, stubs, and external files are not included.
+    pub async fn find_duplicate_files(&self) -> Vec<Vec<PermanentId>> {
+        self.load_remaining_subtrees_or_warn().await;
+        let inner_read = self.inner.read().await;
+        let mut content_groups: HashMap<Vec<Cid>, Vec<PermanentId>> = HashMap::new();
+
+        for node in inner_read.node_iter() {
+            if !matches!(node.kind(), NodeKind::File) {
+                continue;
+            }
+
+            let data_cids = match node.data_cids() {
+                Some(data_cids) if !data_cids.is_empty() => data_cids,
+                _ => continue,
+            };
+
+            content_groups
+                .entry(data_cids)
+                .or_default()
+                .push(node.permanent_id());
+        }
+
+        let mut duplicates: Vec<_> = content_groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort();
+                group
+            })
+            .collect();
+        duplicates.sort();
+
+        duplicates
+    }
+
     pub async fn has_maintenance_access(&self, actor_id: &ActorId) -> bool {
         let inner = self.inner.read().await;
         inner.access().has_maintenance_access(actor_id)
@@ -155,6 +680,12 @@ impl Drive {
         self.filesystem_id
     }
 
+    /// Whether the contents of this drive are encrypted. Only private drives are currently
+    /// supported so this will presently always be true.
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
     /// Create a new encrypted drive with the provided [`SigningKey`]. This will generate a random
     /// fileystem ID.
     pub fn initialize_private(
@@ -184,11 +715,94 @@ impl Drive {
             filesystem_id,
             private: true,
             inner: Arc::new(RwLock::new(inner)),
+            shard_cache: Arc::new(RwLock::new(HashMap::new())),
+            journal: Arc::new(RwLock::new(Vec::new())),
+        };
+
+        Ok(drive)
+    }
+
+    /// Creates a new encrypted drive whose filesystem and actor vector clocks start from the
+    /// provided values instead of their initial state. This is intended for tools that fork or
+    /// restore a drive and need the new drive to continue the causal history of the original,
+    /// such as by starting from the original's [`Drive::vector_clock`].
+    ///
+    /// The clocks only ever move forward and changes are ordered by them when drives are merged.
+    /// The provided values must be at least those of the drive whose history is being continued,
+    /// starting behind them makes new changes appear to precede changes that were already made.
+    /// Clocks are compared allowing for them to wrap around, so values should be taken from
+    /// an existing drive rather than made up. The actor clock has to belong to the actor of
+    /// `current_key`.
+    pub fn initialize_private_with_clocks(
+        rng: &mut impl CryptoRngCore,
+        current_key: Arc<SigningKey>,
+        filesystem_id: FilesystemId,
+        vector_clocks: VectorClockFilesystemActorSnapshot,
+    ) -> Result<Self, DriveError> {
+        let verifying_key = current_key.verifying_key();
+        let actor_id = verifying_key.actor_id();
+
+        let actor_clock = vector_clocks.actor();
+        if actor_clock.actor_id() != actor_id {
+            return Err(DriveError::VectorClockActorMismatch);
+        }
+
+        trace!(
+            ?actor_id,
+            ?filesystem_id,
+            "drive::initializing_private_with_clocks"
+        );
+
+        let access = DriveAccess::initialize(rng, verifying_key, actor_clock)?;
+        let inner = InnerDrive::initialize_with_clocks(rng, actor_id, access, vector_clocks)?;
+
+        let drive = Self {
+            current_key,
+            filesystem_id,
+            private: true,
+            inner: Arc::new(RwLock::new(inner)),
+            shard_cache: Arc::new(RwLock::new(HashMap::new())),
+            journal: Arc::new(RwLock::new(Vec::new())),
         };
 
         Ok(drive)
     }
 
+    /// Confirms the provided key belongs to an actor that currently has at least read access to
+    /// the drive, producing the key's [`ActorId`] when it does. Keys that were never authorized,
+    /// that have been marked as historical, or that only hold keys unrelated to reading the
+    /// filesystem are rejected. This allows callers to fail early instead of running into a
+    /// decryption failure part way through an operation.
+    pub async fn assert_key_authorized(
+        &self,
+        key: &SigningKey,
+    ) -> Result<ActorId, DriveAccessError> {
+        let actor_id = key.actor_id();
+
+        let inner_read = self.inner.read().await;
+        let access = inner_read.access();
+
+        let access_mask = access
+            .actor_access(&actor_id)
+            .ok_or(DriveAccessError::UnknownActorId(actor_id))?;
+
+        if access_mask.is_historical() {
+            return Err(DriveAccessError::HistoricalActor(actor_id));
+        }
+
+        if access.actor_key(&actor_id) != Some(key.verifying_key()) {
+            return Err(DriveAccessError::AccessDenied(
+                "key doesn't match the registered actor",
+            ));
+        }
+
+        if !access.has_read_access(&actor_id) {
+            return Err(DriveAccessError::AccessDenied("actor lacks read access"));
+        }
+
+        Ok(actor_id)
+    }
+
     /// Registers a new key as an actor with the provided AccessMask. Will produce an error if used
     /// to attempt to change the permissions of an existing key.
     pub async fn authorize_key(
@@ -205,6 +819,97 @@ impl Drive {
         Ok(())
     }
 
+    /// Produces a handle on the same drive that acts as the provided key. Changes made through
+    /// either handle are visible to both, but new nodes created through the returned handle are
+    /// owned by the new key's actor and capability tokens it grants are issued by it. The key needs
+    /// to be authorized to read the drive, see [`Drive::assert_key_authorized`].
+    ///
+    /// This allows clients holding several authorized keys to switch identities without
+    /// reloading the drive.
+    pub async fn with_current_key(&self, key: Arc<SigningKey>) -> Result<Drive, DriveAccessError> {
+        self.assert_key_authorized(&key).await?;
+
+        Ok(Self {
+            current_key: key,
+            ..self.clone()
+        })
+    }
+
+    /// Mints a [`CapabilityToken`] granting the recipient key the provided permissions without
+    /// modifying the drive. The token can be handed to the recipient out of band who can then
+    /// redeem it using [`Drive::redeem_capability`] on any copy of the drive with access to the
+    /// granted keys. The current key must hold every key it is attempting to grant.
+    pub async fn grant_capability(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        recipient: &VerifyingKey,
+        access_mask: AccessMask,
+    ) -> Result<CapabilityToken, DriveAccessError> {
+        let inner_read = self.inner.read().await;
+
+        let issuer_id = self.current_key.actor_id();
+        let keys = inner_read
+            .access()
+            .capability_keys(&issuer_id, access_mask)?;
+
+        CapabilityToken::issue(
+            rng,
+            self.filesystem_id,
+            &self.current_key,
+            recipient.clone(),
+            access_mask,
+            keys,
+        )
+        .await
+    }
+
+    /// Registers the recipient of a [`CapabilityToken`] produced by [`Drive::grant_capability`]
+    /// as an actor within the drive. The [`SigningKey`] matching the token's recipient is required
+    /// to prove the token is being redeemed by the party it was issued to. Tokens issued for
+    /// other drives, tokens that have been modified, and tokens whose issuer no longer has the
+    /// access they granted will be rejected.
+    pub async fn redeem_capability(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        token: &CapabilityToken,
+        recipient_key: &SigningKey,
+    ) -> Result<(), DriveAccessError> {
+        if token.filesystem_id() != self.filesystem_id {
+            return Err(DriveAccessError::InvalidCapability(
+                "token was issued for a different drive",
+            ));
+        }
+
+        token.verify_signature().await?;
+
+        let mut inner_write = self.inner.write().await;
+        let vector_clock_snapshot = inner_write.vector_clock().actor();
+        inner_write
+            .access_mut()
+            .redeem_capability(rng, token, recipient_key, vector_clock_snapshot)
+    }
+
+    /// Exports the locked copies of the drive's permission keys held by each active actor so they
+    /// can be backed up separately from the drive, see [`WrappedKeyEntry`].
+    pub async fn export_key_escrow(&self) -> Vec<WrappedKeyEntry> {
+        let inner_read = self.inner.read().await;
+        inner_read.access().export_key_escrow()
+    }
+
+    /// Restores permission keys missing from the escrow of the drive's actors from a backup made
+    /// with [`Drive::export_key_escrow`], returning the number of keys restored. See
+    /// [`DriveAccess::import_key_escrow`] for the checks performed on the entries.
+    pub async fn import_key_escrow(
+        &self,
+        current_key: &SigningKey,
+        entries: &[WrappedKeyEntry],
+    ) -> Result<usize, DriveAccessError> {
+        let mut inner_write = self.inner.write().await;
+        inner_write
+            .access_mut()
+            .import_key_escrow(current_key, entries)
+    }
+
     /// Marks the key with the matching actor id as historical. Requires that the corresponding key
     /// not be protected. Requires that the current key be an owner.
     pub async fn remove_key(
@@ -218,6 +923,39 @@ impl Drive {
             .remove_actor(current_key, removal_id)?;
         Ok(())
     }
+
+    /// Revokes the access of every actor that isn't an owner or protected in a single change,
+    /// intended for locking collaborators out of a drive in an emergency. Requires that the
+    /// current key be an owner that holds the data key.
+    ///
+    /// The filesystem and data keys are rotated as part of the revocation, exactly as
+    /// [`Drive::rekey_data_references`] would, so the revoked actors are unable to read the
+    /// metadata or unlock the file keys of any later version of the drive. Either both happen or
+    /// the drive is left unchanged. Returns the IDs of the actors that were revoked along with the CIDs of the data
+    /// blocks belonging to the resealed files.
+    pub async fn revoke_all_except_owners(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        current_key: &SigningKey,
+    ) -> Result<(Vec<ActorId>, HashSet<Cid>), DriveError> {
+        self.load_remaining_subtrees().await?;
+
+        let mut inner_write = self.inner.write().await;
+        let mut access = inner_write.access().clone();
+        let revoked = access.revoke_all_except_owners(current_key)?;
+
+        let data_cids = rekey_with_access(
+            rng,
+            &mut inner_write,
+            &self.shard_cache,
+            access,
+            current_key,
+        )
+        .await?;
+
+        Ok((revoked, data_cids))
+    }
+
     /// If the caller knows the [`PermanentId`] of a directory, they can retrieve a handle on it
     /// directly. Generally users will traverse the filesystem themselves to get this information,
     /// but that can be a costly operation. This allows the external cacheing of permanent IDs to
@@ -258,10 +996,76 @@ impl Drive {
         Ok(entry)
     }
 
+    /// Walks the path from the root of the drive one component at a time, reporting what each
+    /// component resolved to and, if the walk didn't complete, which component failed and why.
+    /// This is intended for diagnosing failed operations where the returned [`OperationError`]
+    /// doesn't identify the part of the path that was at fault. An empty path trivially resolves.
+    pub async fn resolve_path_verbose(&self, path: &[&str]) -> PathResolution {
+        let inner_read = self.inner.read().await;
+
+        match inner_read.root_node() {
+            Ok(root) => inner_read.resolve_path(root.id(), path),
+            Err(_) => PathResolution::new().fail(
+                path.first().copied().unwrap_or_default(),
+                PathResolutionFailure::InconsistentDrive,
+            ),
+        }
+    }
+
+    /// Streams the file or directory at the provided path along with everything underneath it as
+    /// a tar archive. Entries are named relative to the parent of the requested path, exporting
+    /// the root of the drive (an empty path) produces an archive of its contents. File content is
+    /// retrieved from the store and decrypted one chunk at a time as the archive is consumed so
+    /// memory use stays bounded regardless of the size of the files.
+    ///
+    /// Node names that tar or an extracting system would interpret specially, such as those
+    /// containing backslashes or control characters, have those characters replaced with
+    /// underscores. Names too long for a tar header are written with GNU long name entries.
+    ///
+    /// The drive is only locked while the list of entries is collected. The archive can't contain
+    /// files whose content lives outside of BanyanFS or hasn't been uploaded yet, and the stream
+    /// ends after the first error. Drives opened with [`DriveLoader::load_subtree`] first load
+    /// whichever of their subtrees the export covers.
+    pub fn export_archive<'a, S: DataStore>(
+        &'a self,
+        store: &'a S,
+        path: &[&str],
+    ) -> impl futures::Stream<Item = Result<Bytes, OperationError>> + 'a {
+        let path: Vec<String> = path.iter().map(|c| c.to_string()).collect();
+
+        async move {
+            let path: Vec<&str> = path.iter().map(String::as_str).collect();
+            if path.is_empty() {
+                self.load_remaining_subtrees().await?;
+            } else {
+                self.load_subtree(&path).await?;
+            }
+
+            let inner_read = self.inner.read().await;
+            if !inner_read
+                .access()
+                .has_read_access(&self.current_key.actor_id())
+            {
+                return Err(OperationError::AccessDenied);
+            }
+
+            let root_id = inner_read.root_node()?.id();
+            let node_id = match inner_read.walk_path(root_id, &path)? {
+                WalkState::FoundNode { node_id } => node_id,
+                WalkState::MissingComponent { .. } => return Err(OperationError::PathNotFound),
+            };
+
+            let entries = ArchiveEntry::collect(&inner_read, node_id)?;
+            Ok(archive::archive_stream(store, entries))
+        }
+        .try_flatten_stream()
+    }
+
     pub async fn for_each_node<F, R>(&self, operation: F) -> Result<Vec<R>, OperationError>
     where
         F: Fn(&Node) -> Result<Option<R>, OperationError> + Send + Sync,
     {
+        self.load_remaining_subtrees().await?;
         let inner_read = self.inner.read().await;
 
         let mut responses = Vec::new();
@@ -274,8 +1078,304 @@ impl Drive {
         Ok(responses)
     }
 
-    pub fn rekey_data_references(_rng: &mut impl CryptoRngCore) -> Result<(), DriveError> {
-        todo!("not needed yet, but keeping as a placeholder")
+    /// Folds every node of the drive into an accumulator, stopping as soon as `operation` returns
+    /// [`ControlFlow::Break`]. Unlike [`Drive::for_each_node`] no results are collected along the
+    /// way, making this suited to reductions and searches that can finish without visiting the
+    /// whole drive. Nodes are visited in no particular order. The value carried by whichever
+    /// variant is returned last becomes the result.
+    pub async fn try_fold_nodes<B, F>(&self, init: B, mut operation: F) -> Result<B, OperationError>
+    where
+        F: FnMut(B, &Node) -> ControlFlow<B, B>,
+    {
+        self.load_remaining_subtrees().await?;
+        let inner_read = self.inner.read().await;
+
+        let mut accumulator = init;
+        for node in inner_read.node_iter() {
+            match operation(accumulator, node) {
+                ControlFlow::Continue(next) => accumulator = next,
+                ControlFlow::Break(result) => return Ok(result),
+            }
+        }
+
+        Ok(accumulator)
+    }
+
+    /// Whether some of the subtrees of the drive haven't been loaded yet, which is only the case
+    /// for drives loaded with [`DriveLoader::load_subtree`].
+    pub async fn is_partially_loaded(&self) -> bool {
+        !self.inner.read().await.unloaded_shards().is_empty()
+    }
+
+    /// Makes the subtree containing `path` available in a drive that was only partially loaded
+    /// with [`DriveLoader::load_subtree`]. Nothing is done if the subtree is already available,
+    /// which is always the case for drives that were loaded in full.
+    pub async fn load_subtree(&self, path: &[&str]) -> Result<(), OperationError> {
+        let top_level = match path.first() {
+            Some(name) => NodeName::named(name.to_string())?,
+            None => return Ok(()),
+        };
+
+        let mut inner_write = self.inner.write().await;
+        let shard_pid = match inner_write
+            .root_node()?
+            .data()
+            .child_permanent_id(&top_level)
+        {
+            Some(pid) if inner_write.unloaded_shards().contains_key(&pid) => pid,
+            _ => return Ok(()),
+        };
+
+        self.load_shards(&mut inner_write, &[shard_pid]).await
+    }
+
+    /// Loads every subtree of a partially loaded drive that hasn't been loaded yet, see
+    /// [`Drive::load_subtree`].
+    pub async fn load_remaining_subtrees(&self) -> Result<(), OperationError> {
+        if !self.is_partially_loaded().await {
+            return Ok(());
+        }
+
+        let mut inner_write = self.inner.write().await;
+        let shard_pids: Vec<PermanentId> = inner_write.unloaded_shards().keys().copied().collect();
+
+        self.load_shards(&mut inner_write, &shard_pids).await
+    }
+
+    /// Operations covering the entire drive that can't report errors still need every subtree
+    /// to be available, failing to load one only leaves them with an incomplete view.
+    async fn load_remaining_subtrees_or_warn(&self) {
+        if let Err(err) = self.load_remaining_subtrees().await {
+            tracing::warn!("unable to load every subtree of the drive: {err}");
+        }
+    }
+
+    async fn load_shards(
+        &self,
+        inner: &mut InnerDrive,
+        shard_pids: &[PermanentId],
+    ) -> Result<(), OperationError> {
+        let filesystem_key = inner
+            .access()
+            .filesystem_key()
+            .ok_or(OperationError::AccessDenied)?
+            .clone();
+        let shard_cache = self.shard_cache.read().await;
+
+        for shard_pid in shard_pids {
+            let source_cid = match inner.unloaded_shards().get(shard_pid) {
+                Some(source_cid) => source_cid,
+                None => continue,
+            };
+
+            let encrypted_shard = shard_cache
+                .get(source_cid)
+                .ok_or(OperationError::Other("encrypted subtree shard is missing"))?;
+            let shard_buffer = loader::decrypt_shard(encrypted_shard, &filesystem_key)
+                .map_err(|_| OperationError::Other("unable to decrypt subtree shard"))?;
+
+            inner.load_shard(shard_pid, &shard_buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// The kind of the drive as last recorded with [`Drive::set_platform_attributes`], allowing it
+    /// to be displayed without contacting the platform. `None` if it was never recorded.
+    #[cfg(feature = "banyan-api")]
+    pub async fn kind(&self) -> Option<DriveKind> {
+        let inner_read = self.inner.read().await;
+        let root_node = inner_read.root_node().ok()?;
+
+        platform_attributes::read(root_node, platform_attributes::DRIVE_KIND_ATTRIBUTE)
+    }
+
+    /// Counts how many nodes carry each [`MetadataKey`] across the entire drive in a single pass
+    /// over its nodes. This covers the built-in keys such as [`MetadataKey::MimeType`] along with
+    /// any custom keys that have been applied, allowing clients to discover the attributes in use
+    /// without walking the filesystem themselves.
+    pub async fn metadata_key_histogram(&self) -> HashMap<MetadataKey, usize> {
+        self.load_remaining_subtrees_or_warn().await;
+        let inner_read = self.inner.read().await;
+
+        let mut histogram = HashMap::new();
+        for node in inner_read.node_iter() {
+            for key in node.metadata().keys() {
+                *histogram.entry(key.clone()).or_insert(0) += 1;
+            }
+        }
+
+        histogram
+    }
+
+    /// Totals the size of the content owned by each actor, for attributing storage use to the
+    /// actors responsible for it. Only the content of files and their associated data counts,
+    /// the size of the filesystem metadata itself is left out. See [`Drive::ownership_stats`]
+    /// for how ownership is determined.
+    pub async fn owned_bytes(&self) -> HashMap<ActorId, u64> {
+        self.load_remaining_subtrees_or_warn().await;
+        let inner_read = self.inner.read().await;
+
+        let mut owned_bytes = HashMap::new();
+        for node in inner_read.node_iter() {
+            *owned_bytes.entry(node.owner_id()).or_insert(0) += node.data().content_size();
+        }
+
+        owned_bytes
+    }
+
+    /// Counts the nodes owned by each actor. The owner of a node is the actor that created it,
+    /// or for nodes brought in with [`Drive::transfer_node`] the actor that transferred them.
+    /// Actors that don't own any nodes are absent from the result.
+    pub async fn ownership_stats(&self) -> HashMap<ActorId, usize> {
+        self.load_remaining_subtrees_or_warn().await;
+        let inner_read = self.inner.read().await;
+
+        let mut stats = HashMap::new();
+        for node in inner_read.node_iter() {
+            *stats.entry(node.owner_id()).or_insert(0) += 1;
+        }
+
+        stats
+    }
+
+    /// Lists every file whose modification time falls within `start_ms..end_ms`, the start being
+    /// inclusive and the end exclusive, along with the path of each file relative to the root of
+    /// the drive. See [`Drive::modified_since`] for how modification times are tracked.
+    pub async fn modified_between(
+        &self,
+        start_ms: i64,
+        end_ms: i64,
+    ) -> Vec<(Vec<String>, PermanentId)> {
+        self.modified_files(start_ms..end_ms).await
+    }
+
+    async fn modified_files(
+        &self,
+        range: impl RangeBounds<i64>,
+    ) -> Vec<(Vec<String>, PermanentId)> {
+        self.load_remaining_subtrees_or_warn().await;
+        let inner_read = self.inner.read().await;
+
+        let mut modified = Vec::new();
+        for node in inner_read.node_iter() {
+            if node.kind() != NodeKind::File || !range.contains(&node.modified_at()) {
+                continue;
+            }
+
+            match path_from_root(&inner_read, node) {
+                Ok(path) => modified.push((path, node.permanent_id())),
+                Err(err) => {
+                    tracing::warn!(permanent_id = ?node.permanent_id(), "skipping file with unresolvable path: {err}")
+                }
+            }
+        }
+
+        modified.sort();
+        modified
+    }
+
+    /// Lists every file modified at or after the provided time, in milliseconds since the Unix
+    /// epoch, along with the path of each file relative to the root of the drive. The results are
+    /// sorted by path. This is intended for finding the files an incremental backup needs to
+    /// cover.
+    ///
+    /// A file's modification time is updated whenever its content, name, location, or
+    /// attributes change. Files that have been deleted are no longer part of the drive and can't
+    /// be reported here.
+    pub async fn modified_since(&self, since_ms: i64) -> Vec<(Vec<String>, PermanentId)> {
+        self.modified_files(since_ms..).await
+    }
+
+    /// Applies a batch of moves while holding a single write lock on the drive. Each move is a
+    /// pair of source and destination paths relative to the root of the drive and follows the
+    /// same semantics as [`DirectoryHandle::mv`]. Moves are applied in order so later moves may
+    /// refer to the results of earlier ones.
+    ///
+    /// Every move is checked before any of them are applied. If one would fail, such as from a
+    /// missing path, a name collision, or an attempt to move a directory inside of itself, its
+    /// error is returned and the drive is left untouched.
+    ///
+    /// As with [`DirectoryHandle::mv`] the `rng` isn't used at the moment, moves only relink
+    /// existing nodes and don't need any new keys or nonces. It is accepted so the batch and
+    /// single move operations keep the same shape.
+    pub async fn move_many(
+        &self,
+        _rng: &mut impl CryptoRngCore,
+        moves: Vec<(Vec<String>, Vec<String>)>,
+    ) -> Result<(), OperationError> {
+        let mut inner_write = self.inner.write().await;
+
+        let moves: Vec<(Vec<&str>, Vec<&str>)> = moves
+            .iter()
+            .map(|(src_path, dst_path)| {
+                (
+                    src_path.iter().map(String::as_str).collect(),
+                    dst_path.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        inner_write.check_moves(&moves)?;
+
+        let mut applied_moves = Vec::with_capacity(moves.len());
+        let mut move_result = Ok(());
+
+        for (src_path, dst_path) in moves.iter() {
+            match inner_write.move_path(src_path, dst_path).await {
+                Ok(original_location) => applied_moves.push(original_location),
+                Err(err) => {
+                    move_result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        if move_result.is_err() {
+            // Only an inconsistency within the drive can fail a move that passed the checks above.
+            // Walking the completed moves backwards restores each node to the state it was in
+            // before the move, every one of them is attempted even if an earlier one fails.
+            while let Some((node_pid, parent_pid, name)) = applied_moves.pop() {
+                if let Err(err) = inner_write.move_node(node_pid, parent_pid, name).await {
+                    tracing::error!(?node_pid, "failed to revert move: {err}");
+                }
+            }
+        }
+
+        inner_write.clean_drive().await?;
+
+        move_result
+    }
+
+    /// Replaces the drive's filesystem and data keys with newly generated ones and reseals the key
+    /// of every file with the new data key, intended to follow removing an actor with
+    /// [`Drive::remove_key`] or [`Drive::revoke_all_except_owners`]. Each new key is escrowed for
+    /// every remaining actor that held the one it replaces, so removed actors are unable to read
+    /// the metadata or unlock the file keys of any later version of the drive, even if they kept
+    /// the previous keys. Requires that the current key hold both keys and be able to record
+    /// changes.
+    ///
+    /// Returns the CIDs of the data blocks belonging to the resealed files. The blocks themselves
+    /// remain encrypted with their file's own key and don't change, only the drive's metadata
+    /// needs to be pushed for the rotation to take effect. Removed actors that already read a
+    /// file's key, or hold an earlier version of the metadata, can still decrypt those blocks;
+    /// the returned CIDs identify the content that needs to be written again when that matters.
+    pub async fn rekey_data_references(
+        &self,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<HashSet<Cid>, DriveError> {
+        self.load_remaining_subtrees().await?;
+
+        let mut inner_write = self.inner.write().await;
+        let access = inner_write.access().clone();
+
+        rekey_with_access(
+            rng,
+            &mut inner_write,
+            &self.shard_cache,
+            access,
+            &self.current_key,
+        )
+        .await
     }
 
     /// Retrieve a handle on the root directory of the filesystem. This is the starting point for
@@ -297,33 +1397,336 @@ impl Drive {
         Ok(root_cid)
     }
 
+    /// Records the storage class and kind the platform reports for this drive (see
+    /// [`crate::api::platform::ApiDrive`]) as attributes of the root directory, so they persist
+    /// with the drive's metadata and remain available to offline clients through
+    /// [`Drive::storage_class`] and [`Drive::kind`]. They're only a record of the platform's view,
+    /// changing them here has no effect on how the platform treats the drive.
+    #[cfg(feature = "banyan-api")]
+    pub async fn set_platform_attributes(
+        &self,
+        storage_class: StorageClass,
+        kind: DriveKind,
+    ) -> Result<(), OperationError> {
+        let mut root = self.root().await?;
+
+        let storage_class_key =
+            MetadataKey::Custom(platform_attributes::STORAGE_CLASS_ATTRIBUTE.into());
+        root.set_attribute(
+            &[],
+            storage_class_key,
+            storage_class.to_string().into_bytes(),
+        )
+        .await?;
+
+        let kind_key = MetadataKey::Custom(platform_attributes::DRIVE_KIND_ATTRIBUTE.into());
+        root.set_attribute(&[], kind_key, kind.to_string().into_bytes())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Captures an immutable copy of the current state of the drive that can serve reads without
+    /// taking the lock on the drive, see [`DriveSnapshot`] for how the snapshot relates to later
+    /// changes. Capturing a snapshot copies the entire filesystem structure (but not any file
+    /// content) so its cost grows with the size of the drive, snapshots are intended to be
+    /// captured once and shared between many readers. Subtrees of a partially loaded drive are
+    /// loaded before the snapshot is captured.
+    ///
+    /// The drive's current key needs read access to the drive.
+    pub async fn snapshot(&self) -> Result<DriveSnapshot, OperationError> {
+        self.load_remaining_subtrees().await?;
+
+        let inner_read = self.inner.read().await;
+        if !inner_read
+            .access()
+            .has_read_access(&self.current_key.actor_id())
+        {
+            return Err(OperationError::AccessDenied);
+        }
+
+        let root_cid = inner_read.root_node()?.cid().await?;
+        let inner = inner_read.deep_copy(inner_read.vector_clock()).await?;
+
+        Ok(DriveSnapshot::new(inner, root_cid))
+    }
+
+    /// The storage class of the drive as last recorded with [`Drive::set_platform_attributes`].
+    /// `None` if it was never recorded.
+    #[cfg(feature = "banyan-api")]
+    pub async fn storage_class(&self) -> Option<StorageClass> {
+        let inner_read = self.inner.read().await;
+        let root_node = inner_read.root_node().ok()?;
+
+        platform_attributes::read(root_node, platform_attributes::STORAGE_CLASS_ATTRIBUTE)
+    }
+
+    /// Moves the node with the provided permanent ID, along with everything below it, out of this
+    /// drive and into `dest_drive`. The destination path follows the same rules as
+    /// [`DirectoryHandle::mv`]: when it points at an existing directory the node is placed inside
+    /// of it, otherwise the final component of the path becomes the node's new name. The node and
+    /// each of its descendants receive a new [`PermanentId`] in the destination, which is returned
+    /// for the node itself, and are owned by the destination drive's current key.
+    ///
+    /// No block data is copied or re-encrypted. The nodes in the destination keep referencing the
+    /// same data blocks, only the per-file keys get re-sealed with the destination's data key.
+    /// **Both drives must be backed by the same [`DataStore`]**, the blocks are not made available
+    /// to the destination's store by this call. The blocks also remain required by any earlier
+    /// version of this drive that still references them.
+    ///
+    /// The current keys of both drives need to hold the filesystem and data keys of their drive.
+    /// The root directory can't be transferred and moves within a single drive should use
+    /// [`DirectoryHandle::mv`] instead.
+    pub async fn transfer_node(
+        &self,
+        rng: &mut impl CryptoRngCore,
+        permanent_id: PermanentId,
+        dest_drive: &Drive,
+        dest_path: &[&str],
+    ) -> Result<PermanentId, OperationError> {
+        if Arc::ptr_eq(&self.inner, &dest_drive.inner) {
+            return Err(OperationError::Other(
+                "nodes can only be transferred to a different drive",
+            ));
+        }
+
+        self.load_remaining_subtrees().await?;
+        dest_drive.load_remaining_subtrees().await?;
+
+        // Acquiring the locks in a consistent order prevents transfers running in opposite
+        // directions between the same drives from deadlocking.
+        let (mut source_inner, mut dest_inner) =
+            if Arc::as_ptr(&self.inner) < Arc::as_ptr(&dest_drive.inner) {
+                let source_inner = self.inner.write().await;
+                (source_inner, dest_drive.inner.write().await)
+            } else {
+                let dest_inner = dest_drive.inner.write().await;
+                (self.inner.write().await, dest_inner)
+            };
+
+        let dest_actor_id = dest_drive.current_key.actor_id();
+        if !source_inner
+            .access()
+            .has_data_access(&self.current_key.actor_id())
+            || !dest_inner.access().has_data_access(&dest_actor_id)
+        {
+            return Err(OperationError::AccessDenied);
+        }
+
+        if source_inner
+            .by_perm_id(&permanent_id)?
+            .parent_id()
+            .is_none()
+        {
+            return Err(OperationError::Other(
+                "the root directory can't be transferred",
+            ));
+        }
+
+        let new_pid = dest_inner
+            .graft_subtree(rng, &source_inner, permanent_id, dest_path, dest_actor_id)
+            .await?;
+        dest_inner.clean_drive().await?;
+
+        // The data CIDs of the removed nodes are deliberately not reported for removal, the
+        // destination now references the same blocks.
+        source_inner.remove_node(permanent_id).await?;
+        source_inner.clean_drive().await?;
+
+        Ok(new_pid)
+    }
+
+    /// The current values of the filesystem's vector clock and the vector clock of the actor
+    /// holding the drive, which together order the changes made to the drive.
+    pub async fn vector_clock(&self) -> VectorClockFilesystemActorSnapshot {
+        self.inner.read().await.vector_clock()
+    }
+
+    /// Produces a single identifier for the current version of the drive covering both its tree
+    /// and who has access to it. The fingerprint is the hash of:
+    ///
+    /// * The CID of the root node, see [`Drive::root_cid`]. This covers the structure, names,
+    ///   attributes, and permissions of every node along with the content of every file.
+    /// * For every actor registered with the drive, ordered by their [`ActorId`], the actor's
+    ///   public key and their access mask. The access mask records which of the drive's keys the
+    ///   actor holds along with whether they are an owner, protected, or historical.
+    ///
+    /// Any change to either part produces a different fingerprint, two drives with identical trees
+    /// but a different set of authorized keys (or the same keys with different access) won't
+    /// match. Everything else is left out so the fingerprint is stable across encodes and library
+    /// versions: the [`FilesystemId`], the vector clocks, each actor's recorded user agent, and the
+    /// encrypted form of the drive's keys, which is different each time a key is escrowed even
+    /// when access hasn't changed.
+    pub async fn version_fingerprint(&self) -> Result<Cid, OperationError> {
+        let inner_read = self.inner.read().await;
+        let root_cid = inner_read.root_node()?.cid().await?;
+
+        let mut fingerprint_data = root_cid.as_bytes().to_vec();
+        for settings in inner_read.access().sorted_actor_settings() {
+            fingerprint_data.extend_from_slice(&settings.verifying_key().to_bytes());
+            settings
+                .access()
+                .encode(&mut fingerprint_data)
+                .await
+                .map_err(|_| OperationError::Other("failed to encode access mask"))?;
+        }
+
+        Ok(crate::utils::calculate_cid(&fingerprint_data))
+    }
+
+    /// Finds every file and directory whose full path from the root of the drive matches the
+    /// glob `pattern`, returned along with their paths in path order. Within a path segment `*`
+    /// matches any run of characters and `?` matches any single character, while a segment of
+    /// `**` matches any number of directories. The root is always implied, so `**/*.txt` and
+    /// `/**/*.txt` find the same text files anywhere in the drive. Associated data such as
+    /// thumbnails is never included.
+    pub async fn find(
+        &self,
+        pattern: &str,
+    ) -> Result<Vec<(Vec<String>, PermanentId)>, OperationError> {
+        self.load_remaining_subtrees().await?;
+
+        let pattern = PathPattern::parse(pattern);
+        let inner_read = self.inner.read().await;
+
+        let mut found = Vec::new();
+        for node in inner_read.node_iter() {
+            if !matches!(node.kind(), NodeKind::Directory | NodeKind::File) {
+                continue;
+            }
+
+            let path = path_from_root(&inner_read, node)?;
+            if pattern.matches(&path) {
+                found.push((path, node.permanent_id()));
+            }
+        }
+        found.sort();
+
+        Ok(found)
+    }
+
     pub async fn full_path_from_root(
         &self,
         target: &PermanentId,
     ) -> Result<Vec<String>, OperationError> {
         let inner_read = &self.inner.read().await;
-        let mut target_node = inner_read
+        let target_node = inner_read
             .by_perm_id(target)
             .map_err(|_| OperationError::MissingPermanentId(*target))?;
 
-        let target_node_name = match target_node.name() {
-            NodeName::Root => return Ok(Vec::new()),
-            NodeName::Named(name) => name.to_string(),
-        };
+        path_from_root(inner_read, target_node)
+    }
+}
 
-        let mut path = vec![target_node_name];
-        while let Some(parent_id) = target_node.parent_id() {
-            let parent_node = inner_read.by_perm_id(&parent_id)?;
+/// Reconstructs the path of a node relative to the root of the drive by following its parents.
+fn path_from_root(inner: &InnerDrive, node: &Node) -> Result<Vec<String>, OperationError> {
+    let mut target_node = node;
 
-            match parent_node.name() {
-                NodeName::Root => break,
-                NodeName::Named(name) => path.push(name.to_string()),
-            }
-            target_node = parent_node
+    let target_node_name = match target_node.name() {
+        NodeName::Root => return Ok(Vec::new()),
+        NodeName::Named(name) => name.to_string(),
+    };
+
+    let mut path = vec![target_node_name];
+    while let Some(parent_id) = target_node.parent_id() {
+        let parent_node = inner.by_perm_id(&parent_id)?;
+
+        match parent_node.name() {
+            NodeName::Root => break,
+            NodeName::Named(name) => path.push(name.to_string()),
         }
-        path.reverse();
+        target_node = parent_node
+    }
+    path.reverse();
+
+    Ok(path)
+}
+
+/// Produces the checkpoint that should be added to the journal for the current version of the
+/// filesystem, or `None` when the latest checkpoint already covers it.
+async fn pending_checkpoint(
+    inner: &InnerDrive,
+    journal: &[JournalCheckpoint],
+) -> std::io::Result<Option<JournalCheckpoint>> {
+    let root_cid = inner
+        .root_node()
+        .map_err(|_| std_io_err("missing root node"))?
+        .cid()
+        .await
+        .map_err(|_| std_io_err("unable to calculate root CID"))?;
+
+    if journal
+        .last()
+        .is_some_and(|checkpoint| checkpoint.merkle_root_cid() == &root_cid)
+    {
+        return Ok(None);
+    }
+
+    let checkpoint = JournalCheckpoint::new(
+        root_cid,
+        inner.vector_clock().filesystem(),
+        crate::utils::current_time_ms(),
+    );
+
+    Ok(Some(checkpoint))
+}
+
+/// Errors produced while encoding a drive with [`Drive::encode`] or [`Drive::encode_delta`].
+/// Rotates the data key within `access`, a copy of the drive's access that may already carry
+/// other changes, and reseals every file key with the new one. The copy only replaces the
+/// drive's access once the file keys have been resealed, so a failure leaves the drive untouched.
+async fn rekey_with_access(
+    rng: &mut impl CryptoRngCore,
+    inner: &mut InnerDrive,
+    shard_cache: &RwLock<HashMap<Cid, Vec<u8>>>,
+    mut access: DriveAccess,
+    current_key: &SigningKey,
+) -> Result<HashSet<Cid>, DriveError> {
+    let filesystem_key = AccessKey::generate(rng);
+    access.rotate_filesystem_key(rng, current_key, &filesystem_key)?;
+
+    let new_key = AccessKey::generate(rng);
+    let previous_key = access.rotate_data_key(rng, current_key, &new_key)?;
+
+    let data_cids = inner.relock_data_keys(rng, &previous_key, &new_key).await?;
+    *inner.access_mut() = access;
+    inner.clean_drive().await?;
+
+    // The cached shards are encrypted with the previous filesystem key and can't be reused
+    shard_cache.write().await.clear();
+
+    Ok(data_cids)
+}
 
-        Ok(path)
+/// Failures caused by the current key lacking the access needed to produce the requested content
+/// are reported separately from failures writing the encoding out.
+#[derive(Debug, thiserror::Error)]
+pub enum DriveEncodeError {
+    #[error("an I/O error occurred while encoding the drive: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("the current key doesn't hold the filesystem key needed to encode the filesystem")]
+    MissingFilesystemKey,
+
+    #[error("the root of the filesystem couldn't be recorded in the integrity manifest: {0}")]
+    ManifestRootUnavailable(OperationError),
+
+    #[error("encoding public drives is not supported")]
+    PublicEncodingUnsupported,
+
+    #[error("a subtree of the drive couldn't be loaded to be included in the encoding: {0}")]
+    SubtreeUnavailable(OperationError),
+}
+
+impl From<DriveEncodeError> for std::io::Error {
+    fn from(err: DriveEncodeError) -> Self {
+        match err {
+            DriveEncodeError::Io(err) => err,
+            DriveEncodeError::MissingFilesystemKey => {
+                StdError::new(StdErrorKind::PermissionDenied, err)
+            }
+            err => StdError::new(StdErrorKind::Other, err),
+        }
     }
 }
 
@@ -337,4 +1740,2298 @@ pub enum DriveError {
 
     #[error("operation on the drive failed due to an error: {0}")]
     OperationError(#[from] OperationError),
+
+    #[error("the starting actor vector clock belongs to a different actor than the drive's key")]
+    VectorClockActorMismatch,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::filesystem::drive::inner::test::build_interesting_inner;
+
+    async fn interesting_drive() -> Drive {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = SigningKey::generate(&mut rng);
+        let inner = build_interesting_inner(Some(signing_key.clone())).await;
+
+        Drive {
+            filesystem_id: FilesystemId::generate(&mut rng),
+            private: true,
+            current_key: Arc::new(signing_key),
+            inner: Arc::new(RwLock::new(inner)),
+            shard_cache: Arc::new(RwLock::new(HashMap::new())),
+            journal: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    fn path(components: &[&str]) -> Vec<String> {
+        components.iter().map(|c| c.to_string()).collect()
+    }
+
+    fn sorted_names(entries: Vec<DirectoryEntry>) -> Vec<String> {
+        let mut names: Vec<String> = entries
+            .iter()
+            .filter_map(|entry| match entry.name() {
+                NodeName::Named(name) => Some(name),
+                NodeName::Root => None,
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    async fn root_names(drive: &Drive) -> Vec<String> {
+        let root = drive.root().await.unwrap();
+        sorted_names(root.ls(&[]).await.unwrap())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn header_round_trips_the_filesystem_vector_clock() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1"], false).await.unwrap();
+        let filesystem_clock = drive.inner.read().await.vector_clock().filesystem();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+
+        let loaded = DriveLoader::new(&signing_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(
+            loaded.inner.read().await.vector_clock().filesystem(),
+            filesystem_clock
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn move_many_applies_moves_in_order() {
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        drive
+            .move_many(
+                &mut rng,
+                vec![
+                    (path(&["file_1"]), path(&["dir_1"])),
+                    (path(&["dir_1", "file_1"]), path(&["dir_1", "renamed"])),
+                    (path(&["file_2"]), path(&["dir_1", "dir_2"])),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(root_names(&drive).await, vec!["dir_1"]);
+
+        let root = drive.root().await.unwrap();
+        let dir_1_names = sorted_names(root.ls(&["dir_1"]).await.unwrap());
+        assert_eq!(dir_1_names, vec!["dir_2", "renamed"]);
+
+        assert!(root.ls(&["dir_1", "dir_2", "file_2"]).await.is_ok());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn move_many_rejects_cycle() {
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        let result = drive
+            .move_many(
+                &mut rng,
+                vec![
+                    (path(&["file_1"]), path(&["dir_1", "moved_file"])),
+                    (path(&["dir_1"]), path(&["dir_1", "dir_2", "dir_3"])),
+                ],
+            )
+            .await;
+
+        assert!(matches!(result, Err(OperationError::RecursiveMove)));
+        assert_eq!(root_names(&drive).await, vec!["dir_1", "file_1", "file_2"]);
+
+        let root = drive.root().await.unwrap();
+        let dir_1_names = sorted_names(root.ls(&["dir_1"]).await.unwrap());
+        assert_eq!(dir_1_names, vec!["dir_2"]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn move_many_rejects_collision() {
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        let result = drive
+            .move_many(
+                &mut rng,
+                vec![
+                    (path(&["file_1"]), path(&["dir_1", "file_2"])),
+                    (path(&["file_2"]), path(&["dir_1"])),
+                ],
+            )
+            .await;
+
+        assert!(matches!(result, Err(OperationError::Exists(_))));
+        assert_eq!(root_names(&drive).await, vec!["dir_1", "file_1", "file_2"]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn move_many_checks_sources_after_earlier_moves() {
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        // The source of the second move only stops existing once the first one is applied
+        let result = drive
+            .move_many(
+                &mut rng,
+                vec![
+                    (path(&["file_1"]), path(&["dir_1"])),
+                    (path(&["file_1"]), path(&["dir_1", "dir_2"])),
+                ],
+            )
+            .await;
+        assert!(matches!(result, Err(OperationError::PathNotFound)));
+
+        assert_eq!(root_names(&drive).await, vec!["dir_1", "file_1", "file_2"]);
+        let root = drive.root().await.unwrap();
+        let dir_1_names = sorted_names(root.ls(&["dir_1"]).await.unwrap());
+        assert_eq!(dir_1_names, vec!["dir_2"]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn sharded_encoding_round_trips_and_reuses_unchanged_shards() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2", "dir_3"], true)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["dir_a"], false).await.unwrap();
+        root.mkdir(&mut rng, &["dir_a", "dir_b"], false)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        // One shard for each of the top level directories along with the root
+        let first_shards = drive.shard_cache.read().await.clone();
+        assert_eq!(first_shards.len(), 3);
+
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(root_names(&loaded).await, vec!["dir_1", "dir_a"]);
+        let loaded_root = loaded.root().await.unwrap();
+        assert!(loaded_root.ls(&["dir_1", "dir_2", "dir_3"]).await.is_ok());
+        assert!(loaded_root.ls(&["dir_a", "dir_b"]).await.is_ok());
+        assert_eq!(*loaded.shard_cache.read().await, first_shards);
+
+        root.mkdir(&mut rng, &["dir_1", "new_dir"], false)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        // Only the shard for dir_a should have been left untouched
+        let second_shards = drive.shard_cache.read().await.clone();
+        assert_eq!(second_shards.len(), 3);
+        let reused_shards = second_shards
+            .iter()
+            .filter(|(cid, shard)| first_shards.get(*cid) == Some(*shard))
+            .count();
+        assert_eq!(reused_shards, 1);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn subtree_loading_defers_other_shards() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2"], true)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["dir_a", "dir_b"], true)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let partial = DriveLoader::new(&drive.current_key)
+            .load_subtree(encoded.as_slice(), &["dir_1", "dir_2"])
+            .await
+            .unwrap();
+        assert!(partial.is_partially_loaded().await);
+
+        let mut partial_root = partial.root().await.unwrap();
+        assert!(partial_root.ls(&["dir_1", "dir_2"]).await.is_ok());
+        assert!(matches!(
+            partial_root.ls(&["dir_a", "dir_b"]).await,
+            Err(OperationError::SubtreeNotLoaded(_))
+        ));
+
+        // Changes can be made to the loaded subtree while the others are still outstanding
+        partial_root
+            .mkdir(&mut rng, &["dir_1", "new_dir"], false)
+            .await
+            .unwrap();
+
+        partial.load_subtree(&["dir_a"]).await.unwrap();
+        assert!(!partial.is_partially_loaded().await);
+        assert!(partial_root.ls(&["dir_a", "dir_b"]).await.is_ok());
+
+        // Encoding loads anything outstanding so nothing is lost
+        let partial = DriveLoader::new(&drive.current_key)
+            .load_subtree(encoded.as_slice(), &[])
+            .await
+            .unwrap();
+        partial
+            .root()
+            .await
+            .unwrap()
+            .mkdir(&mut rng, &["dir_c"], false)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        partial
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+        assert!(!partial.is_partially_loaded().await);
+
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(root_names(&loaded).await, vec!["dir_1", "dir_a", "dir_c"]);
+        let loaded_root = loaded.root().await.unwrap();
+        assert!(loaded_root.ls(&["dir_a", "dir_b"]).await.is_ok());
+    }
+
+    #[cfg(feature = "compression")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn compressed_drives_load_while_decompressing() {
+        use async_compression::futures::bufread::{GzipEncoder, ZstdEncoder};
+        use futures::AsyncReadExt;
+
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let mut gzipped = Vec::new();
+        GzipEncoder::new(encoded.as_slice())
+            .read_to_end(&mut gzipped)
+            .await
+            .unwrap();
+
+        let mut zstd_compressed = Vec::new();
+        ZstdEncoder::new(encoded.as_slice())
+            .read_to_end(&mut zstd_compressed)
+            .await
+            .unwrap();
+
+        let expected_names = root_names(&drive).await;
+        for (compressed, encoding) in [
+            (gzipped, MetadataEncoding::Gzip),
+            (zstd_compressed, MetadataEncoding::Zstd),
+            (encoded.clone(), MetadataEncoding::Identity),
+        ] {
+            let loaded = DriveLoader::new(&drive.current_key)
+                .from_compressed_reader(compressed.as_slice(), encoding)
+                .await
+                .unwrap();
+            assert_eq!(root_names(&loaded).await, expected_names);
+        }
+
+        // Content that doesn't match the encoding is rejected rather than parsed
+        let result = DriveLoader::new(&drive.current_key)
+            .from_compressed_reader(encoded.as_slice(), MetadataEncoding::Gzip)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn delta_encoding_references_unchanged_shards() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2", "dir_3"], true)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["dir_a", "dir_b"], true)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let previous = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        let previous_shards = previous.shard_cids().await;
+        assert_eq!(previous_shards, drive.shard_cids().await);
+
+        root.mkdir(&mut rng, &["dir_1", "new_dir"], false)
+            .await
+            .unwrap();
+
+        let mut full = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut full)
+            .await
+            .unwrap();
+
+        let mut delta = Vec::new();
+        drive
+            .encode_delta(&mut rng, &previous_shards, &mut delta)
+            .await
+            .unwrap();
+
+        // Only the root and dir_1 shards changed, dir_a is left for the previous version
+        assert!(delta.len() < full.len());
+
+        let loaded = DriveLoader::new(&drive.current_key)
+            .with_previous_version(&previous)
+            .await
+            .from_reader(delta.as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(root_names(&loaded).await, vec!["dir_1", "dir_a"]);
+        let loaded_root = loaded.root().await.unwrap();
+        assert!(loaded_root.ls(&["dir_1", "new_dir"]).await.is_ok());
+        assert!(loaded_root.ls(&["dir_a", "dir_b"]).await.is_ok());
+        assert_eq!(
+            loaded.root_cid().await.unwrap(),
+            drive.root_cid().await.unwrap()
+        );
+        assert_eq!(
+            *loaded.shard_cache.read().await,
+            *drive.shard_cache.read().await
+        );
+
+        let missing_previous = DriveLoader::new(&drive.current_key)
+            .from_reader(delta.as_slice())
+            .await;
+        assert!(matches!(
+            missing_previous,
+            Err(DriveLoaderError::MissingPreviousShard(_))
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn delta_encoding_includes_shards_reencrypted_since_the_previous_version() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "nested"], true)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["dir_a", "nested"], true)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let previous = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        let previous_shards = previous.shard_cids().await;
+
+        let drive = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        let mut root = drive.root().await.unwrap();
+
+        // Renaming the shard away and back again leaves a shard with the same name, but its
+        // encrypted form no longer matches the copy held by the previous version
+        root.mv(&mut rng, &["dir_a"], &["dir_b"]).await.unwrap();
+        let mut scratch = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut scratch)
+            .await
+            .unwrap();
+
+        root.mv(&mut rng, &["dir_b"], &["dir_a"]).await.unwrap();
+        scratch.clear();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut scratch)
+            .await
+            .unwrap();
+
+        let mut delta = Vec::new();
+        drive
+            .encode_delta(&mut rng, &previous_shards, &mut delta)
+            .await
+            .unwrap();
+
+        let loaded = DriveLoader::new(&drive.current_key)
+            .with_previous_version(&previous)
+            .await
+            .from_reader(delta.as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(root_names(&loaded).await, vec!["dir_1", "dir_a"]);
+        let loaded_root = loaded.root().await.unwrap();
+        assert!(loaded_root.ls(&["dir_a", "nested"]).await.is_ok());
+        assert_eq!(loaded.shard_cids().await, drive.shard_cids().await);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn capability_tokens_register_only_their_recipient() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let recipient_key = SigningKey::generate(&mut rng);
+        let recipient_id = recipient_key.actor_id();
+        let access_mask = AccessMaskBuilder::structural().build().unwrap();
+
+        let token = drive
+            .grant_capability(&mut rng, &recipient_key.verifying_key(), access_mask)
+            .await
+            .unwrap();
+        assert!(!drive.has_read_access(&recipient_id).await);
+
+        let mut encoded = Vec::new();
+        token.encode(&mut encoded).await.unwrap();
+        let (remaining, token) = CapabilityToken::parse(Stream::new(&encoded)).unwrap();
+        assert!(remaining.is_empty());
+
+        let other_key = SigningKey::generate(&mut rng);
+        let result = drive.redeem_capability(&mut rng, &token, &other_key).await;
+        assert!(matches!(
+            result,
+            Err(DriveAccessError::InvalidCapability(_))
+        ));
+
+        let other_drive = Drive::initialize_private(&mut rng, Arc::new(other_key.clone())).unwrap();
+        let result = other_drive
+            .redeem_capability(&mut rng, &token, &recipient_key)
+            .await;
+        assert!(matches!(
+            result,
+            Err(DriveAccessError::InvalidCapability(_))
+        ));
+
+        // Flipping a bit in the escrowed keys must invalidate the issuer's signature
+        let mut tampered = encoded.clone();
+        let tamper_idx = tampered.len() - 100;
+        tampered[tamper_idx] ^= 0x01;
+        let (_, tampered_token) = CapabilityToken::parse(Stream::new(&tampered)).unwrap();
+        let result = drive
+            .redeem_capability(&mut rng, &tampered_token, &recipient_key)
+            .await;
+        assert!(matches!(
+            result,
+            Err(DriveAccessError::InvalidCapability(_))
+        ));
+
+        drive
+            .redeem_capability(&mut rng, &token, &recipient_key)
+            .await
+            .unwrap();
+
+        let summary = drive.access_summary(&recipient_id).await;
+        assert!(summary.has_read_access());
+        assert!(summary.has_maintenance_access());
+        assert!(!summary.holds_data_key());
+
+        let result = drive
+            .redeem_capability(&mut rng, &token, &recipient_key)
+            .await;
+        assert!(matches!(result, Err(DriveAccessError::ActorAlreadyPresent)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn clone_with_new_id_produces_independent_copy() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2"], true)
+            .await
+            .unwrap();
+
+        let copy = drive.clone_with_new_id(&mut rng).await.unwrap();
+        assert!(copy.id() != drive.id());
+        assert_eq!(root_names(&copy).await, vec!["dir_1"]);
+
+        let original_entry = root.entry().await.unwrap();
+        let copied_entry = copy.root().await.unwrap().entry().await.unwrap();
+        assert_eq!(original_entry.permanent_id(), copied_entry.permanent_id());
+
+        let mut copy_root = copy.root().await.unwrap();
+        copy_root
+            .mkdir(&mut rng, &["only_in_copy"], false)
+            .await
+            .unwrap();
+        assert_eq!(root_names(&drive).await, vec!["dir_1"]);
+        assert_eq!(root_names(&copy).await, vec!["dir_1", "only_in_copy"]);
+
+        let mut encoded = Vec::new();
+        copy.encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let loaded = DriveLoader::new(&copy.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert!(loaded.id() == copy.id());
+        assert!(loaded
+            .root()
+            .await
+            .unwrap()
+            .ls(&["dir_1", "dir_2"])
+            .await
+            .is_ok());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn clone_with_new_id_copies_unloaded_subtrees() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2"], true)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["dir_a", "dir_b"], true)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let partial = DriveLoader::new(&signing_key)
+            .load_subtree(encoded.as_slice(), &["dir_1"])
+            .await
+            .unwrap();
+        assert!(partial.is_partially_loaded().await);
+
+        let copy = partial.clone_with_new_id(&mut rng).await.unwrap();
+        assert!(!copy.is_partially_loaded().await);
+        assert_eq!(root_names(&copy).await, vec!["dir_1", "dir_a"]);
+
+        let copy_root = copy.root().await.unwrap();
+        assert!(copy_root.ls(&["dir_1", "dir_2"]).await.is_ok());
+        assert!(copy_root.ls(&["dir_a", "dir_b"]).await.is_ok());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn initialization_continues_provided_clocks() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let actor_id = signing_key.actor_id();
+
+        let mut encoded_clocks = 500u64.to_le_bytes().to_vec();
+        actor_id.encode(&mut encoded_clocks).await.unwrap();
+        encoded_clocks.extend_from_slice(&900u64.to_le_bytes());
+        let (_, clocks) =
+            VectorClockFilesystemActorSnapshot::parse(Stream::new(&encoded_clocks)).unwrap();
+
+        let filesystem_id = FilesystemId::generate(&mut rng);
+        let drive = Drive::initialize_private_with_clocks(
+            &mut rng,
+            signing_key.clone(),
+            filesystem_id,
+            clocks,
+        )
+        .unwrap();
+        assert_eq!(drive.vector_clock().await, clocks);
+        assert!(
+            drive.vector_clock().await
+                > Drive::initialize_private(&mut rng, signing_key.clone())
+                    .unwrap()
+                    .vector_clock()
+                    .await
+        );
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let loaded = DriveLoader::new(&signing_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(
+            loaded.vector_clock().await.filesystem(),
+            clocks.filesystem()
+        );
+
+        let other_key = Arc::new(SigningKey::generate(&mut rng));
+        let mismatched =
+            Drive::initialize_private_with_clocks(&mut rng, other_key, filesystem_id, clocks);
+        assert!(matches!(
+            mismatched,
+            Err(DriveError::VectorClockActorMismatch)
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn root_metadata_and_permissions_round_trip() {
+        use crate::codec::filesystem::Permissions;
+
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let label_key = MetadataKey::Custom("label".into());
+        let permissions = Permissions::default().with_owner_write_only(true);
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1"], false).await.unwrap();
+        let previous = root
+            .set_attribute(&[], label_key.clone(), b"shared photos".to_vec())
+            .await
+            .unwrap();
+        assert!(previous.is_none());
+        root.chmod(&[], permissions).await.unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+
+        let root_entry = loaded.root().await.unwrap().entry().await.unwrap();
+        assert_eq!(
+            root_entry.metadata().get(&label_key),
+            Some(&b"shared photos".to_vec())
+        );
+        assert_eq!(root_entry.permissions(), Some(permissions));
+        assert_eq!(root_names(&loaded).await, vec!["dir_1"]);
+        assert_eq!(
+            loaded.root_cid().await.unwrap(),
+            drive.root_cid().await.unwrap()
+        );
+    }
+
+    #[cfg(feature = "banyan-api")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn platform_attributes_are_validated_and_persisted() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        assert_eq!(drive.storage_class().await, None);
+        assert_eq!(drive.kind().await, None);
+
+        drive
+            .set_platform_attributes(StorageClass::Warm, DriveKind::Backup)
+            .await
+            .unwrap();
+
+        // The attributes can't be given values outside of the platform's enums
+        let mut root = drive.root().await.unwrap();
+        let storage_class_key =
+            MetadataKey::Custom(platform_attributes::STORAGE_CLASS_ATTRIBUTE.into());
+        assert!(matches!(
+            root.set_attribute(&[], storage_class_key, b"lukewarm".to_vec())
+                .await,
+            Err(OperationError::InvalidMetadata(_))
+        ));
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+
+        assert_eq!(loaded.storage_class().await, Some(StorageClass::Warm));
+        assert_eq!(loaded.kind().await, Some(DriveKind::Backup));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn thumbnails_round_trip_as_associated_data() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.write(&mut rng, &mut store, &["photo.jpg"], b"full image")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["notes.txt"], b"no preview")
+            .await
+            .unwrap();
+        assert!(root
+            .get_thumbnail(&store, &["photo.jpg"])
+            .await
+            .unwrap()
+            .is_none());
+
+        root.set_thumbnail(&mut rng, &mut store, &["photo.jpg"], b"first", "image/png")
+            .await
+            .unwrap();
+        root.set_thumbnail(&mut rng, &mut store, &["photo.jpg"], b"small", "image/webp")
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+
+        let loaded_root = loaded.root().await.unwrap();
+        let thumbnail = loaded_root
+            .get_thumbnail(&store, &["photo.jpg"])
+            .await
+            .unwrap();
+        assert_eq!(
+            thumbnail,
+            Some((b"small".to_vec(), "image/webp".to_string()))
+        );
+        assert!(loaded_root
+            .get_thumbnail(&store, &["notes.txt"])
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            loaded_root.read(&store, &["photo.jpg"]).await.unwrap(),
+            b"full image"
+        );
+        assert_eq!(root_names(&loaded).await, vec!["notes.txt", "photo.jpg"]);
+
+        // Other sidecar data is kept alongside the thumbnail under its own name
+        root.write_associated(&mut rng, &mut store, &["photo.jpg"], "caption", b"a cat")
+            .await
+            .unwrap();
+        assert_eq!(
+            root.read_associated(&store, &["photo.jpg"], "caption")
+                .await
+                .unwrap(),
+            Some(b"a cat".to_vec())
+        );
+        assert_eq!(
+            root.read_associated(&store, &["notes.txt"], "caption")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            root.get_thumbnail(&store, &["photo.jpg"])
+                .await
+                .unwrap()
+                .map(|(data, _)| data),
+            Some(b"small".to_vec())
+        );
+
+        // Replacing the thumbnail without a content type doesn't keep the previous one around
+        root.write_associated(&mut rng, &mut store, &["photo.jpg"], "thumbnail", b"tiny")
+            .await
+            .unwrap();
+        assert_eq!(
+            root.get_thumbnail(&store, &["photo.jpg"]).await.unwrap(),
+            Some((b"tiny".to_vec(), String::new()))
+        );
+
+        root.mkdir(&mut rng, &["album"], false).await.unwrap();
+        let result = root
+            .set_thumbnail(&mut rng, &mut store, &["album"], b"small", "image/webp")
+            .await;
+        assert!(matches!(result, Err(OperationError::NotAFile)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn key_authorization_rejects_unknown_historical_and_unreadable_keys() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let owner_id = drive.assert_key_authorized(&signing_key).await.unwrap();
+        assert_eq!(owner_id, signing_key.actor_id());
+
+        let reader_key = SigningKey::generate(&mut rng);
+        let structural = AccessMaskBuilder::structural().build().unwrap();
+        let result = drive.assert_key_authorized(&reader_key).await;
+        assert!(matches!(result, Err(DriveAccessError::UnknownActorId(_))));
+
+        drive
+            .authorize_key(&mut rng, reader_key.verifying_key(), structural)
+            .await
+            .unwrap();
+        let reader_id = drive.assert_key_authorized(&reader_key).await.unwrap();
+        assert_eq!(reader_id, reader_key.actor_id());
+
+        let maintainer_key = SigningKey::generate(&mut rng);
+        let maintenance = AccessMaskBuilder::maintenance().build().unwrap();
+        drive
+            .authorize_key(&mut rng, maintainer_key.verifying_key(), maintenance)
+            .await
+            .unwrap();
+        let result = drive.assert_key_authorized(&maintainer_key).await;
+        assert!(matches!(result, Err(DriveAccessError::AccessDenied(_))));
+
+        drive.remove_key(&signing_key, &reader_id).await.unwrap();
+        let result = drive.assert_key_authorized(&reader_key).await;
+        assert!(matches!(result, Err(DriveAccessError::HistoricalActor(_))));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn loading_with_unauthorized_key_is_reported() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+
+        let mut other_key = SigningKey::generate(&mut rng);
+        while other_key.key_id() == signing_key.key_id() {
+            other_key = SigningKey::generate(&mut rng);
+        }
+
+        let result = DriveLoader::new(&other_key)
+            .from_reader(encoded.as_slice())
+            .await;
+        assert!(matches!(result, Err(DriveLoaderError::KeyNotAuthorized)));
+
+        DriveLoader::new(&signing_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn encoding_without_filesystem_key_is_reported() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let maintainer_key = SigningKey::generate(&mut rng);
+        let maintenance = AccessMaskBuilder::maintenance().build().unwrap();
+        drive
+            .authorize_key(&mut rng, maintainer_key.verifying_key(), maintenance)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        // Only holding the maintenance key leaves nothing to encrypt the filesystem with
+        drive
+            .inner
+            .write()
+            .await
+            .access_mut()
+            .unlock_keys(&maintainer_key)
+            .unwrap();
+
+        let result = drive
+            .encode(&mut rng, ContentOptions::metadata(), &mut Vec::new())
+            .await;
+        assert!(matches!(
+            result,
+            Err(DriveEncodeError::MissingFilesystemKey)
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn read_only_actors_cannot_change_node_metadata() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["shared"], false).await.unwrap();
+
+        let reader_key = Arc::new(SigningKey::generate(&mut rng));
+        let structural = AccessMaskBuilder::structural().build().unwrap();
+        drive
+            .authorize_key(&mut rng, reader_key.verifying_key(), structural)
+            .await
+            .unwrap();
+
+        let reader_drive = drive.with_current_key(reader_key).await.unwrap();
+        let mut reader_root = reader_drive.root().await.unwrap();
+        let permissions = reader_root.permissions(&["shared"]).await.unwrap();
+
+        assert!(matches!(
+            reader_root
+                .chmod(&["shared"], permissions.with_owner_write_only(true))
+                .await,
+            Err(OperationError::AccessDenied)
+        ));
+        assert!(matches!(
+            reader_root.set_executable(&["shared"], true).await,
+            Err(OperationError::AccessDenied)
+        ));
+        assert!(matches!(
+            reader_root
+                .set_attribute(&[], MetadataKey::Custom("label".into()), b"reader".to_vec())
+                .await,
+            Err(OperationError::AccessDenied)
+        ));
+
+        assert_eq!(root.permissions(&["shared"]).await.unwrap(), permissions);
+        let previous = root
+            .set_attribute(&[], MetadataKey::Custom("label".into()), b"owner".to_vec())
+            .await
+            .unwrap();
+        assert!(previous.is_none());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn with_current_key_acts_as_the_new_identity() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let second_key = Arc::new(SigningKey::generate(&mut rng));
+        let result = drive.with_current_key(second_key.clone()).await;
+        assert!(matches!(result, Err(DriveAccessError::UnknownActorId(_))));
+
+        let full_access = AccessMaskBuilder::full_access().build().unwrap();
+        drive
+            .authorize_key(&mut rng, second_key.verifying_key(), full_access)
+            .await
+            .unwrap();
+
+        let second_drive = drive.with_current_key(second_key.clone()).await.unwrap();
+        let mut second_root = second_drive.root().await.unwrap();
+        second_root
+            .mkdir(&mut rng, &["from_second"], false)
+            .await
+            .unwrap();
+
+        // Both handles share the same filesystem
+        assert_eq!(root_names(&drive).await, vec!["from_second"]);
+
+        let inner_read = drive.inner.read().await;
+        let created = inner_read
+            .node_iter()
+            .find(|node| node.name() == NodeName::Named("from_second".into()))
+            .unwrap();
+        assert_eq!(created.owner_id(), second_key.actor_id());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn rekey_data_references_rotates_the_data_key() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let full_access = AccessMaskBuilder::full_access().build().unwrap();
+        let remaining_key = SigningKey::generate(&mut rng);
+        let removed_key = SigningKey::generate(&mut rng);
+        for key in [&remaining_key, &removed_key] {
+            drive
+                .authorize_key(&mut rng, key.verifying_key(), full_access)
+                .await
+                .unwrap();
+        }
+
+        let mut root = drive.root().await.unwrap();
+        root.write(&mut rng, &mut store, &["file.txt"], b"secret")
+            .await
+            .unwrap();
+
+        drive
+            .remove_key(&signing_key, &removed_key.actor_id())
+            .await
+            .unwrap();
+
+        let previous_key = drive.inner.read().await.access().data_key().cloned();
+        let rekeyed = drive.rekey_data_references(&mut rng).await.unwrap();
+        assert_eq!(rekeyed, drive.all_cids().await);
+
+        let inner_read = drive.inner.read().await;
+        let access = inner_read.access();
+        let new_key = access.data_key().cloned();
+        assert!(new_key.is_some());
+        assert_ne!(new_key, previous_key);
+        let removed_access = access.actor_access(&removed_key.actor_id()).unwrap();
+        assert!(!removed_access.has_data_key());
+        assert!(!removed_access.has_filesystem_key());
+
+        // Remaining actors have the new key escrowed for them
+        let escrowed = access
+            .export_key_escrow()
+            .into_iter()
+            .find(|entry| entry.verifying_key().actor_id() == remaining_key.actor_id())
+            .unwrap();
+        let unlocked = escrowed.data_key().unwrap().unlock(&remaining_key).ok();
+        assert_eq!(unlocked, new_key);
+        drop(inner_read);
+
+        assert_eq!(root.read(&store, &["file.txt"]).await.unwrap(), b"secret");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn revoke_all_except_owners_rotates_the_data_key() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let full_access = AccessMaskBuilder::full_access().build().unwrap();
+        let revoked_key = SigningKey::generate(&mut rng);
+        drive
+            .authorize_key(&mut rng, revoked_key.verifying_key(), full_access)
+            .await
+            .unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.write(&mut rng, &mut store, &["file.txt"], b"secret")
+            .await
+            .unwrap();
+
+        let previous_key = drive.inner.read().await.access().data_key().cloned();
+        let (revoked, rekeyed) = drive
+            .revoke_all_except_owners(&mut rng, &signing_key)
+            .await
+            .unwrap();
+        assert_eq!(revoked, vec![revoked_key.actor_id()]);
+        assert_eq!(rekeyed, drive.all_cids().await);
+
+        let inner_read = drive.inner.read().await;
+        let access = inner_read.access();
+        assert_ne!(access.data_key().cloned(), previous_key);
+
+        let revoked_access = access.actor_access(&revoked_key.actor_id()).unwrap();
+        assert!(revoked_access.is_historical());
+        assert!(!revoked_access.has_data_key());
+        assert!(!revoked_access.has_filesystem_key());
+        drop(inner_read);
+
+        assert_eq!(root.read(&store, &["file.txt"]).await.unwrap(), b"secret");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn revoked_actors_cannot_read_later_metadata() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let full_access = AccessMaskBuilder::full_access().build().unwrap();
+        let revoked_key = SigningKey::generate(&mut rng);
+        drive
+            .authorize_key(&mut rng, revoked_key.verifying_key(), full_access)
+            .await
+            .unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["before"], true).await.unwrap();
+
+        // The revoked actor can read the drive up to the revocation and keeps its filesystem key
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+        let revoked_view = DriveLoader::new(&revoked_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        let kept_key = revoked_view
+            .inner
+            .read()
+            .await
+            .access()
+            .filesystem_key()
+            .cloned()
+            .unwrap();
+
+        drive
+            .revoke_all_except_owners(&mut rng, &signing_key)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["after"], true).await.unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let shard_cache = drive.shard_cache.read().await;
+        assert!(!shard_cache.is_empty());
+        for encrypted_shard in shard_cache.values() {
+            assert!(loader::decrypt_shard(encrypted_shard, &kept_key).is_err());
+        }
+        drop(shard_cache);
+
+        let loaded = DriveLoader::new(&signing_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(root_names(&loaded).await, vec!["after", "before"]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn metadata_key_histogram_counts_nodes_per_key() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+        assert!(drive.metadata_key_histogram().await.is_empty());
+
+        let label_key = MetadataKey::Custom("label".into());
+        let rating_key = MetadataKey::Custom("rating".into());
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2"], true)
+            .await
+            .unwrap();
+        for path in [&["dir_1"][..], &["dir_1", "dir_2"], &[]] {
+            root.set_attribute(path, label_key.clone(), b"vacation".to_vec())
+                .await
+                .unwrap();
+        }
+        root.set_attribute(&["dir_1"], rating_key.clone(), vec![5])
+            .await
+            .unwrap();
+
+        let histogram = drive.metadata_key_histogram().await;
+        assert_eq!(histogram.len(), 2);
+        assert_eq!(histogram.get(&label_key), Some(&3));
+        assert_eq!(histogram.get(&rating_key), Some(&1));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn ownership_stats_attribute_nodes_and_bytes() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let owner_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, owner_key.clone()).unwrap();
+
+        let other_key = Arc::new(SigningKey::generate(&mut rng));
+        drive
+            .authorize_key(
+                &mut rng,
+                other_key.verifying_key(),
+                AccessMaskBuilder::full_access().build().unwrap(),
+            )
+            .await
+            .unwrap();
+        let other_drive = drive.with_current_key(other_key.clone()).await.unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["docs"], false).await.unwrap();
+        root.write(&mut rng, &mut store, &["docs", "a.txt"], b"hello")
+            .await
+            .unwrap();
+
+        let mut other_root = other_drive.root().await.unwrap();
+        other_root
+            .write(&mut rng, &mut store, &["docs", "b.txt"], b"larger file")
+            .await
+            .unwrap();
+
+        let owner_id = owner_key.actor_id();
+        let other_id = other_key.actor_id();
+
+        // The root directory is owned by the actor that created the drive
+        let stats = drive.ownership_stats().await;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.get(&owner_id), Some(&3));
+        assert_eq!(stats.get(&other_id), Some(&1));
+
+        let owned_bytes = drive.owned_bytes().await;
+        assert_eq!(owned_bytes.get(&owner_id), Some(&5));
+        assert_eq!(owned_bytes.get(&other_id), Some(&11));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn folding_nodes_stops_on_break() {
+        let drive = interesting_drive().await;
+
+        let file_count = drive
+            .try_fold_nodes(0, |count, node| match node.kind() {
+                NodeKind::File => ControlFlow::Continue(count + 1),
+                _ => ControlFlow::Continue(count),
+            })
+            .await
+            .unwrap();
+        // Only file_1 and file_2 are files, the remaining leaves are directories
+        assert_eq!(file_count, 2);
+
+        let mut visited = 0;
+        let found = drive
+            .try_fold_nodes(None, |_, node| {
+                visited += 1;
+
+                match node.name() {
+                    NodeName::Named(name) if name == "dir_2" => ControlFlow::Break(Some(node.id())),
+                    _ => ControlFlow::Continue(None),
+                }
+            })
+            .await
+            .unwrap();
+
+        let inner_read = drive.inner.read().await;
+        let found = inner_read.by_id(found.unwrap()).unwrap();
+        assert_eq!(found.name(), NodeName::Named("dir_2".into()));
+        assert!(visited < inner_read.node_iter().count());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn modified_files_are_listed_by_time_range() {
+        use crate::stores::MemoryDataStore;
+
+        fn paths(modified: Vec<(Vec<String>, PermanentId)>) -> Vec<Vec<String>> {
+            modified.into_iter().map(|(path, _)| path).collect()
+        }
+
+        fn next_millisecond() -> i64 {
+            let start = crate::utils::current_time_ms();
+            loop {
+                let now = crate::utils::current_time_ms();
+                if now > start {
+                    return now;
+                }
+            }
+        }
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["docs"], false).await.unwrap();
+        root.write(&mut rng, &mut store, &["docs", "a.txt"], b"first")
+            .await
+            .unwrap();
+
+        let cutoff = next_millisecond();
+        root.mkdir(&mut rng, &["nested", "deeper"], true)
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["nested", "deeper", "c.txt"], b"c")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["docs", "b.txt"], b"second")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            paths(drive.modified_since(cutoff).await),
+            vec![
+                path(&["docs", "b.txt"]),
+                path(&["nested", "deeper", "c.txt"])
+            ]
+        );
+        assert_eq!(
+            paths(drive.modified_between(0, cutoff).await),
+            vec![path(&["docs", "a.txt"])]
+        );
+        assert_eq!(drive.modified_since(0).await.len(), 3);
+
+        let (_, permanent_id) = drive.modified_between(0, cutoff).await.remove(0);
+        assert_eq!(
+            drive.full_path_from_root(&permanent_id).await.unwrap(),
+            path(&["docs", "a.txt"])
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn find_matches_paths_from_root() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["docs", "nested"], true)
+            .await
+            .unwrap();
+        for path in [
+            &["notes.txt"][..],
+            &["docs", "a.txt"],
+            &["docs", "ab.md"],
+            &["docs", "nested", "b.txt"],
+        ] {
+            root.write(&mut rng, &mut store, path, b"data")
+                .await
+                .unwrap();
+        }
+
+        let paths = |found: Vec<(Vec<String>, PermanentId)>| {
+            found
+                .into_iter()
+                .map(|(path, _)| path.join("/"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            paths(drive.find("**/*.txt").await.unwrap()),
+            vec!["docs/a.txt", "docs/nested/b.txt", "notes.txt"]
+        );
+        assert_eq!(
+            paths(drive.find("docs/*").await.unwrap()),
+            vec!["docs/a.txt", "docs/ab.md", "docs/nested"]
+        );
+        assert_eq!(
+            paths(drive.find("/docs/??.*").await.unwrap()),
+            vec!["docs/ab.md"]
+        );
+        assert_eq!(drive.find("**").await.unwrap().len(), 6);
+        assert!(drive.find("missing/**").await.unwrap().is_empty());
+
+        let (path, permanent_id) = drive.find("docs/a.txt").await.unwrap().remove(0);
+        assert_eq!(
+            drive.full_path_from_root(&permanent_id).await.unwrap(),
+            path
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn estimated_encoded_size_matches_encoding() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2"], true)
+            .await
+            .unwrap();
+        root.mkdir(&mut rng, &["dir_a"], false).await.unwrap();
+        root.write(&mut rng, &mut store, &["dir_1", "notes.txt"], b"some notes")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["empty.txt"], b"")
+            .await
+            .unwrap();
+        root.set_attribute(&["dir_a"], MetadataKey::Custom("label".into()), vec![1, 2])
+            .await
+            .unwrap();
+
+        let options: [fn() -> ContentOptions; 5] = [
+            ContentOptions::metadata,
+            ContentOptions::sharded_metadata,
+            ContentOptions::delta_metadata,
+            ContentOptions::data_only,
+            || ContentOptions::sharded_metadata().with_integrity_manifest(),
+        ];
+
+        for content_options in options {
+            let estimated_size = drive.estimated_encoded_size(content_options()).await;
+
+            let mut encoded = Vec::new();
+            drive
+                .encode(&mut rng, content_options(), &mut encoded)
+                .await
+                .unwrap();
+
+            assert_eq!(estimated_size, encoded.len() as u64);
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn export_archive_streams_subtree_as_tar() {
+        use crate::stores::MemoryDataStore;
+        use futures::TryStreamExt;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let long_name = "n".repeat(120);
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["photos", "trips"], true)
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["photos", "cat.jpg"], &[0x42; 700])
+            .await
+            .unwrap();
+        root.write(
+            &mut rng,
+            &mut store,
+            &["photos", "trips", "back\\slash"],
+            b"",
+        )
+        .await
+        .unwrap();
+        root.write(&mut rng, &mut store, &["photos", &long_name], b"long")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["skipped.txt"], b"outside")
+            .await
+            .unwrap();
+
+        let archive: Vec<Bytes> = drive
+            .export_archive(&store, &["photos"])
+            .try_collect()
+            .await
+            .unwrap();
+        let archive = archive.concat();
+        assert_eq!(archive.len() % 512, 0);
+
+        // Walk the headers collecting each entry's name and content
+        let mut entries = Vec::new();
+        let mut long_name_override = None;
+        let mut offset = 0;
+        while archive[offset..offset + 512].iter().any(|b| *b != 0) {
+            let header = &archive[offset..offset + 512];
+            let size_field = std::str::from_utf8(&header[124..135]).unwrap();
+            let size = usize::from_str_radix(size_field, 8).unwrap();
+            let data = archive[offset + 512..offset + 512 + size].to_vec();
+            offset += 512 + size.div_ceil(512) * 512;
+
+            if header[156] == b'L' {
+                let name = String::from_utf8(data[..size - 1].to_vec()).unwrap();
+                long_name_override = Some(name);
+                continue;
+            }
+
+            let name = long_name_override.take().unwrap_or_else(|| {
+                let name_len = header[..100].iter().position(|b| *b == 0).unwrap_or(100);
+                String::from_utf8(header[..name_len].to_vec()).unwrap()
+            });
+            entries.push((name, header[156], data));
+        }
+        assert!(archive[offset..].iter().all(|b| *b == 0));
+
+        let mut names: Vec<&str> = entries.iter().map(|(name, _, _)| name.as_str()).collect();
+        names.sort();
+        let long_path = format!("photos/{long_name}");
+        let mut expected = vec![
+            "photos/",
+            "photos/cat.jpg",
+            "photos/trips/",
+            "photos/trips/back_slash",
+            long_path.as_str(),
+        ];
+        expected.sort();
+        assert_eq!(names, expected);
+
+        for (name, kind, data) in entries.iter() {
+            match name.as_str() {
+                "photos/cat.jpg" => assert_eq!(data, &vec![0x42; 700]),
+                "photos/trips/back_slash" => assert!(data.is_empty()),
+                name if name == long_path => assert_eq!(data, b"long"),
+                _ => assert_eq!(*kind, b'5'),
+            }
+        }
+
+        let missing: Result<Vec<Bytes>, _> = drive
+            .export_archive(&store, &["missing"])
+            .try_collect()
+            .await;
+        assert!(matches!(missing, Err(OperationError::PathNotFound)));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn export_archive_loads_the_subtrees_it_covers() {
+        use crate::stores::MemoryDataStore;
+        use futures::TryStreamExt;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["docs"], true).await.unwrap();
+        root.mkdir(&mut rng, &["photos"], true).await.unwrap();
+        root.write(&mut rng, &mut store, &["photos", "cat.jpg"], &[0x42; 700])
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::sharded_metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        for path in [&["photos"][..], &[]] {
+            let expected: Vec<Bytes> = drive
+                .export_archive(&store, path)
+                .try_collect()
+                .await
+                .unwrap();
+
+            let partial = DriveLoader::new(&signing_key)
+                .load_subtree(encoded.as_slice(), &["docs"])
+                .await
+                .unwrap();
+            let archive: Vec<Bytes> = partial
+                .export_archive(&store, path)
+                .try_collect()
+                .await
+                .unwrap();
+
+            assert_eq!(archive.concat(), expected.concat());
+            assert!(archive
+                .concat()
+                .windows(700)
+                .any(|window| window == [0x42; 700]));
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn version_fingerprint_covers_tree_and_access() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1"], false).await.unwrap();
+        let original = drive.version_fingerprint().await.unwrap();
+
+        // Unaffected by encoding and loading the drive again
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(loaded.version_fingerprint().await.unwrap(), original);
+
+        // Same tree, different authorized keys
+        let root_cid = drive.root_cid().await.unwrap();
+        let reader_key = SigningKey::generate(&mut rng);
+        let structural = AccessMaskBuilder::structural().build().unwrap();
+        drive
+            .authorize_key(&mut rng, reader_key.verifying_key(), structural)
+            .await
+            .unwrap();
+        assert_eq!(drive.root_cid().await.unwrap(), root_cid);
+
+        let authorized = drive.version_fingerprint().await.unwrap();
+        assert_ne!(authorized, original);
+
+        // Same keys, different tree
+        root.mkdir(&mut rng, &["dir_2"], false).await.unwrap();
+        assert_ne!(drive.version_fingerprint().await.unwrap(), authorized);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn checkpoints_are_recorded_and_persisted() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+        assert!(drive.checkpoints().await.is_empty());
+
+        // The journal is only included when asked for
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::metadata(), &mut encoded)
+            .await
+            .unwrap();
+        assert!(drive.checkpoints().await.is_empty());
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(
+                &mut rng,
+                ContentOptions::metadata().with_journal(),
+                &mut encoded,
+            )
+            .await
+            .unwrap();
+        let first_root = drive.root_cid().await.unwrap();
+
+        // Encoding an unchanged filesystem doesn't record another checkpoint
+        let mut encoded = Vec::new();
+        drive
+            .encode(
+                &mut rng,
+                ContentOptions::metadata().with_journal(),
+                &mut encoded,
+            )
+            .await
+            .unwrap();
+        assert_eq!(drive.checkpoints().await.len(), 1);
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1"], false).await.unwrap();
+        let second_root = drive.root_cid().await.unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(
+                &mut rng,
+                ContentOptions::sharded_metadata().with_journal(),
+                &mut encoded,
+            )
+            .await
+            .unwrap();
+
+        let loaded = DriveLoader::new(&drive.current_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+
+        let checkpoints = loaded.checkpoints().await;
+        assert_eq!(checkpoints, drive.checkpoints().await);
+
+        let roots: Vec<_> = checkpoints.iter().map(|c| c.merkle_root_cid()).collect();
+        assert_eq!(roots, vec![&first_root, &second_root]);
+        assert!(checkpoints[0].created_at() <= checkpoints[1].created_at());
+
+        // Encodings that don't include the journal leave it untouched
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::data_only(), &mut encoded)
+            .await
+            .unwrap();
+        assert_eq!(drive.checkpoints().await.len(), 2);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn find_duplicate_files_groups_shared_content() {
+        use crate::filesystem::nodes::NodeData;
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        for name in ["original.txt", "copy.txt", "rewritten.txt", "empty.txt"] {
+            let data: &[u8] = if name == "empty.txt" { b"" } else { b"same" };
+            root.write(&mut rng, &mut store, &[name], data)
+                .await
+                .unwrap();
+        }
+
+        // Writing the same data separately produces distinct blocks
+        assert!(drive.find_duplicate_files().await.is_empty());
+
+        let mut inner = drive.inner.write().await;
+        let root_id = inner.root_node().unwrap().id();
+        let mut found_ids = Vec::new();
+        for name in ["original.txt", "copy.txt"] {
+            match inner.walk_path(root_id, &[name]).unwrap() {
+                WalkState::FoundNode { node_id } => found_ids.push(node_id),
+                _ => panic!("written file should be present"),
+            }
+        }
+
+        let content = match inner.by_id(found_ids[0]).unwrap().data() {
+            NodeData::File { content, .. } => content.clone(),
+            _ => panic!("expected a file"),
+        };
+        let copy = inner.by_id_mut(found_ids[1]).await.unwrap();
+        *copy.data_mut().await = NodeData::full_file(content);
+
+        let mut expected = vec![
+            inner.by_id(found_ids[0]).unwrap().permanent_id(),
+            inner.by_id(found_ids[1]).unwrap().permanent_id(),
+        ];
+        expected.sort();
+        drop(inner);
+
+        assert_eq!(drive.find_duplicate_files().await, vec![expected]);
+
+        // The block replaced in the copy is no longer referenced at all
+        let mut counts = drive
+            .block_reference_counts()
+            .await
+            .into_values()
+            .collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, vec![1, 2]);
+        assert_eq!(drive.all_cids().await.len(), 2);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn stats_count_shared_blocks_once() {
+        use crate::filesystem::nodes::NodeData;
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["docs"], false).await.unwrap();
+        root.write(&mut rng, &mut store, &["docs", "original.txt"], b"same")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["copy.txt"], b"same")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["empty.txt"], b"")
+            .await
+            .unwrap();
+
+        let stats = drive.stats().await.unwrap();
+        assert_eq!(stats.file_count(), 3);
+        assert_eq!(stats.directory_count(), 2);
+        assert_eq!(stats.total_size(), 8);
+        assert_eq!(stats.data_cid_count(), 2);
+
+        // Shared content counts towards the size of each file but its blocks are counted once
+        let mut inner = drive.inner.write().await;
+        let root_id = inner.root_node().unwrap().id();
+        let mut found_ids = Vec::new();
+        for path in [&["docs", "original.txt"][..], &["copy.txt"][..]] {
+            match inner.walk_path(root_id, path).unwrap() {
+                WalkState::FoundNode { node_id } => found_ids.push(node_id),
+                _ => panic!("written file should be present"),
+            }
+        }
+
+        let content = match inner.by_id(found_ids[0]).unwrap().data() {
+            NodeData::File { content, .. } => content.clone(),
+            _ => panic!("expected a file"),
+        };
+        let copy = inner.by_id_mut(found_ids[1]).await.unwrap();
+        *copy.data_mut().await = NodeData::full_file(content);
+        drop(inner);
+
+        let stats = drive.stats().await.unwrap();
+        assert_eq!(stats.file_count(), 3);
+        assert_eq!(stats.total_size(), 8);
+        assert_eq!(stats.data_cid_count(), 1);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn snapshots_serve_reads_from_capture_time() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["docs"], false).await.unwrap();
+        root.write(&mut rng, &mut store, &["docs", "a.txt"], b"original")
+            .await
+            .unwrap();
+
+        let snapshot = drive.snapshot().await.unwrap();
+        assert_eq!(snapshot.root_cid(), &drive.root_cid().await.unwrap());
+
+        root.write(&mut rng, &mut store, &["docs", "a.txt"], b"changed")
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["docs", "b.txt"], b"new")
+            .await
+            .unwrap();
+
+        // Reads are served while the live drive is locked and don't see later changes
+        let write_guard = drive.inner.write().await;
+        let shared = snapshot.clone();
+        assert_eq!(sorted_names(shared.ls(&["docs"]).unwrap()), vec!["a.txt"]);
+        assert_eq!(
+            shared.read(&store, &["docs", "a.txt"]).await.unwrap(),
+            b"original"
+        );
+        assert!(shared.entry(&["docs"]).unwrap().is_dir());
+        assert!(matches!(
+            shared.entry(&["docs", "b.txt"]),
+            Err(OperationError::PathNotFound)
+        ));
+        drop(write_guard);
+
+        let latest = drive.snapshot().await.unwrap();
+        assert_eq!(
+            sorted_names(latest.ls(&["docs"]).unwrap()),
+            vec!["a.txt", "b.txt"]
+        );
+        assert_ne!(latest.root_cid(), snapshot.root_cid());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn transfer_node_rehomes_subtree_into_other_drive() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+
+        let source_key = Arc::new(SigningKey::generate(&mut rng));
+        let source = Drive::initialize_private(&mut rng, source_key).unwrap();
+        let dest_key = Arc::new(SigningKey::generate(&mut rng));
+        let dest = Drive::initialize_private(&mut rng, dest_key.clone()).unwrap();
+
+        let mut source_root = source.root().await.unwrap();
+        source_root
+            .mkdir(&mut rng, &["docs", "nested"], true)
+            .await
+            .unwrap();
+        source_root
+            .write(&mut rng, &mut store, &["docs", "nested", "a.txt"], b"hello")
+            .await
+            .unwrap();
+        let docs_entry = source_root.cd(&["docs"]).await.unwrap().entry().await;
+        let docs_pid = docs_entry.unwrap().permanent_id();
+
+        let mut dest_root = dest.root().await.unwrap();
+        dest_root
+            .mkdir(&mut rng, &["archive"], false)
+            .await
+            .unwrap();
+
+        let result = source
+            .transfer_node(&mut rng, docs_pid, &source, &["elsewhere"])
+            .await;
+        assert!(matches!(result, Err(OperationError::Other(_))));
+
+        let new_pid = source
+            .transfer_node(&mut rng, docs_pid, &dest, &["archive", "papers"])
+            .await
+            .unwrap();
+        assert_ne!(new_pid, docs_pid);
+        assert!(root_names(&source).await.is_empty());
+
+        let moved_entry = dest_root
+            .cd(&["archive", "papers"])
+            .await
+            .unwrap()
+            .entry()
+            .await;
+        assert_eq!(moved_entry.unwrap().permanent_id(), new_pid);
+
+        // The existing blocks are readable using only the destination's keys
+        let data = dest_root
+            .read(&store, &["archive", "papers", "nested", "a.txt"])
+            .await
+            .unwrap();
+        assert_eq!(data, b"hello");
+
+        // Name collisions in the destination leave both drives untouched
+        source_root
+            .mkdir(&mut rng, &["archive"], false)
+            .await
+            .unwrap();
+        let archive_entry = source_root.cd(&["archive"]).await.unwrap().entry().await;
+        let result = source
+            .transfer_node(&mut rng, archive_entry.unwrap().permanent_id(), &dest, &[])
+            .await;
+        assert!(matches!(result, Err(OperationError::Exists(_))));
+        assert_eq!(root_names(&source).await, vec!["archive"]);
+
+        let dest_cid = dest.root_cid().await.unwrap();
+        let mut encoded = Vec::new();
+        dest.encode(&mut rng, ContentOptions::everything(), &mut encoded)
+            .await
+            .unwrap();
+        let reloaded = DriveLoader::new(&dest_key)
+            .from_reader(encoded.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(reloaded.root_cid().await.unwrap(), dest_cid);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn resolve_path_verbose_reports_failing_component() {
+        use crate::stores::MemoryDataStore;
+
+        let mut rng = crate::utils::crypto_rng();
+        let mut store = MemoryDataStore::default();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key).unwrap();
+
+        let mut root = drive.root().await.unwrap();
+        root.mkdir(&mut rng, &["dir_1", "dir_2"], true)
+            .await
+            .unwrap();
+        root.write(&mut rng, &mut store, &["dir_1", "notes.txt"], b"notes")
+            .await
+            .unwrap();
+
+        let resolution = drive.resolve_path_verbose(&["dir_1", "notes.txt"]).await;
+        assert!(resolution.is_resolved());
+        let kinds: Vec<NodeKind> = resolution.resolved().iter().map(|c| c.kind()).collect();
+        assert_eq!(kinds, vec![NodeKind::Directory, NodeKind::File]);
+
+        let resolution = drive
+            .resolve_path_verbose(&["dir_1", "dir_2", "missing", "deeper"])
+            .await;
+        assert_eq!(resolution.resolved().len(), 2);
+        assert_eq!(
+            resolution.failure(),
+            Some(("missing", PathResolutionFailure::Missing))
+        );
+        assert_eq!(resolution.to_string(), "/dir_1/dir_2/missing (Missing)");
+
+        // Files hold their associated data as children so they can be walked into as well
+        let resolution = drive
+            .resolve_path_verbose(&["dir_1", "notes.txt", "child"])
+            .await;
+        assert_eq!(resolution.resolved().len(), 2);
+        assert_eq!(
+            resolution.failure(),
+            Some(("child", PathResolutionFailure::Missing))
+        );
+
+        let resolution = drive.resolve_path_verbose(&["dir_1", ".."]).await;
+        assert_eq!(
+            resolution.failure(),
+            Some(("..", PathResolutionFailure::InvalidName))
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn untrusted_parsing_rejects_malformed_input() {
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        for content_options in [
+            ContentOptions::metadata(),
+            ContentOptions::sharded_metadata(),
+        ] {
+            let mut encoded = Vec::new();
+            drive
+                .encode(&mut rng, content_options, &mut encoded)
+                .await
+                .unwrap();
+
+            let loaded = DriveLoader::new(&drive.current_key)
+                .parse_untrusted(&encoded)
+                .unwrap();
+            assert_eq!(
+                loaded.root_cid().await.unwrap(),
+                drive.root_cid().await.unwrap()
+            );
+
+            // Parsing is slow in debug builds, so only a spread of positions is checked here
+            let step = (encoded.len() / 32).max(1);
+
+            // Truncation and trailing data are both rejected
+            for len in (0..encoded.len()).step_by(step) {
+                let result = DriveLoader::new(&drive.current_key).parse_untrusted(&encoded[..len]);
+                assert!(result.is_err(), "truncation to {len} bytes was accepted");
+            }
+
+            let mut trailing = encoded.clone();
+            trailing.push(0);
+            let result = DriveLoader::new(&drive.current_key).parse_untrusted(&trailing);
+            assert!(matches!(result, Err(DriveLoaderError::ParserFailure(_))));
+
+            // Corrupting any single byte produces an error rather than a panic
+            for idx in (0..encoded.len()).step_by(step) {
+                let mut corrupted = encoded.clone();
+                corrupted[idx] ^= 0x5a;
+                let _ = DriveLoader::new(&drive.current_key).parse_untrusted(&corrupted);
+            }
+        }
+
+        let oversized = vec![0u8; crate::filesystem::MAX_UNTRUSTED_DRIVE_SIZE + 1];
+        let result = DriveLoader::new(&drive.current_key).parse_untrusted(&oversized);
+        assert!(matches!(result, Err(DriveLoaderError::InputTooLarge(_))));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn escrow_verification_reports_structural_problems() {
+        let mut rng = crate::utils::crypto_rng();
+        let signing_key = Arc::new(SigningKey::generate(&mut rng));
+        let drive = Drive::initialize_private(&mut rng, signing_key.clone()).unwrap();
+
+        let reader_key = SigningKey::generate(&mut rng);
+        let structural = AccessMaskBuilder::structural().build().unwrap();
+        drive
+            .authorize_key(&mut rng, reader_key.verifying_key(), structural)
+            .await
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        drive
+            .encode(&mut rng, ContentOptions::metadata(), &mut encoded)
+            .await
+            .unwrap();
+
+        let loader = DriveLoader::new(&signing_key);
+        loader.verify_escrow(&encoded).unwrap();
+        DriveLoader::new(&reader_key)
+            .verify_escrow(&encoded)
+            .unwrap();
+
+        let outsider = SigningKey::generate(&mut rng);
+        assert!(matches!(
+            DriveLoader::new(&outsider).verify_escrow(&encoded),
+            Err(EscrowError::NoEntryForKey)
+        ));
+
+        let count_offset = IdentityHeader::size() + FilesystemId::size() + PublicSettings::size();
+        let entry_size = KeyId::size() + AsymLockedAccessKey::size();
+        let entry_offset = |index: usize| count_offset + KeyCount::size() + index * entry_size;
+        let owner_index = (0..2)
+            .find(|&index| {
+                let key_id = &encoded[entry_offset(index)..entry_offset(index) + KeyId::size()];
+                key_id == signing_key.key_id().to_le_bytes()
+            })
+            .unwrap();
+
+        let mut empty = encoded.clone();
+        empty[count_offset] = 0;
+        assert!(matches!(
+            loader.verify_escrow(&empty),
+            Err(EscrowError::Empty)
+        ));
+
+        assert!(matches!(
+            loader.verify_escrow(&encoded[..entry_offset(1) + 10]),
+            Err(EscrowError::Truncated {
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        // Damaging the authentication tag of the owner's entry prevents it from being unlocked
+        let mut damaged = encoded.clone();
+        damaged[entry_offset(owner_index + 1) - 1] ^= 0x01;
+        assert!(matches!(
+            loader.verify_escrow(&damaged),
+            Err(EscrowError::UnlockFailed(1))
+        ));
+
+        // Readdressing the other entry leaves it without a matching actor
+        let other_index = 1 - owner_index;
+        let mut readdressed = encoded.clone();
+        readdressed[entry_offset(other_index)] ^= 0x01;
+        assert!(matches!(
+            loader.verify_escrow(&readdressed),
+            Err(EscrowError::UnknownEntry { index, .. }) if index == other_index
+        ));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn encode_reports_the_bytes_written() {
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+
+        for content_options in [
+            ContentOptions::everything(),
+            ContentOptions::metadata().with_journal(),
+            ContentOptions::sharded_metadata(),
+            ContentOptions::metadata().with_integrity_manifest(),
+        ] {
+            let mut encoded = Vec::new();
+            let written = drive
+                .encode(&mut rng, content_options, &mut encoded)
+                .await
+                .unwrap();
+            assert_eq!(written, encoded.len());
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn integrity_manifest_rejects_modified_drives() {
+        // Appends a manifest signed over the provided values to the contents of an encoded drive
+        async fn resigned(contents: &[u8], signing_key: &SigningKey, root_cid: Cid) -> Vec<u8> {
+            let mut rng = crate::utils::crypto_rng();
+            let length = contents.len() as u64;
+            let content_hash = *blake3::hash(contents).as_bytes();
+            let manifest =
+                IntegrityManifest::sign(&mut rng, signing_key, length, content_hash, root_cid);
+
+            let mut resigned = contents.to_vec();
+            manifest.encode(&mut resigned).await.unwrap();
+            resigned
+        }
+
+        let mut rng = crate::utils::crypto_rng();
+        let drive = interesting_drive().await;
+        let root_cid = drive.root_cid().await.unwrap();
+
+        for content_options in [
+            ContentOptions::metadata().with_integrity_manifest(),
+            ContentOptions::sharded_metadata().with_integrity_manifest(),
+        ] {
+            let mut encoded = Vec::new();
+            drive
+                .encode(&mut rng, content_options, &mut encoded)
+                .await
+                .unwrap();
+
+            let manifest = IntegrityManifest::from_trailing(&encoded).unwrap();
+            assert_eq!(manifest.root_cid(), &root_cid);
+            assert_eq!(manifest.verifying_key(), &drive.current_key.verifying_key());
+
+            let loaded = DriveLoader::new(&drive.current_key)
+                .from_reader(encoded.as_slice())
+                .await
+                .unwrap();
+            assert_eq!(loaded.root_cid().await.unwrap(), root_cid);
+
+            let loaded = DriveLoader::new(&drive.current_key)
+                .parse_untrusted(&encoded)
+                .unwrap();
+            assert_eq!(loaded.root_cid().await.unwrap(), root_cid);
+
+            let contents_len = encoded.len() - IntegrityManifest::size();
+            // Data slipped in ahead of the manifest no longer matches the recorded length
+            let mut padded = encoded.clone();
+            padded.insert(contents_len, 0);
+            let result = DriveLoader::new(&drive.current_key).parse_untrusted(&padded);
+            assert!(matches!(
+                result,
+                Err(DriveLoaderError::IntegrityCheckFailed(
+                    IntegrityManifestError::LengthMismatch { .. }
+                ))
+            ));
+
+            // Changing a single byte of the drive, keeping its length, no longer matches the hash
+            let mut flipped = encoded.clone();
+            flipped[contents_len - 1] ^= 0x01;
+            assert!(matches!(
+                IntegrityManifest::from_trailing(&flipped),
+                Err(IntegrityManifestError::ContentMismatch)
+            ));
+            let result = DriveLoader::new(&drive.current_key).parse_untrusted(&flipped);
+            assert!(matches!(
+                result,
+                Err(DriveLoaderError::IntegrityCheckFailed(
+                    IntegrityManifestError::ContentMismatch
+                ))
+            ));
+
+            // A manifest signed by someone outside of the drive isn't trusted
+            let outsider = SigningKey::generate(&mut rng);
+            let result = DriveLoader::new(&drive.current_key)
+                .from_reader(
+                    resigned(&encoded[..contents_len], &outsider, root_cid.clone())
+                        .await
+                        .as_slice(),
+                )
+                .await;
+            assert!(matches!(
+                result,
+                Err(DriveLoaderError::IntegrityCheckFailed(
+                    IntegrityManifestError::UnknownSigner
+                ))
+            ));
+
+            let wrong_root = Cid::from([0x5a; 32]);
+            let tampered = resigned(&encoded[..contents_len], &drive.current_key, wrong_root).await;
+            let result = DriveLoader::new(&drive.current_key).parse_untrusted(&tampered);
+            assert!(matches!(
+                result,
+                Err(DriveLoaderError::IntegrityCheckFailed(
+                    IntegrityManifestError::RootMismatch
+                ))
+            ));
+        }
+    }
 }
@@ -10,12 +10,24 @@ pub enum OperationError {
     #[error("current user doesn't have the correct key to read or write to the drive")]
     AccessDenied,
 
+    #[error("block failed authentication and may have been tampered with: {cid:?}")]
+    BlockAuthenticationFailed { cid: Cid },
+
     #[error("block was found but wasn't valid: {0:?}")]
     BlockCorrupted(Cid),
 
     #[error("block with CID was not found in the data store: {0:?}")]
     BlockUnavailable(Cid),
 
+    #[error("node was changed since it was last read, its CID is now {current:?}")]
+    Conflict { current: Cid },
+
+    #[error("reading the content to write failed: {0}")]
+    ContentReadFailed(std::io::Error),
+
+    #[error("content was declared as {expected} bytes but its blocks contain {actual} bytes")]
+    ContentSizeMismatch { expected: u64, actual: u64 },
+
     #[error("creation of a node failed: {0}")]
     CreationFailed(#[from] NodeBuilderError),
 
@@ -25,12 +37,21 @@ pub enum OperationError {
     #[error("attempted to create a node where one already exists (node {0} in place)")]
     Exists(NodeId),
 
+    #[error("external content did not match the CID recorded for it")]
+    ExternalContentMismatch,
+
+    #[error("reading external content requires an external fetcher")]
+    ExternalFetcherRequired,
+
     #[error("error working with file content: {0}")]
     FileContentError(#[from] FileContentError),
 
     #[error("detected internal violation of assumptions (NID:{0}): {1}")]
     InternalCorruption(NodeId, &'static str),
 
+    #[error("metadata attribute was invalid: {0}")]
+    InvalidMetadata(&'static str),
+
     #[error("node name was invalid: {0:?}")]
     InvalidName(#[from] NodeNameError),
 
@@ -40,6 +61,12 @@ pub enum OperationError {
     #[error("node operation failed: {0}")]
     NodeFailure(#[from] NodeDataError),
 
+    #[error("associated data can only be attached to files")]
+    NotAFile,
+
+    #[error("only content encrypted by BanyanFS can be appended to")]
+    NotAppendable,
+
     #[error("the requested content hasn't been uploaded and recorded yet")]
     NotAvailable,
 
@@ -49,6 +76,9 @@ pub enum OperationError {
     #[error("attempted to read from a node that contains no data")]
     NotReadable,
 
+    #[error("offset {offset} is past the end of the {size} byte content")]
+    OffsetOutOfRange { offset: u64, size: u64 },
+
     #[error("Node({0:?}) was orphaned in filesystem and is unsafe to remove")]
     OrphanNode(PermanentId),
 
@@ -64,6 +94,24 @@ pub enum OperationError {
     #[error("attempted recursion too deep to process")]
     PathTooDeep,
 
+    #[error("attempted to move a node inside of itself")]
+    RecursiveMove,
+
+    #[error("the subtree containing Node({0:?}) hasn't been loaded yet")]
+    SubtreeNotLoaded(PermanentId),
+
     #[error("unable to make use of an empty path")]
     UnexpectedEmptyPath,
 }
+
+impl From<OperationError> for std::io::Error {
+    fn from(err: OperationError) -> Self {
+        use std::io::{Error as StdError, ErrorKind as StdErrorKind};
+
+        match err {
+            OperationError::AccessDenied => StdError::new(StdErrorKind::PermissionDenied, err),
+            OperationError::PathNotFound => StdError::new(StdErrorKind::NotFound, err),
+            err => StdError::other(err),
+        }
+    }
+}
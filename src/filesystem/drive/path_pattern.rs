@@ -0,0 +1,145 @@
+/// A glob pattern matched against the full path of a node from the root of a drive, see
+/// [`crate::filesystem::Drive::find`]. The pattern is split into segments on `/`, within a
+/// segment `*` matches any run of characters and `?` matches a single character. A segment
+/// consisting only of `**` matches any number of path segments, including none.
+pub(crate) struct PathPattern {
+    segments: Vec<PatternSegment>,
+}
+
+enum PatternSegment {
+    AnyDepth,
+    Name(Vec<char>),
+}
+
+impl PathPattern {
+    pub(crate) fn matches(&self, path: &[String]) -> bool {
+        if path.is_empty() {
+            return false;
+        }
+
+        matches_segments(&self.segments, path)
+    }
+
+    /// The root of the drive is always implied, so leading, trailing, and repeated slashes are
+    /// ignored.
+    pub(crate) fn parse(pattern: &str) -> Self {
+        let mut segments = Vec::new();
+
+        for segment in pattern.split('/').filter(|segment| !segment.is_empty()) {
+            if segment == "**" {
+                // Consecutive `**` segments match exactly the same paths as a single one
+                if !matches!(segments.last(), Some(PatternSegment::AnyDepth)) {
+                    segments.push(PatternSegment::AnyDepth);
+                }
+            } else {
+                segments.push(PatternSegment::Name(segment.chars().collect()));
+            }
+        }
+
+        Self { segments }
+    }
+}
+
+fn matches_segments(segments: &[PatternSegment], path: &[String]) -> bool {
+    glob_match(
+        segments,
+        path,
+        |segment| matches!(segment, PatternSegment::AnyDepth),
+        |segment, name| match segment {
+            PatternSegment::AnyDepth => true,
+            PatternSegment::Name(pattern) => {
+                let name: Vec<char> = name.chars().collect();
+                matches_name(pattern, &name)
+            }
+        },
+    )
+}
+
+fn matches_name(pattern: &[char], name: &[char]) -> bool {
+    glob_match(
+        pattern,
+        name,
+        |expected| *expected == '*',
+        |expected, actual| *expected == '?' || expected == actual,
+    )
+}
+
+/// Matches `items` against `pattern`, where the elements accepted by `is_wildcard` match any run
+/// of items (including none) and every other element matches the single item `matches_item`
+/// accepts it for. Only the most recent wildcard is ever revisited when a match fails, keeping
+/// the work bounded by the product of the two lengths rather than growing exponentially with the
+/// number of wildcards.
+fn glob_match<P, I>(
+    pattern: &[P],
+    items: &[I],
+    is_wildcard: impl Fn(&P) -> bool,
+    matches_item: impl Fn(&P, &I) -> bool,
+) -> bool {
+    let (mut pattern_idx, mut item_idx) = (0, 0);
+    // The position just after the most recent wildcard and the next item it could take in
+    let mut backtrack = None;
+
+    while item_idx < items.len() {
+        match pattern.get(pattern_idx) {
+            Some(element) if is_wildcard(element) => {
+                pattern_idx += 1;
+                backtrack = Some((pattern_idx, item_idx + 1));
+            }
+            Some(element) if matches_item(element, &items[item_idx]) => {
+                pattern_idx += 1;
+                item_idx += 1;
+            }
+            _ => match backtrack {
+                Some((resume_pattern, resume_item)) => {
+                    pattern_idx = resume_pattern;
+                    item_idx = resume_item;
+                    backtrack = Some((resume_pattern, resume_item + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_idx..].iter().all(is_wildcard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        let path: Vec<String> = path.split('/').map(String::from).collect();
+        PathPattern::parse(pattern).matches(&path)
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("docs/*.txt", "docs/notes.txt"));
+        assert!(!matches("docs/*.txt", "docs/nested/notes.txt"));
+        assert!(!matches("docs/*.txt", "notes.txt"));
+
+        assert!(matches("/docs/?.md", "docs/a.md"));
+        assert!(!matches("docs/?.md", "docs/ab.md"));
+        assert!(matches("docs/*", "docs/ünïcode"));
+
+        assert!(matches("**/*.txt", "notes.txt"));
+        assert!(matches("**/*.txt", "docs/nested/notes.txt"));
+        assert!(matches("docs/**", "docs/nested/notes.txt"));
+        assert!(matches("docs/**/notes.txt", "docs/notes.txt"));
+        assert!(matches("docs/**/**/notes.txt", "docs/a/b/notes.txt"));
+        assert!(!matches("docs/**/notes.txt", "other/notes.txt"));
+
+        assert!(!matches("", "docs"));
+    }
+
+    #[test]
+    fn test_many_wildcards_match_quickly() {
+        let name = "a".repeat(64);
+        assert!(!matches(&format!("{}b", "*a".repeat(16)), &name));
+        assert!(matches(&format!("{}*", "*a".repeat(16)), &name));
+
+        let path = vec!["a"; 64].join("/");
+        assert!(!matches(&format!("{}b", "**/a/".repeat(16)), &path));
+        assert!(matches(&format!("{}**", "**/a/".repeat(16)), &path));
+    }
+}
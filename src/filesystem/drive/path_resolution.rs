@@ -0,0 +1,112 @@
+use crate::codec::filesystem::NodeKind;
+use crate::codec::PermanentId;
+use crate::filesystem::nodes::Node;
+
+/// A path component that was successfully resolved to a node while walking a path with
+/// [`crate::filesystem::Drive::resolve_path_verbose`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedComponent {
+    name: String,
+    permanent_id: PermanentId,
+    kind: NodeKind,
+}
+
+impl ResolvedComponent {
+    pub(crate) fn new(name: &str, node: &Node) -> Self {
+        Self {
+            name: name.to_string(),
+            permanent_id: node.permanent_id(),
+            kind: node.kind(),
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind.clone()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn permanent_id(&self) -> PermanentId {
+        self.permanent_id
+    }
+}
+
+/// Why resolution of a path component failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathResolutionFailure {
+    /// The drive refers to a node that isn't present. This indicates the drive is corrupted.
+    InconsistentDrive,
+
+    /// The component can never be a valid node name, such as an empty name or one containing a
+    /// slash.
+    InvalidName,
+
+    /// The parent of the component doesn't have a child with this name.
+    Missing,
+
+    /// The parent of the component is a node that can't contain children.
+    NotTraversable,
+
+    /// The path is nested deeper than the drive allows.
+    TooDeep,
+}
+
+/// A component-by-component account of walking a path through a drive, produced by
+/// [`crate::filesystem::Drive::resolve_path_verbose`]. Walking stops at the first component that
+/// couldn't be resolved, which is recorded along with the reason.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathResolution {
+    resolved: Vec<ResolvedComponent>,
+    failure: Option<(String, PathResolutionFailure)>,
+}
+
+impl PathResolution {
+    pub(crate) fn new() -> Self {
+        Self {
+            resolved: Vec::new(),
+            failure: None,
+        }
+    }
+
+    pub(crate) fn fail(mut self, name: &str, reason: PathResolutionFailure) -> Self {
+        self.failure = Some((name.to_string(), reason));
+        self
+    }
+
+    pub(crate) fn push(&mut self, component: ResolvedComponent) {
+        self.resolved.push(component);
+    }
+
+    /// The component that couldn't be resolved and the reason why, `None` when the full path
+    /// resolved.
+    pub fn failure(&self) -> Option<(&str, PathResolutionFailure)> {
+        self.failure
+            .as_ref()
+            .map(|(name, reason)| (name.as_str(), *reason))
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// Each of the components that resolved in the order they appear in the path.
+    pub fn resolved(&self) -> &[ResolvedComponent] {
+        &self.resolved
+    }
+}
+
+impl std::fmt::Display for PathResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for component in &self.resolved {
+            write!(f, "/{}", component.name)?;
+        }
+
+        match &self.failure {
+            Some((name, reason)) => write!(f, "/{name} ({reason:?})"),
+            None if self.resolved.is_empty() => write!(f, "/"),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use crate::api::platform::{DriveKind, StorageClass};
+use crate::filesystem::drive::OperationError;
+use crate::filesystem::nodes::{MetadataKey, Node};
+
+/// The root directory attribute recording the [`DriveKind`] the platform reported for the drive.
+pub(crate) const DRIVE_KIND_ATTRIBUTE: &str = "banyan.drive_kind";
+
+/// The root directory attribute recording the [`StorageClass`] the platform reported for the
+/// drive.
+pub(crate) const STORAGE_CLASS_ATTRIBUTE: &str = "banyan.storage_class";
+
+/// Reads one of the platform attributes from the root node. Values that aren't recognized, such
+/// as a storage class introduced after this release of the library, are treated as unknown.
+pub(crate) fn read<T: FromStr>(root_node: &Node, attribute: &str) -> Option<T> {
+    let value = root_node
+        .metadata()
+        .get(&MetadataKey::Custom(attribute.to_string()))?;
+
+    let parsed = std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| T::from_str(value).ok());
+    if parsed.is_none() {
+        tracing::warn!(attribute, "ignoring unrecognized platform attribute value");
+    }
+
+    parsed
+}
+
+/// Rejects values for the platform attributes that don't correspond to a [`StorageClass`] or
+/// [`DriveKind`], all other attributes are accepted as is.
+pub(crate) fn validate(key: &MetadataKey, value: &[u8]) -> Result<(), OperationError> {
+    let value = std::str::from_utf8(value).ok();
+
+    match key.as_str() {
+        DRIVE_KIND_ATTRIBUTE => value
+            .and_then(|value| DriveKind::from_str(value).ok())
+            .map(|_| ())
+            .ok_or(OperationError::InvalidMetadata("unrecognized drive kind")),
+        STORAGE_CLASS_ATTRIBUTE => value
+            .and_then(|value| StorageClass::from_str(value).ok())
+            .map(|_| ())
+            .ok_or(OperationError::InvalidMetadata(
+                "unrecognized storage class",
+            )),
+        _ => Ok(()),
+    }
+}
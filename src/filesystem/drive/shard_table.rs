@@ -0,0 +1,157 @@
+use futures::{AsyncWrite, AsyncWriteExt};
+use winnow::binary::{le_u16, le_u64};
+use winnow::Parser;
+
+use crate::codec::{Cid, ParserResult, PermanentId, Stream};
+
+/// A single independently encrypted segment of the filesystem metadata. Each shard contains the
+/// complete subtree underneath one node of the filesystem, which is identified here by the CID
+/// that node had when the shard was encoded. As long as that node's CID is unchanged, the
+/// encrypted shard can be reused as is.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ShardEntry {
+    source_cid: Cid,
+    cid: Cid,
+    encrypted_len: u64,
+}
+
+impl ShardEntry {
+    /// The CID of the encrypted shard data itself
+    pub(crate) fn cid(&self) -> &Cid {
+        &self.cid
+    }
+
+    pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let mut written_bytes = 0;
+
+        written_bytes += self.source_cid.encode(writer).await?;
+        written_bytes += self.cid.encode(writer).await?;
+
+        let len_bytes = self.encrypted_len.to_le_bytes();
+        writer.write_all(&len_bytes).await?;
+        written_bytes += len_bytes.len();
+
+        Ok(written_bytes)
+    }
+
+    pub(crate) fn encrypted_len(&self) -> u64 {
+        self.encrypted_len
+    }
+
+    pub(crate) fn new(source_cid: Cid, cid: Cid, encrypted_len: u64) -> Self {
+        Self {
+            source_cid,
+            cid,
+            encrypted_len,
+        }
+    }
+
+    pub(crate) fn parse(input: Stream) -> ParserResult<Self> {
+        let (input, source_cid) = Cid::parse(input)?;
+        let (input, cid) = Cid::parse(input)?;
+        let (input, encrypted_len) = le_u64.parse_peek(input)?;
+
+        let entry = Self {
+            source_cid,
+            cid,
+            encrypted_len,
+        };
+
+        Ok((input, entry))
+    }
+
+    /// The CID of the node at the root of the subtree contained within this shard
+    pub(crate) fn source_cid(&self) -> &Cid {
+        &self.source_cid
+    }
+}
+
+/// Lists the location and identity of each of the shards that make up the filesystem when it is
+/// encoded with [`crate::codec::header::ContentOptions::sharded_metadata`]. The shards follow the
+/// table in the same order they're listed here.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ShardTable {
+    root_pid: PermanentId,
+    entries: Vec<ShardEntry>,
+}
+
+impl ShardTable {
+    pub(crate) async fn encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        let entry_count = self.entries.len();
+        if entry_count > u16::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "too many shards in a single filesystem",
+            ));
+        }
+
+        let mut written_bytes = self.root_pid.encode(writer).await?;
+
+        let count_bytes = (entry_count as u16).to_le_bytes();
+        writer.write_all(&count_bytes).await?;
+        written_bytes += count_bytes.len();
+
+        for entry in self.entries.iter() {
+            written_bytes += entry.encode(writer).await?;
+        }
+
+        Ok(written_bytes)
+    }
+
+    pub(crate) fn entries(&self) -> &[ShardEntry] {
+        &self.entries
+    }
+
+    pub(crate) fn new(root_pid: PermanentId, entries: Vec<ShardEntry>) -> Self {
+        Self { root_pid, entries }
+    }
+
+    pub(crate) fn parse(input: Stream) -> ParserResult<Self> {
+        let (input, root_pid) = PermanentId::parse(input)?;
+        let (mut input, entry_count) = le_u16.parse_peek(input)?;
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let (remaining, entry) = ShardEntry::parse(input)?;
+            entries.push(entry);
+            input = remaining;
+        }
+
+        Ok((input, Self { root_pid, entries }))
+    }
+
+    pub(crate) fn root_pid(&self) -> PermanentId {
+        self.root_pid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_shard_table_round_trip() {
+        let mut rng = crate::utils::crypto_rng();
+
+        let entries = vec![
+            ShardEntry::new(Cid::from([1u8; 32]), Cid::from([2u8; 32]), 1024),
+            ShardEntry::new(Cid::from([3u8; 32]), Cid::from([4u8; 32]), 48),
+        ];
+        let table = ShardTable::new(PermanentId::generate(&mut rng), entries);
+
+        let mut encoded = Vec::new();
+        let size = table.encode(&mut encoded).await.unwrap();
+        assert_eq!(size, encoded.len());
+
+        let (remaining, parsed) = ShardTable::parse(Stream::new(&encoded)).unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(parsed, table);
+    }
+}
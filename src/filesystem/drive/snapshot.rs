@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use crate::codec::Cid;
+use crate::filesystem::drive::directory_handle::ReadableContent;
+use crate::filesystem::drive::{DirectoryEntry, InnerDrive, OperationError, WalkState};
+use crate::filesystem::nodes::{NodeData, NodeId};
+use crate::stores::DataStore;
+
+/// An immutable view of a [`crate::filesystem::Drive`] as it was when
+/// [`crate::filesystem::Drive::snapshot`] was called. Snapshots hold their own copy of the
+/// filesystem structure so reads never touch the lock on the live drive, making them suitable for
+/// serving many concurrent listings and reads. Cloning a snapshot is cheap as the copy is shared
+/// between the clones.
+///
+/// A snapshot is never updated. Changes made to the drive after the snapshot was captured,
+/// including changes to who has access, aren't visible through it and a new snapshot needs to be
+/// captured to observe them. Reading file content still goes through the data store, content
+/// whose blocks were removed from the store after the snapshot was captured can no longer be read
+/// even though the snapshot still lists the file. Paths are always relative to the root of the
+/// drive.
+#[derive(Clone)]
+pub struct DriveSnapshot {
+    inner: Arc<InnerDrive>,
+    root_cid: Cid,
+    captured_at: i64,
+}
+
+impl DriveSnapshot {
+    pub(crate) fn new(inner: InnerDrive, root_cid: Cid) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            root_cid,
+            captured_at: crate::utils::current_time_ms(),
+        }
+    }
+
+    /// When the snapshot was captured, in milliseconds since the Unix epoch.
+    pub fn captured_at(&self) -> i64 {
+        self.captured_at
+    }
+
+    /// Retrieves the entry for the node at the provided path, an empty path refers to the root
+    /// directory.
+    pub fn entry(&self, path: &[&str]) -> Result<DirectoryEntry, OperationError> {
+        let node_id = self.find_node(path)?;
+        DirectoryEntry::try_from(self.inner.by_id(node_id)?)
+    }
+
+    fn find_node(&self, path: &[&str]) -> Result<NodeId, OperationError> {
+        let root_id = self.inner.root_node()?.id();
+
+        match self.inner.walk_path(root_id, path)? {
+            WalkState::FoundNode { node_id } => Ok(node_id),
+            WalkState::MissingComponent { .. } => Err(OperationError::PathNotFound),
+        }
+    }
+
+    /// Lists the children of the directory at the provided path, following the same rules as
+    /// [`crate::filesystem::DirectoryHandle::ls`]. Listing a file produces the single entry for
+    /// it.
+    pub fn ls(&self, path: &[&str]) -> Result<Vec<DirectoryEntry>, OperationError> {
+        let listed_node = self.inner.by_id(self.find_node(path)?)?;
+
+        let children = match listed_node.data() {
+            NodeData::Directory { children, .. } => children,
+            _ => return Ok(vec![DirectoryEntry::try_from(listed_node)?]),
+        };
+
+        children
+            .values()
+            .map(|entry| DirectoryEntry::try_from(self.inner.by_perm_id(entry.permanent_id())?))
+            .collect()
+    }
+
+    /// Reads the full content of the file at the provided path as it was when the snapshot was
+    /// captured.
+    pub async fn read(
+        &self,
+        store: &impl DataStore,
+        path: &[&str],
+    ) -> Result<Vec<u8>, OperationError> {
+        if path.is_empty() {
+            return Err(OperationError::UnexpectedEmptyPath);
+        }
+
+        let node_id = self.find_node(path)?;
+        ReadableContent::from_node(&self.inner, node_id)?
+            .read(store, None)
+            .await
+    }
+
+    /// The CID of the root directory when the snapshot was captured, identifying the version of
+    /// the filesystem the snapshot reflects.
+    pub fn root_cid(&self) -> &Cid {
+        &self.root_cid
+    }
+}
@@ -0,0 +1,23 @@
+/// Options for [`crate::filesystem::DirectoryHandle::write_stream`]. By default existing files
+/// are overwritten and the mime type of the file is only guessed from its extension.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteStreamOptions {
+    sniff_content: bool,
+}
+
+impl WriteStreamOptions {
+    /// Whether the mime type of the file is sniffed from its leading bytes.
+    pub fn sniff_content(&self) -> bool {
+        self.sniff_content
+    }
+
+    /// Sets the mime type of the file based on its leading bytes as they are streamed in, falling
+    /// back to guessing from the file's extension when the content is ambiguous. At most
+    /// [`crate::filesystem::nodes::metadata::MIME_SNIFF_LENGTH`] bytes are held on to for this.
+    /// Without this only the extension is used. This has no effect without the `mime-type`
+    /// feature.
+    pub fn with_content_sniffing(mut self) -> Self {
+        self.sniff_content = true;
+        self
+    }
+}
@@ -1,8 +1,10 @@
+use elliptic_curve::rand_core::CryptoRngCore;
 use futures::{AsyncWrite, AsyncWriteExt};
-use winnow::binary::{le_u64, le_u8};
+use winnow::binary::{le_u16, le_u64, le_u8};
+use winnow::token::take;
 use winnow::Parser;
 
-use crate::codec::crypto::SymLockedAccessKey;
+use crate::codec::crypto::{AccessKey, SymLockedAccessKey};
 use crate::codec::{Cid, ParserResult, Stream};
 use crate::filesystem::ContentReference;
 
@@ -14,6 +16,8 @@ const FILE_CONTENT_TYPE_ENCRYPTED: u8 = 0x03;
 
 const FILE_CONTENT_TYPE_EMPTY: u8 = 0x04;
 
+const FILE_CONTENT_TYPE_EXTERNAL: u8 = 0x05;
+
 // todo(sstelfox): need to rename NodeContent...
 #[derive(Clone, Debug)]
 pub enum FileContent {
@@ -24,6 +28,13 @@ pub enum FileContent {
         content: Vec<ContentReference>,
     },
     EmptyFile,
+    /// Content that lives entirely outside of BanyanFS at the provided URL. The CID is calculated
+    /// over the plaintext content and is used to verify the data when it is retrieved.
+    External {
+        url: String,
+        cid: Cid,
+        data_size: u64,
+    },
     Public {
         cid: Cid,
         data_size: u64,
@@ -36,11 +47,20 @@ pub enum FileContent {
 
 #[derive(Debug, thiserror::Error)]
 pub enum FileContentError {
+    #[error("file key could not be unlocked with the provided data key")]
+    KeyUnavailable,
+
     #[error("node type does not contain any content")]
     NoContent,
 
     #[error("key requested on unencrypted data")]
     NotEncrypted,
+
+    #[error("file content can reference at most 255 blocks ({0} provided)")]
+    TooManyReferences(usize),
+
+    #[error("external content URL is too long ({0} bytes)")]
+    UrlTooLong(usize),
 }
 
 impl FileContent {
@@ -58,12 +78,44 @@ impl FileContent {
         }
     }
 
+    /// Unencrypted content made up of the provided blocks, the content of a file is limited to
+    /// 255 content references.
+    pub fn public(
+        cid: Cid,
+        data_size: u64,
+        content: Vec<ContentReference>,
+    ) -> Result<Self, FileContentError> {
+        if content.len() > u8::MAX as usize {
+            return Err(FileContentError::TooManyReferences(content.len()));
+        }
+
+        Ok(Self::Public {
+            cid,
+            data_size,
+            content,
+        })
+    }
+
+    pub fn external(url: String, cid: Cid, data_size: u64) -> Result<Self, FileContentError> {
+        if url.len() > u16::MAX as usize {
+            return Err(FileContentError::UrlTooLong(url.len()));
+        }
+
+        Ok(Self::External {
+            url,
+            cid,
+            data_size,
+        })
+    }
+
     /// Return the CID over the plaintext content of the file. Be careful not to confuse these with
     /// the data CIDs which are the CIDs of the stored data blocks.
     #[allow(dead_code)]
     pub fn cid(&self) -> Option<Cid> {
         match self {
-            Self::Encrypted { cid, .. } | Self::Public { cid, .. } => Some(cid.clone()),
+            Self::Encrypted { cid, .. } | Self::Public { cid, .. } | Self::External { cid, .. } => {
+                Some(cid.clone())
+            }
             Self::Stub { .. } => None,
             Self::EmptyFile => None,
         }
@@ -72,7 +124,7 @@ impl FileContent {
     pub fn content_references(&self) -> Result<&[ContentReference], FileContentError> {
         match self {
             Self::Encrypted { content, .. } | Self::Public { content, .. } => Ok(content),
-            Self::Stub { .. } => Err(FileContentError::NoContent),
+            Self::Stub { .. } | Self::External { .. } => Err(FileContentError::NoContent),
             Self::EmptyFile { .. } => Ok(&[]),
         }
     }
@@ -82,7 +134,7 @@ impl FileContent {
             Self::Encrypted { content, .. } | Self::Public { content, .. } => {
                 Some(content.iter().map(|c| c.data_block_cid()).collect())
             }
-            Self::Stub { .. } => None,
+            Self::Stub { .. } | Self::External { .. } => None,
             Self::EmptyFile => None,
         }
     }
@@ -96,6 +148,31 @@ impl FileContent {
         }
     }
 
+    /// Seals the file's own key with a different data key, allowing the content to be referenced
+    /// from a drive using that data key. The data blocks are encrypted with the file's key and
+    /// are untouched by this. Content that isn't encrypted is left as is.
+    pub(crate) fn relock_data_key(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        current_key: &AccessKey,
+        new_key: &AccessKey,
+    ) -> Result<(), FileContentError> {
+        if let Self::Encrypted {
+            locked_access_key, ..
+        } = self
+        {
+            let file_key = locked_access_key
+                .unlock(current_key)
+                .map_err(|_| FileContentError::KeyUnavailable)?;
+
+            *locked_access_key = file_key
+                .lock_with(rng, new_key)
+                .map_err(|_| FileContentError::KeyUnavailable)?;
+        }
+
+        Ok(())
+    }
+
     pub async fn encode<W: AsyncWrite + Unpin + Send>(
         &self,
         writer: &mut W,
@@ -107,6 +184,34 @@ impl FileContent {
                 writer.write_all(&[FILE_CONTENT_TYPE_EMPTY]).await?;
                 written_bytes += 1;
             }
+            Self::External {
+                url,
+                cid,
+                data_size,
+            } => {
+                writer.write_all(&[FILE_CONTENT_TYPE_EXTERNAL]).await?;
+                written_bytes += 1;
+                written_bytes += cid.encode(writer).await?;
+
+                let data_size_bytes = data_size.to_le_bytes();
+                writer.write_all(&data_size_bytes).await?;
+                written_bytes += data_size_bytes.len();
+
+                let url_bytes = url.as_bytes();
+                let url_length = u16::try_from(url_bytes.len()).map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        FileContentError::UrlTooLong(url_bytes.len()),
+                    )
+                })?;
+
+                let url_length_bytes = url_length.to_le_bytes();
+                writer.write_all(&url_length_bytes).await?;
+                written_bytes += url_length_bytes.len();
+
+                writer.write_all(url_bytes).await?;
+                written_bytes += url_bytes.len();
+            }
             Self::Stub { data_size } => {
                 writer.write_all(&[FILE_CONTENT_TYPE_STUB]).await?;
                 written_bytes += 1;
@@ -152,6 +257,19 @@ impl FileContent {
         Ok(written_bytes)
     }
 
+    /// The number of bytes [`FileContent::encode`] will produce for this content.
+    pub(crate) fn encoded_size(&self) -> usize {
+        match self {
+            Self::EmptyFile => 1,
+            Self::External { url, .. } => 1 + Cid::size() + 8 + 2 + url.len(),
+            Self::Stub { .. } => 1 + 8,
+            Self::Public { content, .. } => 1 + Cid::size() + 8 + content_list_size(content),
+            Self::Encrypted { content, .. } => {
+                1 + Cid::size() + 8 + SymLockedAccessKey::size() + content_list_size(content)
+            }
+        }
+    }
+
     pub fn is_encrypted(&self) -> bool {
         matches!(self, Self::Encrypted { .. })
     }
@@ -164,6 +282,7 @@ impl FileContent {
         match self {
             Self::EmptyFile => true,
             Self::Encrypted { data_size, .. } => *data_size < 1u64,
+            Self::External { data_size, .. } => *data_size < 1u64,
             Self::Public { data_size, .. } => *data_size < 1u64,
             Self::Stub { .. } => true,
         }
@@ -178,6 +297,31 @@ impl FileContent {
                 let (input, data_size) = le_u64.parse_peek(input)?;
                 (input, FileContent::Stub { data_size })
             }
+            FILE_CONTENT_TYPE_EXTERNAL => {
+                let (input, cid) = Cid::parse(input)?;
+                let (input, data_size) = le_u64.parse_peek(input)?;
+                let (input, url_length) = le_u16.parse_peek(input)?;
+                let (input, url_bytes) = take(url_length).parse_peek(input)?;
+
+                let url = match String::from_utf8(url_bytes.to_vec()) {
+                    Ok(url) => url,
+                    Err(_) => {
+                        let err = winnow::error::ParserError::from_error_kind(
+                            &input,
+                            winnow::error::ErrorKind::Verify,
+                        );
+                        return Err(winnow::error::ErrMode::Cut(err));
+                    }
+                };
+
+                let data = FileContent::External {
+                    url,
+                    cid,
+                    data_size,
+                };
+
+                (input, data)
+            }
             FILE_CONTENT_TYPE_PUBLIC => {
                 let (input, cid) = Cid::parse(input)?;
                 let (input, data_size) = le_u64.parse_peek(input)?;
@@ -225,6 +369,7 @@ impl FileContent {
     pub fn size(&self) -> u64 {
         match self {
             FileContent::Encrypted { data_size, .. } => *data_size,
+            FileContent::External { data_size, .. } => *data_size,
             FileContent::Public { data_size, .. } => *data_size,
             FileContent::Stub { data_size } => *data_size,
             FileContent::EmptyFile => 0,
@@ -232,6 +377,10 @@ impl FileContent {
     }
 }
 
+fn content_list_size(content: &[ContentReference]) -> usize {
+    1 + content.iter().map(ContentReference::size).sum::<usize>()
+}
+
 async fn encode_content_list<W: AsyncWrite + Unpin + Send>(
     writer: &mut W,
     content: &[ContentReference],
@@ -253,3 +402,39 @@ async fn encode_content_list<W: AsyncWrite + Unpin + Send>(
 
     Ok(written_bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_external_round_trip() {
+        let url = "https://example.com/objects/large-video.mp4".to_string();
+        let cid = crate::utils::calculate_cid(b"external data");
+        let content = FileContent::external(url.clone(), cid.clone(), 13).unwrap();
+
+        let mut encoded = Vec::new();
+        let size = content.encode(&mut encoded).await.unwrap();
+        assert_eq!(size, encoded.len());
+
+        let (remaining, parsed) = FileContent::parse(Stream::new(&encoded)).unwrap();
+        assert!(remaining.is_empty());
+
+        match parsed {
+            FileContent::External {
+                url: parsed_url,
+                cid: parsed_cid,
+                data_size,
+            } => {
+                assert_eq!(parsed_url, url);
+                assert_eq!(parsed_cid, cid);
+                assert_eq!(data_size, 13);
+            }
+            _ => panic!("parsed content was not external"),
+        }
+    }
+}
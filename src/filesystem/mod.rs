@@ -5,11 +5,17 @@ mod file_content;
 pub mod nodes;
 
 pub(crate) use content_reference::{ContentLocation, ContentReference};
-pub(crate) use drive::InnerDrive;
+#[cfg(feature = "compression")]
+pub(crate) use drive::ACCEPTED_ENCODINGS;
+pub(crate) use drive::{ChunkDecoder, InnerDrive};
 pub(crate) use file_content::{FileContent, FileContentError};
 pub(crate) use nodes::NodeBuilder;
 
 pub use drive::{
-    DirectoryEntry, DirectoryHandle, Drive, DriveAccess, DriveLoader, DriveLoaderError,
-    OperationError,
+    AccessSummary, ActorStatus, CapabilityToken, CollisionPolicy, DirectoryEntry, DirectoryHandle,
+    Drive, DriveAccess, DriveAccessError, DriveEncodeError, DriveLoader, DriveLoaderError,
+    DriveSnapshot, DriveStats, EscrowError, MetadataEncoding, OperationError, PathResolution,
+    PathResolutionFailure, ResolvedComponent, WriteStreamOptions, MAX_UNTRUSTED_DRIVE_SIZE,
 };
+#[cfg(feature = "local-store")]
+pub use drive::{ImportError, ImportManifest, ImportSummary};
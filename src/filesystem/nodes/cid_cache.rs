@@ -1,4 +1,5 @@
 use async_std::sync::RwLock;
+use futures::{AsyncWrite, AsyncWriteExt};
 use std::sync::Arc;
 
 use crate::codec::meta::Cid;
@@ -52,9 +53,28 @@ impl CidCache {
         inner.dirty = false;
     }
 
-    pub(crate) async fn take_cached(&self) -> Option<Vec<u8>> {
-        let mut inner = self.0.write().await;
-        inner.encoded.take()
+    /// Provides the CID without waiting on the cache, `None` if it hasn't been calculated or the
+    /// cache is currently being updated. Used where the cache can't be awaited, such as while
+    /// parsing, which is also when the CID is guaranteed to be known.
+    pub(crate) fn try_cid(&self) -> Option<Cid> {
+        self.0.try_read().and_then(|inner| inner.cid.clone())
+    }
+
+    /// Writes the cached encoding out if one is available and still reflects the current state
+    /// of the node. Returns `None` without writing anything otherwise.
+    pub(crate) async fn write_cached<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<Option<usize>> {
+        let inner = self.0.read().await;
+
+        match (&inner.encoded, inner.dirty) {
+            (Some(encoded), false) => {
+                writer.write_all(encoded).await?;
+                Ok(Some(encoded.len()))
+            }
+            _ => Ok(None),
+        }
     }
 }
 
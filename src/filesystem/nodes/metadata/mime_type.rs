@@ -1,5 +1,10 @@
 use crate::filesystem::nodes::NodeName;
 
+/// The number of leading bytes of content considered when sniffing, matching the resource header
+/// length used by <https://mimesniff.spec.whatwg.org/>. Data provided beyond this is ignored so
+/// content can be fed in as it arrives without buffering more than is needed.
+pub const MIME_SNIFF_LENGTH: usize = 1445;
+
 #[derive(Default)]
 pub struct MimeGuesser {
     name: Option<String>,
@@ -7,6 +12,12 @@ pub struct MimeGuesser {
 }
 
 impl MimeGuesser {
+    /// Whether the guesser would still make use of more data, false once [`MIME_SNIFF_LENGTH`]
+    /// bytes have been provided.
+    pub fn needs_more_data(&self) -> bool {
+        self.data.len() < MIME_SNIFF_LENGTH
+    }
+
     pub fn with_name(mut self, name: NodeName) -> Self {
         match name {
             NodeName::Named(name) => self.name = Some(name.clone()),
@@ -15,8 +26,11 @@ impl MimeGuesser {
         self
     }
 
+    /// Adds content to sniff, this can be called repeatedly with consecutive pieces of the
+    /// content. Only the first [`MIME_SNIFF_LENGTH`] bytes across all calls are kept.
     pub fn with_data(mut self, data: &[u8]) -> Self {
-        self.data.extend_from_slice(data);
+        let wanted = MIME_SNIFF_LENGTH.saturating_sub(self.data.len());
+        self.data.extend_from_slice(&data[..data.len().min(wanted)]);
         self
     }
 
@@ -3,9 +3,9 @@ use std::str::FromStr;
 mod mime_type;
 
 #[cfg(feature = "mime-type")]
-pub use mime_type::MimeGuesser;
+pub use mime_type::{MimeGuesser, MIME_SNIFF_LENGTH};
 
-#[derive(Hash, Eq, PartialEq, Debug)]
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
 pub enum MetadataKey {
     MimeType,
     Custom(String),
@@ -21,7 +21,7 @@ mod node_name;
 pub(crate) use cid_cache::CidCache;
 pub(crate) use node_builder::{NodeBuilder, NodeBuilderError};
 
-pub(crate) use node_data::{NodeData, NodeDataError};
+pub(crate) use node_data::{ChildMapEntry, NodeData, NodeDataError};
 pub use node_name::{NodeName, NodeNameError};
 
 use std::{
@@ -31,7 +31,7 @@ use std::{
 
 use futures::{AsyncWrite, AsyncWriteExt};
 use winnow::{
-    binary::{le_i64, le_u32, le_u8},
+    binary::{le_i64, le_u16, le_u32, le_u8},
     stream::Offset,
     token::take,
     Parser,
@@ -39,9 +39,9 @@ use winnow::{
 
 use crate::{
     codec::{
-        filesystem::NodeKind,
+        filesystem::{NodeKind, Permissions},
         meta::{ActorId, Cid, PermanentId},
-        ParserResult, Stream, VectorClockNode,
+        ParserResult, Stream, VectorClockNode, VectorClockNodeSnapshot,
     },
     filesystem::drive::OperationError,
 };
@@ -49,6 +49,19 @@ pub use metadata::MetadataKey;
 
 pub(crate) type NodeId = usize;
 
+/// The most metadata entries a single node can hold.
+pub(crate) const MAX_METADATA_ENTRIES: usize = u16::MAX as usize;
+
+/// Set in the flags byte of an encoded node when the node has a parent, the parent's
+/// [`PermanentId`] follows the flags.
+const NODE_FLAG_PARENT_PRESENT: u8 = 0x01;
+
+/// Set in the flags byte of an encoded node when its metadata entry count is encoded as a
+/// little-endian `u16` rather than a single byte. Only nodes with more than 255 metadata entries
+/// set this, every other node keeps the original encoding and remains readable by earlier
+/// releases.
+const NODE_FLAG_WIDE_METADATA_COUNT: u8 = 0x02;
+
 /// The core structure that represents a node within the filesystem. This structure represents a
 /// wrapper over any kind of filesystem metadata, structure, or associated data contained within
 /// the BanyanFS structures.
@@ -98,7 +111,7 @@ impl Node {
     /// hiearchy is directory -> (directory | file), file -> associated data.
     ///
     /// Calling this function will modify the CID of the node and as such will invalidate the
-    /// internal encoding cache if available from [`Node::cached_encoding`].
+    /// internal encoding cache used by [`Node::encode_cached`].
     pub(crate) async fn add_child(
         &mut self,
         name: NodeName,
@@ -106,30 +119,22 @@ impl Node {
         child_cid: Cid,
         child_size: u64,
     ) -> Result<(), NodeDataError> {
+        // Invalidate the cached CID before the change is made so an interrupted call can never
+        // leave behind a modified node still carrying its old CID.
+        self.notify_of_change().await;
         self.inner
             .add_child(name, child_id, child_cid, child_size)?;
-        self.notify_of_change().await;
 
         Ok(())
     }
 
-    /// During the encoding process we first need the CID of the node (via [`Node::cid`]) which
-    /// requires fully encoding the node to calculate. That method caches the result of that
-    /// encoding if it needed to generate it. This consumes that cached encoding if we have it and
-    /// is mostly used as an optimization that is described in the [`Node::cid`] documentation.
-    #[allow(dead_code)]
-    pub(crate) async fn cached_encoding(&self) -> Option<Vec<u8>> {
-        self.cid.take_cached().await
-    }
-
     /// Returns the CID of the node. If the internal data has changed in anyway (as indicated by
     /// and internal call to CidCache::is_dirty), this will fully encode the node as it would
     /// appear on disk and calculates the CID over that data.
     ///
     /// As an optimization this cache's that encoding so we don't have to re-encode it when we're
-    /// writing the filesystem out to disk. This comes with a small memory penalty if some
-    /// non-encoding process attempts to access a large number of node CIDs but that seems like an
-    /// unlikely use case.
+    /// writing the filesystem out to disk (see [`Node::encode_cached`]). This comes with a memory
+    /// penalty as the encoding is held onto until the node changes.
     pub async fn cid(&self) -> Result<Cid, OperationError> {
         if self.cid.is_dirty().await {
             let mut node_data = Vec::new();
@@ -144,6 +149,12 @@ impl Node {
         Ok(self.cid.cid().await.expect("enforced cid generation above"))
     }
 
+    /// Returns the CID of the node when it is already known without encoding the node. Nodes
+    /// that were just parsed always know their CID, modified nodes need to use [`Node::cid`].
+    pub(crate) fn try_cid(&self) -> Option<Cid> {
+        self.cid.try_cid()
+    }
+
     /// Returnes the unix timestamp (in milliseconds precision) of when the node was created.
     pub fn created_at(&self) -> i64 {
         self.created_at
@@ -154,10 +165,10 @@ impl Node {
     }
 
     pub(crate) async fn data_mut(&mut self) -> &mut NodeData {
-        // note(sstelfox): we should probably arbitrate changes to the inner data in a way that
-        // allows us to detect if changes actually occurred. For now we have to assume that by
-        // grabbing a mutable handle they intend to mutate the content which will invalidate our
-        // CID so we mark ourselves as dirty.
+        // note(sstelfox): we can't tell what happens through the returned handle, so we have to
+        // assume the caller intends to mutate the content which will invalidate our CID and mark
+        // ourselves as dirty. Callers that may not end up changing anything should use the
+        // targeted mutators such as `update_child` instead, which only do so on an actual change.
         self.notify_of_change().await;
         &mut self.inner
     }
@@ -171,15 +182,26 @@ impl Node {
         self.permanent_id.encode(&mut node_data).await?;
         self.vector_clock.encode(&mut node_data).await?;
 
-        match self.parent_id {
-            Some(pid) => {
-                node_data.write_all(&[0x01]).await?;
-                pid.encode(&mut node_data).await?;
-            }
-            None => {
-                node_data.write_all(&[0x00]).await?;
-            }
-        };
+        let metadata_entries = self.metadata.len();
+        if metadata_entries > MAX_METADATA_ENTRIES {
+            return Err(StdError::new(
+                StdErrorKind::Other,
+                "too many metadata entries",
+            ));
+        }
+
+        let mut flags = 0x00;
+        if self.parent_id.is_some() {
+            flags |= NODE_FLAG_PARENT_PRESENT;
+        }
+        if metadata_entries > u8::MAX as usize {
+            flags |= NODE_FLAG_WIDE_METADATA_COUNT;
+        }
+
+        node_data.write_all(&[flags]).await?;
+        if let Some(pid) = self.parent_id {
+            pid.encode(&mut node_data).await?;
+        }
 
         self.owner_id.encode(&mut node_data).await?;
 
@@ -191,17 +213,13 @@ impl Node {
 
         self.name.encode(&mut node_data).await?;
 
-        let metadata_entries = self.metadata.len();
-        if metadata_entries > u8::MAX as usize {
-            return Err(StdError::new(
-                StdErrorKind::Other,
-                "too many metadata entries",
-            ));
+        if flags & NODE_FLAG_WIDE_METADATA_COUNT != 0 {
+            let entry_count = metadata_entries as u16;
+            node_data.write_all(&entry_count.to_le_bytes()).await?;
+        } else {
+            node_data.write_all(&[metadata_entries as u8]).await?;
         }
 
-        let entry_count = metadata_entries as u8;
-        node_data.write_all(&[entry_count]).await?;
-
         let mut sorted_metadata = self.metadata.iter().collect::<Vec<_>>();
         sorted_metadata.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(&b.as_bytes()));
 
@@ -249,6 +267,64 @@ impl Node {
         Ok(written_bytes)
     }
 
+    /// The number of bytes [`Node::encode`] will write for this node in its current state,
+    /// including the CID and length prefix. Unlike [`Node::size`] this doesn't count the size of
+    /// the file data or the node's children.
+    pub(crate) fn encoded_size(&self) -> u64 {
+        // The outer estimate covers the CID and the node's attributes, what remains is the length
+        // prefix, the vector clock, and the metadata entry count.
+        let entry_count_size = if self.metadata.len() > u8::MAX as usize {
+            2
+        } else {
+            1
+        };
+        let remaining_size = 4 + VectorClockNodeSnapshot::size() + entry_count_size;
+
+        self.outer_size_estimate() + remaining_size as u64 + self.inner.encoded_size() as u64
+    }
+
+    /// Behaves the same as [`Node::encode`] but reuses the encoding produced by an earlier call
+    /// (or by [`Node::cid`]) when the node hasn't changed since. When the node has changed it is
+    /// encoded again and the result is kept for next time. Encoding a large filesystem where only
+    /// a few nodes have changed only needs to do the work for those nodes.
+    pub(crate) async fn encode_cached<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<usize> {
+        if let Some(written_bytes) = self.cid.write_cached(writer).await? {
+            return Ok(written_bytes);
+        }
+
+        let mut encoded = Vec::new();
+        self.encode(&mut encoded).await?;
+
+        writer.write_all(&encoded).await?;
+        let written_bytes = encoded.len();
+        self.cid.set_cached(encoded).await;
+
+        Ok(written_bytes)
+    }
+
+    /// Parses a single encoded node in isolation, such as one retrieved from a block store by its
+    /// CID. The entire buffer must be consumed by the node. As there is no drive to allocate an ID
+    /// the caller provides one, see the [identifiers](#identifiers) section for the caveats.
+    ///
+    /// The node's children are only referenced by their [`PermanentId`], they need to be parsed
+    /// separately.
+    pub fn from_bytes(data: &[u8], allocated_id: NodeId) -> Result<Self, NodeError> {
+        let (remaining, node) = match Self::parse(Stream::new(data), allocated_id) {
+            Ok(result) => result,
+            Err(winnow::error::ErrMode::Incomplete(_)) => return Err(NodeError::Incomplete),
+            Err(err) => return Err(NodeError::ParserFailure(format!("{err:?}"))),
+        };
+
+        if !remaining.is_empty() {
+            return Err(NodeError::TrailingData(remaining.len()));
+        }
+
+        Ok(node)
+    }
+
     /// Retrieves the in-memory identifier for this node instance, initialized when the node is
     /// first created from the drive that loads it. This is a volatile ID. See the
     /// [identifiers](#identifiers) section for detailed usage recommendations.
@@ -336,30 +412,34 @@ impl Node {
 
         let (input, permanent_id) = PermanentId::parse(input)?;
         let (input, vector_clock) = VectorClockNode::parse(input)?;
-        let (input, parent_present) = take(1u8).parse_peek(input)?;
+        let (input, flags) = le_u8.parse_peek(input)?;
 
         tracing::trace!(node_data_len, ?cid, "cid/node_data_len");
 
-        let (input, parent_id) = match parent_present[0] {
-            0x00 => (input, None),
-            0x01 => {
-                let (node_data_buf, pid) = PermanentId::parse(input)?;
-                (node_data_buf, Some(pid))
-            }
-            _ => {
-                let err = winnow::error::ParserError::from_error_kind(
-                    &input,
-                    winnow::error::ErrorKind::Token,
-                );
-                return Err(winnow::error::ErrMode::Cut(err));
-            }
+        if flags & !(NODE_FLAG_PARENT_PRESENT | NODE_FLAG_WIDE_METADATA_COUNT) != 0 {
+            let err = winnow::error::ParserError::from_error_kind(
+                &input,
+                winnow::error::ErrorKind::Token,
+            );
+            return Err(winnow::error::ErrMode::Cut(err));
+        }
+
+        let (input, parent_id) = if flags & NODE_FLAG_PARENT_PRESENT != 0 {
+            let (node_data_buf, pid) = PermanentId::parse(input)?;
+            (node_data_buf, Some(pid))
+        } else {
+            (input, None)
         };
 
         let (input, owner_id) = ActorId::parse(input)?;
         let (input, created_at) = le_i64.parse_peek(input)?;
         let (input, modified_at) = le_i64.parse_peek(input)?;
         let (input, name) = NodeName::parse(input)?;
-        let (mut input, metadata_entries) = le_u8.parse_peek(input)?;
+        let (mut input, metadata_entries) = if flags & NODE_FLAG_WIDE_METADATA_COUNT != 0 {
+            le_u16.parse_peek(input)?
+        } else {
+            le_u8.map(u16::from).parse_peek(input)?
+        };
 
         let mut metadata = HashMap::new();
         for _ in 0..metadata_entries {
@@ -380,10 +460,17 @@ impl Node {
         }
 
         let (input, inner) = NodeData::parse(input)?;
-        debug_assert!(
-            input.offset_from(&node_data_start) == usize::try_from(node_data_len).unwrap(), //Unwrap safe on 32bit and up systems (unsafe on 16 bit systems)
-            "consumed to little or too much during parse based on the node's data_len field"
-        );
+
+        // The recorded length has to account for exactly what was parsed, anything else means
+        // the node was malformed and the position of any following node can't be trusted
+        if Ok(input.offset_from(&node_data_start)) != usize::try_from(node_data_len) {
+            tracing::warn!(node_data_len, "node data length did not match its contents");
+            let err = winnow::error::ParserError::from_error_kind(
+                &input,
+                winnow::error::ErrorKind::Verify,
+            );
+            return Err(winnow::error::ErrMode::Cut(err));
+        }
 
         let node = Self {
             id: allocated_id,
@@ -411,8 +498,8 @@ impl Node {
         &mut self,
         child_name: &NodeName,
     ) -> Result<(), NodeDataError> {
-        self.inner.remove_child(child_name)?;
         self.notify_of_change().await;
+        self.inner.remove_child(child_name)?;
 
         Ok(())
     }
@@ -421,20 +508,53 @@ impl Node {
         &mut self,
         child_id: &PermanentId,
     ) -> Result<(), NodeDataError> {
-        self.inner.remove_permanent_id(child_id)?;
         self.notify_of_change().await;
+        self.inner.remove_permanent_id(child_id)?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn set_permissions(
+        &mut self,
+        permissions: Permissions,
+    ) -> Result<(), NodeDataError> {
+        if self.inner.set_permissions(permissions)? {
+            self.notify_of_change().await;
+        }
+
+        Ok(())
+    }
+
+    /// Records the current CID and size of one of this node's children. Unlike changes made
+    /// through [`Node::data_mut`] the node's own CID is only invalidated when the recorded values
+    /// actually differ, so refreshing children that haven't changed leaves the node clean.
+    pub(crate) async fn update_child(
+        &mut self,
+        child_permanent_id: &PermanentId,
+        cid: Cid,
+        size: u64,
+    ) -> Result<(), NodeDataError> {
+        if self.inner.update_child(child_permanent_id, cid, size)? {
+            self.notify_of_change().await;
+        }
 
         Ok(())
     }
 
     pub(crate) async fn set_name(&mut self, new_name: NodeName) {
-        self.name = new_name;
         self.notify_of_change().await;
+        self.name = new_name;
     }
 
     pub(crate) async fn set_parent_id(&mut self, parent_id: PermanentId) {
-        self.parent_id = Some(parent_id);
         self.notify_of_change().await;
+        self.parent_id = Some(parent_id);
+    }
+
+    /// A read-only snapshot of this node's vector clock, which can be compared against the clocks
+    /// of other versions of the same node to determine their causal ordering.
+    pub fn vector_clock(&self) -> VectorClockNodeSnapshot {
+        self.vector_clock.as_snapshot()
     }
 
     pub fn size(&self) -> u64 {
@@ -445,6 +565,44 @@ impl Node {
         matches!(self.inner.kind(), NodeKind::Directory | NodeKind::File)
     }
 
+    /// Produces a copy of this node under a new identity for placement in a different drive. The
+    /// copy is made by round-tripping the node through its encoded form, keeping the name,
+    /// metadata, timestamps, and content references of the original. As far as the destination
+    /// is concerned the copy is a new node so it receives a fresh vector clock.
+    ///
+    /// The child entries of the copy still reference the original children and need to be
+    /// pointed at their copies with [`NodeData::replace_child_id`]. Encrypted content still has
+    /// its key sealed with the original drive's data key, see [`NodeData::relock_data_key`].
+    pub(crate) async fn transplant(
+        &self,
+        allocated_id: NodeId,
+        permanent_id: PermanentId,
+        parent_id: PermanentId,
+        owner_id: ActorId,
+    ) -> Result<Self, OperationError> {
+        let mut encoded = Vec::new();
+        self.encode(&mut encoded).await.map_err(|_| {
+            OperationError::InternalCorruption(self.id, "failed to encode node for transplant")
+        })?;
+
+        let mut node = Self::from_bytes(&encoded, allocated_id).map_err(|_| {
+            OperationError::InternalCorruption(self.id, "failed to parse transplanted node")
+        })?;
+
+        node.permanent_id = permanent_id;
+        node.parent_id = Some(parent_id);
+        node.owner_id = owner_id;
+        node.vector_clock = VectorClockNode::initialize();
+        node.cid = CidCache::empty();
+
+        Ok(node)
+    }
+
+    /// The permissions of the node, only directories and files carry permissions.
+    pub fn permissions(&self) -> Option<Permissions> {
+        self.inner.permissions()
+    }
+
     pub fn permanent_id(&self) -> PermanentId {
         self.permanent_id
     }
@@ -460,10 +618,19 @@ impl Node {
             })
     }
 
+    /// Removes the attribute from the node, producing its previous value if it was set.
+    pub async fn remove_attribute(&mut self, key: &MetadataKey) -> Option<Vec<u8>> {
+        if !self.metadata.contains_key(key) {
+            return None;
+        }
+
+        self.notify_of_change().await;
+        self.metadata.remove(key)
+    }
+
     pub async fn set_attribute(&mut self, key: MetadataKey, value: Vec<u8>) -> Option<Vec<u8>> {
-        let old_value = self.metadata.insert(key, value);
         self.notify_of_change().await;
-        old_value
+        self.metadata.insert(key, value)
     }
 }
 
@@ -477,7 +644,13 @@ impl std::fmt::Debug for Node {
                 .field(&self.owner_id)
                 .field(&self.name)
                 .finish(),
-            NodeData::AssociatedData { .. } => unimplemented!(),
+            NodeData::AssociatedData { .. } => f
+                .debug_tuple("NodeAssociatedData")
+                .field(&self.id)
+                .field(&self.permanent_id)
+                .field(&self.owner_id)
+                .field(&self.name)
+                .finish(),
             NodeData::Directory { .. } => f
                 .debug_tuple("NodeDirectory")
                 .field(&self.id)
@@ -489,6 +662,18 @@ impl std::fmt::Debug for Node {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum NodeError {
+    #[error("encoded node ended before it was complete")]
+    Incomplete,
+
+    #[error("failed to parse node: {0}")]
+    ParserFailure(String),
+
+    #[error("{0} unexpected bytes remained after the encoded node")]
+    TrailingData(usize),
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use crate::codec::crypto::Fingerprint;
@@ -516,6 +701,34 @@ pub(crate) mod test {
         assert!(test_node.cid.is_dirty().await);
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_unchanged_data_update_keeps_cid_clean() {
+        let mut rng = crate::utils::crypto_rng();
+        let mut test_node = NodeBuilder::directory(NodeName::Named("TestDir".into()))
+            .with_id(0)
+            .with_owner(ActorId::from(Fingerprint::from([0; Fingerprint::size()])))
+            .with_parent(PermanentId::generate(&mut rng))
+            .build(&mut rng)
+            .unwrap();
+
+        let original_cid = test_node.cid().await.unwrap();
+        let original_modified_at = test_node.modified_at();
+
+        let permissions = test_node.permissions().unwrap();
+        test_node.set_permissions(permissions).await.unwrap();
+        assert!(!test_node.cid.is_dirty().await);
+        assert_eq!(test_node.cid().await.unwrap(), original_cid);
+        assert_eq!(test_node.modified_at(), original_modified_at);
+
+        test_node
+            .set_permissions(permissions.with_immutable(true))
+            .await
+            .unwrap();
+        assert!(test_node.cid.is_dirty().await);
+        assert_ne!(test_node.cid().await.unwrap(), original_cid);
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
     async fn test_empty_file_round_trip() {
@@ -558,6 +771,56 @@ pub(crate) mod test {
         assert_node_equality(&test_node, &parsed);
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_cached_encoding_tracks_changes() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut test_node = setup_test_node(&mut rng, NodeData::empty_file()).await;
+
+        let mut expected = Vec::new();
+        test_node.encode(&mut expected).await.unwrap();
+
+        let mut cold = Vec::new();
+        test_node.encode_cached(&mut cold).await.unwrap();
+        assert_eq!(cold, expected);
+
+        let mut warm = Vec::new();
+        test_node.encode_cached(&mut warm).await.unwrap();
+        assert_eq!(warm, expected);
+
+        test_node
+            .set_attribute(MetadataKey::MimeType, b"text/plain".to_vec())
+            .await;
+
+        let mut expected = Vec::new();
+        test_node.encode(&mut expected).await.unwrap();
+
+        let mut changed = Vec::new();
+        test_node.encode_cached(&mut changed).await.unwrap();
+        assert_ne!(changed, warm);
+        assert_eq!(changed, expected);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_from_bytes() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let test_node = setup_test_node(&mut rng, NodeData::empty_file()).await;
+
+        let mut buffer = Vec::new();
+        test_node.encode(&mut buffer).await.unwrap();
+
+        let parsed = Node::from_bytes(&buffer, test_node.id).unwrap();
+        assert_node_equality(&test_node, &parsed);
+
+        let truncated = Node::from_bytes(&buffer[..buffer.len() - 1], test_node.id);
+        assert!(matches!(truncated, Err(NodeError::Incomplete)));
+
+        buffer.push(0x00);
+        let trailing = Node::from_bytes(&buffer, test_node.id);
+        assert!(matches!(trailing, Err(NodeError::TrailingData(1))));
+    }
+
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
     #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
     async fn test_stub_round_trip() {
@@ -583,6 +846,69 @@ pub(crate) mod test {
         assert_node_equality(&test_node, &parsed);
     }
 
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_associated_data_round_trip() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut test_node = setup_test_node(&mut rng, NodeData::stub_associated_data(42)).await;
+        test_node
+            .set_attribute(MetadataKey::MimeType, b"image/webp".to_vec())
+            .await;
+
+        let mut buffer = Vec::new();
+        test_node.encode(&mut buffer).await.unwrap();
+
+        let (remaining, parsed) = Node::parse(Stream::new(&buffer), test_node.id).unwrap();
+        assert!(remaining.is_empty());
+        assert_node_equality(&test_node, &parsed);
+        assert_eq!(parsed.kind(), NodeKind::AssociatedData);
+        assert!(parsed.data().permissions().is_none());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_many_metadata_entries_round_trip() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        let mut test_node = setup_test_node(&mut rng, NodeData::empty_file()).await;
+
+        for idx in 0..255u32 {
+            let key = MetadataKey::Custom(format!("attribute_{idx}"));
+            test_node
+                .set_attribute(key, idx.to_le_bytes().to_vec())
+                .await;
+        }
+
+        // Nodes within the original limit keep the single byte entry count
+        let mut buffer = Vec::new();
+        test_node.encode(&mut buffer).await.unwrap();
+        let flags_offset = Cid::size() + 4 + PermanentId::size() + VectorClockNodeSnapshot::size();
+        assert_eq!(buffer[flags_offset], NODE_FLAG_PARENT_PRESENT);
+        assert_eq!(buffer.len() as u64, test_node.encoded_size());
+
+        let (_, parsed) = Node::parse(Stream::new(&buffer), test_node.id).unwrap();
+        assert_node_equality(&test_node, &parsed);
+
+        for idx in 255..300u32 {
+            let key = MetadataKey::Custom(format!("attribute_{idx}"));
+            test_node
+                .set_attribute(key, idx.to_le_bytes().to_vec())
+                .await;
+        }
+
+        let mut buffer = Vec::new();
+        test_node.encode(&mut buffer).await.unwrap();
+        assert_eq!(
+            buffer[flags_offset],
+            NODE_FLAG_PARENT_PRESENT | NODE_FLAG_WIDE_METADATA_COUNT
+        );
+        assert_eq!(buffer.len() as u64, test_node.encoded_size());
+
+        let (remaining, parsed) = Node::parse(Stream::new(&buffer), test_node.id).unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(parsed.metadata().len(), 300);
+        assert_node_equality(&test_node, &parsed);
+    }
+
     fn assert_node_equality(test_node: &Node, parsed: &Node) {
         assert_eq!(test_node.id, parsed.id);
         assert_eq!(test_node.parent_id, parsed.parent_id);
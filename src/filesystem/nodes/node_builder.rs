@@ -38,6 +38,7 @@ impl NodeBuilder {
 
         tracing::info!("building node with size hint: {:?}", self.size_hint);
         let inner = match self.kind {
+            NodeKind::AssociatedData => NodeData::stub_associated_data(self.size_hint.unwrap_or(0)),
             NodeKind::File => NodeData::stub_file(self.size_hint.unwrap_or(0)),
             NodeKind::Directory => NodeData::new_directory(),
             _ => unimplemented!("haven't made it there yet"),
@@ -66,6 +67,20 @@ impl NodeBuilder {
         Ok(new_node)
     }
 
+    pub(crate) fn associated_data(name: NodeName) -> Self {
+        Self {
+            id: None,
+            parent_id: None,
+
+            name,
+            owner_id: None,
+            size_hint: None,
+
+            kind: NodeKind::AssociatedData,
+            metadata: HashMap::new(),
+        }
+    }
+
     pub fn directory(name: NodeName) -> Self {
         Self {
             id: None,
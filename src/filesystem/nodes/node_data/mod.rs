@@ -1,19 +1,21 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
+use elliptic_curve::rand_core::CryptoRngCore;
 use futures::{AsyncWrite, AsyncWriteExt};
 use winnow::binary::le_u16;
 use winnow::Parser;
 
+use crate::codec::crypto::AccessKey;
 use crate::codec::filesystem::Permissions;
 use crate::codec::{Cid, ParserResult, PermanentId, Stream};
 use crate::filesystem::nodes::{NodeKind, NodeName};
-use crate::filesystem::FileContent;
+use crate::filesystem::{FileContent, FileContentError};
 
 mod child_map;
 use child_map::ChildMap;
 
-use self::child_map::ChildMapEntry;
+pub(crate) use self::child_map::ChildMapEntry;
 
 pub enum NodeData {
     File {
@@ -21,8 +23,9 @@ pub enum NodeData {
         associated_data: ChildMap,
         content: FileContent,
     },
-    #[allow(dead_code)]
-    AssociatedData { content: FileContent },
+    AssociatedData {
+        content: FileContent,
+    },
     Directory {
         permissions: Permissions,
         children: ChildMap,
@@ -47,14 +50,16 @@ impl NodeData {
         Ok(())
     }
 
+    /// Records the current CID and size of a child, returning whether either of them differed
+    /// from what was already recorded.
     pub fn update_child(
         &mut self,
         child_permanent_id: &PermanentId,
         cid: Cid,
         size: u64,
-    ) -> Result<(), NodeDataError> {
+    ) -> Result<bool, NodeDataError> {
         let children = match self.children_mut() {
-            None => return Ok(()),
+            None => return Ok(false),
             Some(children) => children,
         };
 
@@ -63,9 +68,14 @@ impl NodeData {
             .find(|entry| entry.1.permanent_id() == child_permanent_id)
             .ok_or(NodeDataError::ChildIdMissing)?
             .1;
+
+        if child.cid() == &cid && child.size() == size {
+            return Ok(false);
+        }
+
         child.set_cid(cid);
         child.set_size(size);
-        Ok(())
+        Ok(true)
     }
 
     #[tracing::instrument(skip(self, writer))]
@@ -98,10 +108,33 @@ impl NodeData {
 
                 Ok(written_bytes)
             }
-            _ => unimplemented!(),
+            NodeData::AssociatedData { content } => {
+                written_bytes += content.encode(writer).await?;
+                Ok(written_bytes)
+            }
         }
     }
 
+    /// The number of bytes [`NodeData::encode`] will produce, unlike [`NodeData::size`] this
+    /// doesn't include the size of the file data or any of the children.
+    pub(crate) fn encoded_size(&self) -> usize {
+        let children_size = self.children().map_or(0, |children| {
+            let entry_size = PermanentId::size() + Cid::size() + 8;
+            2 + children
+                .keys()
+                .map(|name| name.size() + entry_size)
+                .sum::<usize>()
+        });
+
+        let content_size = match self {
+            NodeData::Directory { .. } => Permissions::size(),
+            NodeData::File { content, .. } => Permissions::size() + content.encoded_size(),
+            NodeData::AssociatedData { content } => content.encoded_size(),
+        };
+
+        1 + children_size + content_size
+    }
+
     pub(crate) fn kind(&self) -> NodeKind {
         match self {
             NodeData::File { .. } => NodeKind::File,
@@ -147,6 +180,28 @@ impl NodeData {
             } => associated_data.into(),
         }
     }
+
+    pub(crate) fn child_entries(&self) -> impl Iterator<Item = &ChildMapEntry> {
+        self.children()
+            .into_iter()
+            .flat_map(|children| children.values())
+    }
+
+    pub(crate) fn child_permanent_id(&self, name: &NodeName) -> Option<PermanentId> {
+        self.children()?
+            .get(name)
+            .map(|entry| *entry.permanent_id())
+    }
+
+    /// The size of the file content or associated data held directly by this node, excluding
+    /// any children. Directories hold no content of their own.
+    pub(crate) fn content_size(&self) -> u64 {
+        match self {
+            NodeData::File { content, .. } | NodeData::AssociatedData { content } => content.size(),
+            NodeData::Directory { .. } => 0,
+        }
+    }
+
     pub(crate) fn data_cids(&self) -> Option<Vec<Cid>> {
         match self {
             NodeData::File { content, .. } | NodeData::AssociatedData { content } => {
@@ -173,7 +228,10 @@ impl NodeData {
 
                 Ok((data_buf, data))
             }
-            //NodeKind::AssociatedData => {}
+            NodeKind::AssociatedData => {
+                let (data_buf, content) = FileContent::parse(input)?;
+                Ok((data_buf, NodeData::AssociatedData { content }))
+            }
             NodeKind::Directory => {
                 let (data_buf, permissions) = Permissions::parse(input)?;
                 let (data_buf, children) = parse_children(data_buf)?;
@@ -189,6 +247,31 @@ impl NodeData {
         }
     }
 
+    pub(crate) fn permissions(&self) -> Option<Permissions> {
+        match self {
+            Self::AssociatedData { .. } => None,
+            Self::Directory { permissions, .. } | Self::File { permissions, .. } => {
+                Some(*permissions)
+            }
+        }
+    }
+
+    /// Re-seals the key of any encrypted content held directly by this node, see
+    /// [`FileContent::relock_data_key`].
+    pub(crate) fn relock_data_key(
+        &mut self,
+        rng: &mut impl CryptoRngCore,
+        current_key: &AccessKey,
+        new_key: &AccessKey,
+    ) -> Result<(), FileContentError> {
+        match self {
+            Self::File { content, .. } | Self::AssociatedData { content } => {
+                content.relock_data_key(rng, current_key, new_key)
+            }
+            Self::Directory { .. } => Ok(()),
+        }
+    }
+
     pub(crate) fn remove_child(&mut self, name: &NodeName) -> Result<PermanentId, NodeDataError> {
         let child_map = self.children_mut().ok_or(NodeDataError::NotAParent)?;
         match child_map.remove(name) {
@@ -197,6 +280,26 @@ impl NodeData {
         }
     }
 
+    /// Points the child entry currently referencing `current_id` at a different node, keeping the
+    /// name it is listed under.
+    pub(crate) fn replace_child_id(
+        &mut self,
+        current_id: &PermanentId,
+        new_id: PermanentId,
+        cid: Cid,
+        size: u64,
+    ) -> Result<(), NodeDataError> {
+        let child_map = self.children_mut().ok_or(NodeDataError::NotAParent)?;
+
+        let entry = child_map
+            .values_mut()
+            .find(|entry| entry.permanent_id() == current_id)
+            .ok_or(NodeDataError::ChildIdMissing)?;
+        *entry = ChildMapEntry::new(new_id, cid, size);
+
+        Ok(())
+    }
+
     /// This function should be used with care, multiple entries may potentially be pointing to the
     /// same the same permanent ID and this will remove all of them. This is also signficantly less
     /// performant than removing a child by name as it requires visiting every possible entry in
@@ -213,6 +316,22 @@ impl NodeData {
         Ok(())
     }
 
+    /// Replaces the permissions of the node, returning whether they differed from the current
+    /// ones.
+    pub(crate) fn set_permissions(
+        &mut self,
+        new_permissions: Permissions,
+    ) -> Result<bool, NodeDataError> {
+        match self {
+            Self::AssociatedData { .. } => Err(NodeDataError::PermissionsUnsupported),
+            Self::Directory { permissions, .. } | Self::File { permissions, .. } => {
+                let changed = *permissions != new_permissions;
+                *permissions = new_permissions;
+                Ok(changed)
+            }
+        }
+    }
+
     pub(crate) fn size(&self) -> u64 {
         match self {
             NodeData::AssociatedData { content } => content.size(),
@@ -238,6 +357,12 @@ impl NodeData {
         })
     }
 
+    pub(crate) fn stub_associated_data(data_size: u64) -> Self {
+        Self::AssociatedData {
+            content: FileContent::Stub { data_size },
+        }
+    }
+
     pub(crate) fn empty_file() -> Self {
         Self::File {
             permissions: Permissions::default(),
@@ -319,6 +444,9 @@ pub enum NodeDataError {
     #[error("non-parent node cannot have or interact with children")]
     NotAParent,
 
+    #[error("node type does not carry permissions")]
+    PermissionsUnsupported,
+
     #[error("Passed in PermanentId does not refer to a valid child")]
     ChildIdMissing,
 }
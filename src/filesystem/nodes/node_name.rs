@@ -80,6 +80,10 @@ impl NodeName {
         matches!(self, Self::Root)
     }
 
+    /// Names are always UTF-8 and are held to the same rules as names created locally. A drive
+    /// containing names that are not valid UTF-8, or that could never have been created such as
+    /// ones containing slashes or directory traversals, fails to parse rather than producing
+    /// paths that would be interpreted differently than intended.
     pub(crate) fn parse(input: Stream) -> ParserResult<Self> {
         let (input, name_type) = le_u8.parse_peek(input)?;
 
@@ -89,13 +93,17 @@ impl NodeName {
                 let (input, name_length) = le_u8.parse_peek(input)?;
                 let (input, name) = winnow::token::take(name_length as usize).parse_peek(input)?;
 
-                let name = String::from_utf8(name.to_vec()).map_err(|_| {
+                let invalid_name = || {
                     winnow::error::ErrMode::Cut(winnow::error::ParserError::from_error_kind(
                         &input,
                         winnow::error::ErrorKind::Verify,
                     ))
-                })?;
-                Ok((input, Self::Named(name)))
+                };
+
+                let name = String::from_utf8(name.to_vec()).map_err(|_| invalid_name())?;
+                let name = Self::named(name).map_err(|_| invalid_name())?;
+
+                Ok((input, name))
             }
             _ => {
                 let err = winnow::error::ParserError::from_error_kind(
@@ -181,4 +189,23 @@ mod tests {
         assert_eq!(Vec::<u8>::new(), remaining);
         assert_eq!(original, parsed);
     }
+
+    #[test]
+    fn test_parse_rejects_invalid_names() {
+        let invalid_utf8 = [0x01, 0x03, b'a', 0xff, 0xfe];
+        assert!(matches!(
+            NodeName::parse(Stream::new(&invalid_utf8)),
+            Err(winnow::error::ErrMode::Cut(_))
+        ));
+
+        // Names that couldn't have been created locally are rejected as well
+        let traversal = [0x01, 0x02, b'.', b'.'];
+        assert!(NodeName::parse(Stream::new(&traversal)).is_err());
+
+        let slash = [0x01, 0x03, b'a', b'/', b'b'];
+        assert!(NodeName::parse(Stream::new(&slash)).is_err());
+
+        let empty = [0x01, 0x00];
+        assert!(NodeName::parse(Stream::new(&empty)).is_err());
+    }
 }
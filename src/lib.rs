@@ -33,7 +33,9 @@ pub mod prelude {
     pub use crate::version::*;
 
     pub use crate::codec::crypto::{SigningKey, VerifyingKey};
+    pub use crate::codec::filesystem::NodeKind;
     pub use crate::codec::header::{AccessMask, AccessMaskBuilder, ContentOptions};
     pub use crate::codec::FilesystemId;
     pub use crate::codec::VectorClockActor;
+    pub use crate::codec::VectorClockNodeSnapshot;
 }
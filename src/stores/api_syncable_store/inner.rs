@@ -1,11 +1,12 @@
 pub use crate::stores::traits::{DataStore, DataStoreError, SyncTracker};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use reqwest::Url;
 
 use crate::api::ApiClient;
 use crate::codec::Cid;
+use crate::stores::api_syncable_store::MissingBlocks;
 
 pub struct ApiSyncableStoreInner<MS: DataStore, ST: SyncTracker> {
     cached_store: MS,
@@ -50,6 +51,110 @@ impl<MS: DataStore, ST: SyncTracker> ApiSyncableStoreInner<MS, ST> {
         Ok(locations.contains_cid(&cid))
     }
 
+    /// Bulk variant of [`Self::contains_cid`]. Any CIDs that aren't available locally or already
+    /// known to be held remotely are looked up with a single request to the platform. Unlike the
+    /// single CID variant, blocks the platform reports as missing are considered absent rather
+    /// than an error.
+    pub(crate) async fn contains_cids(
+        &mut self,
+        client: &ApiClient,
+        cids: &[Cid],
+    ) -> Result<HashMap<Cid, bool>, DataStoreError> {
+        let mut presence = HashMap::with_capacity(cids.len());
+        let mut unknown_cids = Vec::new();
+
+        for cid in cids {
+            if self.cid_map.contains_key(cid) || self.cached_store.contains_cid(cid.clone()).await?
+            {
+                presence.insert(cid.clone(), true);
+            } else {
+                unknown_cids.push(cid.clone());
+            }
+        }
+
+        if unknown_cids.is_empty() {
+            return Ok(presence);
+        }
+
+        let locations = crate::api::platform::blocks::locate(client, &unknown_cids)
+            .await
+            .map_err(|err| {
+                tracing::error!("failed to locate blocks: {err}");
+                DataStoreError::LookupFailure
+            })?;
+
+        for cid in unknown_cids {
+            if let Some(locs) = locations.storage_hosts_with_cid(&cid) {
+                self.cid_map.insert(cid.clone(), locs);
+            }
+
+            presence.insert(cid.clone(), locations.contains_cid(&cid));
+        }
+
+        Ok(presence)
+    }
+
+    /// Determines which of the provided blocks no storage host holds. Blocks the sync tracker is
+    /// still waiting to upload are known to be missing, the platform is asked about the rest.
+    /// Earlier lookups aren't relied upon as blocks may have been lost since they were made.
+    pub(crate) async fn missing_remote_blocks(
+        &mut self,
+        client: &ApiClient,
+        cids: &[Cid],
+    ) -> Result<MissingBlocks, DataStoreError> {
+        let tracked_cids: HashSet<Cid> = self
+            .sync_tracker
+            .tracked_cids()
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut missing_cids = Vec::new();
+        let mut unconfirmed_cids = Vec::new();
+
+        for cid in cids.iter().collect::<HashSet<_>>() {
+            if tracked_cids.contains(cid) {
+                missing_cids.push(cid.clone());
+            } else {
+                unconfirmed_cids.push(cid.clone());
+            }
+        }
+
+        if !unconfirmed_cids.is_empty() {
+            let locations = crate::api::platform::blocks::locate(client, &unconfirmed_cids)
+                .await
+                .map_err(|err| {
+                    tracing::error!("failed to locate blocks: {err}");
+                    DataStoreError::LookupFailure
+                })?;
+
+            for cid in unconfirmed_cids {
+                match locations.storage_hosts_with_cid(&cid) {
+                    Some(locs) if !locs.is_empty() => {
+                        self.cid_map.insert(cid, locs);
+                    }
+                    _ => {
+                        self.cid_map.remove(&cid);
+                        missing_cids.push(cid);
+                    }
+                }
+            }
+        }
+
+        let mut missing = MissingBlocks::default();
+        for cid in missing_cids {
+            if !self.cached_store.contains_cid(cid.clone()).await? {
+                missing.insert_unavailable(cid);
+                continue;
+            }
+
+            let block_size = self.cached_store.retrieve(cid.clone()).await?.len() as u64;
+            missing.insert_pending(cid, block_size);
+        }
+
+        Ok(missing)
+    }
+
     pub(crate) fn new(cached_store: MS, sync_tracker: ST) -> Self {
         Self {
             cached_store,
@@ -65,6 +170,12 @@ impl<MS: DataStore, ST: SyncTracker> ApiSyncableStoreInner<MS, ST> {
         cid: Cid,
         recursive: bool,
     ) -> Result<(), DataStoreError> {
+        // Pinned blocks are kept available even once nothing in the filesystem refers to them
+        if self.sync_tracker.is_pinned(&cid).await? {
+            tracing::debug!(?cid, "keeping pinned block");
+            return Ok(());
+        }
+
         self.cached_store.remove(cid.clone(), recursive).await?;
         self.sync_tracker.untrack(cid.clone()).await?;
 
@@ -201,13 +312,21 @@ impl<MS: DataStore, ST: SyncTracker> ApiSyncableStoreInner<MS, ST> {
 
         let session_data_size = self.sync_tracker.tracked_size().await?;
 
-        let session =
-            blocks::create_session(client, &storage_host_url, metadata_id, session_data_size)
-                .await
-                .map_err(|_| DataStoreError::SessionRejected)?;
+        let cid_count = tracked_cids.len();
+
+        // The size of individual blocks isn't tracked, only the number of them can be hinted
+        let session = blocks::create_session_with_hints(
+            client,
+            &storage_host_url,
+            metadata_id,
+            session_data_size,
+            cid_count as u64,
+            None,
+        )
+        .await
+        .map_err(|_| DataStoreError::SessionRejected)?;
 
         let upload_id = session.upload_id();
-        let cid_count = tracked_cids.len();
 
         for (idx, cid) in tracked_cids.into_iter().enumerate() {
             let data = self.cached_store.retrieve(cid.clone()).await?;
@@ -0,0 +1,44 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::codec::Cid;
+
+/// The blocks that aren't held by any storage host, produced by
+/// [`crate::stores::ApiSyncableStore::missing_remote_blocks`]. Blocks that are still available
+/// in the local store can be uploaded to complete the backup, the remainder are unavailable
+/// everywhere and can't be recovered by syncing.
+#[derive(Clone, Debug, Default)]
+pub struct MissingBlocks {
+    pending: HashMap<Cid, u64>,
+    unavailable: HashSet<Cid>,
+}
+
+impl MissingBlocks {
+    /// Whether every block is held by at least one storage host.
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty() && self.unavailable.is_empty()
+    }
+
+    pub(crate) fn insert_pending(&mut self, cid: Cid, size: u64) {
+        self.pending.insert(cid, size);
+    }
+
+    pub(crate) fn insert_unavailable(&mut self, cid: Cid) {
+        self.unavailable.insert(cid);
+    }
+
+    /// The blocks missing remotely that are available locally, along with their sizes. These are
+    /// exactly the blocks that need to be uploaded.
+    pub fn pending(&self) -> &HashMap<Cid, u64> {
+        &self.pending
+    }
+
+    /// The total size of the blocks that need to be uploaded.
+    pub fn pending_size(&self) -> u64 {
+        self.pending.values().sum()
+    }
+
+    /// The blocks missing remotely that aren't available locally either.
+    pub fn unavailable(&self) -> &HashSet<Cid> {
+        &self.unavailable
+    }
+}
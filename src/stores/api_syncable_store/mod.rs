@@ -1,7 +1,10 @@
 mod inner;
+mod missing_blocks;
 
 use inner::ApiSyncableStoreInner;
+pub use missing_blocks::MissingBlocks;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use url::Url;
 
@@ -34,6 +37,99 @@ impl<MS: DataStore, ST: SyncTracker> ApiSyncableStore<MS, ST> {
             inner: Arc::new(RwLock::new(inner)),
         }
     }
+
+    /// Determines which of the provided blocks aren't held by any storage host, such as those of
+    /// [`crate::filesystem::Drive::all_cids`]. A drive is only fully backed up once this reports
+    /// the blocks as complete. The size of each block that still needs to be uploaded is read
+    /// from the local store, which requires retrieving them.
+    ///
+    /// The result can be passed to [`ApiSyncableStore::track_missing_blocks`] to have the next
+    /// sync upload exactly the blocks that are missing.
+    pub async fn missing_remote_blocks(
+        &self,
+        cids: &[Cid],
+    ) -> Result<MissingBlocks, DataStoreError> {
+        self.inner
+            .write()
+            .await
+            .missing_remote_blocks(&self.client, cids)
+            .await
+    }
+
+    /// The net change in stored bytes the next sync will cause, the size of the blocks waiting to
+    /// be uploaded less the size of the blocks waiting to be purged. Positive values are storage
+    /// the sync will consume, negative values are storage it will free. Both sizes are read
+    /// together so the result reflects a single consistent state of the tracker.
+    ///
+    /// Only deletions of blocks whose size the tracker knows contribute, see
+    /// [`SyncTracker::deleted_size`].
+    pub async fn net_change(&self) -> Result<i64, DataStoreError> {
+        let inner = self.inner.read().await;
+        let tracker = inner.sync_tracker();
+
+        let tracked_size = tracker.tracked_size().await?;
+        let deleted_size = tracker.deleted_size().await?;
+
+        let net_change = i128::from(tracked_size) - i128::from(deleted_size);
+        Ok(net_change.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+
+    /// Limits the rate, in bytes per second, blocks are synced to and retrieved from storage
+    /// hosts. This can be changed while a sync is in progress. See
+    /// [`ApiClient::set_bandwidth_limit`] for the details, the limit is shared with the client
+    /// the store was created with.
+    pub fn set_bandwidth_limit(&self, bytes_per_second: Option<u64>) {
+        self.client.set_bandwidth_limit(bytes_per_second);
+    }
+
+    /// Performs a final sync of every tracked block to the storage host, clears the block
+    /// deletions that have been reported to the platform, and flushes the sync tracker so its
+    /// state is durable. This is intended to be the last call made before the store is dropped,
+    /// such as when a process is asked to terminate.
+    ///
+    /// Upload sessions and block deletions are both tied to a metadata version on the platform,
+    /// which is why shutting down needs one. `metadata_id` must be the most recent version pushed
+    /// along with the store's [`SyncTracker::deleted_cids`], the same version that would be
+    /// passed to [`SyncableDataStore::sync`]. Once the blocks are synced the deletions reported
+    /// by that push are cleared. Blocks shouldn't be removed from the store between the push and
+    /// the shutdown, as those deletions would be cleared without having been reported.
+    ///
+    /// The store is consumed to prevent further use. Any clones of the store share its state and
+    /// will wait for the shutdown to complete before their operations proceed, they should not be
+    /// used afterwards.
+    ///
+    /// If the sync fails, the blocks that weren't transferred remain in the cached store and the
+    /// tracker, along with every pending deletion, and can be synced by a new store constructed
+    /// over them.
+    pub async fn shutdown(self, metadata_id: &str) -> Result<(), DataStoreError> {
+        let mut inner = self.inner.write().await;
+
+        inner.sync(&self.client, metadata_id).await?;
+
+        let tracker = inner.sync_tracker_mut();
+        tracker.clear_deleted().await?;
+        tracker.flush().await
+    }
+
+    /// Tracks each of the locally available blocks reported by
+    /// [`ApiSyncableStore::missing_remote_blocks`] so they're uploaded by the next sync. Blocks
+    /// that are already tracked are left as is.
+    pub async fn track_missing_blocks(
+        &self,
+        missing: &MissingBlocks,
+    ) -> Result<(), DataStoreError> {
+        let mut inner = self.inner.write().await;
+        let tracker = inner.sync_tracker_mut();
+
+        let tracked_cids = tracker.tracked_cids().await?;
+        for (cid, size) in missing.pending() {
+            if !tracked_cids.contains(cid) {
+                tracker.track(cid.clone(), *size).await?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait(?Send)]
@@ -46,6 +142,14 @@ impl<MS: DataStore, ST: SyncTracker> DataStore for ApiSyncableStore<MS, ST> {
             .await
     }
 
+    async fn contains_cids(&self, cids: &[Cid]) -> Result<HashMap<Cid, bool>, DataStoreError> {
+        self.inner
+            .write()
+            .await
+            .contains_cids(&self.client, cids)
+            .await
+    }
+
     async fn remove(&mut self, cid: Cid, recursive: bool) -> Result<(), DataStoreError> {
         self.inner
             .write()
@@ -96,6 +200,26 @@ impl<MS: DataStore, ST: SyncTracker> SyncTracker for ApiSyncableStore<MS, ST> {
         self.inner.read().await.sync_tracker().deleted_cids().await
     }
 
+    async fn deleted_size(&self) -> Result<u64, DataStoreError> {
+        self.inner.read().await.sync_tracker().deleted_size().await
+    }
+
+    async fn flush(&mut self) -> Result<(), DataStoreError> {
+        self.inner.write().await.sync_tracker_mut().flush().await
+    }
+
+    async fn is_pinned(&self, cid: &Cid) -> Result<bool, DataStoreError> {
+        self.inner.read().await.sync_tracker().is_pinned(cid).await
+    }
+
+    async fn pin(&mut self, cid: Cid) -> Result<(), DataStoreError> {
+        self.inner.write().await.sync_tracker_mut().pin(cid).await
+    }
+
+    async fn pinned_cids(&self) -> Result<Vec<Cid>, DataStoreError> {
+        self.inner.read().await.sync_tracker().pinned_cids().await
+    }
+
     async fn track(&mut self, cid: Cid, size: u64) -> Result<(), DataStoreError> {
         self.inner
             .write()
@@ -113,6 +237,10 @@ impl<MS: DataStore, ST: SyncTracker> SyncTracker for ApiSyncableStore<MS, ST> {
         self.inner.read().await.sync_tracker().tracked_size().await
     }
 
+    async fn unpin(&mut self, cid: Cid) -> Result<(), DataStoreError> {
+        self.inner.write().await.sync_tracker_mut().unpin(cid).await
+    }
+
     async fn untrack(&mut self, cid: Cid) -> Result<(), DataStoreError> {
         self.inner
             .write()
@@ -146,3 +274,263 @@ impl<MS: DataStore, ST: SyncTracker> Clone for ApiSyncableStore<MS, ST> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::codec::crypto::SigningKey;
+    use crate::stores::{MemoryDataStore, MemorySyncTracker};
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_net_change_combines_tracked_and_deleted() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let client = ApiClient::new("http://127.0.0.1/", "test-account", key).unwrap();
+
+        let mut store = ApiSyncableStore::new(
+            client,
+            MemoryDataStore::default(),
+            MemorySyncTracker::default(),
+        );
+        assert_eq!(store.net_change().await.unwrap(), 0);
+
+        store.track(Cid::from([1u8; 32]), 120).await.unwrap();
+        store.track(Cid::from([2u8; 32]), 40).await.unwrap();
+        assert_eq!(store.net_change().await.unwrap(), 160);
+
+        // Previously synced blocks free their space once deleted
+        store.untrack(Cid::from([2u8; 32])).await.unwrap();
+        store.delete(Cid::from([2u8; 32])).await.unwrap();
+        assert_eq!(store.net_change().await.unwrap(), 80);
+
+        store.untrack(Cid::from([1u8; 32])).await.unwrap();
+        assert_eq!(store.net_change().await.unwrap(), -40);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_pinned_blocks_survive_removal() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let client = ApiClient::new("http://127.0.0.1/", "test-account", key).unwrap();
+
+        let mut store = ApiSyncableStore::new(
+            client,
+            MemoryDataStore::default(),
+            MemorySyncTracker::default(),
+        );
+
+        let pinned_cid = Cid::from([1u8; 32]);
+        store
+            .store(pinned_cid.clone(), b"shared".to_vec(), false)
+            .await
+            .unwrap();
+        store.pin(pinned_cid.clone()).await.unwrap();
+
+        store.remove(pinned_cid.clone(), true).await.unwrap();
+        assert_eq!(store.retrieve(pinned_cid.clone()).await.unwrap(), b"shared");
+        assert!(store.deleted_cids().await.unwrap().is_empty());
+
+        store.unpin(pinned_cid.clone()).await.unwrap();
+        store.remove(pinned_cid.clone(), true).await.unwrap();
+        assert_eq!(store.deleted_cids().await.unwrap(), vec![pinned_cid]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_shutdown_clears_reported_deletions() {
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let client = ApiClient::new("http://127.0.0.1/", "test-account", key).unwrap();
+
+        let mut store = ApiSyncableStore::new(
+            client,
+            MemoryDataStore::default(),
+            MemorySyncTracker::default(),
+        );
+
+        let cid = Cid::from([1u8; 32]);
+        store.store(cid.clone(), vec![0; 10], false).await.unwrap();
+        store.remove(cid.clone(), true).await.unwrap();
+        assert_eq!(store.deleted_cids().await.unwrap(), vec![cid]);
+
+        // Clones share the store's state, allowing it to be inspected after the shutdown
+        let observer = store.clone();
+        store.shutdown("reporting-metadata-id").await.unwrap();
+
+        assert!(observer.deleted_cids().await.unwrap().is_empty());
+        assert_eq!(observer.tracked_size().await.unwrap(), 0);
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_local_store_failures_are_reported() {
+        use crate::stores::{FaultyDataStore, FaultyOperation};
+
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+        let client = ApiClient::new("http://127.0.0.1/", "test-account", key).unwrap();
+
+        let failing_cid = Cid::from([1u8; 32]);
+        let stored_cid = Cid::from([2u8; 32]);
+
+        let mut cached_store = FaultyDataStore::default();
+        cached_store.fail_cid(
+            FaultyOperation::Store,
+            failing_cid.clone(),
+            DataStoreError::StoreFailure,
+        );
+        cached_store.fail_cid(
+            FaultyOperation::Retrieve,
+            stored_cid.clone(),
+            DataStoreError::RetrievalFailure,
+        );
+
+        let mut store = ApiSyncableStore::new(client, cached_store, MemorySyncTracker::default());
+
+        // Blocks that never made it into the local store aren't tracked for the next sync
+        let result = store.store(failing_cid, vec![0; 10], false).await;
+        assert!(matches!(result, Err(DataStoreError::StoreFailure)));
+        assert_eq!(store.tracked_size().await.unwrap(), 0);
+
+        store
+            .store(stored_cid.clone(), vec![0; 20], false)
+            .await
+            .unwrap();
+        assert_eq!(store.tracked_size().await.unwrap(), 20);
+
+        // Blocks held locally are never looked up remotely, a local failure is reported as is
+        let result = store.retrieve(stored_cid).await;
+        assert!(matches!(result, Err(DataStoreError::RetrievalFailure)));
+    }
+
+    #[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_contains_cids() {
+        use crate::api::client::MockTransport;
+
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        let mut store = ApiSyncableStore::new(
+            client,
+            MemoryDataStore::default(),
+            MemorySyncTracker::default(),
+        );
+
+        let local_cid = Cid::from([1u8; 32]);
+        let remote_cid = Cid::from([2u8; 32]);
+        let missing_cid = Cid::from([3u8; 32]);
+
+        store
+            .store(local_cid.clone(), vec![0; 10], false)
+            .await
+            .unwrap();
+
+        // Only the blocks that aren't held locally are looked up
+        transport.push_json(200, serde_json::json!({ "api_version": "1.0.0" }));
+        transport.push_json(
+            200,
+            serde_json::json!({
+                "https://host.invalid/": [remote_cid.as_base64url_multicodec()],
+                "NA": [missing_cid.as_base64url_multicodec()],
+            }),
+        );
+
+        let presence = store
+            .contains_cids(&[local_cid.clone(), remote_cid.clone(), missing_cid.clone()])
+            .await
+            .unwrap();
+        assert_eq!(presence.len(), 3);
+        assert!(presence[&local_cid]);
+        assert!(presence[&remote_cid]);
+        assert!(!presence[&missing_cid]);
+
+        // Blocks found remotely are remembered, no further requests are needed for them
+        let presence = store
+            .contains_cids(&[local_cid.clone(), remote_cid.clone()])
+            .await
+            .unwrap();
+        assert!(presence.values().all(|present| *present));
+        assert_eq!(transport.take_requests().len(), 2);
+    }
+
+    #[cfg(all(feature = "test-utils", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_missing_remote_blocks() {
+        use crate::api::client::MockTransport;
+
+        let mut rng = crate::utils::crypto_rng();
+        let key = Arc::new(SigningKey::generate(&mut rng));
+
+        let transport = Arc::new(MockTransport::default());
+        let client = ApiClient::new("http://platform.invalid/", "test-account", key)
+            .unwrap()
+            .with_transport(transport.clone());
+
+        let mut store = ApiSyncableStore::new(
+            client,
+            MemoryDataStore::default(),
+            MemorySyncTracker::default(),
+        );
+
+        let remote_cid = Cid::from([1u8; 32]);
+        let pending_cid = Cid::from([2u8; 32]);
+        let lost_cid = Cid::from([3u8; 32]);
+        let untracked_cid = Cid::from([4u8; 32]);
+
+        // Stored blocks are tracked for the next sync, one of them has since lost its tracking
+        store
+            .store(pending_cid.clone(), vec![0; 100], false)
+            .await
+            .unwrap();
+        store
+            .store(untracked_cid.clone(), vec![0; 40], false)
+            .await
+            .unwrap();
+        store.untrack(untracked_cid.clone()).await.unwrap();
+
+        // Tracked blocks are known to be missing and aren't looked up
+        transport.push_json(200, serde_json::json!({ "api_version": "1.0.0" }));
+        transport.push_json(
+            200,
+            serde_json::json!({
+                "https://host.invalid/": [remote_cid.as_base64url_multicodec()],
+                "NA": [
+                    lost_cid.as_base64url_multicodec(),
+                    untracked_cid.as_base64url_multicodec(),
+                ],
+            }),
+        );
+
+        let cids = [
+            remote_cid.clone(),
+            pending_cid.clone(),
+            lost_cid.clone(),
+            untracked_cid.clone(),
+        ];
+        let missing = store.missing_remote_blocks(&cids).await.unwrap();
+        assert!(!missing.is_complete());
+        assert_eq!(missing.pending().len(), 2);
+        assert_eq!(missing.pending_size(), 140);
+        assert_eq!(
+            missing.unavailable().iter().collect::<Vec<_>>(),
+            vec![&lost_cid]
+        );
+
+        store.track_missing_blocks(&missing).await.unwrap();
+        assert_eq!(store.tracked_size().await.unwrap(), 140);
+
+        let mut tracked = store.tracked_cids().await.unwrap();
+        tracked.sort();
+        assert_eq!(tracked, vec![pending_cid, untracked_cid]);
+    }
+}
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::codec::Cid;
+use crate::stores::traits::{DataStore, DataStoreError};
+use crate::stores::MemoryDataStore;
+
+/// The [`DataStore`] operations that can be made to fail by a [`FaultyDataStore`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FaultyOperation {
+    ContainsCid,
+    Retrieve,
+    Store,
+}
+
+/// A [`DataStore`] intended for testing how consumers handle storage failures. It behaves exactly
+/// like the [`MemoryDataStore`] it wraps until configured to fail specific operations, either for
+/// designated CIDs using [`FaultyDataStore::fail_cid`] or after a number of successful calls using
+/// [`FaultyDataStore::fail_after`]. Failed operations return the configured [`DataStoreError`]
+/// and leave the wrapped store untouched.
+#[derive(Default)]
+pub struct FaultyDataStore {
+    inner: MemoryDataStore,
+
+    cid_faults: HashMap<(FaultyOperation, Cid), DataStoreError>,
+    call_limits: HashMap<FaultyOperation, (usize, DataStoreError)>,
+    call_counts: RefCell<HashMap<FaultyOperation, usize>>,
+}
+
+impl FaultyDataStore {
+    /// The number of times the operation has been attempted, including any that were failed.
+    pub fn call_count(&self, operation: FaultyOperation) -> usize {
+        self.call_counts
+            .borrow()
+            .get(&operation)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Removes all configured faults, the store will behave normally from this point on. Call
+    /// counts are not reset.
+    pub fn clear_faults(&mut self) {
+        self.cid_faults.clear();
+        self.call_limits.clear();
+    }
+
+    /// Allows the first `successful_calls` attempts of the operation to succeed, every attempt
+    /// after that will fail with the provided error.
+    pub fn fail_after(
+        &mut self,
+        operation: FaultyOperation,
+        successful_calls: usize,
+        error: DataStoreError,
+    ) {
+        self.call_limits
+            .insert(operation, (successful_calls, error));
+    }
+
+    /// Every attempt of the operation involving the provided CID will fail with the provided
+    /// error.
+    pub fn fail_cid(&mut self, operation: FaultyOperation, cid: Cid, error: DataStoreError) {
+        self.cid_faults.insert((operation, cid), error);
+    }
+
+    /// Provides direct access to the wrapped store, bypassing any configured faults.
+    pub fn inner_mut(&mut self) -> &mut MemoryDataStore {
+        &mut self.inner
+    }
+
+    pub fn new(inner: MemoryDataStore) -> Self {
+        Self {
+            inner,
+            ..Default::default()
+        }
+    }
+
+    /// Records the attempt and returns the error the operation should fail with, if any.
+    fn check_fault(&self, operation: FaultyOperation, cid: &Cid) -> Result<(), DataStoreError> {
+        let previous_calls = {
+            let mut call_counts = self.call_counts.borrow_mut();
+            let count = call_counts.entry(operation).or_default();
+            *count += 1;
+            *count - 1
+        };
+
+        if let Some(err) = self.cid_faults.get(&(operation, cid.clone())) {
+            return Err(err.clone());
+        }
+
+        if let Some((successful_calls, err)) = self.call_limits.get(&operation) {
+            if previous_calls >= *successful_calls {
+                return Err(err.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl DataStore for FaultyDataStore {
+    async fn contains_cid(&self, cid: Cid) -> Result<bool, DataStoreError> {
+        self.check_fault(FaultyOperation::ContainsCid, &cid)?;
+        self.inner.contains_cid(cid).await
+    }
+
+    async fn remove(&mut self, cid: Cid, recursive: bool) -> Result<(), DataStoreError> {
+        self.inner.remove(cid, recursive).await
+    }
+
+    async fn retrieve(&self, cid: Cid) -> Result<Vec<u8>, DataStoreError> {
+        self.check_fault(FaultyOperation::Retrieve, &cid)?;
+        self.inner.retrieve(cid).await
+    }
+
+    async fn store(
+        &mut self,
+        cid: Cid,
+        data: Vec<u8>,
+        immediate: bool,
+    ) -> Result<(), DataStoreError> {
+        self.check_fault(FaultyOperation::Store, &cid)?;
+        self.inner.store(cid, data, immediate).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_fails_designated_cids() {
+        let mut store = FaultyDataStore::default();
+
+        let good_cid = Cid::from([1u8; 32]);
+        let bad_cid = Cid::from([2u8; 32]);
+        store.fail_cid(
+            FaultyOperation::Store,
+            bad_cid.clone(),
+            DataStoreError::StoreFailure,
+        );
+
+        store.store(good_cid.clone(), vec![1], true).await.unwrap();
+        let result = store.store(bad_cid.clone(), vec![2], true).await;
+        assert!(matches!(result, Err(DataStoreError::StoreFailure)));
+
+        assert!(store.contains_cid(good_cid).await.unwrap());
+        assert!(!store.contains_cid(bad_cid).await.unwrap());
+        assert_eq!(store.call_count(FaultyOperation::Store), 2);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_fails_after_call_limit() {
+        let mut store = FaultyDataStore::default();
+
+        let cid = Cid::from([1u8; 32]);
+        store.store(cid.clone(), vec![1, 2, 3], true).await.unwrap();
+        store.fail_after(
+            FaultyOperation::Retrieve,
+            1,
+            DataStoreError::RetrievalFailure,
+        );
+
+        assert_eq!(store.retrieve(cid.clone()).await.unwrap(), vec![1, 2, 3]);
+        let result = store.retrieve(cid.clone()).await;
+        assert!(matches!(result, Err(DataStoreError::RetrievalFailure)));
+
+        store.clear_faults();
+        assert!(store.retrieve(cid).await.is_ok());
+    }
+}
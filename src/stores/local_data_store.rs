@@ -1,18 +1,75 @@
 use async_trait::async_trait;
 use directories::ProjectDirs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::codec::Cid;
 use crate::stores::traits::{DataStore, DataStoreError};
 
+const DEFAULT_SPOOL_DIR: &str = ".spool";
+
+static SPOOL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Stores blocks as individual files named after their CID within a single directory on the local
+/// disk. Blocks are first written in full to a spool directory and then atomically renamed into
+/// place, a block being present at its final path means it was completely written.
 pub struct LocalDataStore {
     data_dir: PathBuf,
+    spool_dir: PathBuf,
 }
 
 impl LocalDataStore {
+    /// Opens a store rooted at the provided directory creating it if needed. Blocks are spooled
+    /// in a hidden directory inside of it by default.
+    pub fn new(data_dir: impl Into<PathBuf>) -> Result<Self, DataStoreError> {
+        let data_dir = data_dir.into();
+        let spool_dir = data_dir.join(DEFAULT_SPOOL_DIR);
+
+        std::fs::create_dir_all(&data_dir).map_err(|_| DataStoreError::StoreFailure)?;
+
+        Ok(Self {
+            data_dir,
+            spool_dir,
+        })
+    }
+
+    /// Changes where blocks are written before they're moved into place. The renames are only
+    /// atomic when the spool directory lives on the same filesystem as the store itself.
+    pub fn with_spool_dir(mut self, spool_dir: impl Into<PathBuf>) -> Self {
+        self.spool_dir = spool_dir.into();
+        self
+    }
+
+    pub fn spool_dir(&self) -> &Path {
+        &self.spool_dir
+    }
+
     fn cid_to_path(&self, cid: &Cid) -> PathBuf {
         self.data_dir.join(cid.to_string())
     }
+
+    /// Writes the complete block to a uniquely named file in the spool directory, flushing it to
+    /// disk before returning the path it was written to.
+    fn spool(&self, cid: &Cid, data: &[u8]) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.spool_dir)?;
+
+        let spool_id = SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let spool_name = format!("{cid}.{}.{spool_id}.partial", std::process::id());
+        let spool_path = self.spool_dir.join(spool_name);
+
+        let write_result = std::fs::File::create(&spool_path).and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        });
+
+        if let Err(err) = write_result {
+            let _ = std::fs::remove_file(&spool_path);
+            return Err(err);
+        }
+
+        Ok(spool_path)
+    }
 }
 
 #[async_trait(?Send)]
@@ -40,8 +97,17 @@ impl DataStore for LocalDataStore {
         data: Vec<u8>,
         _immediate: bool,
     ) -> Result<(), DataStoreError> {
-        let path = self.cid_to_path(&cid);
-        std::fs::write(path, data).map_err(|_| DataStoreError::StoreFailure)?;
+        let spool_path = self.spool(&cid, &data).map_err(|err| {
+            tracing::error!("failed to spool block: {err}");
+            DataStoreError::StoreFailure
+        })?;
+
+        if let Err(err) = std::fs::rename(&spool_path, self.cid_to_path(&cid)) {
+            tracing::error!("failed to move spooled block into place: {err}");
+            let _ = std::fs::remove_file(&spool_path);
+            return Err(DataStoreError::StoreFailure);
+        }
+
         Ok(())
     }
 }
@@ -49,8 +115,46 @@ impl DataStore for LocalDataStore {
 impl Default for LocalDataStore {
     fn default() -> Self {
         let proj_dirs = ProjectDirs::from("computer", "Banyan", "banyan-fuse").unwrap();
-        let data_dir = proj_dirs.data_dir().to_owned();
-        std::fs::create_dir_all(&data_dir).expect("Failed to create data directory");
-        LocalDataStore { data_dir }
+        LocalDataStore::new(proj_dirs.data_dir()).expect("Failed to create data directory")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use elliptic_curve::rand_core::RngCore;
+
+    fn scratch_dir() -> PathBuf {
+        let mut rng = crate::utils::crypto_rng();
+        std::env::temp_dir().join(format!("banyanfs-local-store-{:016x}", rng.next_u64()))
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_store_leaves_no_block() {
+        let data_dir = scratch_dir();
+        let mut store = LocalDataStore::new(&data_dir)
+            .unwrap()
+            .with_spool_dir(data_dir.join("custom-spool"));
+
+        let cid = Cid::from([1u8; 32]);
+        let data = vec![0x42; 4096];
+
+        // Spooling without the final rename is what remains when a store is interrupted
+        let spool_path = store.spool(&cid, &data).unwrap();
+        assert!(spool_path.starts_with(store.spool_dir()));
+        assert!(!store.contains_cid(cid.clone()).await.unwrap());
+        assert!(matches!(
+            store.retrieve(cid.clone()).await,
+            Err(DataStoreError::LookupFailure)
+        ));
+
+        store.store(cid.clone(), data.clone(), true).await.unwrap();
+        assert_eq!(store.retrieve(cid.clone()).await.unwrap(), data);
+
+        let remaining_spool = std::fs::read_dir(store.spool_dir()).unwrap().count();
+        assert_eq!(remaining_spool, 1);
+
+        std::fs::remove_dir_all(data_dir).unwrap();
     }
 }
@@ -12,8 +12,13 @@ use crate::codec::Cid;
 /// would need to implement to create an alternate block tracking system.
 #[derive(Default)]
 pub struct MemorySyncTracker {
-    pending_deletion: HashSet<Cid>,
+    pending_deletion: HashMap<Cid, u64>,
+    pinned: HashSet<Cid>,
     tracked: HashMap<Cid, u64>,
+
+    /// Sizes of the CIDs that are no longer tracked, kept so they can be reported if the CID is
+    /// later deleted.
+    untracked_sizes: HashMap<Cid, u64>,
 }
 
 #[async_trait(?Send)]
@@ -24,15 +29,55 @@ impl SyncTracker for MemorySyncTracker {
     }
 
     async fn delete(&mut self, cid: Cid) -> Result<(), DataStoreError> {
-        self.pending_deletion.insert(cid);
+        if self.pinned.contains(&cid) {
+            tracing::debug!(?cid, "ignoring deletion of pinned CID");
+            return Ok(());
+        }
+
+        let size = self
+            .tracked
+            .get(&cid)
+            .or_else(|| self.untracked_sizes.get(&cid))
+            .copied()
+            .unwrap_or_default();
+
+        self.untracked_sizes.remove(&cid);
+        self.pending_deletion.insert(cid, size);
+
         Ok(())
     }
 
     async fn deleted_cids(&self) -> Result<Vec<Cid>, DataStoreError> {
-        Ok(self.pending_deletion.iter().cloned().collect())
+        Ok(self.pending_deletion.keys().cloned().collect())
+    }
+
+    async fn deleted_size(&self) -> Result<u64, DataStoreError> {
+        Ok(self.pending_deletion.values().sum())
+    }
+
+    async fn is_pinned(&self, cid: &Cid) -> Result<bool, DataStoreError> {
+        Ok(self.pinned.contains(cid))
+    }
+
+    async fn pin(&mut self, cid: Cid) -> Result<(), DataStoreError> {
+        // Keep the size of a cancelled deletion around in case the CID is deleted again after
+        // being unpinned
+        if let Some(size) = self.pending_deletion.remove(&cid) {
+            if !self.tracked.contains_key(&cid) {
+                self.untracked_sizes.insert(cid.clone(), size);
+            }
+        }
+
+        self.pinned.insert(cid);
+        Ok(())
+    }
+
+    async fn pinned_cids(&self) -> Result<Vec<Cid>, DataStoreError> {
+        Ok(self.pinned.iter().cloned().collect())
     }
 
     async fn track(&mut self, cid: Cid, size: u64) -> Result<(), DataStoreError> {
+        self.untracked_sizes.remove(&cid);
         self.tracked.entry(cid).or_insert(size);
         Ok(())
     }
@@ -45,8 +90,75 @@ impl SyncTracker for MemorySyncTracker {
         Ok(self.tracked.values().sum())
     }
 
+    async fn unpin(&mut self, cid: Cid) -> Result<(), DataStoreError> {
+        self.pinned.remove(&cid);
+        Ok(())
+    }
+
     async fn untrack(&mut self, cid: Cid) -> Result<(), DataStoreError> {
-        self.tracked.remove(&cid);
+        if let Some(size) = self.tracked.remove(&cid) {
+            self.untracked_sizes.insert(cid, size);
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_deleted_size_includes_untracked_cids() {
+        let mut tracker = MemorySyncTracker::default();
+
+        let synced_cid = Cid::from([1u8; 32]);
+        let pending_cid = Cid::from([2u8; 32]);
+        let unknown_cid = Cid::from([3u8; 32]);
+
+        tracker.track(synced_cid.clone(), 300).await.unwrap();
+        tracker.track(pending_cid.clone(), 40).await.unwrap();
+        tracker.untrack(synced_cid.clone()).await.unwrap();
+        assert_eq!(tracker.tracked_size().await.unwrap(), 40);
+
+        tracker.delete(synced_cid).await.unwrap();
+        tracker.delete(pending_cid).await.unwrap();
+        tracker.delete(unknown_cid).await.unwrap();
+        assert_eq!(tracker.deleted_cids().await.unwrap().len(), 3);
+        assert_eq!(tracker.deleted_size().await.unwrap(), 340);
+
+        tracker.clear_deleted().await.unwrap();
+        assert_eq!(tracker.deleted_size().await.unwrap(), 0);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_pinned_cids_are_never_deleted() {
+        let mut tracker = MemorySyncTracker::default();
+
+        let pinned_cid = Cid::from([1u8; 32]);
+        let other_cid = Cid::from([2u8; 32]);
+
+        tracker.track(pinned_cid.clone(), 100).await.unwrap();
+        tracker.delete(pinned_cid.clone()).await.unwrap();
+
+        // Pinning cancels the pending deletion and ignores later ones
+        tracker.pin(pinned_cid.clone()).await.unwrap();
+        tracker.delete(pinned_cid.clone()).await.unwrap();
+        tracker.delete(other_cid.clone()).await.unwrap();
+        assert_eq!(tracker.deleted_cids().await.unwrap(), vec![other_cid]);
+        assert_eq!(
+            tracker.pinned_cids().await.unwrap(),
+            vec![pinned_cid.clone()]
+        );
+        assert!(tracker.is_pinned(&pinned_cid).await.unwrap());
+
+        tracker.unpin(pinned_cid.clone()).await.unwrap();
+        assert!(!tracker.is_pinned(&pinned_cid).await.unwrap());
+
+        tracker.delete(pinned_cid).await.unwrap();
+        assert_eq!(tracker.deleted_cids().await.unwrap().len(), 2);
+        assert_eq!(tracker.deleted_size().await.unwrap(), 100);
+    }
+}
@@ -6,15 +6,19 @@
 //! a custom block storage system.
 
 mod api_syncable_store;
+#[cfg(feature = "test-utils")]
+mod faulty_data_store;
 #[cfg(feature = "local-store")]
 mod local_data_store;
 mod memory_data_store;
 mod memory_sync_tracker;
 mod traits;
 
-pub use api_syncable_store::ApiSyncableStore;
+pub use api_syncable_store::{ApiSyncableStore, MissingBlocks};
+#[cfg(feature = "test-utils")]
+pub use faulty_data_store::{FaultyDataStore, FaultyOperation};
 #[cfg(feature = "local-store")]
 pub use local_data_store::LocalDataStore;
 pub use memory_data_store::MemoryDataStore;
 pub use memory_sync_tracker::MemorySyncTracker;
-pub use traits::{DataStore, DataStoreError, SyncTracker, SyncableDataStore};
+pub use traits::{DataStore, DataStoreError, ExternalFetcher, SyncTracker, SyncableDataStore};
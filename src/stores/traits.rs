@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 use url::Url;
 
@@ -20,6 +22,21 @@ pub trait DataStore {
     /// BanyanFS has explicitly standardized on the BLAKE3 hash algorithm.
     async fn contains_cid(&self, cid: Cid) -> Result<bool, DataStoreError>;
 
+    /// Checks whether each of the provided CIDs is known to the store, returning a map of each CID
+    /// to its presence. The default implementation checks each CID individually using
+    /// [`DataStore::contains_cid`]. Implementors that are able to answer these queries in bulk,
+    /// such as those that need to make a network request per lookup, should override this.
+    async fn contains_cids(&self, cids: &[Cid]) -> Result<HashMap<Cid, bool>, DataStoreError> {
+        let mut presence = HashMap::with_capacity(cids.len());
+
+        for cid in cids {
+            let present = self.contains_cid(cid.clone()).await?;
+            presence.insert(cid.clone(), present);
+        }
+
+        Ok(presence)
+    }
+
     /// Allows the filesystem to request the removal of a block. Once done it assumes the block is
     /// no longer available and will not attempt to retrieve it unless the block gets added back to
     /// the filesystem.
@@ -49,6 +66,17 @@ pub trait DataStore {
     ) -> Result<(), DataStoreError>;
 }
 
+/// Files may reference content that lives entirely outside of BanyanFS, such as objects held by
+/// a cloud storage provider. When one of these files is read, the implementor of this trait is
+/// responsible for retrieving the raw content from the recorded URL. The content is verified
+/// against the CID recorded in the filesystem before it is returned to the caller so the
+/// implementor doesn't need to perform any verification itself.
+#[async_trait(?Send)]
+pub trait ExternalFetcher {
+    /// Retrieve the complete content stored at the provided URL.
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, DataStoreError>;
+}
+
 /// An optional additional trait for implementors that would like to perform periodic flushing of
 /// storage instead of reading and writing every block immediately. This is particularly useful in
 /// aggregating filesystem operations for one large write, splitting up sync operations into
@@ -91,16 +119,56 @@ pub trait SyncableDataStore: DataStore + SyncTracker {
 pub trait SyncTracker {
     /// Clears the list of all blocks that have been indicated they've been deleted. Intended use
     /// is for immediately after notifying a remote system that a set of blocks are no longer
-    /// needed.
+    /// needed. Pinned CIDs are never part of that list.
     async fn clear_deleted(&mut self) -> Result<(), DataStoreError>;
 
     /// Indicate the provide CID is no longer needed and can be removed from the store but does not
-    /// sync this information on its own.
+    /// sync this information on its own. Deleting a pinned CID has no effect, see
+    /// [`SyncTracker::pin`].
     async fn delete(&mut self, cid: Cid) -> Result<(), DataStoreError>;
 
     /// Returns he currently tracked list of CIDs that have been marked for deletion.
     async fn deleted_cids(&self) -> Result<Vec<Cid>, DataStoreError>;
 
+    /// Returns the total size of all the CIDs that have been marked for deletion but not yet
+    /// cleared, which is the amount of storage that will be reclaimed once the deletions are
+    /// synced. Implementations can only report the sizes of CIDs they were previously asked to
+    /// [`SyncTracker::track`], any other deleted CIDs don't contribute to the total.
+    async fn deleted_size(&self) -> Result<u64, DataStoreError>;
+
+    /// Ensures all of the tracker's state has been durably persisted. Trackers that hold their
+    /// state purely in memory have nothing to persist, which is the default behavior.
+    async fn flush(&mut self) -> Result<(), DataStoreError> {
+        Ok(())
+    }
+
+    /// Whether the CID is currently pinned. The default implementation searches the list
+    /// returned by [`SyncTracker::pinned_cids`], implementations are encouraged to provide a more
+    /// efficient lookup.
+    async fn is_pinned(&self, cid: &Cid) -> Result<bool, DataStoreError> {
+        Ok(self.pinned_cids().await?.contains(cid))
+    }
+
+    /// Protects the CID from being deleted until it is unpinned, regardless of whether the
+    /// filesystem still references it. This is intended for blocks that are needed outside of the
+    /// filesystem, such as those of content that is being shared or exported. A pending deletion
+    /// of the CID is cancelled and later deletions are ignored. Pinning an already pinned CID has
+    /// no effect.
+    ///
+    /// Pins are part of the tracker's state, trackers that persist their state are expected to
+    /// persist their pins along with it. Trackers that don't support pinning keep the default
+    /// implementation, which rejects every pin with [`DataStoreError::Implementation`].
+    async fn pin(&mut self, _cid: Cid) -> Result<(), DataStoreError> {
+        Err(DataStoreError::Implementation(
+            "pinning isn't supported by this tracker".to_string(),
+        ))
+    }
+
+    /// Returns every CID that is currently pinned. By default nothing is ever pinned.
+    async fn pinned_cids(&self) -> Result<Vec<Cid>, DataStoreError> {
+        Ok(Vec::new())
+    }
+
     /// Track a provided CID indicating that it still needs to be synced/persisted. The reported
     /// size is used for needed storage calculations and can be accessed through the
     /// [`SyncTracker::tracked_size`] method.
@@ -113,13 +181,21 @@ pub trait SyncTracker {
     /// when selecting where the data will be persisted.
     async fn tracked_size(&self) -> Result<u64, DataStoreError>;
 
+    /// Removes the protection [`SyncTracker::pin`] placed on the CID, allowing it to be deleted
+    /// again. A deletion ignored while the CID was pinned isn't restored, the CID needs to be
+    /// deleted again once it is no longer needed. By default nothing is ever pinned so there is
+    /// nothing to remove.
+    async fn unpin(&mut self, _cid: Cid) -> Result<(), DataStoreError> {
+        Ok(())
+    }
+
     /// Allows marking individual CIDs as no longer needing to be tracked. Useful for stores to to
     /// perform incremental block-based synchronization that keeps track of its ongoing state.
     async fn untrack(&mut self, cid: Cid) -> Result<(), DataStoreError>;
 }
 
 /// Various common errors that can generically
-#[derive(Debug, thiserror::Error)]
+#[derive(Clone, Debug, thiserror::Error)]
 pub enum DataStoreError {
     /// An error that couldn't be represented by one of the standard error types, representing some
     /// kind of error specific to the underlying implementation.
@@ -11,11 +11,36 @@ pub const CID_HASH_SIZE: usize = 32;
 /// Quick helper that produces the library specific [`Cid`] object over the given data. Internally
 /// this is simply a 32 byte BLAKE3 hash of the data wrapped around a helper struct. For formating
 /// as a standard CIDv1 string, please see the [`Cid::as_base64url_multicodec`] method.
+///
+/// When the `parallel-hashing` feature is enabled on native targets, inputs of at least
+/// [`PARALLEL_HASH_THRESHOLD`] bytes are hashed across multiple threads. The resulting CID is
+/// identical to the serial path.
 pub fn calculate_cid(data: &[u8]) -> Cid {
-    let hash: [u8; CID_HASH_SIZE] = blake3::hash(data).into();
+    let hash: [u8; CID_HASH_SIZE] = hash_data(data).into();
     Cid::from(hash)
 }
 
+/// Inputs at or above this size will make use of blake3's multithreaded hashing when the
+/// `parallel-hashing` feature is enabled. Below this size the cost of coordinating the threads
+/// outweighs any gains and the serial implementation is used instead.
+pub const PARALLEL_HASH_THRESHOLD: usize = 128 * 1024;
+
+#[cfg(all(feature = "parallel-hashing", not(target_arch = "wasm32")))]
+fn hash_data(data: &[u8]) -> blake3::Hash {
+    if data.len() < PARALLEL_HASH_THRESHOLD {
+        return blake3::hash(data);
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_rayon(data);
+    hasher.finalize()
+}
+
+#[cfg(any(not(feature = "parallel-hashing"), target_arch = "wasm32"))]
+fn hash_data(data: &[u8]) -> blake3::Hash {
+    blake3::hash(data)
+}
+
 /// Helper utility in regular builds to produce a standard RNG for cryptographic use. Implemented
 /// to allow a standardized way to access an environment specific secure RNG.
 ///
@@ -54,3 +79,29 @@ pub fn current_time_ms() -> i64 {
 pub(crate) fn std_io_err(msg: &'static str) -> std::io::Error {
     std::io::Error::new(std::io::ErrorKind::Other, msg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test(async))]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_large_cid_matches_serial_hash() {
+        let mut rng = crypto_rng();
+
+        let mut data = vec![0u8; PARALLEL_HASH_THRESHOLD * 4 + 17];
+        rng.fill(data.as_mut_slice());
+
+        let serial_hash: [u8; CID_HASH_SIZE] = blake3::hash(&data).into();
+        assert_eq!(calculate_cid(&data), Cid::from(serial_hash));
+
+        let small_data = &data[..PARALLEL_HASH_THRESHOLD - 1];
+        let serial_hash: [u8; CID_HASH_SIZE] = blake3::hash(small_data).into();
+        assert_eq!(calculate_cid(small_data), Cid::from(serial_hash));
+    }
+}
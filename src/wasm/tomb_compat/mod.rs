@@ -36,6 +36,11 @@ use crate::wasm::data_storage::{initialize_store, WasmDataStorage};
 
 use models::TombBucket;
 
+/// Uploads are assembled entirely in memory before being sent from the browser, larger uploads are
+/// rejected rather than risking exhausting the memory available to the page. Can be adjusted with
+/// `setMaxUploadSize`.
+const DEFAULT_MAX_UPLOAD_SIZE: u64 = 512 * 1024 * 1024;
+
 #[derive(Clone)]
 #[wasm_bindgen(js_name = TombWasm)]
 pub struct TombCompat {
@@ -74,6 +79,13 @@ impl TombCompat {
         Ok(())
     }
 
+    /// Stops any block or metadata uploads currently in progress, the operations that started
+    /// them will fail.
+    #[wasm_bindgen(js_name = cancelUploads)]
+    pub fn cancel_uploads(&self) {
+        self.client.cancel_uploads();
+    }
+
     // new transfered and checked,
     //
     // note(sstelfox): we already have the private key, and that gives us the public key. I'm
@@ -230,8 +242,8 @@ impl TombCompat {
         let mount = WasmMount::pull(wasm_bucket.clone(), self.clone()).await?;
 
         // note(sstelfox): the old version attempts to unlock the mount here, but I've migrated that into the
-        // pull itself if the key matches. This mount will only fail if the bucket doesn't have any
-        // metadata or the current key is incorrect.
+        // pull itself if the key matches. Buckets without any metadata yet are mounted as an empty
+        // drive, this will otherwise only fail if the current key is incorrect.
         //
         // Given this is an explicit mount operation it likely should be an error if we're unable
         // to do so...
@@ -256,6 +268,7 @@ impl TombCompat {
 
         let client = ApiClient::new(&api_endpoint, &account_id, key.clone())
             .expect("need return type fixed");
+        client.set_max_upload_size(Some(DEFAULT_MAX_UPLOAD_SIZE));
 
         let store = initialize_store(client.clone());
 
@@ -265,8 +278,16 @@ impl TombCompat {
     // new transfered and checked
     #[wasm_bindgen(js_name = renameBucket)]
     pub async fn rename_bucket(&mut self, bucket_id: String, name: String) -> BanyanFsResult<()> {
-        let attrs = platform::ApiDriveUpdateAttributes { name: Some(name) };
+        let attrs = platform::ApiDriveUpdateAttributes::default().with_name(name);
         platform::drives::update(&self.client, &bucket_id, attrs).await?;
         Ok(())
     }
+
+    /// Sets the largest upload, in bytes, that will be assembled in memory. Passing nothing (or
+    /// zero) removes the limit.
+    #[wasm_bindgen(js_name = setMaxUploadSize)]
+    pub fn set_max_upload_size(&self, max_bytes: Option<usize>) {
+        self.client
+            .set_max_upload_size(max_bytes.map(|max_bytes| max_bytes as u64));
+    }
 }
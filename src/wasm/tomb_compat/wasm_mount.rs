@@ -1,8 +1,6 @@
 use std::collections::HashSet;
 
 use elliptic_curve::rand_core::CryptoRngCore;
-use futures::io::Cursor;
-use futures::StreamExt;
 
 use crate::prelude::*;
 
@@ -39,21 +37,7 @@ impl WasmMount {
         let mut rng = crypto_rng();
         let signing_key = wasm_client.signing_key();
 
-        let api_assigned_id = bucket.id().replace("-", "");
-        let mut id_bytes = [0u8; 16];
-
-        for (i, byte_chunk) in api_assigned_id.as_bytes().chunks(2).enumerate() {
-            let byte_str = std::str::from_utf8(byte_chunk).map_err(|_| {
-                "UUID assigned from platform has non-hex and hyphen characters present"
-            })?;
-
-            let byte = u8::from_str_radix(byte_str, 16)
-                .map_err(|_| "failed to convert byte string chunk to a byte")?;
-
-            id_bytes[i] = byte;
-        }
-
-        let filesystem_id = FilesystemId::from(id_bytes);
+        let filesystem_id = platform::drives::filesystem_id(&bucket.id())?;
 
         let drive = Drive::initialize_private_with_id(&mut rng, signing_key, filesystem_id)
             .map_err(|e| BanyanFsError::from(e.to_string()))?;
@@ -81,13 +65,18 @@ impl WasmMount {
         let client = wasm_client.client();
         let drive_id = bucket.id();
 
-        let current_metadata = platform::metadata::get_current(client, &drive_id).await?;
+        let current_metadata = match platform::metadata::get_current(client, &drive_id).await {
+            Ok(metadata) => metadata,
+            Err(ApiError::NoMetadataVersion(_)) => {
+                tracing::info!(%drive_id, "drive has no metadata yet, mounting an empty drive");
+                return Self::pull_empty(bucket, wasm_client).await;
+            }
+            Err(err) => return Err(err.into()),
+        };
         let metadata_id = current_metadata.id();
 
         // note(sstelfox): It doesn't make sense that we wouldn't have a signing key here, but if anything goes
-        // wrong at this point we simply consider the drive to remain locked. There could be a 404
-        // in here indicating that an initial metadata hasn't be pushed but that is a weird failure
-        // case. We should really enforce an initial metadata push during the bucket creation...
+        // wrong at this point we simply consider the drive to remain locked.
         let drive = try_load_drive(client, &drive_id, &metadata_id).await;
 
         if let Some(d) = drive.as_ref() {
@@ -113,6 +102,34 @@ impl WasmMount {
         Ok(mount)
     }
 
+    /// Mounts a drive that exists on the platform but hasn't had any metadata pushed for it yet.
+    /// The mount holds a new empty drive and is considered dirty, the first sync will push it as
+    /// the drive's initial version.
+    async fn pull_empty(bucket: WasmBucket, wasm_client: TombCompat) -> BanyanFsResult<Self> {
+        let mut rng = crypto_rng();
+
+        let filesystem_id = platform::drives::filesystem_id(&bucket.id())?;
+        let drive =
+            Drive::initialize_private_with_id(&mut rng, wasm_client.signing_key(), filesystem_id)
+                .map_err(|e| BanyanFsError::from(e.to_string()))?;
+
+        let pubkey = wasm_client.client().platform_public_key().await?;
+        ensure_platform_key_present(&mut rng, &drive, pubkey).await?;
+
+        let store = wasm_client.store();
+
+        Ok(Self {
+            wasm_client,
+
+            bucket,
+            drive: Some(drive),
+            store,
+
+            dirty: true,
+            last_saved_metadata: None,
+        })
+    }
+
     pub(crate) async fn sync(&mut self) -> BanyanFsResult<()> {
         let mut rng = crypto_rng();
 
@@ -123,11 +140,10 @@ impl WasmMount {
 
         let content_options = ContentOptions::metadata();
 
-        let mut encoded_drive = Vec::new();
-        unlocked_drive
-            .encode(&mut rng, content_options, &mut encoded_drive)
-            .await
-            .map_err(|e| format!("error while encoding drive for sync: {e}"))?;
+        let encoded_drive =
+            platform::metadata::encode_drive_stream(&mut rng, unlocked_drive, content_options)
+                .await
+                .map_err(|e| format!("error while encoding drive for sync: {e}"))?;
 
         let expected_data_size = self
             .store
@@ -135,10 +151,7 @@ impl WasmMount {
             .await
             .map_err(|e| format!("failed to read unsynced data size: {e}"))?;
 
-        let root_cid = unlocked_drive
-            .root_cid()
-            .await
-            .map_err(|e| format!("error while getting root cid for sync: {e}"))?;
+        let root_cid = encoded_drive.root_cid().clone();
 
         // todo(sstelfox): still need the following:
         let valid_keys = vec![];
@@ -149,7 +162,7 @@ impl WasmMount {
             .await
             .map_err(|e| format!("unable to retrieve deleted data CIDs: {e}"))?;
 
-        let drive_stream = crate::api::client::utils::VecStream::new(encoded_drive).pinned();
+        let drive_stream = encoded_drive.into_stream();
 
         let push_response = platform::metadata::push_stream(
             self.wasm_client.client(),
@@ -393,9 +406,7 @@ impl WasmMount {
         let client = self.wasm_client.client();
         let drive_id = self.bucket.id();
 
-        let update_drive_attrs = platform::ApiDriveUpdateAttributes {
-            name: Some(name.clone()),
-        };
+        let update_drive_attrs = platform::ApiDriveUpdateAttributes::default().with_name(&name);
         platform::drives::update(client, &drive_id, update_drive_attrs).await?;
         self.bucket.0.set_name(name);
 
@@ -540,41 +551,10 @@ impl WasmMount {
 async fn try_load_drive(client: &ApiClient, drive_id: &str, metadata_id: &str) -> Option<Drive> {
     let key = client.signing_key();
 
-    // todo(sstelfox): we should return something other than a 404 when we've seen at least once
-    // metadata for a drive (if we've seen zero its safe to create a new drive, its not otherwise).
-    let mut stream = match platform::metadata::pull_stream(client, drive_id, metadata_id).await {
-        Ok(stream) => stream,
-        Err(err) => {
-            // note(sstelfox): there is a chance to dodge the API design issue mentioned in the
-            // pull method, we may need to check if the response was a 404 and if so initialize a
-            // new drive to return (as there may not have been one pushed previously).
-            tracing::warn!("requested metadata unavailable: {}", err);
-            return None;
-        }
-    };
-
-    let mut drive_bytes = Vec::new();
-    while let Some(chunk) = stream.next().await {
-        let byte_chunk = match chunk {
-            Ok(byte_chunk) => byte_chunk,
-            Err(err) => {
-                tracing::warn!("error pulling chunk in metadata stream: {}", err);
-                return None;
-            }
-        };
-
-        drive_bytes.extend(byte_chunk.to_vec());
-    }
-
-    // todo(sstelfox): optimally we'd pass the Stream above directly to the loader rather than
-    // loading it in memory. There are ways to do it but this is sufficient for the time being.
-
-    let mut drive_cursor = Cursor::new(drive_bytes);
-    let drive_loader = DriveLoader::new(&key);
-    match drive_loader.from_reader(&mut drive_cursor).await {
+    match platform::drives::load_version(client, drive_id, metadata_id, &key).await {
         Ok(drive) => Some(drive),
         Err(err) => {
-            tracing::warn!("error loading drive from metadata stream: {}", err);
+            tracing::warn!("unable to load drive from platform metadata: {}", err);
             None
         }
     }